@@ -1,62 +1,709 @@
 use std::{
-    ffi::{OsStr, OsString},
-    fs,
+    collections::{HashMap, HashSet},
     path::Path,
 };
 
-use squishy::{error::SquishyError, EntryKind, SquashFS, SquashFSEntry};
+use rayon::iter::ParallelIterator;
+use serde_json::{json, Map, Value};
+use squishy::{
+    appimage::{
+        bundle::{BundleOptions, BundleStatus},
+        filter::{Filter, FilterMode, Pattern, PatternKind},
+        icon::{IconFormat, IconPreference},
+        naming::{FileKind, NameTemplate},
+        signature::SignatureVerdict,
+        AppImage,
+    },
+    collision::CollisionStrategy,
+    EntryKind, SquashFSEntry,
+};
+
+use crate::{
+    archive::TarWriter,
+    cat,
+    cli::{AppImageArgs, AppImageCommand, FilterArgs, FilterKind, IconFormatChoice},
+    collision_strategy, color,
+    dryrun::{self, PlannedWrite},
+    format::tsv_field,
+    open_image,
+    output::{Output, EXIT_PARTIAL},
+    progress::ExtractProgress,
+};
+
+mod exec;
+mod extract;
+pub(crate) mod integrate;
+mod list;
+mod metadata;
+mod runtime;
+mod validate;
+
+/// Converts the status of a bundle file to JSON.
+///
+/// # Arguments
+/// * `status` - The status
+///
+/// # Returns
+/// The JSON object, or None if the file was skipped
+fn status_json(status: &BundleStatus) -> Option<Value> {
+    match status {
+        BundleStatus::Written { source, output } => Some(json!({
+            "status": "written",
+            "source": source.to_string_lossy(),
+            "output": output.to_string_lossy(),
+        })),
+        BundleStatus::Failed { source, error } => Some(json!({
+            "status": "failed",
+            "source": source.to_string_lossy(),
+            "error": error.to_string(),
+        })),
+        BundleStatus::Exists { source, output } => Some(json!({
+            "status": "exists",
+            "source": source.to_string_lossy(),
+            "output": output.to_string_lossy(),
+        })),
+        BundleStatus::Missing => Some(json!({ "status": "missing" })),
+        BundleStatus::Skipped => None,
+    }
+}
 
-pub type Result<T> = std::result::Result<T, SquishyError>;
+/// Finds the AppRun entry point, exiting if it can't be read.
+///
+/// # Arguments
+/// * `appimage` - The AppImage
+/// * `out` - Output settings
+///
+/// # Returns
+/// The resolved AppRun file under the `AppRun` path, if found
+fn apprun_entry<'a>(appimage: &'a AppImage, out: Output) -> Option<SquashFSEntry<'a>> {
+    appimage
+        .find_apprun()
+        .unwrap_or_else(|e| out.fail(e))
+        .map(|apprun| apprun.into_entry())
+}
+
+/// Finds the files that `--write` would write, like
+/// [`AppImage::extract_metadata_bundle`] and [`AppImage::write_icon_theme`] do.
+///
+/// # Arguments
+/// * `appimage` - The AppImage
+/// * `write_path` - Directory the files would be written to
+/// * `options` - Which files to find and how to name them
+/// * `hicolor` - Whether icons would be written in the hicolor layout
+/// * `out` - Output settings
+///
+/// # Returns
+/// The files that would be written, and the kinds of the requested files that
+/// weren't found
+fn plan_bundle(
+    appimage: &AppImage,
+    write_path: &Path,
+    options: BundleOptions,
+    hicolor: bool,
+    out: Output,
+) -> (Vec<PlannedWrite>, Vec<&'static str>) {
+    let mut missing_kinds = Vec::new();
+    let mut planned = Vec::new();
+    let values = options
+        .name_template
+        .map(|_| appimage.name_values())
+        .unwrap_or_default();
+    let mut plan = |requested: bool, entry: Option<SquashFSEntry>, kind, key, missing| {
+        if !requested {
+            return;
+        }
+        match entry {
+            Some(entry) => planned.push(PlannedWrite {
+                output: write_path.join(options.file_name(&entry.path, kind, &values)),
+                source: entry.path,
+                size: entry.size as u64,
+            }),
+            None => {
+                missing_kinds.push(key);
+                elog!(out.errors_silent(), "{}", missing);
+            }
+        }
+    };
+    plan(
+        options.desktop,
+        appimage.find_desktop(),
+        FileKind::Desktop,
+        "desktop",
+        "No desktop file found.",
+    );
+    plan(
+        options.icon && !hicolor,
+        appimage.find_preferred_icon(options.icon_preference),
+        FileKind::Icon,
+        "icon",
+        "No icon found.",
+    );
+    plan(
+        options.appstream,
+        appimage.find_appstream(),
+        FileKind::Appstream,
+        "appstream",
+        "No appstream file found.",
+    );
+    plan(
+        options.apprun,
+        apprun_entry(appimage, out),
+        FileKind::AppRun,
+        "apprun",
+        "No AppRun found.",
+    );
 
-pub fn extract_file<P: AsRef<Path>>(
-    squashfs: &SquashFS,
-    entry: &SquashFSEntry,
-    output_dir: P,
-    output_name: Option<&OsStr>,
+    if options.icon && hicolor {
+        match appimage.icon_theme_paths(write_path, options.output_name) {
+            Ok(icons) => {
+                if icons.is_empty() {
+                    missing_kinds.push("icon");
+                    elog!(out.errors_silent(), "No icon found.");
+                }
+                planned.extend(icons.into_iter().map(|(icon, output)| PlannedWrite {
+                    source: icon.path,
+                    output,
+                    size: icon.entry.size as u64,
+                }));
+            }
+            Err(e) => {
+                missing_kinds.push("icon");
+                elog!(
+                    out.errors_silent(),
+                    "{}",
+                    color::error(format!("Failed to find icons: {}", e))
+                );
+            }
+        }
+    }
+    (planned, missing_kinds)
+}
+
+/// Writes the files that `--write` would write as a tar stream instead, named as
+/// they would be.
+///
+/// # Arguments
+/// * `appimage` - The AppImage
+/// * `target` - Path of the tar file, or `-` for stdout
+/// * `options` - Which files to write and how to name them
+/// * `hicolor` - Whether to write icons in the hicolor layout
+/// * `copy_permissions` - Whether to record the modes of the entries instead of 0644
+/// * `out` - Output settings
+///
+/// # Returns
+/// The files written, and the kinds of the requested files that weren't found
+fn write_tar(
+    appimage: &AppImage,
+    target: &Path,
+    options: BundleOptions,
+    hicolor: bool,
     copy_permissions: bool,
-) -> Result<()> {
-    if let EntryKind::File(basic_file) = entry.kind {
-        let file = &entry.path;
-        let file_name = output_name
-            .map(|output_name| {
-                let name_with_extension = file
-                    .extension()
-                    .map(|ext| {
-                        let file_str = file.file_name().unwrap().to_string_lossy();
-                        if file_str.ends_with("appdata.xml") || file_str.ends_with("metainfo.xml") {
-                            let base_name = if file_str.ends_with("appdata.xml") {
-                                "appdata"
-                            } else {
-                                "metainfo"
-                            };
-                            format!(
-                                "{}.{}.{}",
-                                output_name.to_string_lossy(),
-                                base_name,
-                                ext.to_string_lossy()
-                            )
-                        } else {
-                            format!(
-                                "{}.{}",
-                                output_name.to_string_lossy(),
-                                ext.to_string_lossy()
-                            )
-                        }
-                    })
-                    .unwrap_or_else(|| file.file_name().unwrap().to_string_lossy().to_string());
+    out: Output,
+) -> (Vec<Value>, Vec<&'static str>) {
+    let (planned, missing_kinds) = plan_bundle(appimage, Path::new(""), options, hicolor, out);
+    let sources = planned
+        .iter()
+        .map(|planned| planned.source.as_path())
+        .collect::<HashSet<_>>();
+    let entries = appimage
+        .par_entries()
+        .filter(|entry| sources.contains(entry.path.as_path()))
+        .map(|entry| (entry.path.clone(), entry))
+        .collect::<HashMap<_, _>>();
 
-                OsString::from(name_with_extension)
-            })
-            .unwrap_or_else(|| file.file_name().unwrap().to_os_string());
+    let mut tar = TarWriter::create(target, out);
+    let mut written = Vec::new();
+    for planned in &planned {
+        let entry = &entries[&planned.source];
+        let resolved = match entry.kind {
+            EntryKind::Symlink(_) => appimage.resolve_symlink(entry).ok().flatten(),
+            _ => None,
+        };
+        let entry = resolved.as_ref().unwrap_or(entry);
+        // AppRun must stay executable
+        let mode = if planned.source == Path::new("/AppRun") {
+            None
+        } else {
+            (!copy_permissions).then_some(0o644)
+        };
+        if let Err(e) = tar.append(entry, &planned.output, mode, |offset, length| {
+            appimage.read_range(entry, offset, length)
+        }) {
+            out.fail(format!("Failed to add {}: {}", planned.source.display(), e));
+        }
+        log!(
+            out.silent() || target.as_os_str() == "-",
+            "Added {} as {}",
+            planned.source.display(),
+            planned.output.display()
+        );
+        written.push(json!({
+            "source": planned.source.to_string_lossy(),
+            "output": planned.output.to_string_lossy(),
+        }));
+    }
+    tar.finish()
+        .unwrap_or_else(|e| out.fail(format!("{}: {}", target.display(), e)));
+    (written, missing_kinds)
+}
 
-        fs::create_dir_all(&output_dir)?;
-        let output_path = output_dir.as_ref().join(file_name);
-        if copy_permissions {
-            squashfs.write_file_with_permissions(basic_file, &output_path, entry.header)?;
+/// Builds the filter selected by the command line options, exiting if a pattern is
+/// invalid.
+///
+/// # Arguments
+/// * `filters` - The options
+/// * `out` - Output settings
+///
+/// # Returns
+/// The filter
+fn build_filter(filters: &FilterArgs, out: Output) -> Filter {
+    let mode = if filters.filter_all {
+        FilterMode::All
+    } else {
+        FilterMode::Any
+    };
+    let kind = match filters.filter_kind {
+        FilterKind::Substring => PatternKind::Substring,
+        FilterKind::Glob => PatternKind::Glob,
+        FilterKind::Regex => PatternKind::Regex,
+    };
+    let pattern = |pattern: &String| Pattern::new(kind, pattern).unwrap_or_else(|e| out.fail(e));
+    let filter = filters
+        .filter
+        .iter()
+        .fold(Filter::new(mode), |acc, p| acc.include(pattern(p)));
+    filters
+        .filter_not
+        .iter()
+        .fold(filter, |acc, p| acc.exclude(pattern(p)))
+}
+
+/// Runs the `appimage` subcommand. It exits with [`EXIT_PARTIAL`] if a requested
+/// file isn't found or written, and [`EXIT_INVALID`](crate::output::EXIT_INVALID) if the file isn't an AppImage.
+///
+/// # Arguments
+/// * `args` - Arguments of the subcommand
+/// * `out` - Output settings
+pub fn run(args: AppImageArgs, out: Output) {
+    if let Some(command) = args.command {
+        match command {
+            AppImageCommand::List {
+                file,
+                filters,
+                offset,
+            } => {
+                let appimage = open_image(&file, offset, out);
+                list::list(&appimage, &build_filter(&filters, out), out);
+            }
+            AppImageCommand::Extract {
+                file,
+                globs,
+                write,
+                existing,
+                copy_permissions,
+                offset,
+            } => {
+                let appimage = open_image(&file, offset, out);
+                let output_dir = write.unwrap_or_else(|| std::env::current_dir().unwrap());
+                let collision = collision_strategy(&existing, CollisionStrategy::Overwrite);
+                extract::extract(
+                    &appimage,
+                    &globs,
+                    &output_dir,
+                    collision,
+                    copy_permissions,
+                    out,
+                );
+            }
+            AppImageCommand::Exec {
+                file,
+                strip_field_codes,
+                offset,
+            } => {
+                let appimage = open_image(&file, offset, out);
+                exec::exec(&appimage, strip_field_codes, out);
+            }
+            AppImageCommand::Runtime {
+                file,
+                write,
+                offset,
+            } => {
+                let appimage = open_image(&file, offset, out);
+                runtime::runtime(&appimage, &file, write.as_deref(), out);
+            }
+            AppImageCommand::Metadata { file, offset } => {
+                let appimage = open_image(&file, offset, out);
+                metadata::metadata(&appimage, out);
+            }
+            AppImageCommand::Integrate {
+                file,
+                data_dir,
+                offset,
+            } => {
+                let appimage = open_image(&file, offset, out);
+                let data_dir = integrate::data_dir_or_default(data_dir, out);
+                integrate::integrate(&appimage, &data_dir, out);
+            }
+            AppImageCommand::Unintegrate { target, data_dir } => {
+                let data_dir = integrate::data_dir_or_default(data_dir, out);
+                integrate::unintegrate(&target, &data_dir, out);
+            }
+            AppImageCommand::Validate { file, offset } => {
+                let appimage = open_image(&file, offset, out);
+                validate::validate(&appimage, out);
+            }
+        }
+        return;
+    }
+
+    let AppImageArgs {
+        command: _,
+        file,
+        offset,
+        filters,
+        icon,
+        icon_size,
+        icon_format,
+        desktop,
+        appstream,
+        apprun,
+        all,
+        write,
+        dry_run,
+        cat,
+        original_name,
+        name_template,
+        copy_permissions,
+        hicolor,
+        rewrite_desktop,
+        validate,
+        verify,
+        existing,
+        to_tar,
+    } = args;
+    let (icon, desktop, appstream) = (icon || all, desktop || all, appstream || all);
+    let icon_preference = IconPreference {
+        size: icon_size,
+        format: match icon_format {
+            IconFormatChoice::Png => Some(IconFormat::Png),
+            IconFormatChoice::Svg => Some(IconFormat::Svg),
+            IconFormatChoice::Any => None,
+        },
+    };
+    let collision = collision_strategy(&existing, CollisionStrategy::Overwrite);
+    let quiet = out.silent();
+    if out.json && to_tar.as_deref() == Some(Path::new("-")) {
+        out.fail_invalid("--json can't be used when writing the tar stream to stdout");
+    }
+    if cat && (out.json || out.tsv() || out.print0) {
+        out.fail_invalid("--cat can't be used with --json, --format tsv or --print0");
+    }
+    let kinds = [icon, desktop, appstream, apprun];
+    if cat && kinds.into_iter().filter(|&kind| kind).count() > 1 {
+        out.fail_invalid("--cat prints a single file, give only one of -i, -d, -a or --apprun");
+    }
+
+    // Required when there is no subcommand
+    let file = file.unwrap();
+    if !file.exists() {
+        out.fail_invalid(format!("{}: No such file", file.display()));
+    }
+
+    let appimage = AppImage::with_filter(build_filter(&filters, out), &file, offset)
+        .unwrap_or_else(|e| out.fail_invalid(e));
+
+    let mut result = Map::new();
+    let mut complete = true;
+    // Kinds of the requested files that weren't found, for the `--all` summary
+    let mut missing_kinds = Vec::new();
+
+    if verify {
+        match appimage.verify_signature() {
+            Ok(SignatureVerdict::Valid { fingerprint }) => {
+                log!(quiet, "Signature: valid (key {})", fingerprint);
+                result.insert(
+                    "signature".into(),
+                    json!({ "status": "valid", "fingerprint": fingerprint }),
+                );
+            }
+            Ok(SignatureVerdict::Invalid(reason)) => {
+                let message = format!("Signature: invalid ({})", reason);
+                result.insert(
+                    "signature".into(),
+                    json!({ "status": "invalid", "reason": reason }),
+                );
+                out.fail_with(result, message);
+            }
+            Ok(SignatureVerdict::Unsigned) => {
+                result.insert("signature".into(), json!({ "status": "unsigned" }));
+                out.fail_with(result, "Signature: AppImage is not signed");
+            }
+            Ok(SignatureVerdict::MissingKey) => {
+                result.insert("signature".into(), json!({ "status": "missing-key" }));
+                out.fail_with(result, "Signature: no public key embedded");
+            }
+            Err(e) => out.fail_with(result, e),
+        }
+    }
+
+    if validate {
+        match appimage.validate() {
+            Ok(report) => {
+                for issue in &report.issues {
+                    elog!(quiet, "{}", issue);
+                }
+                let issues = report
+                    .issues
+                    .iter()
+                    .map(|issue| {
+                        json!({
+                            "severity": issue.severity.to_string(),
+                            "message": issue.message,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                result.insert(
+                    "validation".into(),
+                    json!({ "valid": report.is_valid(), "issues": issues }),
+                );
+                if !report.is_valid() {
+                    out.fail_invalid_with(result, "AppImage is not valid");
+                }
+                log!(quiet, "AppImage is valid");
+            }
+            Err(e) => out.fail_with(result, e),
+        }
+    }
+
+    let write_path = if let Some(write) = write {
+        if let Some(path) = write {
+            Some(path)
         } else {
-            squashfs.write_file(basic_file, &output_path)?;
+            Some(std::env::current_dir().unwrap())
         }
-        println!("Wrote {} to {}", file.display(), output_path.display());
+    } else {
+        None
+    };
+
+    let name_template = name_template
+        .map(|template| NameTemplate::new(&template).unwrap_or_else(|e| out.fail_invalid(e)));
+    let output_name = if original_name {
+        None
+    } else {
+        file.file_name()
+    };
+
+    if let Some(target) = &to_tar {
+        let (written, missing) = write_tar(
+            &appimage,
+            target,
+            BundleOptions {
+                icon,
+                icon_preference,
+                desktop,
+                appstream,
+                apprun,
+                output_name,
+                name_template: name_template.as_ref(),
+                ..Default::default()
+            },
+            hicolor,
+            copy_permissions,
+            out,
+        );
+        complete = missing.is_empty();
+        missing_kinds = missing;
+        result.insert("written".into(), Value::Array(written));
+        result.insert("output".into(), json!(target.to_string_lossy()));
+    } else if let (true, Some(write_path)) = (dry_run, &write_path) {
+        let (planned, missing) = plan_bundle(
+            &appimage,
+            write_path,
+            BundleOptions {
+                icon,
+                icon_preference,
+                desktop,
+                appstream,
+                apprun,
+                output_name,
+                name_template: name_template.as_ref(),
+                ..Default::default()
+            },
+            hicolor,
+            out,
+        );
+        complete = missing.is_empty();
+        missing_kinds = missing;
+        result.insert("planned".into(), dryrun::report(&planned, collision, out));
+    } else if let Some(ref write_path) = write_path {
+        let progress = ExtractProgress::new(out);
+        let update = |p| progress.update(p);
+        let options = BundleOptions {
+            icon: icon && !hicolor,
+            icon_preference,
+            desktop,
+            appstream,
+            apprun,
+            output_name,
+            name_template: name_template.as_ref(),
+            copy_permissions,
+            rewrite_desktop,
+            collision,
+            progress: Some(&update),
+        };
+        let report = appimage.extract_metadata_bundle(write_path, &options);
+        progress.finish();
+        complete = report.is_complete();
+        for (key, status, missing) in [
+            ("desktop", report.desktop, "No desktop file found."),
+            ("icon", report.icon, "No icon found."),
+            ("appstream", report.appstream, "No appstream file found."),
+            ("apprun", report.apprun, "No AppRun found."),
+        ] {
+            if let Some(value) = status_json(&status) {
+                result.insert(key.into(), value);
+            }
+            match status {
+                BundleStatus::Written { source, output } => {
+                    log!(quiet, "Wrote {} to {}", source.display(), output.display());
+                }
+                BundleStatus::Failed { source, error } => {
+                    elog!(
+                        out.errors_silent(),
+                        "{}",
+                        color::error(format!("Failed to write {}: {}", source.display(), error))
+                    );
+                }
+                BundleStatus::Exists { output, .. } => {
+                    log!(quiet, "Skipped existing {}", output.display());
+                }
+                BundleStatus::Missing => {
+                    missing_kinds.push(key);
+                    elog!(out.errors_silent(), "{}", missing);
+                }
+                BundleStatus::Skipped => {}
+            }
+        }
+        if icon && hicolor {
+            match appimage.write_icon_theme(write_path, output_name, collision) {
+                Ok(icons) => {
+                    if icons.is_empty() {
+                        complete = false;
+                        missing_kinds.push("icon");
+                        elog!(out.errors_silent(), "No icon found.");
+                    }
+                    let icons = icons
+                        .into_iter()
+                        .map(|(source, output)| {
+                            log!(quiet, "Wrote {} to {}", source.display(), output.display());
+                            json!({
+                                "status": "written",
+                                "source": source.to_string_lossy(),
+                                "output": output.to_string_lossy(),
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    result.insert("icons".into(), Value::Array(icons));
+                }
+                Err(e) => {
+                    complete = false;
+                    elog!(
+                        out.errors_silent(),
+                        "{}",
+                        color::error(format!("Failed to write icons: {}", e))
+                    );
+                    result.insert(
+                        "icons".into(),
+                        json!({ "status": "failed", "error": e.to_string() }),
+                    );
+                }
+            }
+        }
+    } else {
+        let mut found = |key: &'static str, entry: Option<SquashFSEntry>, label, missing| {
+            match &entry {
+                Some(entry) if cat => cat::cat(&appimage, &entry.path, 0, None, out),
+                Some(entry) if out.tsv() => out.record(format!(
+                    "{}\t{}",
+                    key,
+                    tsv_field(&entry.path.to_string_lossy())
+                )),
+                Some(entry) if out.print0 => out.record(entry.path.display()),
+                Some(entry) => log!(quiet, "{}: {}", label, entry.path.display()),
+                None => {
+                    complete = false;
+                    missing_kinds.push(key);
+                    elog!(out.errors_silent(), "{}", missing);
+                }
+            }
+            result.insert(
+                key.into(),
+                json!(entry.map(|entry| entry.path.to_string_lossy().into_owned())),
+            );
+        };
+        if desktop {
+            found(
+                "desktop",
+                appimage.find_desktop(),
+                "Desktop file",
+                "No desktop file found.",
+            );
+        }
+        if icon {
+            found(
+                "icon",
+                appimage.find_preferred_icon(icon_preference),
+                "Icon",
+                "No icon found.",
+            );
+        }
+        if appstream {
+            found(
+                "appstream",
+                appimage.find_appstream(),
+                "Appstream file",
+                "No appstream file found.",
+            );
+        }
+        if apprun {
+            found(
+                "apprun",
+                apprun_entry(&appimage, out),
+                "AppRun",
+                "No AppRun found.",
+            );
+        }
+    }
+
+    if all {
+        let (missing, found): (Vec<_>, Vec<_>) = ["desktop", "icon", "appstream"]
+            .into_iter()
+            .partition(|key| missing_kinds.contains(key));
+        elog!(
+            quiet,
+            "Found {} of 3: {}. Missing: {}",
+            found.len(),
+            if found.is_empty() {
+                "none".to_owned()
+            } else {
+                found.join(", ")
+            },
+            if missing.is_empty() {
+                "none".to_owned()
+            } else {
+                missing.join(", ")
+            }
+        );
+        result.insert(
+            "summary".into(),
+            json!({ "found": found, "missing": missing }),
+        );
+    }
+
+    if out.json {
+        out.print_json(&Value::Object(result));
+    }
+    if !complete {
+        std::process::exit(EXIT_PARTIAL);
     }
-    Ok(())
 }
@@ -0,0 +1,58 @@
+use serde_json::json;
+use squishy::appimage::AppImage;
+
+use crate::output::{Output, EXIT_PARTIAL};
+
+/// Quotes an argument for a POSIX shell, leaving it as is when it only has safe
+/// characters.
+///
+/// # Arguments
+/// * `arg` - The argument
+///
+/// # Returns
+/// The quoted argument
+fn shell_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if safe {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Prints the `Exec` value of the desktop file, so launcher scripts know what the
+/// application runs. Exits with [`EXIT_PARTIAL`] if there's no desktop file or it
+/// has no `Exec` key.
+///
+/// # Arguments
+/// * `appimage` - The AppImage
+/// * `strip_field_codes` - Whether to print the command with the field codes, like
+///   `%U`, removed, quoted for a shell, instead of the raw value
+/// * `out` - Output settings
+pub fn exec(appimage: &AppImage, strip_field_codes: bool, out: Output) {
+    let Some(entry) = appimage.desktop_entry().unwrap_or_else(|e| out.fail(e)) else {
+        elog!(out.errors_silent(), "No desktop file found.");
+        std::process::exit(EXIT_PARTIAL);
+    };
+    let Some(exec) = &entry.exec else {
+        elog!(out.errors_silent(), "Desktop file has no Exec key.");
+        std::process::exit(EXIT_PARTIAL);
+    };
+
+    let args = entry.exec_args();
+    if out.json {
+        out.print_json(&json!({ "exec": exec, "args": args }));
+    } else if strip_field_codes {
+        let command = args
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+        out.record(command);
+    } else {
+        out.record(exec);
+    }
+}
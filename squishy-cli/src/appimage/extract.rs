@@ -0,0 +1,112 @@
+use std::{fs, os::unix, path::Path};
+
+use serde_json::json;
+use squishy::{
+    appimage::AppImage,
+    collision::{Collision, CollisionStrategy},
+    EntryKind,
+};
+
+use crate::{
+    color,
+    list::sorted_entries,
+    output::{Output, EXIT_FAILURE, EXIT_PARTIAL},
+    select::Selection,
+};
+
+/// Writes the payload entries matching the globs to the output directory, keeping
+/// their paths relative to the AppImage root. A matching directory selects
+/// everything below it. Exits with [`EXIT_PARTIAL`] if nothing matches, and
+/// [`EXIT_FAILURE`] if an entry can't be written.
+///
+/// # Arguments
+/// * `appimage` - The AppImage
+/// * `globs` - Globs of the paths to extract, e.g. `usr/share/applications/*`
+/// * `output_dir` - Directory to write the entries to
+/// * `collision` - How to handle outputs that already exist
+/// * `copy_permissions` - Whether to copy permissions from the entries
+/// * `out` - Output settings
+pub fn extract(
+    appimage: &AppImage,
+    globs: &[String],
+    output_dir: &Path,
+    collision: CollisionStrategy,
+    copy_permissions: bool,
+    out: Output,
+) {
+    let selection = Selection::new(globs, &[], false).unwrap_or_else(|e| out.fail(e));
+    let mut entries = selection.select(sorted_entries(appimage));
+    entries.retain(|entry| entry.kind != EntryKind::Unknown);
+    if entries.is_empty() {
+        elog!(out.errors_silent(), "No paths matched {}", globs.join(" "));
+        std::process::exit(EXIT_PARTIAL);
+    }
+
+    let quiet = out.silent();
+    let mut written = Vec::new();
+    let mut skipped = Vec::new();
+    let mut failed = Vec::new();
+    for entry in &entries {
+        let output = output_dir.join(entry.path.strip_prefix("/").unwrap_or(&entry.path));
+        let is_dir = entry.kind == EntryKind::Directory;
+        let result = collision.prepare(&output, is_dir).and_then(|prepared| {
+            if prepared == Collision::Skip {
+                return Ok(false);
+            }
+            match &entry.kind {
+                EntryKind::Directory => fs::create_dir_all(&output)?,
+                EntryKind::File(_) => {
+                    appimage.write(entry, output.parent().unwrap(), None, copy_permissions)?;
+                }
+                EntryKind::Symlink(target) => {
+                    fs::create_dir_all(output.parent().unwrap())?;
+                    unix::fs::symlink(target.strip_prefix("/").unwrap_or(target), &output)?;
+                }
+                EntryKind::Unknown => unreachable!(),
+            }
+            Ok(true)
+        });
+        match result {
+            // Directories leading to the selected files are not reported
+            Ok(_) if is_dir => {}
+            Ok(true) => {
+                log!(
+                    quiet,
+                    "Wrote {} to {}",
+                    entry.path.display(),
+                    output.display()
+                );
+                written.push(json!({
+                    "source": entry.path.to_string_lossy(),
+                    "output": output.to_string_lossy(),
+                }));
+            }
+            Ok(false) => {
+                log!(quiet, "Skipped existing {}", output.display());
+                skipped.push(output.to_string_lossy().into_owned());
+            }
+            Err(e) => {
+                elog!(
+                    out.errors_silent(),
+                    "{}",
+                    color::error(format!("Failed to write {}: {}", entry.path.display(), e))
+                );
+                failed.push(json!({
+                    "source": entry.path.to_string_lossy(),
+                    "error": e.to_string(),
+                }));
+            }
+        }
+    }
+
+    if out.json {
+        out.print_json(&json!({
+            "written": written,
+            "skipped": skipped,
+            "failed": failed,
+        }));
+    }
+    if !failed.is_empty() {
+        std::process::exit(EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde_json::json;
+use squishy::appimage::{integration, AppImage};
+
+use crate::{
+    color,
+    output::{Output, EXIT_PARTIAL},
+};
+
+/// Runs a cache update tool on a directory, if it is installed.
+///
+/// # Arguments
+/// * `program` - The tool, e.g. `update-desktop-database`
+/// * `dir` - Directory to update
+/// * `out` - Output settings
+fn run_tool(program: &str, dir: &Path, out: Output) {
+    let status = Command::new(program)
+        .arg(dir)
+        .stdout(Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => {
+            elog!(!out.verbose(1), "Ran {} {}", program, dir.display());
+        }
+        Ok(status) => elog!(
+            out.errors_silent(),
+            "{}",
+            color::error(format!("{} failed: {}", program, status))
+        ),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            elog!(!out.verbose(1), "{} not found, skipped", program);
+        }
+        Err(e) => elog!(
+            out.errors_silent(),
+            "{}",
+            color::error(format!("Failed to run {}: {}", program, e))
+        ),
+    }
+}
+
+/// Refreshes the desktop and MIME databases of the data directory after
+/// integration files were installed or removed, with `update-desktop-database` and
+/// `update-mime-database` when they are available, so launchers pick up the change.
+///
+/// # Arguments
+/// * `data_dir` - The data directory
+/// * `mime` - Whether MIME packages changed
+/// * `out` - Output settings
+pub(crate) fn refresh_databases(data_dir: &Path, mime: bool, out: Output) {
+    run_tool(
+        "update-desktop-database",
+        &data_dir.join("applications"),
+        out,
+    );
+    if mime {
+        run_tool("update-mime-database", &data_dir.join("mime"), out);
+    }
+}
+
+/// Picks the data directory integration files go to, exiting if there is none.
+///
+/// # Arguments
+/// * `data_dir` - Directory given on the command line, if any
+/// * `out` - Output settings
+///
+/// # Returns
+/// The data directory, `$XDG_DATA_HOME` or `~/.local/share` by default
+pub(crate) fn data_dir_or_default(data_dir: Option<PathBuf>, out: Output) -> PathBuf {
+    data_dir
+        .or_else(integration::default_data_dir)
+        .unwrap_or_else(|| out.fail("Couldn't determine the data directory"))
+}
+
+/// Installs the desktop integration of an AppImage: the desktop file rewritten to
+/// launch it, its icons in the hicolor theme, its appstream file and its MIME
+/// packages, then refreshes the desktop and MIME databases.
+///
+/// # Arguments
+/// * `appimage` - The AppImage
+/// * `data_dir` - Data directory to install the files to
+/// * `out` - Output settings
+pub fn integrate(appimage: &AppImage, data_dir: &Path, out: Output) {
+    let integration = appimage
+        .integrate_in(data_dir)
+        .unwrap_or_else(|e| out.fail(format!("Failed to integrate: {}", e)));
+    refresh_databases(data_dir, !integration.mime_packages.is_empty(), out);
+
+    let files = integration.files();
+    if out.json {
+        out.print_json(&json!({
+            "id": integration.id,
+            "files": files.iter().map(|file| file.to_string_lossy()).collect::<Vec<_>>(),
+        }));
+        return;
+    }
+    for file in &files {
+        log!(out.silent(), "Installed {}", file.display());
+    }
+}
+
+/// Removes the desktop integration installed for an AppImage, then refreshes the
+/// desktop and MIME databases. Exits with [`EXIT_PARTIAL`] if nothing was installed
+/// for it.
+///
+/// # Arguments
+/// * `target` - Path the AppImage was integrated from, which doesn't need to exist
+///   anymore, or the integration identifier
+/// * `data_dir` - Data directory the files were installed to
+/// * `out` - Output settings
+pub fn unintegrate(target: &Path, data_dir: &Path, out: Output) {
+    let id = target.to_str().filter(|_| !target.exists());
+    let removed = match id {
+        Some(id) if id.len() == 32 && id.bytes().all(|byte| byte.is_ascii_hexdigit()) => {
+            integration::remove_integration(id, data_dir)
+        }
+        _ => integration::unintegrate_in(target, data_dir),
+    }
+    .unwrap_or_else(|e| out.fail(format!("Failed to remove integration: {}", e)));
+
+    if !removed.is_empty() {
+        let mime = removed
+            .iter()
+            .any(|file| file.starts_with(data_dir.join("mime")));
+        refresh_databases(data_dir, mime, out);
+    }
+    if out.json {
+        out.print_json(&json!({
+            "removed": removed.iter().map(|file| file.to_string_lossy()).collect::<Vec<_>>(),
+        }));
+    } else {
+        for file in &removed {
+            log!(out.silent(), "Removed {}", file.display());
+        }
+    }
+    if removed.is_empty() {
+        elog!(
+            out.errors_silent(),
+            "No integration found for {}",
+            target.display()
+        );
+        std::process::exit(EXIT_PARTIAL);
+    }
+}
@@ -0,0 +1,18 @@
+use squishy::appimage::{filter::Filter, AppImage};
+
+use crate::{list::sorted_entries, output::Output};
+
+/// Prints the entries of the AppImage payload matching the filter, like `list`
+/// does, so its contents can be inspected without locating the payload offset.
+///
+/// # Arguments
+/// * `appimage` - The AppImage
+/// * `filter` - Filter the entries must match
+/// * `out` - Output settings
+pub fn list(appimage: &AppImage, filter: &Filter, out: Output) {
+    let entries = sorted_entries(appimage)
+        .into_iter()
+        .filter(|entry| filter.matches_entry(entry))
+        .collect::<Vec<_>>();
+    crate::list::print_entries(&entries, out);
+}
@@ -0,0 +1,54 @@
+use serde_json::{json, Value};
+use squishy::appimage::AppImage;
+
+use crate::output::Output;
+
+/// Prints the metadata of an AppImage as one JSON object, so index builders get
+/// everything in one call: name, version, id, categories, the desktop file, the
+/// icons, the appstream summary and the update information. Missing metadata is
+/// null or empty.
+///
+/// # Arguments
+/// * `appimage` - The AppImage
+/// * `out` - Output settings
+pub fn metadata(appimage: &AppImage, out: Output) {
+    let desktop = appimage.find_desktop();
+    let desktop_entry = appimage.desktop_entry().unwrap_or_else(|e| out.fail(e));
+    let appstream_entry = appimage.find_appstream();
+    let appstream = appimage.appstream().unwrap_or_else(|e| out.fail(e));
+    let update_info = appimage.update_info().unwrap_or_else(|e| out.fail(e));
+
+    let icons = appimage
+        .find_all_icons()
+        .iter()
+        .map(|icon| {
+            json!({
+                "path": icon.path.to_string_lossy(),
+                "size": icon.size,
+                "format": icon.format.extension(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let appstream = match (appstream_entry, appstream) {
+        (Some(entry), Some(appstream)) => json!({
+            "path": entry.path.to_string_lossy(),
+            "name": appstream.name,
+            "summary": appstream.summary,
+            "project_license": appstream.project_license,
+        }),
+        _ => Value::Null,
+    };
+
+    out.print_json(&json!({
+        "name": appimage.app_name(),
+        "version": appimage.version(),
+        "id": appimage.app_id(),
+        "categories": desktop_entry.as_ref().map_or(&[][..], |entry| &entry.categories),
+        "terminal": desktop_entry.as_ref().is_some_and(|entry| entry.terminal),
+        "desktop": desktop.map(|entry| entry.path.to_string_lossy().into_owned()),
+        "icon": appimage.find_icon().map(|entry| entry.path.to_string_lossy().into_owned()),
+        "icons": icons,
+        "appstream": appstream,
+        "update_info": update_info.map(|info| info.to_string()),
+    }));
+}
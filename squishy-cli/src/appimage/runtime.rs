@@ -0,0 +1,99 @@
+use std::path::Path;
+
+use goblin::elf::{
+    header::{machine_to_str, EM_386},
+    Elf,
+};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use squishy::appimage::{AppImage, AppImageKind};
+
+use crate::{format::human_size, output::Output};
+
+/// Reports the runtime of an AppImage: its type, whether it is statically linked,
+/// the version of its magic bytes, its architecture, size and SHA256 digest, so
+/// users can audit which runtime their AppImages ship. Type-1 AppImages have no
+/// separate runtime, and bundles prefixed with a script only get their size and
+/// digest reported.
+///
+/// # Arguments
+/// * `appimage` - The AppImage
+/// * `file` - Path to the AppImage
+/// * `write` - Path to write the runtime to, if any
+/// * `out` - Output settings
+pub fn runtime(appimage: &AppImage, file: &Path, write: Option<&Path>, out: Output) {
+    let kind = AppImage::detect_kind(file).unwrap_or_else(|e| out.fail(e));
+    let runtime = appimage
+        .runtime_bytes()
+        .unwrap_or_else(|e| out.fail_invalid(e));
+    // Some bundles are prefixed with a shell script instead of an ELF runtime
+    let elf = Elf::parse(&runtime).ok();
+
+    let (kind, kind_label) = match kind {
+        AppImageKind::Type1 => ("type1", "Type-1"),
+        AppImageKind::Type2 => ("type2", "Type-2"),
+        AppImageKind::Static => ("static", "Type-3 like (static runtime)"),
+        AppImageKind::Unknown => ("unknown", "unknown"),
+    };
+    // The magic bytes `AI` followed by the AppImage type are stored in the ELF padding
+    let magic_version = (runtime.get(8..10) == Some(b"AI")).then(|| runtime[10]);
+    let interpreter = elf.as_ref().and_then(|elf| elf.interpreter);
+    let architecture = elf.as_ref().map(|elf| match elf.header.e_machine {
+        EM_386 => "i386".to_owned(),
+        machine => machine_to_str(machine).to_lowercase(),
+    });
+    let digest = Sha256::digest(&runtime)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+
+    if let Some(dest) = write {
+        appimage
+            .write_runtime(dest)
+            .unwrap_or_else(|e| out.fail(format!("{}: {}", dest.display(), e)));
+    }
+
+    if out.json {
+        out.print_json(&json!({
+            "type": kind,
+            "static": elf.as_ref().map(|_| interpreter.is_none()),
+            "interpreter": interpreter,
+            "magic_version": magic_version,
+            "architecture": architecture,
+            "size": runtime.len(),
+            "sha256": digest,
+            "written": write.map(|dest| dest.to_string_lossy()),
+        }));
+        return;
+    }
+
+    let linking = match (&elf, interpreter) {
+        (None, _) => "not an ELF executable".to_owned(),
+        (Some(_), Some(interpreter)) => format!("dynamic ({})", interpreter),
+        (Some(_), None) => "static".to_owned(),
+    };
+    let magic_version = magic_version.map_or("none".to_owned(), |version| version.to_string());
+    for (label, value) in [
+        ("Type", kind_label.to_owned()),
+        ("Linking", linking),
+        ("Magic version", magic_version),
+        (
+            "Architecture",
+            architecture.unwrap_or_else(|| "unknown".to_owned()),
+        ),
+        (
+            "Size",
+            format!(
+                "{} ({} bytes)",
+                human_size(runtime.len() as u64),
+                runtime.len()
+            ),
+        ),
+        ("SHA256", digest),
+    ] {
+        log!(out.quiet, "{:<15}{}", format!("{}:", label), value);
+    }
+    if let Some(dest) = write {
+        log!(out.quiet, "Wrote runtime to {}", dest.display());
+    }
+}
@@ -0,0 +1,55 @@
+use serde_json::json;
+use squishy::appimage::{validate::Severity, AppImage};
+
+use crate::{
+    color,
+    output::{Output, EXIT_INVALID, EXIT_PARTIAL},
+};
+
+/// Checks the structure and metadata of an AppImage and prints the issues found,
+/// for use in CI. Exits with 0 if there are none, [`EXIT_PARTIAL`] if there are
+/// only warnings and [`EXIT_INVALID`] if there are errors.
+///
+/// # Arguments
+/// * `appimage` - The AppImage
+/// * `out` - Output settings
+pub fn validate(appimage: &AppImage, out: Output) {
+    let report = appimage.validate().unwrap_or_else(|e| out.fail(e));
+    let errors = report.errors().count();
+    let warnings = report.warnings().count();
+
+    if out.json {
+        let issues = report
+            .issues
+            .iter()
+            .map(|issue| {
+                json!({
+                    "severity": issue.severity.to_string(),
+                    "message": issue.message,
+                })
+            })
+            .collect::<Vec<_>>();
+        out.print_json(&json!({
+            "valid": report.is_valid(),
+            "errors": errors,
+            "warnings": warnings,
+            "issues": issues,
+        }));
+    } else {
+        for issue in &report.issues {
+            let message = match issue.severity {
+                Severity::Error => color::error(issue),
+                Severity::Warning => color::warning(issue),
+            };
+            elog!(out.quiet_errors, "{}", message);
+        }
+        log!(out.silent(), "{} errors, {} warnings", errors, warnings);
+    }
+
+    if errors > 0 {
+        std::process::exit(EXIT_INVALID);
+    }
+    if warnings > 0 {
+        std::process::exit(EXIT_PARTIAL);
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, IsTerminal, Read, Write},
+    path::Path,
+};
+
+use squishy::{EntryKind, SquashFSEntry};
+use tar::{Builder, EntryType, Header};
+
+use crate::output::Output;
+
+/// Number of bytes read from the image at once
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// Reads a file of an image in chunks, so large files aren't held in memory
+struct ChunkReader<F> {
+    /// Reads up to the given number of bytes at the given offset of the file
+    read_range: F,
+    /// Offset of the next chunk
+    position: u64,
+    chunk: Vec<u8>,
+    /// Bytes of the chunk already returned
+    consumed: usize,
+}
+
+impl<F> Read for ChunkReader<F>
+where
+    F: FnMut(u64, u64) -> squishy::Result<Vec<u8>>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.consumed == self.chunk.len() {
+            self.chunk = (self.read_range)(self.position, CHUNK_SIZE).map_err(io::Error::other)?;
+            self.position += self.chunk.len() as u64;
+            self.consumed = 0;
+        }
+        let len = buf.len().min(self.chunk.len() - self.consumed);
+        buf[..len].copy_from_slice(&self.chunk[self.consumed..len + self.consumed]);
+        self.consumed += len;
+        Ok(len)
+    }
+}
+
+/// Writes entries of an image as a tar stream, to a file or stdout
+pub struct TarWriter {
+    builder: Builder<Box<dyn Write>>,
+}
+
+impl TarWriter {
+    /// Creates the tar stream, exiting if it can't be written.
+    ///
+    /// # Arguments
+    /// * `target` - Path of the tar file, or `-` for stdout
+    /// * `out` - Output settings
+    ///
+    /// # Returns
+    /// The writer
+    pub fn create(target: &Path, out: Output) -> Self {
+        let writer: Box<dyn Write> = if target.as_os_str() == "-" {
+            if io::stdout().is_terminal() {
+                out.fail_invalid("Refusing to write a tar stream to a terminal");
+            }
+            Box::new(BufWriter::new(io::stdout().lock()))
+        } else {
+            let file = File::create(target)
+                .unwrap_or_else(|e| out.fail(format!("{}: {}", target.display(), e)));
+            Box::new(BufWriter::new(file))
+        };
+        let mut builder = Builder::new(writer);
+        builder.follow_symlinks(false);
+        Self { builder }
+    }
+
+    /// Adds an entry, reading file contents in chunks.
+    ///
+    /// # Arguments
+    /// * `entry` - The entry
+    /// * `name` - Path of the entry in the tar stream
+    /// * `mode` - Mode to record instead of the one in the image
+    /// * `read_range` - Reads a byte range of the file, like [`AppImage::read_range`](squishy::appimage::AppImage::read_range)
+    ///
+    /// # Returns
+    /// Whether the entry was added, false for entries of unknown kinds, or an error
+    /// if it can't be read or written
+    pub fn append<F>(
+        &mut self,
+        entry: &SquashFSEntry,
+        name: &Path,
+        mode: Option<u32>,
+        read_range: F,
+    ) -> io::Result<bool>
+    where
+        F: FnMut(u64, u64) -> squishy::Result<Vec<u8>>,
+    {
+        let mut header = Self::header(entry, mode);
+        match &entry.kind {
+            EntryKind::File(_) => {
+                header.set_entry_type(EntryType::Regular);
+                header.set_size(entry.size as u64);
+                let reader = ChunkReader {
+                    read_range,
+                    position: 0,
+                    chunk: Vec::new(),
+                    consumed: 0,
+                };
+                self.builder.append_data(&mut header, name, reader)?;
+            }
+            EntryKind::Directory => {
+                header.set_entry_type(EntryType::Directory);
+                self.builder.append_data(&mut header, name, io::empty())?;
+            }
+            EntryKind::Symlink(target) => {
+                header.set_entry_type(EntryType::Symlink);
+                let target = target.strip_prefix("/").unwrap_or(target);
+                self.builder.append_link(&mut header, name, target)?;
+            }
+            EntryKind::Unknown => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Adds a hard link to an entry already in the stream.
+    ///
+    /// # Arguments
+    /// * `entry` - The linked entry
+    /// * `name` - Path of the link in the tar stream
+    /// * `target` - Path of the entry it links to in the tar stream
+    ///
+    /// # Returns
+    /// An empty result, or an error if it can't be written
+    pub fn append_hard_link(
+        &mut self,
+        entry: &SquashFSEntry,
+        name: &Path,
+        target: &Path,
+    ) -> io::Result<()> {
+        let mut header = Self::header(entry, None);
+        header.set_entry_type(EntryType::Link);
+        self.builder.append_link(&mut header, name, target)
+    }
+
+    /// Writes the end of the stream and flushes it.
+    pub fn finish(self) -> io::Result<()> {
+        self.builder.into_inner()?.flush()
+    }
+
+    /// Builds a header with the metadata of the entry, without its type and size.
+    fn header(entry: &SquashFSEntry, mode: Option<u32>) -> Header {
+        let mut header = Header::new_gnu();
+        header.set_mode(mode.unwrap_or(entry.header.permissions as u32));
+        header.set_uid(entry.header.uid as u64);
+        header.set_gid(entry.header.gid as u64);
+        header.set_mtime(entry.header.mtime as u64);
+        header.set_size(0);
+        header
+    }
+}
@@ -0,0 +1,366 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    io::{self, IsTerminal},
+    os::unix,
+    path::{Path, PathBuf},
+};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+use squishy::{appimage::AppImage, EntryKind, SquashFSEntry};
+
+use crate::{format::human_size, list::sorted_entries, output::Output};
+
+/// Number of leading bytes of a file shown in the preview
+const PREVIEW_SIZE: u64 = 64 * 1024;
+/// Number of leading bytes checked for NUL bytes to detect binary files
+const BINARY_CHECK_SIZE: usize = 8000;
+/// Keys shown in the status bar
+const HELP: &str =
+    "↑↓ move  ⏎ open  ← back  space mark  x extract  PgUp/PgDn scroll preview  q quit";
+
+/// State of the file browser
+struct Browser<'a, 'b> {
+    appimage: &'a AppImage<'b>,
+    /// Entries of the image, by path
+    entries: Vec<SquashFSEntry<'a>>,
+    /// Indices of the entries in each directory, directories first then by name
+    children: HashMap<PathBuf, Vec<usize>>,
+    /// Total size of the files in each directory, recursively
+    dir_sizes: HashMap<PathBuf, u64>,
+    /// Directory being browsed
+    dir: PathBuf,
+    list: ListState,
+    /// Entries marked for extraction
+    marked: BTreeSet<usize>,
+    /// Directory extracted entries are written to
+    output_dir: PathBuf,
+    /// Preview of the selected entry, with the index it was made for
+    preview: Option<(usize, String)>,
+    preview_scroll: u16,
+    /// Result of the last action, shown instead of the keys until the next one
+    status: Option<String>,
+}
+
+impl<'a, 'b> Browser<'a, 'b> {
+    fn new(appimage: &'a AppImage<'b>, output_dir: PathBuf) -> Self {
+        let entries = sorted_entries(appimage);
+        let mut children: HashMap<PathBuf, Vec<usize>> = HashMap::new();
+        let mut dir_sizes: HashMap<PathBuf, u64> = HashMap::new();
+        for (index, entry) in entries.iter().enumerate() {
+            if let Some(parent) = entry.path.parent() {
+                children
+                    .entry(parent.to_path_buf())
+                    .or_default()
+                    .push(index);
+            }
+            if matches!(entry.kind, EntryKind::File(_)) {
+                for dir in entry.path.ancestors().skip(1) {
+                    *dir_sizes.entry(dir.to_path_buf()).or_default() += entry.size as u64;
+                }
+            }
+        }
+        for indices in children.values_mut() {
+            indices.sort_by_key(|&index| entries[index].kind != EntryKind::Directory);
+        }
+
+        let mut browser = Self {
+            appimage,
+            entries,
+            children,
+            dir_sizes,
+            dir: PathBuf::from("/"),
+            list: ListState::default(),
+            marked: BTreeSet::new(),
+            output_dir,
+            preview: None,
+            preview_scroll: 0,
+            status: None,
+        };
+        browser.open(PathBuf::from("/"), None);
+        browser
+    }
+
+    /// Indices of the entries in the browsed directory.
+    fn shown(&self) -> &[usize] {
+        self.children.get(&self.dir).map_or(&[], Vec::as_slice)
+    }
+
+    /// Index of the selected entry.
+    fn selected(&self) -> Option<usize> {
+        self.list
+            .selected()
+            .and_then(|position| self.shown().get(position).copied())
+    }
+
+    /// Browses a directory.
+    ///
+    /// # Arguments
+    /// * `dir` - The directory
+    /// * `select` - Path of the entry to select, the first one by default
+    fn open(&mut self, dir: PathBuf, select: Option<&Path>) {
+        self.dir = dir;
+        let position = select
+            .and_then(|path| {
+                self.shown()
+                    .iter()
+                    .position(|&index| self.entries[index].path == path)
+            })
+            .unwrap_or(0);
+        self.list
+            .select((!self.shown().is_empty()).then_some(position));
+    }
+
+    /// Size shown for an entry: the total size of the files below directories.
+    fn size(&self, entry: &SquashFSEntry) -> u64 {
+        match entry.kind {
+            EntryKind::Directory => self.dir_sizes.get(&entry.path).copied().unwrap_or(0),
+            _ => entry.size as u64,
+        }
+    }
+
+    /// Builds the preview of an entry: the start of text files, the target of
+    /// symlinks and a summary of directories and binary files.
+    fn make_preview(&self, index: usize) -> String {
+        let entry = &self.entries[index];
+        let resolved = match entry.kind {
+            EntryKind::Symlink(_) => self.appimage.resolve_symlink(entry).ok().flatten(),
+            _ => None,
+        };
+        let mut preview = match &entry.kind {
+            EntryKind::Symlink(target) => format!("→ {}\n\n", target.display()),
+            _ => String::new(),
+        };
+        let target = resolved.as_ref().unwrap_or(entry);
+        match target.kind {
+            EntryKind::Directory => {
+                let count = self.children.get(&target.path).map_or(0, Vec::len);
+                preview.push_str(&format!(
+                    "Directory, {} entries, {}",
+                    count,
+                    human_size(self.size(target))
+                ));
+            }
+            EntryKind::File(_) => match self.appimage.read_range(target, 0, PREVIEW_SIZE) {
+                Ok(data) if data[..data.len().min(BINARY_CHECK_SIZE)].contains(&0) => {
+                    preview.push_str(&format!("Binary file, {}", human_size(target.size as u64)));
+                }
+                Ok(data) => {
+                    preview.push_str(&String::from_utf8_lossy(&data).replace('\t', "    "));
+                }
+                Err(e) => preview.push_str(&format!("Failed to read: {}", e)),
+            },
+            _ => {}
+        }
+        preview
+    }
+
+    /// Writes an entry, and everything below it if it is a directory, to the
+    /// output directory under its name.
+    ///
+    /// # Returns
+    /// The number of entries written, or an error if one can't be written
+    fn extract(&self, index: usize) -> squishy::Result<usize> {
+        let root = &self.entries[index].path;
+        let base = root.parent().unwrap_or(Path::new("/"));
+        let mut written = 0;
+        for entry in &self.entries {
+            if !entry.path.starts_with(root) {
+                continue;
+            }
+            let output = self.output_dir.join(entry.path.strip_prefix(base).unwrap());
+            match &entry.kind {
+                EntryKind::Directory => fs::create_dir_all(&output)?,
+                EntryKind::File(_) => {
+                    self.appimage
+                        .write(entry, output.parent().unwrap(), None, true)?;
+                }
+                EntryKind::Symlink(target) => {
+                    fs::create_dir_all(output.parent().unwrap())?;
+                    if output.symlink_metadata().is_ok() {
+                        fs::remove_file(&output)?;
+                    }
+                    unix::fs::symlink(target.strip_prefix("/").unwrap_or(target), &output)?;
+                }
+                EntryKind::Unknown => continue,
+            }
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Extracts the marked entries, or the selected one if none are marked.
+    fn extract_marked(&mut self) {
+        let indices = if self.marked.is_empty() {
+            self.selected().into_iter().collect()
+        } else {
+            std::mem::take(&mut self.marked)
+        };
+        let mut written = 0;
+        for index in indices {
+            match self.extract(index) {
+                Ok(count) => written += count,
+                Err(e) => {
+                    self.status = Some(format!(
+                        "Failed to extract {}: {}",
+                        self.entries[index].path.display(),
+                        e
+                    ));
+                    return;
+                }
+            }
+        }
+        self.status = Some(format!(
+            "Extracted {} entries to {}",
+            written,
+            self.output_dir.display()
+        ));
+    }
+
+    /// Handles a key press.
+    ///
+    /// # Returns
+    /// Whether to quit
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        self.status = None;
+        let count = self.shown().len();
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return true,
+            KeyCode::Down | KeyCode::Char('j') => self.list.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.list.select_previous(),
+            KeyCode::Home | KeyCode::Char('g') => self.list.select_first(),
+            KeyCode::End | KeyCode::Char('G') => self.list.select(count.checked_sub(1)),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                if let Some(index) = self.selected() {
+                    if self.entries[index].kind == EntryKind::Directory {
+                        self.open(self.entries[index].path.clone(), None);
+                    }
+                }
+            }
+            KeyCode::Left | KeyCode::Backspace | KeyCode::Char('h') => {
+                if let Some(parent) = self.dir.parent().map(Path::to_path_buf) {
+                    let previous = self.dir.clone();
+                    self.open(parent, Some(&previous));
+                }
+            }
+            KeyCode::Char(' ') => {
+                if let Some(index) = self.selected() {
+                    if !self.marked.remove(&index) {
+                        self.marked.insert(index);
+                    }
+                    self.list.select_next();
+                }
+            }
+            KeyCode::Char('x') => self.extract_marked(),
+            KeyCode::PageDown => self.preview_scroll = self.preview_scroll.saturating_add(10),
+            KeyCode::PageUp => self.preview_scroll = self.preview_scroll.saturating_sub(10),
+            _ => {}
+        }
+        if count > 0
+            && self
+                .list
+                .selected()
+                .is_some_and(|position| position >= count)
+        {
+            self.list.select(Some(count - 1));
+        }
+        false
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [files, preview] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(main);
+
+        let items = self
+            .shown()
+            .iter()
+            .map(|&index| {
+                let entry = &self.entries[index];
+                let mut name = entry
+                    .path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned();
+                let style = match entry.kind {
+                    EntryKind::Directory => {
+                        name.push('/');
+                        Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD)
+                    }
+                    EntryKind::Symlink(_) => Style::new().fg(Color::Cyan),
+                    _ => Style::new(),
+                };
+                let mark = if self.marked.contains(&index) {
+                    '*'
+                } else {
+                    ' '
+                };
+                let size = human_size(self.size(entry));
+                ListItem::new(Line::from(format!("{} {:>8}  {}", mark, size, name))).style(style)
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(Block::bordered().title(format!(" {} ", self.dir.display())))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, files, &mut self.list);
+
+        let selected = self.selected();
+        if selected != self.preview.as_ref().map(|(index, _)| *index) {
+            self.preview = selected.map(|index| (index, self.make_preview(index)));
+            self.preview_scroll = 0;
+        }
+        let text = self.preview.as_ref().map_or("", |(_, text)| text.as_str());
+        let preview_widget = Paragraph::new(text)
+            .block(Block::bordered().title(" Preview "))
+            .scroll((self.preview_scroll, 0));
+        frame.render_widget(preview_widget, preview);
+
+        let status_text = match &self.status {
+            Some(status) => status.clone(),
+            None if self.marked.is_empty() => HELP.to_owned(),
+            None => format!("{} marked  {}", self.marked.len(), HELP),
+        };
+        frame.render_widget(Line::from(status_text).dim(), status);
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && self.handle_key(key.code) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Opens an interactive file browser on the image, with sizes, a preview of the
+/// selected file and keys to extract entries.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `output_dir` - Directory to extract entries to
+/// * `out` - Output settings
+pub fn browse(appimage: &AppImage, output_dir: PathBuf, out: Output) {
+    if !io::stdout().is_terminal() {
+        out.fail_invalid("browse needs a terminal");
+    }
+    let mut browser = Browser::new(appimage, output_dir);
+    let mut terminal = ratatui::init();
+    let result = browser.run(&mut terminal);
+    ratatui::restore();
+    if let Err(e) = result {
+        out.fail(e);
+    }
+}
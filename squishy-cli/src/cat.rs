@@ -0,0 +1,64 @@
+use std::{
+    io::{ErrorKind, Write},
+    path::Path,
+};
+
+use squishy::{appimage::AppImage, error::SquishyError, EntryKind};
+
+use crate::{find_entry, output::Output};
+
+/// Number of bytes read from the image at once
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// Writes the contents of a file in the image to stdout, resolving symlinks.
+/// The file is streamed in chunks, so large files aren't held in memory, and
+/// only the blocks covering the requested byte range are read.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `path` - Path of the file in the image
+/// * `skip` - Number of bytes to skip at the start of the file
+/// * `length` - Maximum number of bytes to write after the skipped bytes, None for
+///   the rest of the file
+/// * `out` - Output settings
+pub fn cat(appimage: &AppImage, path: &Path, skip: u64, length: Option<u64>, out: Output) {
+    let entry = find_entry(appimage, path, out);
+    let path = entry.path.clone();
+    let entry = match entry.kind {
+        EntryKind::Symlink(_) => appimage
+            .resolve_symlink(&entry)
+            .unwrap_or_else(|e| out.fail(e))
+            .unwrap_or_else(|| out.fail(format!("{}: Dangling symlink", path.display()))),
+        _ => entry,
+    };
+    if !matches!(entry.kind, EntryKind::File(_)) {
+        out.fail(SquishyError::NotAFile(path));
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    let end = length.map_or(entry.size as u64, |length| {
+        skip.saturating_add(length).min(entry.size as u64)
+    });
+    let mut position = skip;
+    while position < end {
+        let chunk = appimage
+            .read_range(&entry, position, CHUNK_SIZE.min(end - position))
+            .unwrap_or_else(|e| out.fail(e));
+        if chunk.is_empty() {
+            break;
+        }
+        if let Err(e) = stdout.write_all(&chunk) {
+            // The reader went away, e.g. `squishy cat ... | head`
+            if e.kind() == ErrorKind::BrokenPipe {
+                return;
+            }
+            out.fail(e);
+        }
+        position += chunk.len() as u64;
+    }
+    if let Err(e) = stdout.flush() {
+        if e.kind() != ErrorKind::BrokenPipe {
+            out.fail(e);
+        }
+    }
+}
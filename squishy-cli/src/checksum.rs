@@ -0,0 +1,126 @@
+use rayon::iter::ParallelIterator;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256, Sha512};
+use squishy::{
+    appimage::{
+        filter::{Filter, FilterMode, Pattern, PatternKind},
+        AppImage,
+    },
+    EntryKind, SquashFSEntry,
+};
+
+use crate::{
+    cli::ChecksumAlgo,
+    color,
+    output::{Output, EXIT_FAILURE},
+};
+
+/// Number of bytes read from the image at once
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// Computes the digest of a file of the image, reading it in chunks.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `entry` - The file entry
+///
+/// # Returns
+/// The hex encoded digest, or an error if the file can't be read
+pub fn file_digest<D: Digest>(
+    appimage: &AppImage,
+    entry: &SquashFSEntry,
+) -> squishy::Result<String> {
+    let mut hasher = D::new();
+    let mut position = 0;
+    while position < entry.size as u64 {
+        let chunk = appimage.read_range(entry, position, CHUNK_SIZE)?;
+        if chunk.is_empty() {
+            break;
+        }
+        hasher.update(&chunk);
+        position += chunk.len() as u64;
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Formats a digest line like `sha256sum`: paths containing a backslash or a
+/// newline are escaped and the line is prefixed with a backslash.
+///
+/// # Arguments
+/// * `digest` - The hex encoded digest
+/// * `path` - The path, relative to the image root
+/// * `escape` - Whether to escape the path; `--print0` output is never escaped
+///
+/// # Returns
+/// The digest line
+fn digest_line(digest: &str, path: &str, escape: bool) -> String {
+    if escape && path.contains(['\\', '\n']) {
+        let path = path.replace('\\', "\\\\").replace('\n', "\\n");
+        format!("\\{}  {}", digest, path)
+    } else {
+        format!("{}  {}", digest, path)
+    }
+}
+
+/// Prints the digests of the regular files of the image in `sha256sum` format,
+/// with paths relative to the image root so the output can be checked against an
+/// extracted tree with `sha256sum -c`.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `patterns` - Globs the paths must match, all files if empty
+/// * `algo` - Digest algorithm
+/// * `out` - Output settings
+pub fn checksum(appimage: &AppImage, patterns: &[String], algo: ChecksumAlgo, out: Output) {
+    let filter = patterns
+        .iter()
+        .fold(Filter::new(FilterMode::Any), |filter, pattern| {
+            filter.include(Pattern::new(PatternKind::Glob, pattern).unwrap_or_else(|e| out.fail(e)))
+        });
+
+    let mut digests = appimage
+        .par_entries()
+        .filter(|entry| matches!(entry.kind, EntryKind::File(_)) && filter.matches_entry(entry))
+        .map(|entry| {
+            let digest = match algo {
+                ChecksumAlgo::Sha256 => file_digest::<Sha256>(appimage, &entry),
+                ChecksumAlgo::Sha512 => file_digest::<Sha512>(appimage, &entry),
+            };
+            (entry.path, digest)
+        })
+        .collect::<Vec<_>>();
+    digests.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut failed = false;
+    let mut results = Vec::new();
+    for (path, digest) in digests {
+        let path = path.strip_prefix("/").unwrap_or(&path).to_string_lossy();
+        match digest {
+            Ok(digest) if out.json => results.push(json!({ "path": path, "digest": digest })),
+            Ok(digest) => out.record(digest_line(&digest, &path, !out.print0)),
+            Err(e) => {
+                failed = true;
+                if out.json {
+                    results.push(json!({ "path": path, "error": e.to_string() }));
+                } else {
+                    elog!(
+                        out.quiet_errors,
+                        "{}",
+                        color::error(format!("{}: {}", path, e))
+                    );
+                }
+            }
+        }
+    }
+
+    if out.json {
+        out.print_json(&Value::Array(results));
+    }
+    if failed {
+        std::process::exit(EXIT_FAILURE);
+    }
+}
@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{builder::BoolishValueParser, ArgAction, Parser, Subcommand, ValueEnum};
+
+use crate::owner::{parse_owner, Owner};
 
 #[derive(Parser)]
 #[command(
@@ -18,8 +20,55 @@ pub struct Args {
     #[clap(subcommand)]
     pub command: Commands,
 
-    #[clap(required = false, long, short)]
-    pub quiet: bool,
+    /// Hide informational output, and error messages too when given twice (-qq)
+    #[clap(required = false, long, short, global = true, action = ArgAction::Count, env = "SQUISHY_QUIET", value_parser = parse_level)]
+    pub quiet: u8,
+
+    /// Print details of what is done on stderr, more when given twice (-vv)
+    #[clap(required = false, long, short, global = true, action = ArgAction::Count, env = "SQUISHY_VERBOSE", value_parser = parse_level)]
+    pub verbose: u8,
+
+    /// Also append every log and error message to the file, whatever the verbosity
+    #[clap(required = false, long, global = true, env = "SQUISHY_LOG_FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// Print machine-readable JSON results instead of log lines
+    #[clap(required = false, long, global = true, env = "SQUISHY_JSON", value_parser = BoolishValueParser::new())]
+    pub json: bool,
+
+    /// Terminate listed entries with a NUL byte instead of a newline, for `xargs -0`
+    #[clap(required = false, long, global = true)]
+    pub print0: bool,
+
+    /// Format of listed entries
+    #[clap(required = false, long, global = true, value_enum, default_value_t = OutputFormat::Text, env = "SQUISHY_FORMAT")]
+    pub format: OutputFormat,
+
+    /// When to color listings and error messages
+    #[clap(required = false, long, global = true, value_enum, default_value_t = ColorChoice::Auto, env = "SQUISHY_COLOR")]
+    pub color: ColorChoice,
+
+    /// Number of threads to use for listing, extraction and other parallel
+    /// operations. 0 uses one per CPU, the default
+    #[clap(
+        required = false,
+        long,
+        short = 'j',
+        global = true,
+        env = "SQUISHY_THREADS"
+    )]
+    pub threads: Option<usize>,
+
+    /// Read defaults from this file instead of ~/.config/squishy/config.toml.
+    /// Options given on the command line take precedence
+    #[clap(
+        required = false,
+        long,
+        global = true,
+        value_name = "PATH",
+        env = "SQUISHY_CONFIG"
+    )]
+    pub config: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -27,55 +76,819 @@ pub enum Commands {
     /// AppImage specific tasks
     #[command(arg_required_else_help = true)]
     #[clap(name = "appimage", alias = "ai")]
-    AppImage {
-        /// Path to appimage file
+    AppImage(AppImageArgs),
+
+    /// List the entries of an image like `ls -l`
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "list", alias = "ls")]
+    List {
+        /// Path to AppImage or squashfs file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Offset
+        #[arg(required = false, long, short)]
+        offset: Option<u64>,
+    },
+
+    /// Show the entries of an image as a tree, with directory size totals
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "tree")]
+    Tree {
+        /// Path to AppImage or squashfs file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Directory in the image to start from
+        #[arg(required = false)]
+        path: Option<PathBuf>,
+
+        /// Offset
+        #[arg(required = false, long, short)]
+        offset: Option<u64>,
+    },
+
+    /// Print the total size of the files in each directory of an image, largest
+    /// first
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "du")]
+    Du {
+        /// Path to AppImage or squashfs file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Directory in the image to start from
+        #[arg(required = false)]
+        path: Option<PathBuf>,
+
+        /// Also print the compressed sizes, as stored in the image, and sort by them
+        #[arg(required = false, long, short)]
+        compressed: bool,
+
+        /// Only print directories up to this depth below the start directory
+        #[arg(required = false, long, short = 'd')]
+        max_depth: Option<usize>,
+
+        /// Print sizes in bytes
+        #[arg(required = false, long, short)]
+        bytes: bool,
+
+        /// Offset
+        #[arg(required = false, long, short)]
+        offset: Option<u64>,
+    },
+
+    /// Print the largest files of an image, with their share of the total size
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "top")]
+    Top {
+        /// Path to AppImage or squashfs file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Number of files to print
+        #[arg(required = false, short = 'n', long, default_value_t = 10)]
+        count: usize,
+
+        /// Rank files by compressed size, as stored in the image
+        #[arg(required = false, long, short)]
+        compressed: bool,
+
+        /// Print sizes in bytes
+        #[arg(required = false, long, short)]
+        bytes: bool,
+
+        /// Offset
+        #[arg(required = false, long, short)]
+        offset: Option<u64>,
+    },
+
+    /// Write the contents of a file in an image to stdout, resolving symlinks
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "cat")]
+    Cat {
+        /// Path to AppImage or squashfs file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Path of the file in the image
+        #[arg(required = true)]
+        path: PathBuf,
+
+        /// Number of bytes to skip at the start of the file. It is --skip rather
+        /// than --offset, which gives the offset of the image like in the other commands
+        #[arg(required = false, long, default_value_t = 0)]
+        skip: u64,
+
+        /// Maximum number of bytes to write, counted from --skip
+        #[arg(required = false, long)]
+        length: Option<u64>,
+
+        /// Offset of the image in the file, not of the bytes to write
+        #[arg(required = false, long, short)]
+        offset: Option<u64>,
+    },
+
+    /// Show the metadata of a single entry of an image, without following symlinks
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "stat")]
+    Stat {
+        /// Path to AppImage or squashfs file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Path of the entry in the image
+        #[arg(required = true)]
+        path: PathBuf,
+
+        /// Offset
+        #[arg(required = false, long, short)]
+        offset: Option<u64>,
+    },
+
+    /// Show the filesystem details of an image: compression, block size,
+    /// inode and fragment counts, creation time and offset
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "info")]
+    Info {
+        /// Path to AppImage or squashfs file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Offset
+        #[arg(required = false, long, short)]
+        offset: Option<u64>,
+    },
+
+    /// Check that every file of an image decompresses intact, exiting non-zero
+    /// with the list of damaged files otherwise
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "verify")]
+    Verify {
+        /// Path to AppImage or squashfs file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Offset
+        #[arg(required = false, long, short)]
+        offset: Option<u64>,
+    },
+
+    /// Print the digests of the files of an image in `sha256sum` format
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "checksum")]
+    Checksum {
+        /// Path to AppImage or squashfs file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Only include files matching these globs, e.g. 'usr/lib/**'
+        #[arg(required = false)]
+        patterns: Vec<String>,
+
+        /// Digest algorithm
+        #[arg(required = false, long, value_enum, default_value_t = ChecksumAlgo::Sha256)]
+        algo: ChecksumAlgo,
+
+        /// Offset
+        #[arg(required = false, long, short)]
+        offset: Option<u64>,
+    },
+
+    /// Search the contents of the files of an image, printing the matching lines
+    /// as `path:line`, and exiting with 1 if nothing matches
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "grep")]
+    Grep {
+        /// Path to AppImage or squashfs file
         #[arg(required = true)]
         file: PathBuf,
 
+        /// Regular expression to search for
+        #[arg(required = true)]
+        pattern: String,
+
+        /// Only search files matching these globs, e.g. 'usr/share/**'
+        #[arg(required = false)]
+        patterns: Vec<String>,
+
+        /// Match case-insensitively
+        #[arg(required = false, long, short)]
+        ignore_case: bool,
+
+        /// Search for the pattern as a literal string
+        #[arg(required = false, long, short = 'F')]
+        fixed_strings: bool,
+
+        /// Print the line numbers of the matches
+        #[arg(required = false, long, short = 'n')]
+        line_number: bool,
+
         /// Offset
         #[arg(required = false, long, short)]
         offset: Option<u64>,
+    },
+
+    /// Search a file for SquashFS superblocks, printing the offset of each to pass
+    /// as `--offset`
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "scan")]
+    Scan {
+        /// Path to the file to search
+        #[arg(required = true)]
+        file: PathBuf,
+    },
+
+    /// Show the entries added, removed and changed between two images, exiting
+    /// with 1 if they differ
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "diff")]
+    Diff {
+        /// Path to the old AppImage or squashfs file
+        #[arg(required = true)]
+        old: PathBuf,
+
+        /// Path to the new AppImage or squashfs file
+        #[arg(required = true)]
+        new: PathBuf,
+    },
+
+    /// Mount an image read-only through FUSE, until it is unmounted
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "mount")]
+    Mount {
+        /// Path to AppImage or squashfs file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Directory to mount the image at
+        #[arg(required = true)]
+        mountpoint: PathBuf,
 
-        /// Filter to apply
+        /// Keep running in the foreground, and unmount on Ctrl+C
         #[arg(required = false, long, short)]
-        filter: Option<String>,
+        foreground: bool,
 
-        /// Whether to search for icon
+        /// Offset
         #[arg(required = false, long, short)]
-        icon: bool,
+        offset: Option<u64>,
+    },
+
+    /// Serve the entries of an image read-only over HTTP, with range requests and
+    /// JSON directory indexes
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "serve")]
+    Serve {
+        /// Path to AppImage or squashfs file
+        #[arg(required = true)]
+        file: PathBuf,
 
-        /// Whether to search for desktop file
+        /// Port to listen on
+        #[arg(required = false, long, short, default_value_t = 8080)]
+        port: u16,
+
+        /// Address to listen on, e.g. 0.0.0.0 to accept connections from other machines
+        #[arg(required = false, long, default_value = "127.0.0.1")]
+        bind: String,
+
+        /// Offset
         #[arg(required = false, long, short)]
-        desktop: bool,
+        offset: Option<u64>,
+    },
 
-        /// Whether to search for appstream file
+    /// Browse an image interactively, with sizes, a preview of text files and keys
+    /// to extract entries
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "browse")]
+    Browse {
+        /// Path to AppImage or squashfs file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Directory to extract entries to, the current directory by default
         #[arg(required = false, long, short)]
-        appstream: bool,
+        write: Option<PathBuf>,
 
-        /// Whether to write files to disk
+        /// Offset
         #[arg(required = false, long, short)]
-        write: Option<Option<PathBuf>>,
+        offset: Option<u64>,
+    },
 
-        /// Whether to extract the file with the original name from the squashfs inside the AppImage
-        #[arg(required = false, long = "original-name")]
-        original_name: bool,
+    /// Watch a directory and install or remove the desktop integration of the
+    /// AppImages added to or removed from it, until interrupted
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "watch")]
+    Watch {
+        /// Directory to watch
+        #[arg(required = true)]
+        dir: PathBuf,
 
-        /// Copy permissions from the squashfs entry
+        /// Data directory to install the integration files to, instead of
+        /// $XDG_DATA_HOME or ~/.local/share
+        #[arg(required = false, long)]
+        data_dir: Option<PathBuf>,
+    },
+
+    /// Extract or list the contents of a squashfs image
+    Unsquashfs(UnsquashfsArgs),
+
+    /// Generate man pages for squishy and all its subcommands
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "manpages")]
+    Manpages {
+        /// Directory to write the man pages to
+        #[arg(required = true)]
+        dir: PathBuf,
+    },
+}
+
+#[derive(clap::Args)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct AppImageArgs {
+    #[command(subcommand)]
+    pub command: Option<AppImageCommand>,
+
+    /// Path to appimage file
+    #[arg(required = true)]
+    pub file: Option<PathBuf>,
+
+    /// Offset
+    #[arg(required = false, long, short)]
+    pub offset: Option<u64>,
+
+    #[command(flatten)]
+    pub filters: FilterArgs,
+
+    /// Whether to search for icon
+    #[arg(required = false, long, short)]
+    pub icon: bool,
+
+    /// Whether to search for desktop file
+    #[arg(required = false, long, short)]
+    pub desktop: bool,
+
+    /// Whether to search for appstream file
+    #[arg(required = false, long, short)]
+    pub appstream: bool,
+
+    /// Whether to search for the AppRun entry point. It is written with its
+    /// permissions, so it stays executable
+    #[arg(required = false, long)]
+    pub apprun: bool,
+
+    /// Pick the icon closest to this pixel size, read from icon theme directories
+    /// like 256x256, instead of the largest one
+    #[arg(
+        required = false,
+        long,
+        value_name = "SIZE",
+        conflicts_with = "hicolor"
+    )]
+    pub icon_size: Option<u32>,
+
+    /// Only pick icons of this format
+    #[arg(required = false, long, value_enum, default_value_t = IconFormatChoice::Any, conflicts_with = "hicolor")]
+    pub icon_format: IconFormatChoice,
+
+    /// Search for the icon, desktop file and appstream file, like -i -d -a, and
+    /// summarize which were found
+    #[arg(required = false, long)]
+    pub all: bool,
+
+    /// Whether to write files to disk
+    #[arg(required = false, long, short)]
+    pub write: Option<Option<PathBuf>>,
+
+    /// Report what --write would write, and which outputs already exist, without writing anything
+    #[arg(required = false, long, requires = "write")]
+    pub dry_run: bool,
+
+    /// Print the contents of the found file to stdout instead of its path. Only one
+    /// of --icon, --desktop, --appstream or --apprun can be given
+    #[arg(required = false, long, conflicts_with_all = ["write", "to_tar"])]
+    pub cat: bool,
+
+    /// Write the found files as a tar stream to the file, or to stdout if the file is
+    /// - or omitted, instead of to a directory
+    #[arg(required = false, long, value_name = "FILE|-", num_args = 0..=1, default_missing_value = "-", conflicts_with_all = ["write", "rewrite_desktop"])]
+    pub to_tar: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub existing: ExistingArgs,
+
+    /// Whether to extract the file with the original name from the squashfs inside the AppImage
+    #[arg(required = false, long = "original-name")]
+    pub original_name: bool,
+
+    /// Name the written files after this template instead of the AppImage, e.g.
+    /// '{id}.{ext}'. Placeholders: {appname}, {id}, {version}, {kind} (icon, desktop
+    /// or appstream), {name} and {ext} (of the original file)
+    #[arg(required = false, long, value_name = "TEMPLATE", conflicts_with_all = ["original_name", "hicolor"])]
+    pub name_template: Option<String>,
+
+    /// Copy permissions from the squashfs entry
+    #[arg(required = false, long)]
+    pub copy_permissions: bool,
+
+    /// Write all icon sizes in the hicolor/<size>/apps layout, keeping their names
+    #[arg(required = false, long)]
+    pub hicolor: bool,
+
+    /// Rewrite Exec, TryExec and Icon of the written desktop file to point at the AppImage and the extracted icon
+    #[arg(required = false, long)]
+    pub rewrite_desktop: bool,
+
+    /// Validate the AppImage structure and metadata, exiting with 2 if it isn't valid
+    /// like the validate subcommand
+    #[arg(required = false, long)]
+    pub validate: bool,
+
+    /// Verify the embedded signature, exiting with an error if it isn't valid
+    #[arg(required = false, long)]
+    pub verify: bool,
+}
+
+/// Options filtering the paths of an AppImage
+#[derive(clap::Args)]
+pub struct FilterArgs {
+    /// Filter to apply. Can be repeated, matching paths matching any of them
+    #[arg(required = false, long, short)]
+    pub filter: Vec<String>,
+
+    /// How filters match paths
+    #[arg(required = false, long, value_enum, default_value_t = FilterKind::Substring)]
+    pub filter_kind: FilterKind,
+
+    /// Only match paths matching all the filters
+    #[arg(required = false, long)]
+    pub filter_all: bool,
+
+    /// Skip paths matching the pattern. Can be repeated
+    #[arg(required = false, long)]
+    pub filter_not: Vec<String>,
+}
+
+/// Subcommands of `appimage`, used instead of its file argument
+#[derive(Subcommand)]
+pub enum AppImageCommand {
+    /// List the contents of the AppImage payload, like `list`, keeping only the
+    /// paths matching the filters
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "list")]
+    List {
+        /// Path to appimage file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        #[command(flatten)]
+        filters: FilterArgs,
+
+        /// Offset
+        #[arg(required = false, long, short)]
+        offset: Option<u64>,
+    },
+
+    /// Write the payload files matching the globs, keeping their paths, e.g.
+    /// 'usr/share/applications/*'. A matching directory selects everything below it
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "extract")]
+    Extract {
+        /// Path to appimage file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Globs of the paths to extract, relative to the AppImage root
+        #[arg(required = true)]
+        globs: Vec<String>,
+
+        /// Directory to write the files to, the current directory by default
+        #[arg(required = false, long, short)]
+        write: Option<PathBuf>,
+
+        #[command(flatten)]
+        existing: ExistingArgs,
+
+        /// Copy permissions from the squashfs entries
         #[arg(required = false, long)]
         copy_permissions: bool,
+
+        /// Offset
+        #[arg(required = false, long, short)]
+        offset: Option<u64>,
     },
 
-    Unsquashfs {
-        /// Path to squashfs file
+    /// Print the Exec value of the desktop file, the command the application runs
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "exec")]
+    Exec {
+        /// Path to appimage file
         #[arg(required = true)]
         file: PathBuf,
 
+        /// Remove the field codes, like %U, and print the command quoted for a shell
+        #[arg(required = false, long)]
+        strip_field_codes: bool,
+
         /// Offset
         #[arg(required = false, long, short)]
         offset: Option<u64>,
+    },
+
+    /// Report the runtime of an AppImage: its type, linking, magic version,
+    /// architecture, size and digest
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "runtime")]
+    Runtime {
+        /// Path to appimage file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Also write the runtime ELF to this path, marked executable
+        #[arg(required = false, long, short)]
+        write: Option<PathBuf>,
 
-        /// Whether to write files to disk
+        /// Offset
         #[arg(required = false, long, short)]
-        write: Option<Option<PathBuf>>,
+        offset: Option<u64>,
     },
+
+    /// Print the name, version, id, categories, icons, appstream summary and update
+    /// information of an AppImage as JSON
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "metadata")]
+    Metadata {
+        /// Path to appimage file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Offset
+        #[arg(required = false, long, short)]
+        offset: Option<u64>,
+    },
+
+    /// Install the desktop integration of an AppImage: its desktop file, rewritten
+    /// to launch it, icons, appstream file and MIME packages
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "integrate")]
+    Integrate {
+        /// Path to appimage file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Data directory to install the files to, instead of $XDG_DATA_HOME or
+        /// ~/.local/share
+        #[arg(required = false, long)]
+        data_dir: Option<PathBuf>,
+
+        /// Offset
+        #[arg(required = false, long, short)]
+        offset: Option<u64>,
+    },
+
+    /// Check the structure and metadata of an AppImage, exiting with 1 if there
+    /// are warnings and 2 if there are errors
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "validate")]
+    Validate {
+        /// Path to appimage file
+        #[arg(required = true)]
+        file: PathBuf,
+
+        /// Offset
+        #[arg(required = false, long, short)]
+        offset: Option<u64>,
+    },
+
+    /// Remove the desktop integration installed for an AppImage
+    #[command(arg_required_else_help = true)]
+    #[clap(name = "unintegrate")]
+    Unintegrate {
+        /// Path the AppImage was integrated from, which doesn't need to exist
+        /// anymore, or its integration id
+        #[arg(required = true, value_name = "FILE|ID")]
+        target: PathBuf,
+
+        /// Data directory the files were installed to, instead of $XDG_DATA_HOME or
+        /// ~/.local/share
+        #[arg(required = false, long)]
+        data_dir: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Args)]
+pub struct UnsquashfsArgs {
+    /// Path to squashfs file, or - to read it from stdin
+    #[arg(required = true)]
+    pub file: PathBuf,
+
+    /// Only list or extract paths matching these globs, e.g. 'usr/bin/*'. A
+    /// matching directory selects everything below it
+    #[arg(required = false)]
+    pub patterns: Vec<String>,
+
+    /// Skip paths matching the glob, and everything below matching directories (can be repeated)
+    #[arg(required = false, long)]
+    pub exclude: Vec<String>,
+
+    /// Read exclude globs from a file, one per line. Empty lines and lines starting with `#` are ignored
+    #[arg(required = false, long)]
+    pub exclude_from: Option<PathBuf>,
+
+    /// Match patterns like upstream unsquashfs: per path component, `*` skips
+    /// hidden names and a leading `...` matches at any depth
+    #[arg(required = false, long)]
+    pub wildcards: bool,
+
+    /// Remove this many leading directories from the paths of the written files,
+    /// skipping entries that don't have that many
+    #[arg(required = false, long, value_name = "N", default_value_t = 0)]
+    pub strip_components: usize,
+
+    /// Offset
+    #[arg(required = false, long, short)]
+    pub offset: Option<u64>,
+
+    /// Whether to write files to disk
+    #[arg(required = false, long, short)]
+    pub write: Option<Option<PathBuf>>,
+
+    /// Report what --write would write, and which outputs already exist, without writing anything
+    #[arg(required = false, long, requires = "write")]
+    pub dry_run: bool,
+
+    /// Write the selected entries as a tar stream to the file, or to stdout if the
+    /// file is - or omitted, instead of extracting them
+    #[arg(required = false, long, value_name = "FILE|-", num_args = 0..=1, default_missing_value = "-", conflicts_with = "write")]
+    pub to_tar: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub existing: ExistingArgs,
+
+    /// Keep extracting when an entry can't be written, then report the failures
+    /// and exit with an error, instead of stopping at the first failure
+    #[arg(required = false, long)]
+    pub keep_going: bool,
+
+    /// Only write the entries missing from the output or changed since it was
+    /// written, comparing the size and modification time of files. Implies
+    /// --preserve-mtime, so a later run can compare them. Outputs that changed are
+    /// replaced, so it can't be combined with --force, --skip-existing or
+    /// --backup-existing
+    #[arg(required = false, long, conflicts_with_all = ["force", "skip_existing", "backup_existing"])]
+    pub resume: bool,
+
+    /// Write the entries one at a time in path order, with modes normalized to 755
+    /// or 644 and modification times set to SOURCE_DATE_EPOCH, or 0 if unset, so
+    /// extracting the same image always gives the same tree
+    #[arg(required = false, long, conflicts_with_all = ["resume", "preserve_mtime", "dmode", "fmode", "no_perms"])]
+    pub reproducible: bool,
+
+    /// Give the written entries the uid and gid recorded in the image (root only)
+    #[arg(required = false, long, conflicts_with = "owner")]
+    pub preserve_owner: bool,
+
+    /// Give the written entries this owner, as user[:group] names or ids
+    #[arg(required = false, long, value_name = "USER[:GROUP]", value_parser = parse_owner)]
+    pub owner: Option<Owner>,
+
+    /// Write the extended attributes stored in the image, such as file capabilities,
+    /// to the written entries
+    #[arg(required = false, long)]
+    pub xattrs: bool,
+
+    /// Give the written entries the modification times recorded in the image,
+    /// instead of the time of extraction
+    #[arg(required = false, long)]
+    pub preserve_mtime: bool,
+
+    /// Give the written directories this octal mode instead of the one in the image
+    #[arg(required = false, long, value_name = "MODE", value_parser = parse_mode)]
+    pub dmode: Option<u32>,
+
+    /// Give the written files this octal mode instead of the one in the image
+    #[arg(required = false, long, value_name = "MODE", value_parser = parse_mode)]
+    pub fmode: Option<u32>,
+
+    /// Ignore the modes in the image and create entries with the default modes,
+    /// as limited by the umask. --dmode and --fmode still apply
+    #[arg(required = false, long, overrides_with = "perms")]
+    pub no_perms: bool,
+
+    /// Create entries with the modes in the image, even if the config sets no_perms
+    #[arg(required = false, long, overrides_with = "no_perms")]
+    pub perms: bool,
+
+    /// Write the contents of the files and directories that symlinks point to in
+    /// place of the symlinks, for filesystems that can't hold symlinks
+    #[arg(required = false, long, conflicts_with = "no_symlinks")]
+    pub dereference: bool,
+
+    /// Don't write symlinks
+    #[arg(required = false, long)]
+    pub no_symlinks: bool,
+
+    /// Recreate hard links between files of the image instead of writing each of
+    /// their paths as a separate copy
+    #[arg(required = false, long)]
+    pub hardlinks: bool,
+
+    /// Also extract the SquashFS images and AppImages among the written files,
+    /// next to them in `<name>.extracted`, recursively
+    #[arg(required = false, long)]
+    pub recurse_nested: bool,
+}
+
+/// How extraction handles output files that already exist
+#[derive(clap::Args)]
+pub struct ExistingArgs {
+    /// Replace output files that already exist
+    #[arg(required = false, long, conflicts_with_all = ["skip_existing", "backup_existing"])]
+    pub force: bool,
+
+    /// Keep output files that already exist, and don't write the entries
+    #[arg(required = false, long, conflicts_with = "backup_existing")]
+    pub skip_existing: bool,
+
+    /// Rename output files that already exist to <name>.~N~ before writing
+    #[arg(required = false, long)]
+    pub backup_existing: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum FilterKind {
+    /// Paths containing the filter
+    Substring,
+    /// Paths relative to the AppImage root matching the glob
+    Glob,
+    /// Paths matching the regular expression
+    Regex,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum IconFormatChoice {
+    /// PNG icons
+    Png,
+    /// SVG icons
+    Svg,
+    /// Icons of any format, preferring PNG
+    Any,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ChecksumAlgo {
+    /// SHA-256, as printed by `sha256sum`
+    Sha256,
+    /// SHA-512, as printed by `sha512sum`
+    Sha512,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ColorChoice {
+    /// Color output to terminals, unless NO_COLOR is set
+    Auto,
+    /// Always color output
+    Always,
+    /// Never color output
+    Never,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable lines
+    Text,
+    /// Tab separated fields, with tabs, newlines and backslashes escaped
+    Tsv,
+}
+
+/// Parses an octal file mode, like `chmod`.
+///
+/// # Arguments
+/// * `mode` - The mode, e.g. `755`
+///
+/// # Returns
+/// The mode, or an error if it isn't an octal number up to `7777`
+pub fn parse_mode(mode: &str) -> Result<u32, String> {
+    u32::from_str_radix(mode, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+        .ok_or_else(|| format!("Invalid octal mode: {}", mode))
+}
+
+/// Parses the level of a counted flag given through its environment variable:
+/// a count, or a boolean like `SQUISHY_JSON` where true counts as given once.
+///
+/// # Arguments
+/// * `level` - The level, e.g. `2` or `true`
+///
+/// # Returns
+/// The level, or an error if it is neither a count nor a boolean
+pub fn parse_level(level: &str) -> Result<u8, String> {
+    if let Ok(level) = level.parse() {
+        return Ok(level);
+    }
+    match level.to_lowercase().as_str() {
+        "y" | "yes" | "t" | "true" | "on" => Ok(1),
+        "" | "n" | "no" | "f" | "false" | "off" => Ok(0),
+        _ => Err(format!("Invalid level: {}", level)),
+    }
 }
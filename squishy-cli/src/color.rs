@@ -0,0 +1,55 @@
+use std::fmt::Display;
+
+use console::{style, StyledObject};
+use squishy::{EntryKind, SquashFSEntry};
+
+use crate::cli::ColorChoice;
+
+/// Enables or disables colored output. In `auto` mode colors are used when the
+/// stream is a terminal, unless `NO_COLOR` is set or `CLICOLOR` is `0`.
+///
+/// # Arguments
+/// * `choice` - When to use colors
+pub fn init(choice: ColorChoice) {
+    let enabled = match choice {
+        ColorChoice::Auto => return,
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+    console::set_colors_enabled(enabled);
+    console::set_colors_enabled_stderr(enabled);
+}
+
+/// Styles the path of an entry by kind, like `ls --color`: directories in bold
+/// blue, symlinks in cyan and executable files in green.
+///
+/// # Arguments
+/// * `entry` - The entry
+/// * `path` - The text to style, e.g. the full path or the file name
+///
+/// # Returns
+/// The styled path
+pub fn entry_path<D: Display>(entry: &SquashFSEntry, path: D) -> StyledObject<D> {
+    let path = style(path);
+    match entry.kind {
+        EntryKind::Directory => path.blue().bold(),
+        EntryKind::Symlink(_) => path.cyan(),
+        EntryKind::File(_) if entry.header.permissions & 0o111 != 0 => path.green(),
+        _ => path,
+    }
+}
+
+/// Styles a size in listings.
+pub fn size<D: Display>(size: D) -> StyledObject<D> {
+    style(size).yellow()
+}
+
+/// Styles an error message printed on stderr.
+pub fn error<D: Display>(message: D) -> StyledObject<D> {
+    style(message).red().for_stderr()
+}
+
+/// Styles a warning message printed on stderr.
+pub fn warning<D: Display>(message: D) -> StyledObject<D> {
+    style(message).yellow().for_stderr()
+}
@@ -0,0 +1,145 @@
+use std::{
+    env, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use clap::{parser::ValueSource, ArgMatches, ValueEnum};
+use serde::Deserialize;
+
+use crate::{
+    cli::{parse_mode, Args, ColorChoice, Commands},
+    output::Output,
+};
+
+/// Defaults read from the configuration file. Options given on the command line
+/// take precedence over them.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Number of threads, like `--threads`
+    threads: Option<usize>,
+    /// When to color output, like `--color`
+    color: Option<String>,
+    /// Directory `--write` writes to when given without a path, instead of the
+    /// current directory
+    output_dir: Option<PathBuf>,
+    /// Preferred icon size of `appimage`, like `--icon-size`
+    icon_size: Option<u32>,
+    /// Permissions of the files written by `unsquashfs`
+    permissions: PermissionsConfig,
+}
+
+/// Permission policy of `unsquashfs`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PermissionsConfig {
+    /// Octal mode of directories, like `--dmode`
+    dmode: Option<String>,
+    /// Octal mode of files, like `--fmode`
+    fmode: Option<String>,
+    /// Apply the umask instead of the modes stored in the image, like `--no-perms`.
+    /// `--perms` turns it off
+    no_perms: bool,
+}
+
+/// Path of the default configuration file, `$XDG_CONFIG_HOME/squishy/config.toml`
+/// or `~/.config/squishy/config.toml`.
+fn default_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_home.join("squishy").join("config.toml"))
+}
+
+/// Reads the configuration file, exiting if it is invalid.
+///
+/// # Arguments
+/// * `path` - Path given with `--config`, which must exist, or None for the
+///   default path, which may not
+/// * `out` - Output settings
+///
+/// # Returns
+/// The configuration, empty if there is no file
+pub fn load(path: Option<&Path>, out: Output) -> Config {
+    let Some(file) = path.map(Path::to_path_buf).or_else(default_path) else {
+        return Config::default();
+    };
+    let contents = match fs::read_to_string(&file) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound && path.is_none() => {
+            return Config::default();
+        }
+        Err(e) => out.fail_invalid(format!("{}: {}", file.display(), e)),
+    };
+    let config = toml::from_str::<Config>(&contents)
+        .unwrap_or_else(|e| out.fail_invalid(format!("{}: {}", file.display(), e)));
+    elog!(!out.verbose(1), "Using config {}", file.display());
+
+    let fail = |e: String| -> ! { out.fail_invalid(format!("{}: {}", file.display(), e)) };
+    if let Some(color) = &config.color {
+        ColorChoice::from_str(color, true).unwrap_or_else(|e| fail(e));
+    }
+    for mode in [&config.permissions.dmode, &config.permissions.fmode]
+        .into_iter()
+        .flatten()
+    {
+        parse_mode(mode).unwrap_or_else(|e| fail(e));
+    }
+    config
+}
+
+impl Config {
+    /// Fills in the options that weren't given on the command line or through
+    /// their environment variables. `SQUISHY_OUTPUT_DIR` overrides `output_dir`.
+    ///
+    /// # Arguments
+    /// * `args` - The parsed arguments
+    /// * `matches` - The matches they were parsed from, to tell which options were
+    ///   given
+    pub fn apply(&self, args: &mut Args, matches: &ArgMatches) {
+        if args.threads.is_none() {
+            args.threads = self.threads;
+        }
+        if matches.value_source("color") == Some(ValueSource::DefaultValue) {
+            if let Some(color) = &self.color {
+                args.color = ColorChoice::from_str(color, true).unwrap();
+            }
+        }
+
+        let output_dir = env::var_os("SQUISHY_OUTPUT_DIR")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| self.output_dir.clone());
+        let mode = |mode: &Option<String>| mode.as_deref().map(|mode| parse_mode(mode).unwrap());
+        match &mut args.command {
+            Commands::AppImage(args) => {
+                if let (Some(None), Some(dir)) = (&args.write, &output_dir) {
+                    args.write = Some(Some(dir.clone()));
+                }
+                if !args.hicolor {
+                    args.icon_size = args.icon_size.or(self.icon_size);
+                }
+            }
+            Commands::Unsquashfs(args) => {
+                if let (Some(None), Some(dir)) = (&args.write, &output_dir) {
+                    args.write = Some(Some(dir.clone()));
+                }
+                args.dmode = args.dmode.or(mode(&self.permissions.dmode));
+                args.fmode = args.fmode.or(mode(&self.permissions.fmode));
+                let perms_given = matches
+                    .subcommand_matches("unsquashfs")
+                    .is_some_and(|matches| {
+                        ["no_perms", "perms"]
+                            .iter()
+                            .any(|id| matches.value_source(id) == Some(ValueSource::CommandLine))
+                    });
+                if !perms_given {
+                    args.no_perms = self.permissions.no_perms;
+                }
+            }
+            _ => {}
+        }
+    }
+}
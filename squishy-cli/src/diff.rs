@@ -0,0 +1,174 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use console::style;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use squishy::{
+    appimage::{self, AppImage},
+    EntryKind, SquashFSEntry,
+};
+
+use crate::{
+    checksum::file_digest,
+    format::{entry_json, kind_name, link_target},
+    output::Output,
+};
+
+/// Exit code when the images differ, like `diff`
+const EXIT_DIFFERENT: i32 = 1;
+/// Number of digest characters shown in text output
+const SHORT_DIGEST: usize = 12;
+
+/// An entry in both images that differs between them
+struct Change<'a> {
+    old: &'a SquashFSEntry<'a>,
+    new: &'a SquashFSEntry<'a>,
+    /// SHA-256 digests of the old and new contents, when both are files
+    digests: Option<(String, String)>,
+}
+
+/// Collects the entries of the image by path.
+fn entries_by_path<'a>(appimage: &'a AppImage) -> HashMap<PathBuf, SquashFSEntry<'a>> {
+    appimage
+        .par_entries()
+        .map(|entry| (entry.path.clone(), entry))
+        .collect()
+}
+
+/// Converts one side of a change to a JSON object.
+fn side_json(entry: &SquashFSEntry, digest: Option<&String>) -> Value {
+    json!({
+        "kind": kind_name(entry),
+        "mode": format!("{:04o}", entry.header.permissions),
+        "size": entry.size,
+        "sha256": digest,
+        "target": link_target(entry),
+    })
+}
+
+/// Describes what differs in a change, e.g. `size 10 -> 12, mode 0644 -> 0755`.
+fn describe(change: &Change) -> String {
+    let (old, new) = (change.old, change.new);
+    let mut details = Vec::new();
+    if kind_name(old) != kind_name(new) {
+        details.push(format!("kind {} -> {}", kind_name(old), kind_name(new)));
+    }
+    if old.size != new.size {
+        details.push(format!("size {} -> {}", old.size, new.size));
+    }
+    if old.header.permissions != new.header.permissions {
+        details.push(format!(
+            "mode {:04o} -> {:04o}",
+            old.header.permissions, new.header.permissions
+        ));
+    }
+    if let Some((old_digest, new_digest)) = &change.digests {
+        if old_digest != new_digest {
+            details.push(format!(
+                "sha256 {} -> {}",
+                &old_digest[..SHORT_DIGEST],
+                &new_digest[..SHORT_DIGEST]
+            ));
+        }
+    }
+    if let (Some(old_target), Some(new_target)) = (link_target(old), link_target(new)) {
+        if old_target != new_target {
+            details.push(format!("target {} -> {}", old_target, new_target));
+        }
+    }
+    details.join(", ")
+}
+
+/// Prints the entries added, removed and changed between two images, e.g. two
+/// releases of the same application. Changed entries differ in type, contents,
+/// symlink target or permissions; their sizes, modes and SHA-256 digests are
+/// shown. Exits with 1 if the images differ.
+///
+/// # Arguments
+/// * `old` - The old image
+/// * `new` - The new image
+/// * `out` - Output settings
+pub fn diff(old: &AppImage, new: &AppImage, out: Output) {
+    let report = appimage::compare(old, new).unwrap_or_else(|e| out.fail(e));
+    let old_entries = entries_by_path(old);
+    let new_entries = entries_by_path(new);
+
+    let changes = report
+        .changed
+        .par_iter()
+        .map(|changed| {
+            let old_entry = &old_entries[&changed.path];
+            let new_entry = &new_entries[&changed.path];
+            let digests = match (&old_entry.kind, &new_entry.kind) {
+                (EntryKind::File(_), EntryKind::File(_)) => Some((
+                    file_digest::<Sha256>(old, old_entry)?,
+                    file_digest::<Sha256>(new, new_entry)?,
+                )),
+                _ => None,
+            };
+            Ok(Change {
+                old: old_entry,
+                new: new_entry,
+                digests,
+            })
+        })
+        .collect::<squishy::Result<Vec<_>>>()
+        .unwrap_or_else(|e| out.fail(e));
+    let added = report
+        .added
+        .iter()
+        .map(|path| &new_entries[path])
+        .collect::<Vec<_>>();
+    let removed = report
+        .removed
+        .iter()
+        .map(|path| &old_entries[path])
+        .collect::<Vec<_>>();
+
+    if out.json {
+        let changed = changes
+            .iter()
+            .map(|change| {
+                let (old_digest, new_digest) = change.digests.as_ref().map(|(a, b)| (a, b)).unzip();
+                json!({
+                    "path": change.new.path.to_string_lossy(),
+                    "old": side_json(change.old, old_digest),
+                    "new": side_json(change.new, new_digest),
+                })
+            })
+            .collect();
+        out.print_json(&json!({
+            "added": added.iter().map(|entry| entry_json(entry)).collect::<Vec<_>>(),
+            "removed": removed.iter().map(|entry| entry_json(entry)).collect::<Vec<_>>(),
+            "changed": Value::Array(changed),
+        }));
+    } else {
+        for entry in &added {
+            out.record(style(format!("+ {}", entry.path.display())).green());
+        }
+        for entry in &removed {
+            out.record(style(format!("- {}", entry.path.display())).red());
+        }
+        for change in &changes {
+            out.record(format!(
+                "{} {}",
+                style(format!("~ {}", change.new.path.display())).yellow(),
+                describe(change)
+            ));
+        }
+        if !out.print0 {
+            log!(
+                out.silent(),
+                "{} added, {} removed, {} changed",
+                added.len(),
+                removed.len(),
+                changes.len()
+            );
+        }
+    }
+
+    if !(added.is_empty() && removed.is_empty() && changes.is_empty()) {
+        std::process::exit(EXIT_DIFFERENT);
+    }
+}
@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+use squishy::collision::CollisionStrategy;
+
+use crate::output::Output;
+
+/// A file that an extraction would write
+pub struct PlannedWrite {
+    /// Path of the entry in the image
+    pub source: PathBuf,
+    /// Path the entry would be written to
+    pub output: PathBuf,
+    /// Size of the entry, 0 for directories and symlinks
+    pub size: u64,
+}
+
+/// Reports what an extraction would write without touching the filesystem.
+/// Outputs that already exist are reported as conflicts.
+///
+/// # Arguments
+/// * `planned` - The files the extraction would write, in the order to report them
+/// * `collision` - How the extraction handles existing outputs
+/// * `out` - Output settings
+///
+/// # Returns
+/// The planned writes as a JSON array, for the caller to print in JSON mode
+pub fn report(planned: &[PlannedWrite], collision: CollisionStrategy, out: Output) -> Value {
+    let quiet = out.silent();
+    let on_conflict = match collision {
+        CollisionStrategy::Skip => "skipped",
+        CollisionStrategy::Overwrite => "overwritten",
+        CollisionStrategy::Backup => "backed up",
+    };
+    let total = planned.iter().map(|write| write.size).sum::<u64>();
+    let mut conflicts = 0;
+    let planned = planned
+        .iter()
+        .map(|write| {
+            let conflict = write.output.symlink_metadata().is_ok();
+            if conflict {
+                conflicts += 1;
+            }
+            log!(
+                quiet,
+                "Would write {} to {} ({} bytes){}",
+                write.source.display(),
+                write.output.display(),
+                write.size,
+                if conflict {
+                    format!(" [exists, {}]", on_conflict)
+                } else {
+                    String::new()
+                }
+            );
+            json!({
+                "source": write.source.to_string_lossy(),
+                "output": write.output.to_string_lossy(),
+                "size": write.size,
+                "conflict": conflict,
+            })
+        })
+        .collect::<Vec<_>>();
+    log!(
+        quiet,
+        "Dry run: {} entries, {} bytes, {} conflicts",
+        planned.len(),
+        total,
+        conflicts
+    );
+    Value::Array(planned)
+}
@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{json, Value};
+use squishy::{appimage::AppImage, EntryKind};
+
+use crate::{
+    color,
+    format::{human_size, tsv_field},
+    list::sorted_entries,
+    output::Output,
+};
+
+/// Total sizes of the files in a directory, recursively
+#[derive(Default)]
+struct Usage {
+    size: u64,
+    compressed: u64,
+}
+
+/// Prints the total size of the files in each directory, largest first, like
+/// `du`. Compressed sizes, as stored in the image, can be added to see what makes
+/// the image large.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `path` - Directory to start from, the image root by default
+/// * `compressed` - Whether to print compressed sizes
+/// * `max_depth` - Only print directories this deep below the start directory
+/// * `bytes` - Whether to print sizes in bytes instead of human readable ones
+/// * `out` - Output settings
+pub fn du(
+    appimage: &AppImage,
+    path: Option<&Path>,
+    compressed: bool,
+    max_depth: Option<usize>,
+    bytes: bool,
+    out: Output,
+) {
+    let root = Path::new("/").join(path.unwrap_or(Path::new("/")));
+    let entries = sorted_entries(appimage);
+
+    let Some(root_entry) = entries.iter().find(|entry| entry.path == root) else {
+        out.fail(format!("{}: No such directory", root.display()));
+    };
+    if root_entry.kind != EntryKind::Directory {
+        out.fail(format!("{}: Not a directory", root.display()));
+    }
+    let compressed_sizes = compressed.then(|| {
+        appimage
+            .compressed_sizes()
+            .unwrap_or_else(|| out.fail("Compressed sizes are only available for SquashFS images"))
+            .unwrap_or_else(|e| out.fail(e))
+    });
+
+    let mut usage: HashMap<PathBuf, Usage> = HashMap::new();
+    for entry in &entries {
+        if entry.kind == EntryKind::Directory && entry.path.starts_with(&root) {
+            usage.entry(entry.path.clone()).or_default();
+        }
+        if !matches!(entry.kind, EntryKind::File(_)) || !entry.path.starts_with(&root) {
+            continue;
+        }
+        let stored = compressed_sizes
+            .as_ref()
+            .and_then(|sizes| sizes.get(&entry.path))
+            .copied()
+            .unwrap_or(0);
+        for dir in entry.path.ancestors().skip(1) {
+            if !dir.starts_with(&root) {
+                break;
+            }
+            let dir_usage = usage.entry(dir.to_path_buf()).or_default();
+            dir_usage.size += entry.size as u64;
+            dir_usage.compressed += stored;
+        }
+    }
+
+    let depth = |dir: &Path| dir.components().count() - root.components().count();
+    let mut dirs = usage
+        .into_iter()
+        .filter(|(dir, _)| max_depth.is_none_or(|max_depth| depth(dir) <= max_depth))
+        .collect::<Vec<_>>();
+    dirs.sort_by(|a, b| {
+        let key = |usage: &Usage| {
+            if compressed {
+                usage.compressed
+            } else {
+                usage.size
+            }
+        };
+        key(&b.1).cmp(&key(&a.1)).then_with(|| a.0.cmp(&b.0))
+    });
+
+    if out.json {
+        let dirs = dirs
+            .iter()
+            .map(|(dir, usage)| {
+                let mut value = json!({ "path": dir.to_string_lossy(), "size": usage.size });
+                if compressed {
+                    value["compressed"] = json!(usage.compressed);
+                }
+                value
+            })
+            .collect();
+        out.print_json(&Value::Array(dirs));
+        return;
+    }
+
+    let format_size = |size: u64| {
+        if bytes {
+            size.to_string()
+        } else {
+            human_size(size)
+        }
+    };
+    for (dir, usage) in &dirs {
+        let path = dir.to_string_lossy();
+        if out.tsv() {
+            let mut fields = vec![usage.size.to_string()];
+            if compressed {
+                fields.push(usage.compressed.to_string());
+            }
+            fields.push(tsv_field(&path));
+            out.record(fields.join("\t"));
+        } else if compressed {
+            out.record(format!(
+                "{} {}  {}",
+                color::size(format!("{:>8}", format_size(usage.size))),
+                color::size(format!("{:>8}", format_size(usage.compressed))),
+                path
+            ));
+        } else {
+            out.record(format!(
+                "{}  {}",
+                color::size(format!("{:>8}", format_size(usage.size))),
+                path
+            ));
+        }
+    }
+}
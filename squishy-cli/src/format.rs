@@ -0,0 +1,192 @@
+use serde_json::{json, Value};
+use squishy::{EntryKind, SquashFSEntry};
+
+/// Formats the type and permissions of an entry like `ls -l`, e.g. `drwxr-xr-x`.
+///
+/// # Arguments
+/// * `entry` - The entry to format
+///
+/// # Returns
+/// The mode string
+pub fn mode_string(entry: &SquashFSEntry) -> String {
+    let kind = match entry.kind {
+        EntryKind::File(_) => '-',
+        EntryKind::Directory => 'd',
+        EntryKind::Symlink(_) => 'l',
+        EntryKind::Unknown => '?',
+    };
+    let mode = entry.header.permissions;
+
+    let mut s = String::with_capacity(10);
+    s.push(kind);
+    for (shift, special, set, unset) in [
+        (6, 0o4000, 's', 'S'),
+        (3, 0o2000, 's', 'S'),
+        (0, 0o1000, 't', 'T'),
+    ] {
+        let bits = (mode >> shift) & 0o7;
+        s.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        s.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        s.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => set,
+            (false, true) => unset,
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    s
+}
+
+/// Formats a unix timestamp as `YYYY-MM-DD HH:MM` in UTC.
+///
+/// # Arguments
+/// * `timestamp` - Seconds since the unix epoch
+///
+/// # Returns
+/// The formatted time
+pub fn format_time(timestamp: u32) -> String {
+    let days = timestamp / 86400;
+    let seconds = timestamp % 86400;
+
+    // Civil date from days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+/// Formats the symlink target of an entry for display. Targets are stored
+/// relative to the image root, so the leading `/` is dropped.
+///
+/// # Arguments
+/// * `entry` - The entry to format
+///
+/// # Returns
+/// The link target, if the entry is a symlink
+pub fn link_target(entry: &SquashFSEntry) -> Option<String> {
+    match &entry.kind {
+        EntryKind::Symlink(target) => Some(
+            target
+                .strip_prefix("/")
+                .unwrap_or(target)
+                .display()
+                .to_string(),
+        ),
+        _ => None,
+    }
+}
+
+/// Formats a size in bytes with a binary unit suffix, e.g. `2.9M`.
+///
+/// # Arguments
+/// * `size` - Size in bytes
+///
+/// # Returns
+/// The human readable size
+pub fn human_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["K", "M", "G", "T", "P"];
+    if size < 1024 {
+        return size.to_string();
+    }
+    let mut value = size as f64;
+    let mut unit = "";
+    for next in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    format!("{:.1}{}", value, unit)
+}
+
+/// Name of the kind of an entry, as used in JSON output.
+pub fn kind_name(entry: &SquashFSEntry) -> &'static str {
+    match entry.kind {
+        EntryKind::File(_) => "file",
+        EntryKind::Directory => "directory",
+        EntryKind::Symlink(_) => "symlink",
+        EntryKind::Unknown => "unknown",
+    }
+}
+
+/// Converts an entry to a JSON object with its path, kind, mode, owner, size,
+/// modification time and symlink target.
+///
+/// # Arguments
+/// * `entry` - The entry to convert
+///
+/// # Returns
+/// The JSON object
+pub fn entry_json(entry: &SquashFSEntry) -> Value {
+    json!({
+        "path": entry.path.to_string_lossy(),
+        "kind": kind_name(entry),
+        "mode": format!("{:04o}", entry.header.permissions),
+        "uid": entry.header.uid,
+        "gid": entry.header.gid,
+        "size": entry.size,
+        "mtime": entry.header.mtime,
+        "target": link_target(entry),
+    })
+}
+
+/// Escapes a TSV field: backslashes, tabs, newlines and carriage returns are
+/// written as `\\`, `\t`, `\n` and `\r`.
+///
+/// # Arguments
+/// * `field` - The field to escape
+///
+/// # Returns
+/// The escaped field
+pub fn tsv_field(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Formats an entry as a TSV row with its path, kind, mode, owner, size,
+/// modification time and symlink target, in the same order as [`entry_json`].
+///
+/// # Arguments
+/// * `entry` - The entry to format
+///
+/// # Returns
+/// The TSV row, without the record terminator
+pub fn entry_tsv(entry: &SquashFSEntry) -> String {
+    [
+        tsv_field(&entry.path.to_string_lossy()),
+        kind_name(entry).to_owned(),
+        format!("{:04o}", entry.header.permissions),
+        entry.header.uid.to_string(),
+        entry.header.gid.to_string(),
+        entry.size.to_string(),
+        entry.header.mtime.to_string(),
+        link_target(entry)
+            .map(|target| tsv_field(&target))
+            .unwrap_or_default(),
+    ]
+    .join("\t")
+}
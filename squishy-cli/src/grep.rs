@@ -0,0 +1,177 @@
+use rayon::iter::ParallelIterator;
+use regex::bytes::{Regex, RegexBuilder};
+use serde_json::{json, Value};
+use squishy::{
+    appimage::{
+        filter::{Filter, FilterMode, Pattern, PatternKind},
+        AppImage,
+    },
+    EntryKind, SquashFSEntry,
+};
+
+use crate::{color, output::Output};
+
+/// Exit code when nothing matches, like `grep`
+const EXIT_NO_MATCH: i32 = 1;
+/// Number of bytes read from the image at once
+const CHUNK_SIZE: u64 = 1024 * 1024;
+/// Number of leading bytes checked for NUL bytes to detect binary files
+const BINARY_CHECK_SIZE: usize = 8000;
+
+/// Matches found in a file
+enum Matches {
+    /// Matching lines, with their 1-based line numbers
+    Lines(Vec<(usize, String)>),
+    /// The file is binary and has a match, whose line isn't printed
+    Binary,
+}
+
+/// Searches a file of the image line by line, decompressing it in chunks so large
+/// files aren't held in memory.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `entry` - The file entry
+/// * `regex` - The pattern
+///
+/// # Returns
+/// The matches, None if there are none, or an error if the file can't be read
+fn grep_file(
+    appimage: &AppImage,
+    entry: &SquashFSEntry,
+    regex: &Regex,
+) -> squishy::Result<Option<Matches>> {
+    let mut lines = Vec::new();
+    let mut binary = false;
+    let mut pending = Vec::new();
+    let mut line_number = 0;
+    let mut position = 0;
+    loop {
+        let chunk = appimage.read_range(entry, position, CHUNK_SIZE)?;
+        if position == 0 {
+            binary = chunk[..chunk.len().min(BINARY_CHECK_SIZE)].contains(&0);
+        }
+        position += chunk.len() as u64;
+        let done = chunk.is_empty();
+        pending.extend_from_slice(&chunk);
+
+        let mut start = 0;
+        while let Some(end) = pending[start..].iter().position(|&byte| byte == b'\n') {
+            let line = &pending[start..start + end];
+            line_number += 1;
+            if regex.is_match(line) {
+                if binary {
+                    return Ok(Some(Matches::Binary));
+                }
+                lines.push((line_number, String::from_utf8_lossy(line).into_owned()));
+            }
+            start += end + 1;
+        }
+        pending.drain(..start);
+
+        if done {
+            break;
+        }
+    }
+    // Last line without a trailing newline
+    if !pending.is_empty() && regex.is_match(&pending) {
+        if binary {
+            return Ok(Some(Matches::Binary));
+        }
+        lines.push((
+            line_number + 1,
+            String::from_utf8_lossy(&pending).into_owned(),
+        ));
+    }
+
+    Ok((!lines.is_empty()).then_some(Matches::Lines(lines)))
+}
+
+/// Searches the contents of the files of the image in parallel, and prints the
+/// matching lines as `path:line`, or `path:number:line` with line numbers. Binary
+/// files are reported without their lines, like `grep`. Exits with 1 if nothing
+/// matches.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `pattern` - Regular expression, or literal string with `fixed`
+/// * `patterns` - Globs the paths must match, all files if empty
+/// * `ignore_case` - Whether to match case-insensitively
+/// * `fixed` - Whether the pattern is a literal string
+/// * `line_numbers` - Whether to print line numbers
+/// * `out` - Output settings
+pub fn grep(
+    appimage: &AppImage,
+    pattern: &str,
+    patterns: &[String],
+    ignore_case: bool,
+    fixed: bool,
+    line_numbers: bool,
+    out: Output,
+) {
+    let pattern = if fixed {
+        regex::escape(pattern)
+    } else {
+        pattern.to_owned()
+    };
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .unwrap_or_else(|e| out.fail_invalid(e));
+    let filter = patterns
+        .iter()
+        .fold(Filter::new(FilterMode::Any), |filter, pattern| {
+            filter.include(Pattern::new(PatternKind::Glob, pattern).unwrap_or_else(|e| out.fail(e)))
+        });
+
+    let mut results = appimage
+        .par_entries()
+        .filter(|entry| matches!(entry.kind, EntryKind::File(_)) && filter.matches_entry(entry))
+        .filter_map(|entry| match grep_file(appimage, &entry, &regex) {
+            Ok(None) => None,
+            Ok(Some(matches)) => Some((entry.path, Ok(matches))),
+            Err(e) => Some((entry.path, Err(e))),
+        })
+        .collect::<Vec<_>>();
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut found = false;
+    let mut json_matches = Vec::new();
+    for (path, matches) in results {
+        let path = path.strip_prefix("/").unwrap_or(&path).to_string_lossy();
+        match matches {
+            Ok(Matches::Lines(lines)) => {
+                found = true;
+                for (number, line) in lines {
+                    if out.json {
+                        json_matches.push(json!({ "path": path, "line": number, "text": line }));
+                    } else if line_numbers {
+                        out.record(format!("{}:{}:{}", path, number, line));
+                    } else {
+                        out.record(format!("{}:{}", path, line));
+                    }
+                }
+            }
+            Ok(Matches::Binary) => {
+                found = true;
+                if out.json {
+                    json_matches.push(json!({ "path": path, "binary": true }));
+                } else {
+                    out.record(format!("Binary file {} matches", path));
+                }
+            }
+            Err(e) => elog!(
+                out.quiet_errors,
+                "{}",
+                color::error(format!("{}: {}", path, e))
+            ),
+        }
+    }
+
+    if out.json {
+        out.print_json(&Value::Array(json_matches));
+    }
+    if !found {
+        std::process::exit(EXIT_NO_MATCH);
+    }
+}
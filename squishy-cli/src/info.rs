@@ -0,0 +1,175 @@
+use std::path::Path;
+
+use backhand::compression::{CompressionOptions, Compressor};
+use serde_json::{json, Map, Value};
+use squishy::{
+    appimage::{AppImage, AppImageKind, Payload},
+    SquashFS,
+};
+
+use crate::{format::format_time, output::Output};
+
+/// Offset of a superblock table that isn't present in the image
+const NOT_SET: u64 = u64::MAX;
+
+/// Name of a compression algorithm, as used by `mksquashfs -comp`.
+fn compressor_name(compressor: Compressor) -> &'static str {
+    match compressor {
+        Compressor::None => "none",
+        Compressor::Gzip => "gzip",
+        Compressor::Lzma => "lzma",
+        Compressor::Lzo => "lzo",
+        Compressor::Xz => "xz",
+        Compressor::Lz4 => "lz4",
+        Compressor::Zstd => "zstd",
+    }
+}
+
+/// Lists the compression options stored in the image as name/value pairs.
+fn compression_options(options: &CompressionOptions) -> Vec<(&'static str, u32)> {
+    match options {
+        CompressionOptions::Gzip(gzip) => vec![
+            ("level", gzip.compression_level),
+            ("window_size", gzip.window_size.into()),
+            ("strategies", gzip.strategies.into()),
+        ],
+        CompressionOptions::Lzo(lzo) => vec![
+            ("algorithm", lzo.algorithm),
+            ("level", lzo.compression_level),
+        ],
+        CompressionOptions::Xz(xz) => vec![("dictionary_size", xz.dictionary_size)],
+        CompressionOptions::Lz4(lz4) => vec![("version", lz4.version), ("flags", lz4.flags)],
+        CompressionOptions::Zstd(zstd) => vec![("level", zstd.compression_level)],
+        CompressionOptions::Lzma => Vec::new(),
+    }
+}
+
+/// A detail of the image: JSON key, display label and value
+type Field = (&'static str, &'static str, Value);
+
+/// Collects the superblock details of a SquashFS image.
+///
+/// # Arguments
+/// * `squashfs` - The image
+///
+/// # Returns
+/// The details, in display order
+fn squashfs_info(squashfs: &SquashFS) -> Vec<Field> {
+    let superblock = squashfs.superblock();
+    let options = squashfs
+        .compression_options()
+        .map(compression_options)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, value)| (name.to_owned(), Value::from(value)))
+        .collect::<Map<_, _>>();
+
+    vec![
+        ("format", "Format", json!("squashfs")),
+        (
+            "version",
+            "Version",
+            json!(format!(
+                "{}.{}",
+                superblock.version_major, superblock.version_minor
+            )),
+        ),
+        ("offset", "Offset", json!(squashfs.offset())),
+        (
+            "compression",
+            "Compression",
+            json!(compressor_name(superblock.compressor)),
+        ),
+        (
+            "compression_options",
+            "Compression options",
+            Value::Object(options),
+        ),
+        ("block_size", "Block size", json!(superblock.block_size)),
+        ("inodes", "Inodes", json!(superblock.inode_count)),
+        ("fragments", "Fragments", json!(superblock.frag_count)),
+        ("ids", "Ids", json!(superblock.id_count)),
+        ("created", "Created", json!(superblock.mod_time)),
+        ("bytes_used", "Bytes used", json!(superblock.bytes_used)),
+        ("xattrs", "Xattrs", json!(superblock.xattr_table != NOT_SET)),
+        (
+            "exportable",
+            "Exportable",
+            json!(superblock.nfs_export_table_exists()),
+        ),
+    ]
+}
+
+/// Formats a detail value for display.
+///
+/// # Arguments
+/// * `key` - JSON key of the detail
+/// * `value` - The value
+///
+/// # Returns
+/// The formatted value
+fn display_value(key: &str, value: &Value) -> String {
+    match (key, value) {
+        ("created", Value::Number(time)) => format!(
+            "{} UTC",
+            format_time(time.as_u64().unwrap_or_default() as u32)
+        ),
+        ("compression_options", Value::Object(options)) if options.is_empty() => {
+            "defaults".to_owned()
+        }
+        ("compression_options", Value::Object(options)) => options
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join(", "),
+        (_, Value::Bool(true)) => "yes".to_owned(),
+        (_, Value::Bool(false)) => "no".to_owned(),
+        (_, Value::String(value)) => value.clone(),
+        (_, value) => value.to_string(),
+    }
+}
+
+/// Prints the filesystem details of the image: the AppImage type, and for
+/// SquashFS images the compression, block size, inode and fragment counts,
+/// creation time and offset from the superblock.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `file` - Path to the image
+/// * `out` - Output settings
+pub fn info(appimage: &AppImage, file: &Path, out: Output) {
+    let kind = match AppImage::detect_kind(file).unwrap_or_else(|e| out.fail(e)) {
+        AppImageKind::Type1 => Some(("type1", "Type-1")),
+        AppImageKind::Type2 => Some(("type2", "Type-2")),
+        AppImageKind::Static => Some(("static", "Type-3 like (static runtime)")),
+        AppImageKind::Unknown => None,
+    };
+
+    let mut fields: Vec<Field> = vec![("appimage", "AppImage", json!(kind.map(|(name, _)| name)))];
+    match &appimage.payload {
+        Payload::SquashFS(squashfs) => fields.extend(squashfs_info(squashfs)),
+        Payload::Iso9660(_) => fields.push(("format", "Format", json!("iso9660"))),
+        Payload::DwarFS(dwarfs) => {
+            fields.push(("format", "Format", json!("dwarfs")));
+            fields.push(("offset", "Offset", json!(dwarfs.offset())));
+        }
+    }
+
+    if out.json {
+        let info = fields
+            .into_iter()
+            .map(|(key, _, value)| (key.to_owned(), value))
+            .collect::<Map<_, _>>();
+        out.print_json(&Value::Object(info));
+        return;
+    }
+
+    for (key, label, value) in &fields {
+        let value = match (*key, kind) {
+            ("appimage", Some((_, name))) => name.to_owned(),
+            ("appimage", None) => "no".to_owned(),
+            _ => display_value(key, value),
+        };
+        log!(out.quiet, "{:<21}{}", format!("{}:", label), value);
+    }
+}
@@ -0,0 +1,81 @@
+use rayon::iter::ParallelIterator;
+use squishy::{appimage::AppImage, SquashFSEntry};
+
+use serde_json::Value;
+
+use crate::{
+    color,
+    format::{entry_json, entry_tsv, format_time, link_target, mode_string},
+    output::Output,
+};
+
+/// Collects the entries of the image, sorted by path.
+///
+/// # Arguments
+/// * `appimage` - The image
+///
+/// # Returns
+/// The sorted entries
+pub fn sorted_entries<'a>(appimage: &'a AppImage) -> Vec<SquashFSEntry<'a>> {
+    let mut entries = appimage.par_entries().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    entries
+}
+
+/// Prints the entries of the image like `ls -l`: mode, owner, size, modification
+/// time, path and symlink target. See [`Output::record`] for the TSV and
+/// NUL-terminated variants.
+///
+/// # Arguments
+/// * `appimage` - The image to list
+/// * `out` - Output settings
+pub fn list(appimage: &AppImage, out: Output) {
+    print_entries(&sorted_entries(appimage), out);
+}
+
+/// Prints entries like [`list`] does.
+///
+/// # Arguments
+/// * `entries` - The entries, in the order to print them
+/// * `out` - Output settings
+pub fn print_entries(entries: &[SquashFSEntry], out: Output) {
+    if out.json {
+        out.print_json(&Value::Array(entries.iter().map(entry_json).collect()));
+        return;
+    }
+    if out.tsv() {
+        for entry in entries {
+            out.record(entry_tsv(entry));
+        }
+        return;
+    }
+
+    let owner = |entry: &SquashFSEntry| format!("{}/{}", entry.header.uid, entry.header.gid);
+    let owner_width = entries
+        .iter()
+        .map(|entry| owner(entry).len())
+        .max()
+        .unwrap_or(0);
+    let size_width = entries
+        .iter()
+        .map(|entry| entry.size.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    for entry in entries {
+        // Columns are padded before styling, as escape codes would count as width
+        let mut line = format!(
+            "{} {:<owner_width$} {} {} {}",
+            mode_string(entry),
+            owner(entry),
+            color::size(format!("{:>size_width$}", entry.size)),
+            format_time(entry.header.mtime),
+            color::entry_path(entry, entry.path.display()),
+        );
+        if let Some(target) = link_target(entry) {
+            line.push_str(" -> ");
+            line.push_str(&target);
+        }
+        out.record(line);
+    }
+}
@@ -1,217 +1,277 @@
-use std::{
-    fs::{self, Permissions},
-    os::unix::{self, fs::PermissionsExt},
-};
+use std::path::{Path, PathBuf};
 
-use appimage::extract_file;
-use clap::Parser;
-use cli::Args;
+use clap::{CommandFactory, FromArgMatches};
+use cli::{Args, ExistingArgs};
+use output::Output;
 use rayon::iter::ParallelIterator;
 use squishy::{
-    appimage::{get_offset, AppImage},
-    error::SquishyError,
-    EntryKind, SquashFS,
+    appimage::AppImage, collision::CollisionStrategy, error::SquishyError, SquashFSEntry,
 };
 
-mod appimage;
 mod cli;
 
 macro_rules! log {
-    ($quiet:expr, $($arg:tt)*) => {
-        if !$quiet {
-            println!($($arg)*);
+    ($quiet:expr, $($arg:tt)*) => {{
+        let quiet = $quiet;
+        if !quiet || crate::output::has_log_file() {
+            let line = format!($($arg)*);
+            crate::output::log_to_file(&line);
+            if !quiet {
+                println!("{}", line);
+            }
         }
-    };
+    }};
 }
 
 macro_rules! elog {
-    ($quiet:expr, $($arg:tt)*) => {
-        if !$quiet {
-            eprintln!($($arg)*);
+    ($quiet:expr, $($arg:tt)*) => {{
+        let quiet = $quiet;
+        if !quiet || crate::output::has_log_file() {
+            let line = format!($($arg)*);
+            crate::output::log_to_file(&line);
+            if !quiet {
+                eprintln!("{}", line);
+            }
         }
-    };
+    }};
+}
+
+mod appimage;
+mod archive;
+mod browse;
+mod cat;
+mod checksum;
+mod color;
+mod config;
+mod diff;
+mod dryrun;
+mod du;
+mod format;
+mod grep;
+mod info;
+mod list;
+mod manpages;
+mod mount;
+mod output;
+mod owner;
+mod progress;
+mod scan;
+mod select;
+mod serve;
+mod stat;
+mod top;
+mod tree;
+mod unsquashfs;
+mod verify;
+mod watch;
+
+/// Opens an AppImage or a plain SquashFS image, exiting on failure.
+///
+/// # Arguments
+/// * `file` - Path to the image
+/// * `offset` - Offset of the filesystem, if known
+/// * `out` - Output settings
+///
+/// # Returns
+/// The opened image
+fn open_image(file: &PathBuf, offset: Option<u64>, out: Output) -> AppImage<'_> {
+    if !file.exists() {
+        out.fail_invalid(format!("{}: No such file", file.display()));
+    }
+    AppImage::new(None, file, offset).unwrap_or_else(|e| out.fail_invalid(e))
+}
+
+/// Finds an entry of the image by path, exiting if it doesn't exist.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `path` - Path of the entry, relative to the image root
+/// * `out` - Output settings
+///
+/// # Returns
+/// The entry, symlinks aren't resolved
+fn find_entry<'a>(appimage: &'a AppImage, path: &Path, out: Output) -> SquashFSEntry<'a> {
+    let path = Path::new("/").join(path);
+    appimage
+        .par_entries()
+        .find_any(|entry| entry.path == path)
+        .unwrap_or_else(|| out.fail(SquishyError::FileNotFound(path)))
+}
+
+/// Picks how to handle existing output files from the command line flags.
+///
+/// # Arguments
+/// * `existing` - The flags
+/// * `default` - Strategy of the subcommand when no flag is given
+///
+/// # Returns
+/// The collision strategy
+fn collision_strategy(existing: &ExistingArgs, default: CollisionStrategy) -> CollisionStrategy {
+    if existing.force {
+        CollisionStrategy::Overwrite
+    } else if existing.skip_existing {
+        CollisionStrategy::Skip
+    } else if existing.backup_existing {
+        CollisionStrategy::Backup
+    } else {
+        default
+    }
 }
 
 fn main() {
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let out = Output {
+        quiet: args.quiet > 0,
+        quiet_errors: args.quiet > 1,
+        verbose: args.verbose,
+        json: args.json,
+        print0: args.print0,
+        format: args.format,
+    };
+    config::load(args.config.as_deref(), out).apply(&mut args, &matches);
+    color::init(args.color);
+
+    if let Some(log_file) = &args.log_file {
+        if let Err(e) = output::open_log_file(log_file) {
+            out.fail(format!("{}: {}", log_file.display(), e));
+        }
+    }
+
+    if let Some(threads) = args.threads {
+        if let Err(e) = squishy::set_threads(threads) {
+            out.fail(e);
+        }
+    }
 
     match args.command {
-        cli::Commands::AppImage {
+        cli::Commands::AppImage(args) => appimage::run(args, out),
+        cli::Commands::List { file, offset } => {
+            let appimage = open_image(&file, offset, out);
+            list::list(&appimage, out);
+        }
+        cli::Commands::Tree { file, path, offset } => {
+            let appimage = open_image(&file, offset, out);
+            tree::tree(&appimage, path.as_deref(), out);
+        }
+        cli::Commands::Du {
+            file,
+            path,
+            compressed,
+            max_depth,
+            bytes,
             offset,
-            filter,
+        } => {
+            let appimage = open_image(&file, offset, out);
+            du::du(
+                &appimage,
+                path.as_deref(),
+                compressed,
+                max_depth,
+                bytes,
+                out,
+            );
+        }
+        cli::Commands::Top {
             file,
-            icon,
-            desktop,
-            appstream,
-            write,
-            original_name,
-            copy_permissions,
+            count,
+            compressed,
+            bytes,
+            offset,
         } => {
-            if file.exists() {
-                let appimage = match AppImage::new(filter.as_deref(), &file, offset) {
-                    Ok(appimage) => appimage,
-                    Err(e) => {
-                        elog!(args.quiet, "{}", e);
-                        std::process::exit(-1);
-                    }
-                };
-
-                let write_path = if let Some(write) = write {
-                    if let Some(path) = write {
-                        Some(path)
-                    } else {
-                        Some(std::env::current_dir().unwrap())
-                    }
-                } else {
-                    None
-                };
-
-                let output_name = if original_name {
-                    None
-                } else {
-                    file.file_name()
-                };
-
-                if desktop {
-                    if let Some(desktop) = appimage.find_desktop() {
-                        if let Some(ref write_path) = write_path {
-                            extract_file(
-                                &appimage.squashfs,
-                                &desktop,
-                                write_path,
-                                output_name,
-                                copy_permissions,
-                            )
-                            .unwrap();
-                        } else {
-                            log!(args.quiet, "Desktop file: {}", desktop.path.display());
-                        }
-                    } else {
-                        elog!(args.quiet, "No desktop file found.");
-                    };
-                }
-                if icon {
-                    if let Some(icon) = appimage.find_icon() {
-                        if let Some(ref write_path) = write_path {
-                            extract_file(
-                                &appimage.squashfs,
-                                &icon,
-                                write_path,
-                                output_name,
-                                copy_permissions,
-                            )
-                            .unwrap();
-                        } else {
-                            log!(args.quiet, "Icon: {}", icon.path.display());
-                        }
-                    } else {
-                        elog!(args.quiet, "No icon found.");
-                    };
-                }
-                if appstream {
-                    if let Some(appstream) = appimage.find_appstream() {
-                        if let Some(ref write_path) = write_path {
-                            extract_file(
-                                &appimage.squashfs,
-                                &appstream,
-                                write_path,
-                                output_name,
-                                copy_permissions,
-                            )
-                            .unwrap();
-                        } else {
-                            log!(args.quiet, "Appstream file: {}", appstream.path.display());
-                        }
-                    } else {
-                        elog!(args.quiet, "No appstream file found.");
-                    };
-                }
+            let appimage = open_image(&file, offset, out);
+            top::top(&appimage, count, compressed, bytes, out);
+        }
+        cli::Commands::Cat {
+            file,
+            path,
+            skip,
+            length,
+            offset,
+        } => {
+            let appimage = open_image(&file, offset, out);
+            cat::cat(&appimage, &path, skip, length, out);
+        }
+        cli::Commands::Stat { file, path, offset } => {
+            let appimage = open_image(&file, offset, out);
+            stat::stat(&appimage, &path, out);
+        }
+        cli::Commands::Info { file, offset } => {
+            let appimage = open_image(&file, offset, out);
+            info::info(&appimage, &file, out);
+        }
+        cli::Commands::Verify { file, offset } => {
+            let appimage = open_image(&file, offset, out);
+            verify::verify(&appimage, out);
+        }
+        cli::Commands::Checksum {
+            file,
+            patterns,
+            algo,
+            offset,
+        } => {
+            let appimage = open_image(&file, offset, out);
+            checksum::checksum(&appimage, &patterns, algo, out);
+        }
+        cli::Commands::Grep {
+            file,
+            pattern,
+            patterns,
+            ignore_case,
+            fixed_strings,
+            line_number,
+            offset,
+        } => {
+            let appimage = open_image(&file, offset, out);
+            grep::grep(
+                &appimage,
+                &pattern,
+                &patterns,
+                ignore_case,
+                fixed_strings,
+                line_number,
+                out,
+            );
+        }
+        cli::Commands::Scan { file } => scan::scan(&file, out),
+        cli::Commands::Diff { old, new } => {
+            let old_image = open_image(&old, None, out);
+            let new_image = open_image(&new, None, out);
+            diff::diff(&old_image, &new_image, out);
+        }
+        cli::Commands::Mount {
+            file,
+            mountpoint,
+            foreground,
+            offset,
+        } => {
+            if foreground {
+                let appimage = open_image(&file, offset, out);
+                mount::serve(&appimage, &file, &mountpoint, out);
+            } else {
+                mount::spawn(&file, &mountpoint, out);
             }
         }
-        cli::Commands::Unsquashfs {
+        cli::Commands::Serve {
+            file,
+            port,
+            bind,
             offset,
+        } => {
+            let appimage = open_image(&file, offset, out);
+            serve::serve(&appimage, &bind, port, out);
+        }
+        cli::Commands::Browse {
             file,
             write,
+            offset,
         } => {
-            let write_path = if let Some(write) = write {
-                if let Some(path) = write {
-                    fs::create_dir_all(&path).unwrap();
-                    Some(path)
-                } else {
-                    Some(std::env::current_dir().unwrap())
-                }
-            } else {
-                None
-            };
-
-            let offset = offset.unwrap_or(get_offset(&file).unwrap());
-            let squashfs = SquashFS::from_path_with_offset(&file, offset)
-                .map_err(|_| {
-                    SquishyError::InvalidSquashFS(
-                        "Couldn't find squashfs. Try providing valid offset.".to_owned(),
-                    )
-                })
-                .unwrap();
-
-            squashfs.par_entries().for_each(|entry| {
-                if let Some(output_dir) = &write_path {
-                    let file_path = entry.path.strip_prefix("/").unwrap_or(&entry.path);
-                    let output_path = output_dir.join(file_path);
-                    fs::create_dir_all(output_path.parent().unwrap()).unwrap();
-
-                    match entry.kind {
-                        EntryKind::File(basic_file) => {
-                            if output_path.exists() {
-                                return;
-                            }
-                            let _ = squashfs.write_file_with_permissions(
-                                basic_file,
-                                &output_path,
-                                entry.header,
-                            );
-                            log!(
-                                args.quiet,
-                                "Wrote {} to {}",
-                                entry.path.display(),
-                                output_path.display()
-                            );
-                        }
-                        EntryKind::Directory => {
-                            if output_path.exists() {
-                                return;
-                            }
-                            fs::create_dir_all(&output_path).unwrap();
-                            fs::set_permissions(
-                                &output_path,
-                                Permissions::from_mode(u32::from(entry.header.permissions)),
-                            )
-                            .unwrap();
-                            log!(
-                                args.quiet,
-                                "Wrote {} to {}",
-                                entry.path.display(),
-                                output_path.display()
-                            );
-                        }
-                        EntryKind::Symlink(e) => {
-                            if output_path.exists() {
-                                return;
-                            }
-                            let original_path = e.strip_prefix("/").unwrap_or(&e);
-                            let _ = unix::fs::symlink(original_path, &output_path);
-                            log!(
-                                args.quiet,
-                                "Wrote {} to {}",
-                                entry.path.display(),
-                                output_path.display()
-                            );
-                        }
-                        _ => {}
-                    };
-                } else {
-                    log!(args.quiet, "{}", entry.path.display());
-                }
-            });
+            let appimage = open_image(&file, offset, out);
+            let output_dir = write.unwrap_or_else(|| std::env::current_dir().unwrap());
+            browse::browse(&appimage, output_dir, out);
         }
+        cli::Commands::Watch { dir, data_dir } => watch::watch(&dir, data_dir, out),
+        cli::Commands::Unsquashfs(args) => unsquashfs::run(args, out),
+        cli::Commands::Manpages { dir } => manpages::manpages(&dir, out),
     }
 }
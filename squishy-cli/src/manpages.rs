@@ -0,0 +1,65 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::{Command, CommandFactory};
+use clap_mangen::Man;
+use serde_json::{json, Value};
+
+use crate::{cli::Args, output::Output};
+
+/// Writes the man page of a command and of its subcommands, recursively.
+///
+/// # Arguments
+/// * `cmd` - The command, already built so subcommands have their full names
+/// * `dir` - Directory to write the pages to
+/// * `written` - Paths of the pages written so far
+///
+/// # Returns
+/// An empty result, or an error if a page can't be written
+fn generate(cmd: Command, dir: &Path, written: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for subcommand in cmd.get_subcommands().filter(|s| !s.is_hide_set()) {
+        generate(subcommand.clone(), dir, written)?;
+    }
+    written.push(Man::new(cmd).generate_to(dir)?);
+    Ok(())
+}
+
+/// Generates man pages for squishy and all its subcommands, e.g. `squishy.1` and
+/// `squishy-unsquashfs.1`, for distribution packages.
+///
+/// # Arguments
+/// * `dir` - Directory to write the pages to, created if missing
+/// * `out` - Output settings
+pub fn manpages(dir: &Path, out: Output) {
+    fs::create_dir_all(dir).unwrap_or_else(|e| out.fail(format!("{}: {}", dir.display(), e)));
+
+    // Pages are named after the binary rather than the package
+    let mut cmd = Args::command()
+        .name("squishy")
+        .bin_name("squishy")
+        .disable_help_subcommand(true);
+    cmd.build();
+    let mut written = Vec::new();
+    generate(cmd, dir, &mut written)
+        .unwrap_or_else(|e| out.fail(format!("{}: {}", dir.display(), e)));
+    written.sort();
+
+    for path in &written {
+        log!(!out.verbose(1), "Wrote {}", path.display());
+    }
+    log!(
+        out.silent(),
+        "Wrote {} man pages to {}",
+        written.len(),
+        dir.display()
+    );
+    if out.json {
+        let pages = written
+            .iter()
+            .map(|path| Value::String(path.to_string_lossy().into_owned()))
+            .collect();
+        out.print_json(&json!({ "dir": dir.to_string_lossy(), "pages": Value::Array(pages) }));
+    }
+}
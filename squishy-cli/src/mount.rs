@@ -0,0 +1,165 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::{ErrorKind, Read},
+    os::{
+        fd::FromRawFd,
+        unix::{fs::MetadataExt, process::CommandExt},
+    },
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicI32, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde_json::json;
+use squishy::appimage::{
+    fuse::{self, FuseMount},
+    AppImage,
+};
+
+use crate::output::{Output, EXIT_FAILURE};
+
+/// How long to wait for the background process to mount the image
+const MOUNT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Write end of the pipe signals are forwarded to, -1 until set up
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// Signal handler forwarding the signal to the pipe. It only loads an atomic and
+/// calls write, which are async-signal-safe.
+extern "C" fn on_signal(_: libc::c_int) {
+    let fd = SIGNAL_PIPE.load(Ordering::Relaxed);
+    // SAFETY: the buffer is a live one byte array. The pipe is set up before the
+    // handler is installed and never closed, and a bad descriptor only makes
+    // write fail
+    unsafe { libc::write(fd, [0_u8].as_ptr().cast(), 1) };
+}
+
+/// Unmounts the image when the process is interrupted or terminated, which makes
+/// serving stop. Unmounting isn't safe in a signal handler, so the handler only
+/// wakes up a thread doing it.
+///
+/// # Arguments
+/// * `mountpoint` - Directory the image is mounted at
+/// * `out` - Output settings
+fn unmount_on_signal(mountpoint: PathBuf, out: Output) {
+    let mut fds = [0; 2];
+    // SAFETY: `fds` has room for the two descriptors written by pipe2
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return;
+    }
+    // The write end stays open for the rest of the process, as the handler can
+    // run at any time
+    SIGNAL_PIPE.store(fds[1], Ordering::Relaxed);
+    for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+        // SAFETY: `on_signal` is an `extern "C"` handler that only makes
+        // async-signal-safe calls
+        unsafe { libc::signal(signal, on_signal as *const () as libc::sighandler_t) };
+    }
+
+    // SAFETY: pipe2 succeeded, so the read end is open, and `signals` is its only
+    // owner
+    let mut signals = unsafe { File::from_raw_fd(fds[0]) };
+    thread::spawn(move || loop {
+        match signals.read(&mut [0_u8]) {
+            Ok(0) => break,
+            Ok(_) => {
+                if let Err(e) = fuse::unmount(&mountpoint) {
+                    elog!(out.quiet_errors, "Failed to unmount: {}", e);
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        }
+    });
+}
+
+/// Mounts the image and serves it until it is unmounted, either externally or on
+/// Ctrl+C.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `file` - Path of the image, for messages
+/// * `mountpoint` - Directory to mount the image at
+/// * `out` - Output settings
+pub fn serve(appimage: &AppImage, file: &Path, mountpoint: &Path, out: Output) {
+    let mount = FuseMount::mount(appimage, mountpoint)
+        .unwrap_or_else(|e| out.fail(format!("{}: {}", mountpoint.display(), e)));
+    unmount_on_signal(mount.mountpoint().to_path_buf(), out);
+    log!(
+        out.silent(),
+        "Mounted {} at {}, press Ctrl+C to unmount",
+        file.display(),
+        mount.mountpoint().display()
+    );
+    if out.json {
+        out.print_json(&json!({
+            "file": file.to_string_lossy(),
+            "mountpoint": mount.mountpoint().to_string_lossy(),
+        }));
+    }
+    mount.run().unwrap_or_else(|e| out.fail(e));
+}
+
+/// Mounts the image from a background process, running this command again with
+/// `--foreground`, and returns once the image is mounted. The background process
+/// exits when the image is unmounted.
+///
+/// # Arguments
+/// * `file` - Path of the image
+/// * `mountpoint` - Directory to mount the image at
+/// * `out` - Output settings
+pub fn spawn(file: &Path, mountpoint: &Path, out: Output) {
+    let mounted_device = |out: Output| {
+        fs::metadata(mountpoint)
+            .unwrap_or_else(|e| out.fail(format!("{}: {}", mountpoint.display(), e)))
+            .dev()
+    };
+    let device = mounted_device(out);
+
+    let exe = env::current_exe().unwrap_or_else(|e| out.fail(e));
+    // The background process keeps serving after this one exits
+    #[allow(clippy::zombie_processes)]
+    let mut child = Command::new(exe)
+        .args(env::args_os().skip(1))
+        .arg("--foreground")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        // Keep Ctrl+C in this terminal from reaching the background process
+        .process_group(0)
+        .spawn()
+        .unwrap_or_else(|e| out.fail(e));
+
+    let started = Instant::now();
+    while mounted_device(out) == device {
+        if let Ok(Some(status)) = child.try_wait() {
+            // The background process already reported the error
+            std::process::exit(status.code().unwrap_or(EXIT_FAILURE));
+        }
+        if started.elapsed() > MOUNT_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            out.fail(format!("Timed out mounting {}", file.display()));
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let mountpoint = mountpoint
+        .canonicalize()
+        .unwrap_or(mountpoint.to_path_buf());
+    log!(
+        out.silent(),
+        "Mounted {} at {}",
+        file.display(),
+        mountpoint.display()
+    );
+    if out.json {
+        out.print_json(&json!({
+            "file": file.to_string_lossy(),
+            "mountpoint": mountpoint.to_string_lossy(),
+            "pid": child.id(),
+        }));
+    }
+}
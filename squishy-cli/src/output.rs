@@ -0,0 +1,179 @@
+use std::{
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+
+use serde_json::{Map, Value};
+
+use crate::{cli::OutputFormat, color};
+
+/// Exit code when some of the requested files weren't found or written, or when
+/// validating an AppImage finds only warnings
+pub const EXIT_PARTIAL: i32 = 1;
+
+/// Exit code when the file is missing or isn't a readable image, or when an
+/// AppImage fails validation
+pub const EXIT_INVALID: i32 = 2;
+
+/// Exit code of the other fatal errors
+pub const EXIT_FAILURE: i32 = -1;
+
+/// File every log and error message is appended to, set by `--log-file`
+static LOG_FILE: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Opens the log file in append mode, creating it if needed.
+///
+/// # Arguments
+/// * `path` - Path to the log file
+///
+/// # Returns
+/// An empty result, or an error if the file can't be opened
+pub fn open_log_file(path: &Path) -> std::io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let _ = LOG_FILE.set(Mutex::new(file));
+    Ok(())
+}
+
+/// Whether messages are appended to a log file.
+pub fn has_log_file() -> bool {
+    LOG_FILE.get().is_some()
+}
+
+/// Appends a message to the log file, if any, without its colors. Write errors
+/// are ignored so logging never stops the command.
+///
+/// # Arguments
+/// * `line` - The message
+pub fn log_to_file(line: &str) {
+    if let Some(file) = LOG_FILE.get() {
+        let _ = writeln!(file.lock().unwrap(), "{}", console::strip_ansi_codes(line));
+    }
+}
+
+/// Output settings shared by the subcommands
+#[derive(Clone, Copy)]
+pub struct Output {
+    /// Whether to suppress informational log output
+    pub quiet: bool,
+    /// Whether to suppress error messages too
+    pub quiet_errors: bool,
+    /// Level of detail of the log output, from the number of `-v`
+    pub verbose: u8,
+    /// Whether to print JSON results instead of log lines
+    pub json: bool,
+    /// Whether to terminate listed entries with a NUL byte
+    pub print0: bool,
+    /// Format of listed entries
+    pub format: OutputFormat,
+}
+
+impl Output {
+    /// Whether log lines are suppressed, either by `--quiet` or by `--json`.
+    pub fn silent(&self) -> bool {
+        self.quiet || self.json
+    }
+
+    /// Whether error messages are suppressed, either by `-qq` or by `--json`,
+    /// which reports errors in the JSON result instead.
+    pub fn errors_silent(&self) -> bool {
+        self.quiet_errors || self.json
+    }
+
+    /// Whether details at the verbosity level are logged.
+    ///
+    /// # Arguments
+    /// * `level` - Number of `-v` needed for the details
+    ///
+    /// # Returns
+    /// boolean stating if the details are logged
+    pub fn verbose(&self, level: u8) -> bool {
+        !self.silent() && self.verbose >= level
+    }
+
+    /// Whether listed entries are printed as tab separated fields.
+    pub fn tsv(&self) -> bool {
+        self.format == OutputFormat::Tsv
+    }
+
+    /// Prints a listed entry on stdout, terminated by a NUL byte with `--print0`
+    /// or by a newline otherwise.
+    ///
+    /// # Arguments
+    /// * `record` - The entry line
+    pub fn record(&self, record: impl Display) {
+        if self.quiet {
+            return;
+        }
+        if self.print0 {
+            print!("{}\0", record);
+        } else {
+            println!("{}", record);
+        }
+    }
+
+    /// Prints a JSON result on stdout.
+    ///
+    /// # Arguments
+    /// * `value` - The result
+    pub fn print_json(&self, value: &Value) {
+        println!("{}", value);
+    }
+
+    /// Reports a fatal error and exits. See [`Output::fail_with`].
+    ///
+    /// # Arguments
+    /// * `message` - The error message
+    pub fn fail(&self, message: impl Display) -> ! {
+        self.fail_with(Map::new(), message)
+    }
+
+    /// Reports a fatal error and exits. In JSON mode, the error is added to the
+    /// partial result under the `error` key and printed on stdout, otherwise it is
+    /// logged on stderr unless error messages are suppressed with `-qq`.
+    ///
+    /// # Arguments
+    /// * `result` - Partial result gathered before the error
+    /// * `message` - The error message
+    pub fn fail_with(&self, result: Map<String, Value>, message: impl Display) -> ! {
+        self.exit_with(EXIT_FAILURE, result, message)
+    }
+
+    /// Reports that the file is missing or isn't a readable image, and exits with
+    /// [`EXIT_INVALID`]. See [`Output::fail_with`].
+    ///
+    /// # Arguments
+    /// * `message` - The error message
+    pub fn fail_invalid(&self, message: impl Display) -> ! {
+        self.fail_invalid_with(Map::new(), message)
+    }
+
+    /// Reports that the file is invalid like [`Output::fail_invalid`], along with
+    /// the partial result. See [`Output::fail_with`].
+    ///
+    /// # Arguments
+    /// * `result` - Partial result gathered before the error
+    /// * `message` - The error message
+    pub fn fail_invalid_with(&self, result: Map<String, Value>, message: impl Display) -> ! {
+        self.exit_with(EXIT_INVALID, result, message)
+    }
+
+    /// Reports a fatal error like [`Output::fail_with`], and exits with the code.
+    ///
+    /// # Arguments
+    /// * `code` - The exit code
+    /// * `result` - Partial result gathered before the error
+    /// * `message` - The error message
+    fn exit_with(&self, code: i32, mut result: Map<String, Value>, message: impl Display) -> ! {
+        if self.json {
+            log_to_file(&message.to_string());
+            result.insert("error".into(), Value::String(message.to_string()));
+            self.print_json(&Value::Object(result));
+        } else {
+            elog!(self.quiet_errors, "{}", color::error(message));
+        }
+        std::process::exit(code);
+    }
+}
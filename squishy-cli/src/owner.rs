@@ -0,0 +1,76 @@
+use std::fs;
+
+/// Owner to give to extracted entries
+#[derive(Clone, Copy)]
+pub enum Owner {
+    /// The uid and gid recorded in the image
+    Preserve,
+    /// A fixed user and group, each left unchanged if None
+    Fixed(Option<u32>, Option<u32>),
+}
+
+/// Looks up the id of a name in `/etc/passwd` or `/etc/group`, where the id is
+/// the third field of each line.
+///
+/// # Arguments
+/// * `database` - Path to the database
+/// * `name` - The user or group name
+///
+/// # Returns
+/// The id, if the name is found
+fn lookup_id(database: &str, name: &str) -> Option<u32> {
+    fs::read_to_string(database)
+        .ok()?
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.first() == Some(&name))
+        .and_then(|fields| fields.get(2)?.parse().ok())
+}
+
+/// Resolves a user or group given by name or numeric id.
+///
+/// # Arguments
+/// * `database` - Path to the database to look names up in
+/// * `value` - The name or id, or an empty string
+///
+/// # Returns
+/// The id, None if the value is empty, or an error if the name is unknown
+fn resolve(database: &str, value: &str) -> Result<Option<u32>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .ok()
+        .or_else(|| lookup_id(database, value))
+        .map(Some)
+        .ok_or_else(|| format!("Unknown name: {}", value))
+}
+
+/// Parses an owner given as `user[:group]`, like `chown`. Users and groups can
+/// be names or numeric ids, and either can be left empty to keep it unchanged.
+///
+/// # Arguments
+/// * `spec` - The owner
+///
+/// # Returns
+/// The owner, or an error if a name is unknown
+pub fn parse_owner(spec: &str) -> Result<Owner, String> {
+    let (user, group) = spec.split_once(':').unwrap_or((spec, ""));
+    Ok(Owner::Fixed(
+        resolve("/etc/passwd", user)?,
+        resolve("/etc/group", group)?,
+    ))
+}
+
+/// Checks whether the process runs with root privileges, from the effective uid
+/// in `/proc/self/status`.
+pub fn is_root() -> bool {
+    fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            let uids = status.lines().find_map(|line| line.strip_prefix("Uid:"))?;
+            uids.split_whitespace().nth(1).map(|euid| euid == "0")
+        })
+        .unwrap_or(false)
+}
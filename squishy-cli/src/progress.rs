@@ -0,0 +1,61 @@
+use std::io::IsTerminal;
+
+use indicatif::{ProgressBar, ProgressStyle};
+use squishy::progress::Progress;
+
+use crate::output::Output;
+
+/// Progress bar of an extraction, showing the files and bytes written and the
+/// estimated time left. It is only shown when stdout is a terminal and output
+/// isn't silenced.
+pub struct ExtractProgress {
+    bar: Option<ProgressBar>,
+}
+
+impl ExtractProgress {
+    /// Creates the progress bar, hidden if output is silenced or stdout isn't a
+    /// terminal.
+    ///
+    /// # Arguments
+    /// * `out` - Output settings
+    pub fn new(out: Output) -> Self {
+        let bar = (!out.silent() && std::io::stdout().is_terminal()).then(|| {
+            let bar = ProgressBar::new(0);
+            bar.set_style(
+                ProgressStyle::with_template(
+                    "{bar:40} {binary_bytes}/{binary_total_bytes} {msg} ETA {eta}",
+                )
+                .unwrap(),
+            );
+            bar
+        });
+        ExtractProgress { bar }
+    }
+
+    /// Updates the progress bar, used as the library progress callback.
+    ///
+    /// # Arguments
+    /// * `progress` - The current progress
+    pub fn update(&self, progress: Progress) {
+        if let Some(bar) = &self.bar {
+            bar.set_length(progress.bytes_total);
+            bar.set_position(progress.bytes_done);
+            bar.set_message(format!(
+                "{}/{} files",
+                progress.files_done, progress.files_total
+            ));
+        }
+    }
+
+    /// Whether the progress bar is shown.
+    pub fn is_shown(&self) -> bool {
+        self.bar.is_some()
+    }
+
+    /// Removes the progress bar.
+    pub fn finish(&self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
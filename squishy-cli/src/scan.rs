@@ -0,0 +1,65 @@
+use std::path::Path;
+
+use serde_json::{json, Value};
+use squishy::{error::SquishyError, scan::scan as scan_file};
+
+use crate::{
+    color,
+    format::{format_time, human_size},
+    output::Output,
+};
+
+/// Searches a file for SquashFS superblocks and prints the offset of each, with
+/// its version, compressor, block size and size, to find the `--offset` of a
+/// filesystem in a firmware blob or an unusual bundle.
+///
+/// # Arguments
+/// * `file` - Path of the file
+/// * `out` - Output settings
+pub fn scan(file: &Path, out: Output) {
+    if !file.exists() {
+        out.fail_invalid(format!("{}: No such file", file.display()));
+    }
+    let candidates = scan_file(file).unwrap_or_else(|e| out.fail(e));
+    if candidates.is_empty() {
+        out.fail_invalid(SquishyError::NoSquashFsFound);
+    }
+
+    if out.json {
+        let candidates = candidates
+            .iter()
+            .map(|candidate| {
+                json!({
+                    "offset": candidate.offset,
+                    "version": format!("{}.{}", candidate.version.0, candidate.version.1),
+                    "compressor": candidate.compressor,
+                    "block_size": candidate.block_size,
+                    "inodes": candidate.inode_count,
+                    "bytes_used": candidate.bytes_used,
+                    "mtime": candidate.mod_time,
+                    "truncated": candidate.truncated,
+                })
+            })
+            .collect();
+        out.print_json(&Value::Array(candidates));
+        return;
+    }
+
+    for candidate in &candidates {
+        let mut line = format!(
+            "{}  SquashFS {}.{}, {}, block size {}, {} inodes, {}, {}",
+            candidate.offset,
+            candidate.version.0,
+            candidate.version.1,
+            candidate.compressor,
+            human_size(candidate.block_size as u64),
+            candidate.inode_count,
+            color::size(human_size(candidate.bytes_used)),
+            format_time(candidate.mod_time)
+        );
+        if candidate.truncated {
+            line.push_str(&format!(" {}", color::error("(truncated)")));
+        }
+        out.record(line);
+    }
+}
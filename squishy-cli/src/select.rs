@@ -0,0 +1,305 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use glob::{MatchOptions, Pattern, PatternError};
+use squishy::{error::SquishyError, EntryKind, SquashFSEntry};
+
+/// Options for matching paths: case-sensitive, and `*` doesn't match `/`
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Options for matching path components like upstream unsquashfs, which uses
+/// `fnmatch` with `FNM_PERIOD`: a leading `.` must be matched explicitly
+const WILDCARD_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: true,
+};
+
+/// A compiled selection pattern
+enum Matcher {
+    /// Glob matched against the whole path, `**` matches any number of directories
+    Glob(Pattern),
+    /// squashfs-tools wildcard, matched one path component at a time
+    Wildcard {
+        /// Whether the pattern started with `...`, matching at any depth
+        recursive: bool,
+        /// Globs of the path components
+        components: Vec<Pattern>,
+    },
+}
+
+impl Matcher {
+    /// Compiles a pattern, ignoring leading `/`.
+    ///
+    /// # Arguments
+    /// * `pattern` - The pattern
+    /// * `wildcards` - Whether to use squashfs-tools wildcard semantics
+    ///
+    /// # Returns
+    /// The compiled pattern, or an error if it is not a valid glob
+    fn new(pattern: &str, wildcards: bool) -> squishy::Result<Self> {
+        let invalid = |e: PatternError| SquishyError::InvalidPattern(format!("{}: {}", pattern, e));
+        let trimmed = pattern.trim_start_matches('/');
+        if !wildcards {
+            return Pattern::new(trimmed).map(Matcher::Glob).map_err(invalid);
+        }
+
+        let (recursive, rest) = match trimmed.strip_prefix("...") {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => (true, rest),
+            _ => (false, trimmed),
+        };
+        let components = rest
+            .split('/')
+            .filter(|component| !component.is_empty() && *component != ".")
+            .map(Pattern::new)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(invalid)?;
+        Ok(Matcher::Wildcard {
+            recursive,
+            components,
+        })
+    }
+
+    /// Checks whether the path, or one of the directories it is in, matches.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the entry, relative to the image root
+    ///
+    /// # Returns
+    /// boolean stating if the path matches
+    fn matches(&self, path: &Path) -> bool {
+        match self {
+            Matcher::Glob(pattern) => path
+                .ancestors()
+                .filter(|ancestor| !ancestor.as_os_str().is_empty())
+                .any(|ancestor| pattern.matches_path_with(ancestor, MATCH_OPTIONS)),
+            Matcher::Wildcard {
+                recursive,
+                components,
+            } => {
+                let names = path
+                    .iter()
+                    .map(|name| name.to_string_lossy())
+                    .collect::<Vec<_>>();
+                let matches_at = |start: usize| {
+                    names.len() >= start + components.len()
+                        && components
+                            .iter()
+                            .zip(&names[start..])
+                            .all(|(pattern, name)| pattern.matches_with(name, WILDCARD_OPTIONS))
+                };
+                if *recursive {
+                    (0..names.len()).any(matches_at)
+                } else {
+                    matches_at(0)
+                }
+            }
+        }
+    }
+}
+
+/// Compiles selection patterns.
+///
+/// # Arguments
+/// * `patterns` - The patterns
+/// * `wildcards` - Whether to use squashfs-tools wildcard semantics
+///
+/// # Returns
+/// The compiled patterns, or an error if a pattern is not a valid glob
+fn compile(patterns: &[String], wildcards: bool) -> squishy::Result<Vec<Matcher>> {
+    patterns
+        .iter()
+        .map(|pattern| Matcher::new(pattern, wildcards))
+        .collect()
+}
+
+/// Checks whether the path, or one of the directories it is in, matches a pattern.
+fn matches_any(patterns: &[Matcher], path: &Path) -> bool {
+    let path = path.strip_prefix("/").unwrap_or(path);
+    patterns.iter().any(|pattern| pattern.matches(path))
+}
+
+/// Selection of the entries to list or extract, from patterns matched against
+/// paths relative to the image root
+pub struct Selection {
+    include: Vec<Matcher>,
+    exclude: Vec<Matcher>,
+}
+
+impl Selection {
+    /// Creates a selection from patterns. Without include patterns, every entry
+    /// that isn't excluded is selected.
+    ///
+    /// By default, patterns are globs matched against the whole path, where `**`
+    /// matches any number of directories. With `wildcards`, they follow upstream
+    /// unsquashfs instead: they are matched one path component at a time, `*`
+    /// doesn't match a leading `.`, and a pattern starting with `...` matches at
+    /// any depth.
+    ///
+    /// # Arguments
+    /// * `include` - Patterns of the paths to select, leading `/` are ignored
+    /// * `exclude` - Patterns of the paths to skip, leading `/` are ignored
+    /// * `wildcards` - Whether to use squashfs-tools wildcard semantics
+    ///
+    /// # Returns
+    /// The selection, or an error if a pattern is not a valid glob
+    pub fn new(include: &[String], exclude: &[String], wildcards: bool) -> squishy::Result<Self> {
+        Ok(Selection {
+            include: compile(include, wildcards)?,
+            exclude: compile(exclude, wildcards)?,
+        })
+    }
+
+    /// Checks whether the path is selected: it, or one of the directories it is
+    /// in, matches an include pattern, and neither matches an exclude pattern. So
+    /// matching a directory selects or skips everything below it.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the entry in the image
+    ///
+    /// # Returns
+    /// boolean stating if the path is selected
+    pub fn matches(&self, path: &Path) -> bool {
+        (self.include.is_empty() || matches_any(&self.include, path))
+            && !matches_any(&self.exclude, path)
+    }
+
+    /// Keeps the selected entries, along with the directories leading to them so
+    /// they are created with their own permissions.
+    ///
+    /// # Arguments
+    /// * `entries` - All the entries of the image
+    ///
+    /// # Returns
+    /// The selected entries, in their original order
+    pub fn select<'a>(&self, entries: Vec<SquashFSEntry<'a>>) -> Vec<SquashFSEntry<'a>> {
+        if self.include.is_empty() && self.exclude.is_empty() {
+            return entries;
+        }
+        let selected = entries
+            .iter()
+            .filter(|entry| self.matches(&entry.path))
+            .map(|entry| entry.path.clone())
+            .collect::<HashSet<PathBuf>>();
+        let parents = selected
+            .iter()
+            .flat_map(|path| path.ancestors().skip(1))
+            .map(Path::to_path_buf)
+            .collect::<HashSet<_>>();
+
+        entries
+            .into_iter()
+            .filter(|entry| {
+                selected.contains(&entry.path)
+                    || (entry.kind == EntryKind::Directory && parents.contains(&entry.path))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use backhand::NodeHeader;
+
+    use super::*;
+
+    fn new_selection(include: &[&str], exclude: &[&str], wildcards: bool) -> Selection {
+        let strings =
+            |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        Selection::new(&strings(include), &strings(exclude), wildcards).unwrap()
+    }
+
+    fn entry(path: &str, kind: EntryKind<'static>) -> SquashFSEntry<'static> {
+        SquashFSEntry {
+            header: NodeHeader::new(0o755, 0, 0, 0),
+            path: PathBuf::from(path),
+            size: 0,
+            kind,
+        }
+    }
+
+    #[test]
+    fn matches_globs_against_whole_paths() {
+        let selection = new_selection(&["/usr/share/*.desktop", "usr/lib/**/*.so"], &[], false);
+        assert!(selection.matches(Path::new("/usr/share/app.desktop")));
+        assert!(selection.matches(Path::new("/usr/lib/x86_64/deep/libfoo.so")));
+        // `*` doesn't match `/`
+        assert!(!selection.matches(Path::new("/usr/share/applications/app.desktop")));
+        assert!(!selection.matches(Path::new("/usr/share/App.DESKTOP")));
+    }
+
+    #[test]
+    fn matching_directory_selects_contents() {
+        let selection = new_selection(&["usr/share"], &[], false);
+        assert!(selection.matches(Path::new("/usr/share/icons/app.png")));
+        assert!(!selection.matches(Path::new("/usr/bin/app")));
+        assert!(new_selection(&[], &[], false).matches(Path::new("/anything")));
+        assert!(matches!(
+            Selection::new(&["usr/[".into()], &[], false),
+            Err(SquishyError::InvalidPattern(_))
+        ));
+    }
+
+    #[test]
+    fn selects_parent_directories() {
+        let entries = vec![
+            entry("/", EntryKind::Directory),
+            entry("/usr", EntryKind::Directory),
+            entry("/usr/bin", EntryKind::Directory),
+            entry("/usr/bin/app", EntryKind::Unknown),
+            entry("/usr/lib", EntryKind::Directory),
+            entry("/AppRun", EntryKind::Unknown),
+        ];
+        let selected = new_selection(&["usr/bin/app"], &[], false)
+            .select(entries)
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            selected,
+            ["/", "/usr", "/usr/bin", "/usr/bin/app"].map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn excludes_take_precedence() {
+        let selection = new_selection(&["usr"], &["usr/share/doc", "**/*.a"], false);
+        assert!(selection.matches(Path::new("/usr/bin/app")));
+        assert!(!selection.matches(Path::new("/usr/share/doc/README")));
+        assert!(!selection.matches(Path::new("/usr/lib/libfoo.a")));
+
+        let selection = new_selection(&[], &["usr/share"], false);
+        assert!(selection.matches(Path::new("/AppRun")));
+        assert!(!selection.matches(Path::new("/usr/share/icons")));
+    }
+
+    #[test]
+    fn matches_wildcards_by_component() {
+        let selection = new_selection(&["usr/*/app*"], &[], true);
+        assert!(selection.matches(Path::new("/usr/bin/app")));
+        assert!(selection.matches(Path::new("/usr/bin/app/data")));
+        assert!(!selection.matches(Path::new("/usr/bin/tools/app")));
+
+        // `*` doesn't match a leading `.`
+        let selection = new_selection(&["*"], &[], true);
+        assert!(selection.matches(Path::new("/AppRun")));
+        assert!(!selection.matches(Path::new("/.DirIcon")));
+        assert!(new_selection(&[".*"], &[], true).matches(Path::new("/.DirIcon")));
+    }
+
+    #[test]
+    fn matches_recursive_wildcards_at_any_depth() {
+        let selection = new_selection(&[".../*.png"], &[], true);
+        assert!(selection.matches(Path::new("/icon.png")));
+        assert!(selection.matches(Path::new("/usr/share/icons/icon.png")));
+        assert!(!selection.matches(Path::new("/usr/share/icons/icon.svg")));
+        // Only a `...` component is recursive
+        assert!(!new_selection(&["...png"], &[], true).matches(Path::new("/usr/a.png")));
+    }
+}
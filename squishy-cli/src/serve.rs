@@ -0,0 +1,366 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    io::{self, Read},
+    os::unix::ffi::OsStringExt,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use serde_json::json;
+use squishy::{appimage::AppImage, EntryKind, SquashFSEntry};
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+use crate::{format::entry_json, list::sorted_entries, output::Output};
+
+/// Number of requests served at the same time
+const WORKERS: usize = 4;
+/// Size of the chunks files are read and sent in
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// Reads a byte range of a file entry in chunks, so large files are streamed
+/// rather than read whole.
+struct EntryReader<'s, 'a> {
+    appimage: &'s AppImage<'a>,
+    entry: &'s SquashFSEntry<'s>,
+    /// Position of the next chunk to read
+    position: u64,
+    /// End of the range, exclusive
+    end: u64,
+    chunk: Vec<u8>,
+    /// Bytes of the chunk already read
+    consumed: usize,
+}
+
+impl Read for EntryReader<'_, '_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.consumed == self.chunk.len() {
+            if self.position >= self.end {
+                return Ok(0);
+            }
+            let length = CHUNK_SIZE.min(self.end - self.position);
+            self.chunk = self
+                .appimage
+                .read_range(self.entry, self.position, length)
+                .map_err(io::Error::other)?;
+            if self.chunk.is_empty() {
+                return Ok(0);
+            }
+            self.position += self.chunk.len() as u64;
+            self.consumed = 0;
+        }
+        let len = buf.len().min(self.chunk.len() - self.consumed);
+        buf[..len].copy_from_slice(&self.chunk[self.consumed..self.consumed + len]);
+        self.consumed += len;
+        Ok(len)
+    }
+}
+
+/// Entries of the image, by path and by directory
+struct Index<'a> {
+    entries: HashMap<PathBuf, &'a SquashFSEntry<'a>>,
+    children: HashMap<PathBuf, Vec<&'a SquashFSEntry<'a>>>,
+}
+
+/// Decodes the `%XX` escapes of a URL path.
+///
+/// # Arguments
+/// * `path` - The path of the URL, without query
+///
+/// # Returns
+/// The decoded path, or None if an escape is invalid
+fn percent_decode(path: &str) -> Option<PathBuf> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Some(PathBuf::from(OsString::from_vec(decoded)))
+}
+
+/// Guesses the content type of a file from its extension.
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("txt" | "desktop" | "md" | "sh" | "conf" | "ini") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Parses a single `bytes` range of a `Range` header against the file size.
+/// Multiple ranges aren't supported, and are answered with the whole file.
+///
+/// # Arguments
+/// * `range` - Value of the header
+/// * `size` - Size of the file
+///
+/// # Returns
+/// None to send the whole file, or the start and exclusive end of the range, or
+/// an error if the range can't be satisfied
+fn parse_range(range: &str, size: u64) -> Option<Result<(u64, u64), ()>> {
+    let range = range.trim().strip_prefix("bytes=")?;
+    if range.contains(',') {
+        return None;
+    }
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", "") => return None,
+        // The last bytes of the file
+        ("", suffix) => {
+            let suffix = suffix.parse::<u64>().ok()?;
+            (size.saturating_sub(suffix), size)
+        }
+        (start, "") => (start.parse().ok()?, size),
+        (start, end) => {
+            let (start, end) = (start.parse::<u64>().ok()?, end.parse::<u64>().ok()?);
+            // A range ending before it starts is invalid, and ignored
+            if end < start {
+                return None;
+            }
+            (start, end.saturating_add(1).min(size))
+        }
+    };
+    Some(if start < end {
+        Ok((start, end))
+    } else {
+        Err(())
+    })
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+/// Answers a request for a path of the image: files with their contents, and
+/// directories with a JSON index of their entries.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `index` - Entries of the image
+/// * `request` - The request
+///
+/// # Returns
+/// The status code of the response, or an error if it can't be sent
+fn respond(appimage: &AppImage, index: &Index, request: Request) -> io::Result<u16> {
+    let text = |status: u16, message: &str| {
+        Response::from_string(message)
+            .with_status_code(status)
+            .with_header(header("Content-Type", "text/plain; charset=utf-8"))
+    };
+    if !matches!(request.method(), Method::Get | Method::Head) {
+        let response = text(405, "Method not allowed").with_header(header("Allow", "GET, HEAD"));
+        request.respond(response)?;
+        return Ok(405);
+    }
+
+    let url_path = request.url().split(['?', '#']).next().unwrap_or("/");
+    let path = percent_decode(url_path).map(|path| {
+        // Normalize to the form of entry paths, without `.` or trailing `/`
+        Path::new("/").join(
+            path.components()
+                .filter(|c| c.as_os_str() != "/")
+                .collect::<PathBuf>(),
+        )
+    });
+    let entry = path
+        .as_ref()
+        .and_then(|path| index.entries.get(path).copied());
+    let resolved;
+    let entry = match entry {
+        Some(entry) if matches!(entry.kind, EntryKind::Symlink(_)) => {
+            match appimage.resolve_symlink(entry).ok().flatten() {
+                Some(target) => {
+                    resolved = target;
+                    Some(&resolved)
+                }
+                None => None,
+            }
+        }
+        entry => entry,
+    };
+
+    let is_root = path.as_deref() == Some(Path::new("/"));
+    match entry {
+        Some(entry) if matches!(entry.kind, EntryKind::File(_)) => {
+            let size = u64::from(entry.size);
+            let range = request
+                .headers()
+                .iter()
+                .find(|header| header.field.equiv("Range"))
+                .and_then(|header| parse_range(header.value.as_str(), size));
+            let (status, start, end) = match range {
+                None => (200, 0, size),
+                Some(Ok((start, end))) => (206, start, end),
+                Some(Err(())) => {
+                    let response = text(416, "Range not satisfiable")
+                        .with_header(header("Content-Range", &format!("bytes */{}", size)));
+                    request.respond(response)?;
+                    return Ok(416);
+                }
+            };
+
+            let reader = EntryReader {
+                appimage,
+                entry,
+                position: start,
+                end,
+                chunk: Vec::new(),
+                consumed: 0,
+            };
+            let mut headers = vec![
+                header("Content-Type", content_type(&entry.path)),
+                header("Accept-Ranges", "bytes"),
+            ];
+            if status == 206 {
+                headers.push(header(
+                    "Content-Range",
+                    &format!("bytes {}-{}/{}", start, end - 1, size),
+                ));
+            }
+            let response = Response::new(
+                StatusCode(status),
+                headers,
+                reader,
+                Some((end - start) as usize),
+                None,
+            )
+            // Send the length, so clients can tell the size and show progress
+            .with_chunked_threshold(usize::MAX);
+            request.respond(response)?;
+            Ok(status)
+        }
+        Some(entry) if entry.kind == EntryKind::Directory => {
+            respond_index(index, &entry.path, request)
+        }
+        None if is_root => respond_index(index, Path::new("/"), request),
+        _ => {
+            request.respond(text(404, "Not found"))?;
+            Ok(404)
+        }
+    }
+}
+
+/// Answers with the JSON index of a directory.
+fn respond_index(index: &Index, dir: &Path, request: Request) -> io::Result<u16> {
+    let entries = index
+        .children
+        .get(dir)
+        .map(|children| children.iter().map(|entry| entry_json(entry)).collect())
+        .unwrap_or_default();
+    let body = json!({
+        "path": dir.to_string_lossy(),
+        "entries": serde_json::Value::Array(entries),
+    });
+    let response = Response::from_string(body.to_string())
+        .with_header(header("Content-Type", "application/json"));
+    request.respond(response)?;
+    Ok(200)
+}
+
+/// Serves the entries of the image read-only over HTTP until interrupted. Files
+/// are sent with range support, directories as a JSON index of their entries,
+/// and symlinks are followed.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `address` - Address to listen on
+/// * `port` - Port to listen on
+/// * `out` - Output settings
+pub fn serve(appimage: &AppImage, address: &str, port: u16, out: Output) {
+    let entries = sorted_entries(appimage);
+    let mut index = Index {
+        entries: HashMap::new(),
+        children: HashMap::new(),
+    };
+    for entry in &entries {
+        index.entries.insert(entry.path.clone(), entry);
+        if let Some(parent) = entry.path.parent() {
+            index
+                .children
+                .entry(parent.to_path_buf())
+                .or_default()
+                .push(entry);
+        }
+    }
+
+    let server = Server::http((address, port))
+        .unwrap_or_else(|e| out.fail(format!("{}:{}: {}", address, port, e)));
+    let url = match server.server_addr().to_ip() {
+        Some(addr) => format!("http://{}", addr),
+        None => format!("http://{}:{}", address, port),
+    };
+    log!(out.silent(), "Serving on {}, press Ctrl+C to stop", url);
+    if out.json {
+        out.print_json(&json!({ "url": url }));
+    }
+
+    thread::scope(|scope| {
+        for _ in 0..WORKERS {
+            scope.spawn(|| {
+                for request in server.incoming_requests() {
+                    let method = request.method().clone();
+                    let url = request.url().to_owned();
+                    match respond(appimage, &index, request) {
+                        Ok(status) => elog!(!out.verbose(1), "{} {} {}", method, url, status),
+                        Err(e) => elog!(!out.verbose(1), "{} {} failed: {}", method, url, e),
+                    }
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ranges_within_the_file() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some(Ok((0, 100))));
+        assert_eq!(parse_range("bytes=900-", 1000), Some(Ok((900, 1000))));
+        assert_eq!(parse_range("bytes=-100", 1000), Some(Ok((900, 1000))));
+        // Ranges past the end are clamped
+        assert_eq!(parse_range("bytes=900-1999", 1000), Some(Ok((900, 1000))));
+        assert_eq!(parse_range("bytes=-2000", 1000), Some(Ok((0, 1000))));
+        assert_eq!(
+            parse_range(&format!("bytes=0-{}", u64::MAX), 1000),
+            Some(Ok((0, 1000)))
+        );
+    }
+
+    #[test]
+    fn rejects_unsatisfiable_ranges() {
+        assert_eq!(parse_range("bytes=1000-", 1000), Some(Err(())));
+        assert_eq!(parse_range("bytes=1000-1999", 1000), Some(Err(())));
+        assert_eq!(parse_range("bytes=-0", 1000), Some(Err(())));
+        assert_eq!(parse_range("bytes=0-", 0), Some(Err(())));
+    }
+
+    #[test]
+    fn ignores_invalid_ranges() {
+        assert_eq!(parse_range("bytes=0-99,200-299", 1000), None);
+        assert_eq!(parse_range("bytes=99-0", 1000), None);
+        assert_eq!(parse_range("bytes=-", 1000), None);
+        assert_eq!(parse_range("bytes=a-b", 1000), None);
+        assert_eq!(parse_range("items=0-99", 1000), None);
+    }
+}
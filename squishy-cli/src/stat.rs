@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+use squishy::{
+    appimage::{AppImage, Payload},
+    xattr::Xattr,
+};
+
+use crate::{
+    find_entry,
+    format::{entry_json, entry_tsv, format_time, kind_name, link_target, mode_string},
+    output::Output,
+};
+
+/// Prints the metadata of an entry of the image: kind, size, mode, owner,
+/// modification time and symlink target, and for SquashFS images the inode number
+/// and extended attributes. Symlinks aren't followed.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `path` - Path of the entry in the image
+/// * `out` - Output settings
+pub fn stat(appimage: &AppImage, path: &Path, out: Output) {
+    let entry = find_entry(appimage, path, out);
+    let (inode, xattrs) = match &appimage.payload {
+        Payload::SquashFS(squashfs) => {
+            let inode = squashfs
+                .inode_numbers()
+                .unwrap_or_else(|e| out.fail(e))
+                .remove(&entry.path);
+            let xattrs = squashfs
+                .xattrs()
+                .unwrap_or_else(|e| out.fail(e))
+                .remove(&entry.path)
+                .unwrap_or_default();
+            (inode, xattrs)
+        }
+        _ => (None, Vec::new()),
+    };
+
+    if out.json {
+        let mut json = entry_json(&entry);
+        json["inode"] = json!(inode);
+        json["xattrs"] = Value::Object(
+            xattrs
+                .iter()
+                .map(|xattr| (xattr.name.clone(), json!(xattr_value(xattr))))
+                .collect::<Map<_, _>>(),
+        );
+        out.print_json(&json);
+        return;
+    }
+    if out.tsv() {
+        out.record(entry_tsv(&entry));
+        return;
+    }
+
+    let quiet = out.quiet;
+    log!(quiet, "  Path: {}", entry.path.display());
+    log!(quiet, "  Kind: {}", kind_name(&entry));
+    log!(quiet, "  Size: {}", entry.size);
+    if let Some(inode) = inode {
+        log!(quiet, " Inode: {}", inode);
+    }
+    log!(
+        quiet,
+        "  Mode: {:04o} ({})",
+        entry.header.permissions,
+        mode_string(&entry)
+    );
+    log!(quiet, "   Uid: {}", entry.header.uid);
+    log!(quiet, "   Gid: {}", entry.header.gid);
+    log!(
+        quiet,
+        "Modify: {} ({})",
+        format_time(entry.header.mtime),
+        entry.header.mtime
+    );
+    if let Some(target) = link_target(&entry) {
+        log!(quiet, "Target: {}", target);
+    }
+    for xattr in &xattrs {
+        log!(quiet, " Xattr: {}={}", xattr.name, xattr_value(xattr));
+    }
+}
+
+/// Formats the value of an extended attribute, with non-printable bytes escaped.
+fn xattr_value(xattr: &Xattr) -> String {
+    xattr.value.escape_ascii().to_string()
+}
@@ -0,0 +1,86 @@
+use rayon::iter::ParallelIterator;
+use serde_json::{json, Value};
+use squishy::{appimage::AppImage, EntryKind};
+
+use crate::{
+    color,
+    format::{human_size, tsv_field},
+    output::Output,
+};
+
+/// Prints the largest files of the image with their share of the total size of
+/// the files, to find out why an image is large. With `compressed`, files are
+/// ranked by the size they take up in the image instead.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `count` - Number of files to print
+/// * `compressed` - Whether to rank files by compressed size
+/// * `bytes` - Whether to print sizes in bytes instead of human readable ones
+/// * `out` - Output settings
+pub fn top(appimage: &AppImage, count: usize, compressed: bool, bytes: bool, out: Output) {
+    let compressed_sizes = compressed.then(|| {
+        appimage
+            .compressed_sizes()
+            .unwrap_or_else(|| out.fail("Compressed sizes are only available for SquashFS images"))
+            .unwrap_or_else(|e| out.fail(e))
+    });
+
+    let mut files = appimage
+        .par_entries()
+        .filter(|entry| matches!(entry.kind, EntryKind::File(_)))
+        .map(|entry| {
+            let size = match &compressed_sizes {
+                Some(sizes) => sizes.get(&entry.path).copied().unwrap_or(0),
+                None => entry.size as u64,
+            };
+            (entry.path, size)
+        })
+        .collect::<Vec<_>>();
+    let total = files.iter().map(|(_, size)| size).sum::<u64>();
+    files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    files.truncate(count);
+
+    let share = |size: u64| {
+        if total == 0 {
+            0.0
+        } else {
+            size as f64 * 100.0 / total as f64
+        }
+    };
+
+    if out.json {
+        let files = files
+            .iter()
+            .map(|(path, size)| {
+                json!({
+                    "path": path.to_string_lossy(),
+                    "size": size,
+                    "percent": (share(*size) * 100.0).round() / 100.0,
+                })
+            })
+            .collect();
+        out.print_json(&json!({ "total": total, "files": Value::Array(files) }));
+        return;
+    }
+
+    for (path, size) in &files {
+        let path = path.to_string_lossy();
+        let percent = share(*size);
+        if out.tsv() {
+            out.record(format!("{}\t{:.2}\t{}", size, percent, tsv_field(&path)));
+        } else {
+            let size = if bytes {
+                size.to_string()
+            } else {
+                human_size(*size)
+            };
+            out.record(format!(
+                "{} {:>6.2}%  {}",
+                color::size(format!("{:>8}", size)),
+                percent,
+                path
+            ));
+        }
+    }
+}
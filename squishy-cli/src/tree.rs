@@ -0,0 +1,136 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use squishy::{appimage::AppImage, EntryKind, SquashFSEntry};
+
+use serde_json::{json, Value};
+
+use crate::{
+    color,
+    format::{entry_json, human_size, link_target},
+    list::sorted_entries,
+    output::Output,
+};
+
+/// Entries of the image grouped by their parent directory
+struct Tree<'a> {
+    children: HashMap<PathBuf, Vec<&'a SquashFSEntry<'a>>>,
+}
+
+impl Tree<'_> {
+    /// Computes the total size of the files in a directory, recursively.
+    fn total_size(&self, dir: &Path) -> u64 {
+        self.children.get(dir).map_or(0, |children| {
+            children
+                .iter()
+                .map(|child| match child.kind {
+                    EntryKind::File(_) => child.size as u64,
+                    EntryKind::Directory => self.total_size(&child.path),
+                    _ => 0,
+                })
+                .sum()
+        })
+    }
+
+    /// Converts an entry to a JSON object, with the total size and the children of
+    /// directories.
+    fn to_json(&self, entry: &SquashFSEntry) -> Value {
+        let mut value = entry_json(entry);
+        if entry.kind == EntryKind::Directory {
+            value["size"] = json!(self.total_size(&entry.path));
+            value["children"] = self.children.get(&entry.path).map_or_else(
+                || json!([]),
+                |children| Value::Array(children.iter().map(|child| self.to_json(child)).collect()),
+            );
+        }
+        value
+    }
+
+    /// Renders the children of a directory, one line per entry.
+    fn render(&self, dir: &Path, prefix: &str, lines: &mut Vec<String>) {
+        let Some(children) = self.children.get(dir) else {
+            return;
+        };
+        for (i, child) in children.iter().enumerate() {
+            let last = i == children.len() - 1;
+            let name = child.path.file_name().map_or_else(
+                || child.path.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            );
+            let mut line = format!(
+                "{}{}{}",
+                prefix,
+                if last { "└── " } else { "├── " },
+                color::entry_path(child, &name)
+            );
+            match child.kind {
+                EntryKind::File(_) => line.push_str(&format!(
+                    " [{}]",
+                    color::size(human_size(child.size as u64))
+                )),
+                EntryKind::Directory => line.push_str(&format!(
+                    "/ [{}]",
+                    color::size(human_size(self.total_size(&child.path)))
+                )),
+                _ => {}
+            }
+            if let Some(target) = link_target(child) {
+                line.push_str(" -> ");
+                line.push_str(&target);
+            }
+            lines.push(line);
+
+            if child.kind == EntryKind::Directory {
+                let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                self.render(&child.path, &prefix, lines);
+            }
+        }
+    }
+}
+
+/// Prints the entries of the image as an indented tree, with the total size of
+/// the files in each directory. In JSON mode, the tree is printed as nested objects.
+///
+/// # Arguments
+/// * `appimage` - The image
+/// * `path` - Directory to start from, the image root by default
+/// * `out` - Output settings
+pub fn tree(appimage: &AppImage, path: Option<&Path>, out: Output) {
+    let root = Path::new("/").join(path.unwrap_or(Path::new("/")));
+    let entries = sorted_entries(appimage);
+
+    let Some(root_entry) = entries.iter().find(|entry| entry.path == root) else {
+        out.fail(format!("{}: No such directory", root.display()));
+    };
+    if root_entry.kind != EntryKind::Directory {
+        out.fail(format!("{}: Not a directory", root.display()));
+    }
+
+    let mut children: HashMap<PathBuf, Vec<&SquashFSEntry>> = HashMap::new();
+    for entry in &entries {
+        if let Some(parent) = entry.path.parent() {
+            children
+                .entry(parent.to_path_buf())
+                .or_default()
+                .push(entry);
+        }
+    }
+    let tree = Tree { children };
+
+    if out.json {
+        out.print_json(&tree.to_json(root_entry));
+        return;
+    }
+
+    let mut lines = vec![format!(
+        "{} [{}]",
+        color::entry_path(root_entry, root.display()),
+        color::size(human_size(tree.total_size(&root)))
+    )];
+    tree.render(&root, "", &mut lines);
+    for line in lines {
+        log!(out.quiet, "{}", line);
+    }
+}
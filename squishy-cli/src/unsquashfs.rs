@@ -0,0 +1,1186 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    fs::{self, File, OpenOptions, Permissions},
+    io::{self, IsTerminal},
+    os::{
+        fd::{AsFd, AsRawFd},
+        unix::{
+            self,
+            fs::{MetadataExt, PermissionsExt},
+        },
+    },
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use filetime::FileTime;
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use serde_json::{json, Map, Value};
+use squishy::{
+    appimage::{get_offset, nested::NestedKind},
+    collision::{Collision, CollisionStrategy},
+    error::SquishyError,
+    progress::ProgressTracker,
+    xattr::Xattr,
+    EntryKind, SquashFS, SquashFSEntry,
+};
+
+use crate::{
+    archive::TarWriter,
+    cli::UnsquashfsArgs,
+    collision_strategy, color,
+    dryrun::{self, PlannedWrite},
+    format::{entry_json, entry_tsv},
+    output::{Output, EXIT_FAILURE},
+    owner::{self, Owner},
+    progress::ExtractProgress,
+    select::Selection,
+};
+
+/// Makes the image given on stdin readable by path, as reading it needs to seek.
+/// A file redirected to stdin is read in place, while anything else, e.g. a pipe
+/// from `curl`, is spooled to an unlinked temporary file, so nothing is left
+/// behind however the process exits.
+///
+/// # Arguments
+/// * `out` - Output settings
+///
+/// # Returns
+/// The spooled file, which must be kept open for the path to stay valid, and the
+/// path to read the image from
+fn stdin_image(out: Output) -> (Option<File>, PathBuf) {
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        out.fail_invalid("Refusing to read the image from a terminal");
+    }
+    let is_file = stdin
+        .as_fd()
+        .try_clone_to_owned()
+        .and_then(|fd| File::from(fd).metadata())
+        .is_ok_and(|metadata| metadata.is_file());
+    if is_file {
+        return (None, PathBuf::from("/proc/self/fd/0"));
+    }
+
+    let spool_path = env::temp_dir().join(format!("squishy-stdin-{}", std::process::id()));
+    let mut spool = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&spool_path)
+        .unwrap_or_else(|e| out.fail(format!("{}: {}", spool_path.display(), e)));
+    let _ = fs::remove_file(&spool_path);
+    io::copy(&mut stdin.lock(), &mut spool)
+        .unwrap_or_else(|e| out.fail(format!("Failed to read stdin: {}", e)));
+
+    let path = PathBuf::from(format!("/proc/self/fd/{}", spool.as_raw_fd()));
+    (Some(spool), path)
+}
+
+/// Removes leading directories from a path, like `tar --strip-components`.
+///
+/// # Arguments
+/// * `path` - The path, relative to the image root
+/// * `count` - Number of leading components to remove
+///
+/// # Returns
+/// The remaining path, or None if nothing remains
+fn strip_leading(path: &Path, count: usize) -> Option<PathBuf> {
+    if count == 0 {
+        return Some(path.to_path_buf());
+    }
+    let stripped = path.components().skip(count).collect::<PathBuf>();
+    (!stripped.as_os_str().is_empty()).then_some(stripped)
+}
+
+/// Lists the directories a path is in, up to the image root.
+fn ancestors(path: &Path) -> Vec<PathBuf> {
+    path.ancestors().skip(1).map(Path::to_path_buf).collect()
+}
+
+/// Replaces symlinks with the entries they point to, like `cp -L`. A symlink to
+/// a directory is replaced by a copy of the whole directory, where symlinks are
+/// dereferenced too. Dangling symlinks, and symlinks to a directory they are in,
+/// which would be copied endlessly, are skipped.
+///
+/// # Arguments
+/// * `squashfs` - The image the entries are from
+/// * `entries` - The selected entries
+/// * `xattrs` - Extended attributes by path, extended with the copied entries
+/// * `out` - Output settings
+///
+/// # Returns
+/// The entries without symlinks, where the copies carry the symlink paths
+fn dereference_symlinks<'a>(
+    squashfs: &'a SquashFS,
+    entries: Vec<SquashFSEntry<'a>>,
+    xattrs: &mut HashMap<PathBuf, Vec<Xattr>>,
+    out: Output,
+) -> Vec<SquashFSEntry<'a>> {
+    let quiet = out.silent();
+    let mut dereferenced = Vec::with_capacity(entries.len());
+    // Symlinks to dereference, with the directories they are in, either in the image
+    // or as copies, which they must not lead back into
+    let mut pending = Vec::new();
+    for entry in entries {
+        match entry.kind {
+            EntryKind::Symlink(_) => {
+                let within = ancestors(&entry.path);
+                pending.push((entry, within));
+            }
+            _ => dereferenced.push(entry),
+        }
+    }
+
+    while let Some((link, within)) = pending.pop() {
+        let target = match squashfs.resolve_symlink(&link) {
+            Ok(Some(target)) => target,
+            Ok(None) => {
+                elog!(quiet, "Skipped dangling symlink {}", link.path.display());
+                continue;
+            }
+            Err(e) => {
+                elog!(quiet, "Skipped symlink {}: {}", link.path.display(), e);
+                continue;
+            }
+        };
+        if target.kind == EntryKind::Directory && within.contains(&target.path) {
+            elog!(quiet, "Skipped symlink loop {}", link.path.display());
+            continue;
+        }
+        if let Some(attrs) = xattrs.get(&target.path).cloned() {
+            xattrs.insert(link.path.clone(), attrs);
+        }
+
+        if target.kind == EntryKind::Directory {
+            let root = target.path.clone();
+            for child in squashfs.find_entries(move |path| path != root && path.starts_with(&root))
+            {
+                let path = link
+                    .path
+                    .join(child.path.strip_prefix(&target.path).unwrap());
+                if let Some(attrs) = xattrs.get(&child.path).cloned() {
+                    xattrs.insert(path.clone(), attrs);
+                }
+                match child.kind {
+                    EntryKind::Symlink(_) => {
+                        let mut child_within = within.clone();
+                        child_within.extend(ancestors(&child.path));
+                        pending.push((SquashFSEntry { path, ..child }, child_within));
+                    }
+                    _ => dereferenced.push(SquashFSEntry { path, ..child }),
+                }
+            }
+        }
+        dereferenced.push(SquashFSEntry {
+            path: link.path,
+            ..target
+        });
+    }
+    dereferenced
+}
+
+/// How entries are written, the same for the image and the images nested in it
+struct WriteOptions {
+    collision: CollisionStrategy,
+    owner: Option<Owner>,
+    dmode: Option<u32>,
+    fmode: Option<u32>,
+    no_perms: bool,
+    preserve_mtime: bool,
+    xattrs: bool,
+    hardlinks: bool,
+    keep_going: bool,
+    resume: bool,
+    /// Modification time of every entry when extracting reproducibly, which also
+    /// normalizes modes and writes entries in order
+    reproducible: Option<FileTime>,
+}
+
+/// Results of an extraction, gathered across the image and the images nested in it
+#[derive(Default)]
+struct Report {
+    written: Mutex<Vec<Value>>,
+    skipped: Mutex<Vec<String>>,
+    failed: Mutex<Vec<Value>>,
+    /// Modification times of the written directories, set once everything is
+    /// written as writing into a directory updates its mtime
+    dir_times: Mutex<Vec<(PathBuf, PathBuf, FileTime)>>,
+    /// Number of entries to write
+    total: AtomicUsize,
+}
+
+/// Whether an earlier extraction already wrote the entry to the output path: a
+/// file of the same size and modification time, a directory, or a symlink to the
+/// same target.
+fn is_up_to_date(entry: &SquashFSEntry, output_path: &Path) -> bool {
+    let Ok(metadata) = output_path.symlink_metadata() else {
+        return false;
+    };
+    match &entry.kind {
+        EntryKind::File(_) => {
+            metadata.is_file()
+                && metadata.len() == entry.size as u64
+                && metadata.mtime() == i64::from(entry.header.mtime)
+        }
+        EntryKind::Directory => metadata.is_dir(),
+        EntryKind::Symlink(target) => {
+            metadata.is_symlink()
+                && fs::read_link(output_path)
+                    .is_ok_and(|link| link == target.strip_prefix("/").unwrap_or(target))
+        }
+        _ => false,
+    }
+}
+
+/// Reads the timestamp of reproducible builds from `SOURCE_DATE_EPOCH`, exiting
+/// if it isn't a number of seconds.
+///
+/// # Arguments
+/// * `out` - Output settings
+///
+/// # Returns
+/// The timestamp, or the unix epoch if the variable isn't set
+fn source_date_epoch(out: Output) -> FileTime {
+    let Some(epoch) = env::var_os("SOURCE_DATE_EPOCH").filter(|epoch| !epoch.is_empty()) else {
+        return FileTime::zero();
+    };
+    let seconds = epoch
+        .to_str()
+        .and_then(|epoch| epoch.parse::<i64>().ok())
+        .unwrap_or_else(|| {
+            out.fail_invalid(format!(
+                "Invalid SOURCE_DATE_EPOCH: {}",
+                epoch.to_string_lossy()
+            ))
+        });
+    FileTime::from_unix_time(seconds, 0)
+}
+
+/// Records an entry that couldn't be written.
+fn failed_to(failed: &Mutex<Vec<Value>>, source: &Path, error: &SquishyError) {
+    failed.lock().unwrap().push(json!({
+        "source": source.to_string_lossy(),
+        "error": error.to_string(),
+    }));
+}
+
+/// Writes entries of an image to disk, in parallel.
+///
+/// # Arguments
+/// * `squashfs` - The image the entries are from
+/// * `entries` - The entries to write
+/// * `output_of` - Output path of an entry path, None to skip the entry
+/// * `source_prefix` - Path the entry paths are shown under in messages, the path
+///   of a nested image in the outer image
+/// * `xattrs` - Extended attributes by entry path
+/// * `options` - How to write the entries
+/// * `report` - Results of the extraction
+/// * `out` - Output settings
+///
+/// # Returns
+/// An empty result, or the entry that stopped the extraction and its error
+#[allow(clippy::too_many_arguments)]
+fn write_entries<'a>(
+    squashfs: &'a SquashFS,
+    entries: Vec<SquashFSEntry<'a>>,
+    output_of: &(dyn Fn(&Path) -> Option<PathBuf> + Sync),
+    source_prefix: &Path,
+    xattrs: &HashMap<PathBuf, Vec<Xattr>>,
+    options: &WriteOptions,
+    report: &Report,
+    out: Output,
+) -> Result<(), (PathBuf, SquishyError)> {
+    let quiet = out.silent();
+    let source_of = |path: &Path| source_prefix.join(path.strip_prefix("/").unwrap_or(path));
+
+    // Paths of hard linked files by the path written first, which they link to
+    let links = if options.hardlinks {
+        let written = entries
+            .iter()
+            .filter(|entry| output_of(&entry.path).is_some())
+            .map(|entry| &entry.path)
+            .collect::<HashSet<_>>();
+        squashfs
+            .hard_links()
+            .map_err(|e| (source_prefix.to_path_buf(), e))?
+            .into_iter()
+            .flat_map(|paths| {
+                let mut paths = paths.into_iter().filter(|path| written.contains(path));
+                let first = paths.next();
+                paths.filter_map(move |path| Some((path, first.clone()?)))
+            })
+            .collect::<HashMap<_, _>>()
+    } else {
+        HashMap::new()
+    };
+    // Links are made once the files they link to are written
+    let (linked, entries): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .partition(|entry| links.contains_key(&entry.path));
+    report
+        .total
+        .fetch_add(entries.len() + linked.len(), Ordering::Relaxed);
+
+    let progress = ExtractProgress::new(out);
+    let update = |p| progress.update(p);
+    let tracker = ProgressTracker::new(
+        (entries.len() + linked.len()) as u64,
+        entries
+            .iter()
+            .chain(&linked)
+            .map(|entry| entry.size as u64)
+            .sum(),
+        Some(&update),
+    );
+
+    // The progress bar replaces the per-file log lines
+    let verbose = !quiet && !progress.is_shown();
+    let wrote = |source: &Path, output: &Path| {
+        log!(
+            !verbose,
+            "Wrote {} to {}",
+            source.display(),
+            output.display()
+        );
+        report.written.lock().unwrap().push(json!({
+            "source": source.to_string_lossy(),
+            "output": output.to_string_lossy(),
+        }));
+    };
+
+    // None leaves the mode the entry was created with
+    let mode_of = |entry: &SquashFSEntry| {
+        if options.reproducible.is_some() {
+            let executable = entry.header.permissions & 0o111 != 0;
+            return Some(if entry.kind == EntryKind::Directory || executable {
+                0o755
+            } else {
+                0o644
+            });
+        }
+        let fixed = if entry.kind == EntryKind::Directory {
+            options.dmode
+        } else {
+            options.fmode
+        };
+        fixed.or((!options.no_perms).then_some(u32::from(entry.header.permissions)))
+    };
+
+    // Owner, mode, extended attributes and modification time of a written entry
+    let set_metadata = |entry: &SquashFSEntry, output_path: &Path| -> squishy::Result<()> {
+        if let Some(owner) = options.owner {
+            let (uid, gid) = match owner {
+                Owner::Preserve => (Some(entry.header.uid), Some(entry.header.gid)),
+                Owner::Fixed(uid, gid) => (uid, gid),
+            };
+            unix::fs::lchown(output_path, uid, gid)?;
+        }
+        // Set after changing the owner, which clears the setuid and setgid bits
+        if let Some(mode) = mode_of(entry).filter(|_| !matches!(entry.kind, EntryKind::Symlink(_)))
+        {
+            fs::set_permissions(output_path, Permissions::from_mode(mode))?;
+        }
+        // Written last, as changing the owner clears `security.capability`
+        for attr in xattrs.get(&entry.path).into_iter().flatten() {
+            xattr::set(output_path, &attr.name, &attr.value)?;
+        }
+        if options.preserve_mtime || options.reproducible.is_some() {
+            let mtime = options
+                .reproducible
+                .unwrap_or_else(|| FileTime::from_unix_time(i64::from(entry.header.mtime), 0));
+            if entry.kind == EntryKind::Directory {
+                report.dir_times.lock().unwrap().push((
+                    source_of(&entry.path),
+                    output_path.to_path_buf(),
+                    mtime,
+                ));
+            } else {
+                filetime::set_symlink_file_times(output_path, mtime, mtime)?;
+            }
+        }
+        Ok(())
+    };
+
+    // Directories created by this run, whose metadata is set once everything is
+    // written into them
+    let created_dirs = Mutex::new(HashSet::new());
+
+    let extract = |entry: &SquashFSEntry| -> squishy::Result<()> {
+        if !matches!(
+            entry.kind,
+            EntryKind::File(_) | EntryKind::Directory | EntryKind::Symlink(_)
+        ) {
+            return Ok(());
+        }
+        let Some(output_path) = output_of(&entry.path) else {
+            return Ok(());
+        };
+        fs::create_dir_all(output_path.parent().unwrap())?;
+
+        // The root is the output directory itself, which may be an existing one of
+        // the user's, so it is only created and its metadata is left alone
+        if entry.path.parent().is_none() {
+            fs::create_dir_all(&output_path)?;
+            return Ok(());
+        }
+        let is_dir = entry.kind == EntryKind::Directory;
+        if options.resume && is_up_to_date(entry, &output_path) {
+            // Directories are created along the way, but writing into them changes
+            // their modification time back
+            if is_dir {
+                let mtime = FileTime::from_unix_time(i64::from(entry.header.mtime), 0);
+                report.dir_times.lock().unwrap().push((
+                    source_of(&entry.path),
+                    output_path.clone(),
+                    mtime,
+                ));
+                return Ok(());
+            }
+            log!(!verbose, "Skipped up to date {}", output_path.display());
+            report
+                .skipped
+                .lock()
+                .unwrap()
+                .push(output_path.to_string_lossy().into_owned());
+            return Ok(());
+        }
+        match options.collision.prepare(&output_path, is_dir)? {
+            // Directories are created along the way, so existing ones aren't worth reporting
+            Collision::Skip if is_dir => return Ok(()),
+            Collision::Skip => {
+                log!(!verbose, "Skipped existing {}", output_path.display());
+                report
+                    .skipped
+                    .lock()
+                    .unwrap()
+                    .push(output_path.to_string_lossy().into_owned());
+                return Ok(());
+            }
+            Collision::BackedUp(backup) => {
+                log!(
+                    !verbose,
+                    "Backed up {} to {}",
+                    output_path.display(),
+                    backup.display()
+                );
+            }
+            _ => {}
+        }
+
+        if let Some(first) = links.get(&entry.path).and_then(|first| output_of(first)) {
+            fs::hard_link(first, &output_path)?;
+            wrote(&source_of(&entry.path), &output_path);
+            return Ok(());
+        }
+
+        match &entry.kind {
+            EntryKind::File(basic_file) => {
+                squashfs.write_file(basic_file, &output_path)?;
+            }
+            EntryKind::Directory => {
+                fs::create_dir_all(&output_path)?;
+                created_dirs.lock().unwrap().insert(entry.path.clone());
+                wrote(&source_of(&entry.path), &output_path);
+                return Ok(());
+            }
+            EntryKind::Symlink(e) => {
+                let original_path = e.strip_prefix("/").unwrap_or(e);
+                unix::fs::symlink(original_path, &output_path)?;
+            }
+            _ => {}
+        };
+        set_metadata(entry, &output_path)?;
+        wrote(&source_of(&entry.path), &output_path);
+        Ok(())
+    };
+
+    let finish = |entry: &SquashFSEntry, result: squishy::Result<()>| match result {
+        Err(e) if options.keep_going => {
+            failed_to(&report.failed, &source_of(&entry.path), &e);
+            Ok(())
+        }
+        result => result.map_err(|e| (source_of(&entry.path), e)),
+    };
+    let run = |entry: &SquashFSEntry| {
+        let result = extract(entry);
+        tracker.file_done(entry.size as u64);
+        finish(entry, result)
+    };
+
+    // Directories are created first and in order, so whether one already existed
+    // doesn't depend on the files written into it concurrently
+    let (mut dirs, mut entries): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .partition(|entry| entry.kind == EntryKind::Directory);
+    dirs.sort_by(|a, b| a.path.cmp(&b.path));
+    let aborted = dirs
+        .iter()
+        .try_for_each(run)
+        .and_then(|_| {
+            if options.reproducible.is_some() {
+                entries.sort_by(|a, b| a.path.cmp(&b.path));
+                entries.iter().try_for_each(run)
+            } else {
+                entries.par_iter().try_for_each(run)
+            }
+        })
+        .and_then(|_| linked.iter().try_for_each(run))
+        .and_then(|_| {
+            // Deepest first, as a directory mode may forbid access to its contents.
+            // The root isn't among the created directories, so the output
+            // directory keeps its own metadata
+            let created_dirs = created_dirs.lock().unwrap();
+            dirs.iter()
+                .rev()
+                .filter(|entry| created_dirs.contains(&entry.path))
+                .try_for_each(|entry| {
+                    let result = match output_of(&entry.path) {
+                        Some(output_path) => set_metadata(entry, &output_path),
+                        None => Ok(()),
+                    };
+                    finish(entry, result)
+                })
+        });
+    progress.finish();
+    aborted
+}
+
+/// Extracts the SquashFS images and AppImages among the written files next to
+/// them, to `<name>.extracted`, then the images nested in those, recursively.
+///
+/// # Arguments
+/// * `squashfs` - The image the files were written from
+/// * `selected` - Whether an entry path was selected for writing
+/// * `output_of` - Output path of an entry path
+/// * `source_prefix` - Path the entry paths are shown under in messages
+/// * `options` - How to write the entries
+/// * `report` - Results of the extraction
+/// * `out` - Output settings
+///
+/// # Returns
+/// An empty result, or the entry that stopped the extraction and its error
+fn extract_nested(
+    squashfs: &SquashFS,
+    selected: &dyn Fn(&Path) -> bool,
+    output_of: &dyn Fn(&Path) -> Option<PathBuf>,
+    source_prefix: &Path,
+    options: &WriteOptions,
+    report: &Report,
+    out: Output,
+) -> Result<(), (PathBuf, SquishyError)> {
+    let mut images = squashfs
+        .find_nested_images()
+        .into_iter()
+        .filter(|nested| selected(&nested.entry.path))
+        .collect::<Vec<_>>();
+    images.sort_by(|a, b| a.entry.path.cmp(&b.entry.path));
+
+    for nested in images {
+        let source = source_prefix.join(
+            nested
+                .entry
+                .path
+                .strip_prefix("/")
+                .unwrap_or(&nested.entry.path),
+        );
+        // Skipped when it couldn't be written
+        let Some(image) = output_of(&nested.entry.path).filter(|image| image.is_file()) else {
+            continue;
+        };
+        let mut dest = image.clone().into_os_string();
+        dest.push(".extracted");
+        let dest = PathBuf::from(dest);
+        elog!(
+            !out.verbose(1),
+            "Extracting nested image {} to {}",
+            source.display(),
+            dest.display()
+        );
+
+        let opened = match nested.kind {
+            NestedKind::SquashFS => Ok(0),
+            NestedKind::AppImage => get_offset(&image).map_err(SquishyError::from),
+        }
+        .and_then(|offset| SquashFS::from_path_with_offset(&image, offset))
+        .and_then(|nested_fs| {
+            let xattrs = if options.xattrs {
+                nested_fs.xattrs()?
+            } else {
+                HashMap::new()
+            };
+            Ok((nested_fs, xattrs))
+        });
+        // Files can look like images by chance, which isn't worth failing for
+        let (nested_fs, xattrs) = match opened {
+            Ok(opened) => opened,
+            Err(e) => {
+                elog!(
+                    out.silent(),
+                    "Skipped nested image {}: {}",
+                    source.display(),
+                    e
+                );
+                continue;
+            }
+        };
+
+        let nested_output_of =
+            |path: &Path| Some(dest.join(path.strip_prefix("/").unwrap_or(path)));
+        let entries = nested_fs.par_entries().collect::<Vec<_>>();
+        write_entries(
+            &nested_fs,
+            entries,
+            &nested_output_of,
+            &source,
+            &xattrs,
+            options,
+            report,
+            out,
+        )?;
+        extract_nested(
+            &nested_fs,
+            &|_| true,
+            &nested_output_of,
+            &source,
+            options,
+            report,
+            out,
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes entries of an image as a tar stream instead of extracting them, with
+/// their paths relative to the image root. Exits if an entry can't be added, as
+/// the stream can't be repaired.
+///
+/// # Arguments
+/// * `squashfs` - The image the entries are from
+/// * `entries` - The entries to write
+/// * `target` - Path of the tar file, or `-` for stdout
+/// * `strip_components` - Number of leading directories to remove from the paths
+/// * `options` - Modes and hard link handling, the other options don't apply
+/// * `out` - Output settings
+fn write_tar(
+    squashfs: &SquashFS,
+    mut entries: Vec<SquashFSEntry>,
+    target: &Path,
+    strip_components: usize,
+    options: &WriteOptions,
+    out: Output,
+) {
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    // Index of the hard link group of each linked path
+    let groups = if options.hardlinks {
+        squashfs
+            .hard_links()
+            .unwrap_or_else(|e| out.fail(e))
+            .into_iter()
+            .enumerate()
+            .flat_map(|(group, paths)| paths.into_iter().map(move |path| (path, group)))
+            .collect::<HashMap<_, _>>()
+    } else {
+        HashMap::new()
+    };
+    // Name of the first entry of each hard link group added to the stream
+    let mut linked_to: HashMap<usize, PathBuf> = HashMap::new();
+
+    let mut tar = TarWriter::create(target, out);
+    let mut written = 0;
+    for entry in &entries {
+        let Some(name) = strip_leading(
+            entry.path.strip_prefix("/").unwrap_or(&entry.path),
+            strip_components,
+        ) else {
+            continue;
+        };
+        if name.as_os_str().is_empty() {
+            continue;
+        }
+        let group = groups.get(&entry.path);
+        let added = match group.and_then(|group| linked_to.get(group)) {
+            Some(first) => tar.append_hard_link(entry, &name, first).map(|_| true),
+            None => {
+                let mode = match entry.kind {
+                    EntryKind::Directory => options.dmode,
+                    EntryKind::File(_) => options.fmode,
+                    _ => None,
+                };
+                tar.append(entry, &name, mode, |offset, length| match entry.kind {
+                    EntryKind::File(file) => squashfs.read_range(file, offset, length),
+                    _ => Ok(Vec::new()),
+                })
+            }
+        };
+        match added {
+            Ok(true) => {
+                written += 1;
+                if let Some(group) = group {
+                    linked_to.entry(*group).or_insert(name);
+                }
+                elog!(!out.verbose(1), "Added {}", entry.path.display());
+            }
+            Ok(false) => {}
+            Err(e) => out.fail(format!("Failed to add {}: {}", entry.path.display(), e)),
+        }
+    }
+    tar.finish()
+        .unwrap_or_else(|e| out.fail(format!("{}: {}", target.display(), e)));
+
+    if out.json {
+        out.print_json(&json!({ "written": written, "output": target.to_string_lossy() }));
+    } else {
+        elog!(
+            out.silent(),
+            "Wrote {} entries to {}",
+            written,
+            if target.as_os_str() == "-" {
+                "stdout".into()
+            } else {
+                target.display().to_string()
+            }
+        );
+    }
+}
+
+/// Runs the `unsquashfs` subcommand.
+///
+/// # Arguments
+/// * `args` - Arguments of the subcommand
+/// * `out` - Output settings
+pub fn run(args: UnsquashfsArgs, out: Output) {
+    let UnsquashfsArgs {
+        file,
+        patterns,
+        mut exclude,
+        exclude_from,
+        wildcards,
+        strip_components,
+        offset,
+        write,
+        dry_run,
+        existing,
+        keep_going,
+        resume,
+        reproducible,
+        preserve_owner,
+        owner,
+        xattrs,
+        preserve_mtime,
+        dmode,
+        fmode,
+        no_perms,
+        perms: _,
+        dereference,
+        no_symlinks,
+        hardlinks,
+        recurse_nested,
+        to_tar,
+    } = args;
+    // Resuming replaces the outputs that changed instead of keeping them
+    let collision = collision_strategy(
+        &existing,
+        if resume {
+            CollisionStrategy::Overwrite
+        } else {
+            CollisionStrategy::Skip
+        },
+    );
+    if out.json && to_tar.as_deref() == Some(Path::new("-")) {
+        out.fail_invalid("--json can't be used when writing the tar stream to stdout");
+    }
+
+    let (_spool, file) = if file.as_os_str() == "-" {
+        stdin_image(out)
+    } else {
+        (None, file)
+    };
+
+    let owner = if preserve_owner {
+        if !owner::is_root() {
+            out.fail("--preserve-owner requires root");
+        }
+        Some(Owner::Preserve)
+    } else {
+        owner
+    };
+
+    let write_path = if let Some(write) = write {
+        if let Some(path) = write {
+            if !dry_run {
+                fs::create_dir_all(&path)
+                    .unwrap_or_else(|e| out.fail(format!("{}: {}", path.display(), e)));
+            }
+            Some(path)
+        } else {
+            Some(std::env::current_dir().unwrap())
+        }
+    } else {
+        None
+    };
+
+    let offset = match offset {
+        Some(offset) => offset,
+        None => get_offset(&file).unwrap_or_else(|e| out.fail_invalid(e)),
+    };
+    elog!(!out.verbose(1), "SquashFS offset: {}", offset);
+    let squashfs = SquashFS::from_path_with_offset(&file, offset).unwrap_or_else(|_| {
+        out.fail_invalid(SquishyError::InvalidSquashFS(
+            "Couldn't find squashfs. Try providing valid offset.".to_owned(),
+        ))
+    });
+
+    let read_xattrs = xattrs && write_path.is_some() && !dry_run;
+    let mut xattrs = if read_xattrs {
+        squashfs.xattrs().unwrap_or_else(|e| out.fail(e))
+    } else {
+        HashMap::new()
+    };
+
+    if let Some(exclude_from) = exclude_from {
+        let content = fs::read_to_string(&exclude_from)
+            .unwrap_or_else(|e| out.fail(format!("{}: {}", exclude_from.display(), e)));
+        exclude.extend(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_owned),
+        );
+    }
+    let selection = Selection::new(&patterns, &exclude, wildcards).unwrap_or_else(|e| out.fail(e));
+    let all_entries = squashfs.par_entries().collect::<Vec<_>>();
+    let total_entries = all_entries.len();
+    let mut entries = selection.select(all_entries);
+    if no_symlinks {
+        entries.retain(|entry| !matches!(entry.kind, EntryKind::Symlink(_)));
+    } else if dereference && (write_path.is_some() || to_tar.is_some()) {
+        entries = dereference_symlinks(&squashfs, entries, &mut xattrs, out);
+    }
+    elog!(
+        !out.verbose(1),
+        "Selected {} of {} entries",
+        entries.len(),
+        total_entries
+    );
+
+    let options = WriteOptions {
+        collision,
+        owner,
+        dmode,
+        fmode,
+        no_perms,
+        preserve_mtime: preserve_mtime || resume,
+        xattrs: read_xattrs,
+        hardlinks,
+        keep_going,
+        resume,
+        reproducible: reproducible.then(|| source_date_epoch(out)),
+    };
+    if let Some(target) = to_tar {
+        write_tar(&squashfs, entries, &target, strip_components, &options, out);
+        return;
+    }
+
+    let Some(output_dir) = write_path else {
+        if out.json {
+            let mut entries = entries;
+            entries.sort_by(|a, b| a.path.cmp(&b.path));
+            out.print_json(&Value::Array(entries.iter().map(entry_json).collect()));
+        } else {
+            entries.into_par_iter().for_each(|entry| {
+                if out.tsv() {
+                    out.record(entry_tsv(&entry));
+                } else {
+                    out.record(entry.path.display());
+                }
+            });
+        }
+        return;
+    };
+
+    if dry_run {
+        let mut planned = entries
+            .iter()
+            .filter(|entry| {
+                matches!(
+                    entry.kind,
+                    EntryKind::File(_) | EntryKind::Directory | EntryKind::Symlink(_)
+                )
+            })
+            .filter_map(|entry| {
+                let file_path = entry.path.strip_prefix("/").unwrap_or(&entry.path);
+                Some(PlannedWrite {
+                    source: entry.path.clone(),
+                    output: output_dir.join(strip_leading(file_path, strip_components)?),
+                    size: entry.size as u64,
+                })
+            })
+            .collect::<Vec<_>>();
+        planned.sort_by(|a, b| a.source.cmp(&b.source));
+        let planned = dryrun::report(&planned, collision, out);
+        if out.json {
+            out.print_json(&json!({ "planned": planned }));
+        }
+        return;
+    }
+
+    let output_of = |path: &Path| {
+        strip_leading(path.strip_prefix("/").unwrap_or(path), strip_components)
+            .map(|path| output_dir.join(path))
+    };
+    let report = Report::default();
+
+    let started = Instant::now();
+    let selected = entries
+        .iter()
+        .map(|entry| entry.path.clone())
+        .collect::<HashSet<_>>();
+    let mut aborted = write_entries(
+        &squashfs,
+        entries,
+        &output_of,
+        Path::new("/"),
+        &xattrs,
+        &options,
+        &report,
+        out,
+    );
+    if recurse_nested && aborted.is_ok() {
+        aborted = extract_nested(
+            &squashfs,
+            &|path| selected.contains(path),
+            &output_of,
+            Path::new("/"),
+            &options,
+            &report,
+            out,
+        );
+    }
+    for (source, output_path, mtime) in report.dir_times.into_inner().unwrap() {
+        if let Err(e) = filetime::set_file_times(&output_path, mtime, mtime) {
+            failed_to(&report.failed, &source, &e.into());
+        }
+    }
+    elog!(!out.verbose(2), "Extraction took {:.2?}", started.elapsed());
+
+    if let Err((source, e)) = &aborted {
+        failed_to(&report.failed, source, e);
+    }
+    let written = report.written.into_inner().unwrap();
+    let skipped = report.skipped.into_inner().unwrap();
+    let mut failed = report.failed.into_inner().unwrap();
+    if let Err((source, e)) = aborted {
+        let mut result = Map::new();
+        result.insert("written".into(), Value::Array(written));
+        result.insert("skipped".into(), json!(skipped));
+        result.insert("failed".into(), Value::Array(failed));
+        out.fail_with(
+            result,
+            format!(
+                "Failed to write {}: {}. Use --keep-going to extract the remaining entries",
+                source.display(),
+                e
+            ),
+        );
+    }
+
+    failed.sort_by(|a, b| a["source"].as_str().cmp(&b["source"].as_str()));
+    if !out.json {
+        for failure in &failed {
+            elog!(
+                out.quiet_errors,
+                "{}",
+                color::error(format!(
+                    "Failed to write {}: {}",
+                    failure["source"].as_str().unwrap_or_default(),
+                    failure["error"].as_str().unwrap_or_default()
+                ))
+            );
+        }
+        if !failed.is_empty() {
+            elog!(
+                out.quiet_errors,
+                "{}",
+                color::error(format!(
+                    "{} of {} entries failed",
+                    failed.len(),
+                    report.total.into_inner()
+                ))
+            );
+        }
+    }
+
+    if out.json {
+        out.print_json(&json!({
+            "written": written,
+            "skipped": skipped,
+            "failed": failed,
+        }));
+    }
+    if !failed.is_empty() {
+        std::process::exit(EXIT_FAILURE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use backhand::{FilesystemWriter, NodeHeader};
+
+    use super::*;
+    use crate::cli::OutputFormat;
+
+    const OUTPUT: Output = Output {
+        quiet: true,
+        quiet_errors: true,
+        verbose: 0,
+        json: false,
+        print0: false,
+        format: OutputFormat::Text,
+    };
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "squishy-unsquashfs-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes an image whose root has mode 0 and mtime 0, holding `dir/file`.
+    fn image(dir: &Path) -> PathBuf {
+        let header = NodeHeader::new(0o755, 0, 0, 0);
+        let mut writer = FilesystemWriter::default();
+        writer.set_root_mode(0);
+        writer.set_time(0);
+        writer.push_dir("dir", header).unwrap();
+        writer
+            .push_file(Cursor::new(b"contents".to_vec()), "dir/file", header)
+            .unwrap();
+        let path = dir.join("image.sqfs");
+        writer.write(&mut File::create(&path).unwrap()).unwrap();
+        path
+    }
+
+    fn options(collision: CollisionStrategy) -> WriteOptions {
+        WriteOptions {
+            collision,
+            owner: None,
+            dmode: None,
+            fmode: None,
+            no_perms: false,
+            preserve_mtime: false,
+            xattrs: false,
+            hardlinks: false,
+            keep_going: false,
+            resume: false,
+            reproducible: None,
+        }
+    }
+
+    /// Extracts the image to `<dir>/output`, an existing directory with mode 755.
+    ///
+    /// # Returns
+    /// The output directory, and the results of the extraction
+    fn extract(dir: &Path, options: &WriteOptions) -> (PathBuf, Report) {
+        let image = image(dir);
+        let output_dir = dir.join("output");
+        fs::create_dir_all(&output_dir).unwrap();
+        fs::set_permissions(&output_dir, Permissions::from_mode(0o755)).unwrap();
+
+        let squashfs = SquashFS::from_path_with_offset(&image, 0).unwrap();
+        let entries = squashfs.par_entries().collect::<Vec<_>>();
+        let output_of = |path: &Path| Some(output_dir.join(path.strip_prefix("/").unwrap()));
+        let report = Report::default();
+        write_entries(
+            &squashfs,
+            entries,
+            &output_of,
+            Path::new("/"),
+            &HashMap::new(),
+            options,
+            &report,
+            OUTPUT,
+        )
+        .unwrap();
+        (output_dir, report)
+    }
+
+    fn mode(path: &Path) -> u32 {
+        fs::metadata(path).unwrap().mode() & 0o7777
+    }
+
+    #[test]
+    fn leaves_existing_output_directory_alone() {
+        for (name, collision) in [
+            ("overwrite", CollisionStrategy::Overwrite),
+            ("backup", CollisionStrategy::Backup),
+        ] {
+            let dir = temp_dir(name);
+            let (output_dir, _) = extract(&dir, &options(collision));
+            assert_eq!(mode(&output_dir), 0o755);
+            assert_eq!(
+                fs::read_to_string(output_dir.join("dir/file")).unwrap(),
+                "contents"
+            );
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn applies_directory_modes_below_output_directory() {
+        let dir = temp_dir("dmode");
+        let (output_dir, _) = extract(
+            &dir,
+            &WriteOptions {
+                dmode: Some(0o700),
+                ..options(CollisionStrategy::Overwrite)
+            },
+        );
+        assert_eq!(mode(&output_dir), 0o755);
+        assert_eq!(mode(&output_dir.join("dir")), 0o700);
+        fs::remove_dir_all(dir).unwrap();
+
+        let dir = temp_dir("no-perms");
+        let (output_dir, _) = extract(
+            &dir,
+            &WriteOptions {
+                no_perms: true,
+                preserve_mtime: true,
+                ..options(CollisionStrategy::Overwrite)
+            },
+        );
+        assert_eq!(mode(&output_dir), 0o755);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_output_directory_time_when_resuming() {
+        let dir = temp_dir("resume");
+        let (output_dir, report) = extract(
+            &dir,
+            &WriteOptions {
+                preserve_mtime: true,
+                resume: true,
+                ..options(CollisionStrategy::Overwrite)
+            },
+        );
+        let dir_times = report.dir_times.into_inner().unwrap();
+        assert!(dir_times.iter().all(|(_, path, _)| *path != output_dir));
+        assert!(dir_times
+            .iter()
+            .any(|(_, path, _)| *path == output_dir.join("dir")));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
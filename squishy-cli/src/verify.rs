@@ -0,0 +1,58 @@
+use rayon::iter::ParallelIterator;
+use serde_json::{json, Value};
+use squishy::{appimage::AppImage, EntryKind};
+
+use crate::{
+    color,
+    output::{Output, EXIT_FAILURE},
+};
+
+/// Checks the integrity of every file of the image and prints the damaged ones.
+/// Exits non-zero if any file is damaged.
+///
+/// # Arguments
+/// * `appimage` - The image to check
+/// * `out` - Output settings
+pub fn verify(appimage: &AppImage, out: Output) {
+    let files = appimage
+        .par_entries()
+        .filter(|entry| matches!(entry.kind, EntryKind::File(_)))
+        .count();
+    let damaged = appimage.check_integrity();
+
+    if out.json {
+        let damaged = damaged
+            .iter()
+            .map(|entry| {
+                json!({
+                    "path": entry.path.to_string_lossy(),
+                    "error": entry.error.to_string(),
+                })
+            })
+            .collect::<Vec<_>>();
+        out.print_json(&json!({ "files": files, "damaged": Value::Array(damaged) }));
+    } else if damaged.is_empty() {
+        log!(out.quiet, "OK: {} files verified", files);
+    } else {
+        for entry in &damaged {
+            elog!(
+                out.quiet_errors,
+                "{}",
+                color::error(format!("{}: {}", entry.path.display(), entry.error))
+            );
+        }
+        elog!(
+            out.quiet_errors,
+            "{}",
+            color::error(format!(
+                "Damaged: {} of {} files failed verification",
+                damaged.len(),
+                files
+            ))
+        );
+    }
+
+    if !damaged.is_empty() {
+        std::process::exit(EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    ffi::{CString, OsStr},
+    fs::{self, File},
+    io::{self, Read},
+    os::{
+        fd::{AsRawFd, FromRawFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+};
+
+use serde_json::json;
+use squishy::appimage::{
+    integration::{self, Integration},
+    AppImage, AppImageKind,
+};
+
+use crate::{
+    appimage::integrate::{data_dir_or_default, refresh_databases},
+    color,
+    output::Output,
+};
+
+/// Events of the watched directory that add or remove AppImages. Files are
+/// integrated once closed after writing, so downloads in progress are skipped
+const WATCHED_EVENTS: u32 =
+    libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_DELETE | libc::IN_MOVED_FROM;
+/// Size of the fixed part of an inotify event, before the file name
+const EVENT_HEADER_SIZE: usize = std::mem::size_of::<libc::inotify_event>();
+
+/// Whether the file is an AppImage, from its magic bytes. Hidden files, such as
+/// partial downloads, are left alone.
+fn is_appimage(path: &Path) -> bool {
+    !path
+        .file_name()
+        .is_some_and(|name| name.as_bytes().starts_with(b"."))
+        && path.is_file()
+        && AppImage::detect_kind(path).is_ok_and(|kind| kind != AppImageKind::Unknown)
+}
+
+/// Installs the desktop integration of an AppImage, logging the result.
+///
+/// # Arguments
+/// * `path` - Path to the AppImage
+/// * `data_dir` - Data directory to install the files to
+/// * `out` - Output settings
+fn integrate(path: &Path, data_dir: &Path, out: Output) {
+    let integrated =
+        AppImage::new(None, &path, None).and_then(|appimage| appimage.integrate_in(data_dir));
+    match integrated {
+        Ok(Integration {
+            desktop,
+            mime_packages,
+            ..
+        }) => {
+            refresh_databases(data_dir, !mime_packages.is_empty(), out);
+            if out.json {
+                out.print_json(&json!({
+                    "event": "integrated",
+                    "path": path.to_string_lossy(),
+                    "desktop": desktop.to_string_lossy(),
+                }));
+            }
+            log!(out.silent(), "Integrated {}", path.display());
+        }
+        Err(e) => {
+            if out.json {
+                out.print_json(&json!({
+                    "event": "failed",
+                    "path": path.to_string_lossy(),
+                    "error": e.to_string(),
+                }));
+            }
+            elog!(
+                out.errors_silent(),
+                "{}",
+                color::error(format!("Failed to integrate {}: {}", path.display(), e))
+            );
+        }
+    }
+}
+
+/// Removes the desktop integration of an AppImage, if it was integrated.
+///
+/// # Arguments
+/// * `path` - Path the AppImage was at
+/// * `data_dir` - Data directory the files were installed to
+/// * `out` - Output settings
+fn unintegrate(path: &Path, data_dir: &Path, out: Output) {
+    match integration::unintegrate_in(path, data_dir) {
+        Ok(removed) if removed.is_empty() => {}
+        Ok(removed) => {
+            let mime = removed
+                .iter()
+                .any(|file| file.starts_with(data_dir.join("mime")));
+            refresh_databases(data_dir, mime, out);
+            if out.json {
+                out.print_json(&json!({
+                    "event": "removed",
+                    "path": path.to_string_lossy(),
+                }));
+            }
+            log!(out.silent(), "Removed integration of {}", path.display());
+        }
+        Err(e) => elog!(
+            out.errors_silent(),
+            "{}",
+            color::error(format!(
+                "Failed to remove integration of {}: {}",
+                path.display(),
+                e
+            ))
+        ),
+    }
+}
+
+/// Watches a directory for AppImages added to or removed from it, and installs or
+/// removes their desktop integration, like appimaged, refreshing the desktop
+/// database after each change. The AppImages already in the directory are
+/// integrated first. Runs until interrupted.
+///
+/// # Arguments
+/// * `dir` - Directory to watch
+/// * `data_dir` - Data directory to install the files to, `~/.local/share` by default
+/// * `out` - Output settings
+pub fn watch(dir: &Path, data_dir: Option<PathBuf>, out: Output) {
+    // Integration identifiers are derived from absolute paths
+    let dir =
+        fs::canonicalize(dir).unwrap_or_else(|e| out.fail(format!("{}: {}", dir.display(), e)));
+    let data_dir = data_dir_or_default(data_dir, out);
+
+    // SAFETY: inotify_init1 has no memory preconditions, failures are returned
+    let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+    if fd < 0 {
+        out.fail(io::Error::last_os_error());
+    }
+    // SAFETY: the descriptor was just opened and is owned by nothing else, `events`
+    // closes it
+    let mut events = unsafe { File::from_raw_fd(fd) };
+    let dir_name = CString::new(dir.as_os_str().as_bytes()).unwrap();
+    // SAFETY: the descriptor is kept open by `events`, and `dir_name` is a
+    // NUL-terminated string that outlives the call
+    let watch =
+        unsafe { libc::inotify_add_watch(events.as_raw_fd(), dir_name.as_ptr(), WATCHED_EVENTS) };
+    if watch < 0 {
+        out.fail(format!("{}: {}", dir.display(), io::Error::last_os_error()));
+    }
+
+    let mut existing = fs::read_dir(&dir)
+        .unwrap_or_else(|e| out.fail(format!("{}: {}", dir.display(), e)))
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| is_appimage(path))
+        .collect::<Vec<_>>();
+    existing.sort();
+    for path in &existing {
+        integrate(path, &data_dir, out);
+    }
+    elog!(out.silent(), "Watching {}", dir.display());
+
+    let mut buffer = vec![0_u8; 64 * (EVENT_HEADER_SIZE + libc::NAME_MAX as usize + 1)];
+    loop {
+        let read = match events.read(&mut buffer) {
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => out.fail(e),
+        };
+        let mut position = 0;
+        while position + EVENT_HEADER_SIZE <= read {
+            // SAFETY: the loop condition leaves at least EVENT_HEADER_SIZE bytes of
+            // the buffer at `position`, and `read_unaligned` doesn't need the
+            // header to be aligned
+            let event = unsafe {
+                buffer[position..]
+                    .as_ptr()
+                    .cast::<libc::inotify_event>()
+                    .read_unaligned()
+            };
+            let name_start = position + EVENT_HEADER_SIZE;
+            let name = &buffer[name_start..name_start + event.len as usize];
+            position = name_start + event.len as usize;
+
+            // The name is padded with NUL bytes
+            let name = &name[..name
+                .iter()
+                .position(|&byte| byte == 0)
+                .unwrap_or(name.len())];
+            if name.is_empty() || name.starts_with(b".") {
+                continue;
+            }
+            let path = dir.join(OsStr::from_bytes(name));
+            if event.mask & (libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO) != 0 {
+                if is_appimage(&path) {
+                    integrate(&path, &data_dir, out);
+                }
+            } else if event.mask & (libc::IN_DELETE | libc::IN_MOVED_FROM) != 0 {
+                unintegrate(&path, &data_dir, out);
+            }
+        }
+    }
+}
@@ -1,17 +1,90 @@
 use std::{
-    fs::File,
-    io::{Read, Seek, SeekFrom},
-    path::Path,
+    collections::{HashMap, HashSet},
+    ffi::{OsStr, OsString},
+    fs::{self, File, Permissions},
+    io::{BufReader, Read, Seek, SeekFrom},
+    os::unix::fs::{FileExt, PermissionsExt},
+    path::{Path, PathBuf},
 };
 
-use goblin::elf::Elf;
-use rayon::iter::ParallelIterator;
+use apprun::{AppRun, AppRunKind};
+use appstream::AppStream;
+use bundle::{BundleOptions, BundleReport, BundleStatus};
+use desktop::{rewrite_desktop, DesktopEntry, DesktopRewrite};
+#[cfg(feature = "dwarfs")]
+use dwarfs::{find_dwarfs_offset, DwarFS};
+use filter::Filter;
+use framework::Framework;
+use goblin::{
+    container::Ctx,
+    elf::{program_header::PT_INTERP, Elf, Header, ProgramHeader},
+};
+use icon::{is_icon_location, png_dimensions, size_from_path, Icon, IconFormat, IconPreference};
+use integration::Integration;
+use iso9660::{is_iso9660, Iso9660};
+use library::{is_shared_library, BundledLibrary};
+use naming::{split_extension, FileKind, NameValues};
+use nested::{detect_nested, open_nested, NestedImage, HEADER_SIZE as NESTED_HEADER_SIZE};
+use rayon::iter::{Either, IntoParallelIterator, ParallelIterator};
+#[cfg(feature = "verify")]
+use signature::SignatureVerdict;
+use update::UpdateInfo;
+use validate::{Issue, ValidationReport};
+#[cfg(feature = "zsync")]
+use zsync::{ZsyncFetcher, ZsyncFile};
+
+use crate::{
+    collision::{Collision, CollisionStrategy},
+    error::SquishyError,
+    progress::ProgressTracker,
+    EntryKind, SquashFS, SquashFSEntry,
+};
 
-use crate::{error::SquishyError, EntryKind, SquashFS, SquashFSEntry};
+pub mod apprun;
+pub mod appstream;
+pub mod bundle;
+pub mod compare;
+pub mod desktop;
+mod digest;
+#[cfg(feature = "dwarfs")]
+pub mod dwarfs;
+pub mod filter;
+pub mod framework;
+#[cfg(feature = "fuse")]
+pub mod fuse;
+pub mod icon;
+pub mod integration;
+pub mod integrity;
+pub mod iso9660;
+pub mod library;
+pub mod naming;
+pub mod nested;
+#[cfg(feature = "verify")]
+pub mod signature;
+pub mod update;
+pub mod validate;
+#[cfg(feature = "zsync")]
+pub mod zsync;
+
+pub use compare::compare;
 
 pub type Result<T> = std::result::Result<T, SquishyError>;
 
+/// SquashFS magic bytes ("hsqs")
+const SQUASHFS_MAGIC: [u8; 4] = *b"hsqs";
+
+/// ELF magic bytes
+const ELF_MAGIC: [u8; 4] = *b"\x7fELF";
+
 /// Get offset for AppImage. This is used by default if no offset is provided.
+/// Both 32-bit and 64-bit ELF runtimes are supported.
+///
+/// The offset is computed from the end of the section headers. If the runtime
+/// has its section headers stripped, it falls back to the end of the program
+/// headers, and finally to scanning for the SquashFS magic bytes.
+///
+/// Bundles prefixed by something other than an ELF runtime, e.g. the shell
+/// script used by shImg, are scanned for the SquashFS magic bytes from the start.
 ///
 /// # Arguments
 /// * `path` - Path to the appimage file.
@@ -19,16 +92,86 @@ pub type Result<T> = std::result::Result<T, SquishyError>;
 /// # Returns
 /// Offset of the appimage, or an error if it fails to parse Elf
 pub fn get_offset<P: AsRef<Path>>(path: P) -> std::io::Result<u64> {
-    let mut file = File::open(path)?;
+    get_offset_from_reader(&mut File::open(path)?)
+}
+
+/// Get offset for AppImage data provided by a reader. See [`get_offset`].
+///
+/// # Arguments
+/// * `file` - Reader providing the appimage data.
+///
+/// # Returns
+/// Offset of the appimage, or an error if it fails to parse Elf
+pub fn get_offset_from_reader<R: Read + Seek>(file: &mut R) -> std::io::Result<u64> {
+    let Some(header) = read_elf_header(file)? else {
+        return find_squashfs_magic(file, 0);
+    };
+
+    if header.e_shoff == 0 || header.e_shnum == 0 {
+        let program_headers_end = get_program_headers_end(file, &header)?;
+        if has_magic(file, program_headers_end, &SQUASHFS_MAGIC)? {
+            return Ok(program_headers_end);
+        }
+        return find_squashfs_magic(file, program_headers_end);
+    }
 
+    get_section_headers_end(file, &header)
+}
+
+/// Computes the end of the ELF runtime: the end of the sections, or of the
+/// segments if the runtime has its section headers stripped.
+///
+/// # Arguments
+/// * `file` - Reader providing the appimage data.
+///
+/// # Returns
+/// End offset of the runtime, 0 if the file isn't an ELF file, or an error if it
+/// fails to parse Elf
+#[cfg(feature = "dwarfs")]
+pub(crate) fn get_elf_end<R: Read + Seek>(file: &mut R) -> std::io::Result<u64> {
+    match read_elf_header(file)? {
+        None => Ok(0),
+        Some(header) if header.e_shoff == 0 || header.e_shnum == 0 => {
+            get_program_headers_end(file, &header)
+        }
+        Some(header) => get_section_headers_end(file, &header),
+    }
+}
+
+/// Reads the ELF header at the start of the file.
+///
+/// # Arguments
+/// * `file` - Reader providing the appimage data.
+///
+/// # Returns
+/// The header, None if the file doesn't start with the ELF magic bytes, or an error
+/// if it fails to parse Elf
+fn read_elf_header<R: Read + Seek>(file: &mut R) -> std::io::Result<Option<Header>> {
+    file.rewind()?;
     let mut elf_header_raw = [0; 64];
     file.read_exact(&mut elf_header_raw)?;
 
-    let section_table_offset = u64::from_le_bytes(elf_header_raw[40..48].try_into().unwrap());
-    let section_count = u16::from_le_bytes(elf_header_raw[60..62].try_into().unwrap());
+    if elf_header_raw[..4] != ELF_MAGIC {
+        return Ok(None);
+    }
+
+    Elf::parse_header(&elf_header_raw)
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
 
-    let section_table_size = section_count as u64 * 64;
-    let required_bytes = section_table_offset + section_table_size;
+/// Computes the end of the data covered by the section headers.
+///
+/// # Arguments
+/// * `file` - The appimage file
+/// * `header` - Parsed ELF header
+///
+/// # Returns
+/// End offset of the section header table or of the last section, or an error if
+/// it fails to parse Elf
+fn get_section_headers_end<R: Read + Seek>(file: &mut R, header: &Header) -> std::io::Result<u64> {
+    let section_table_size = header.e_shentsize as u64 * header.e_shnum as u64;
+    let required_bytes = header.e_shoff + section_table_size;
 
     let mut header_data = vec![0; required_bytes as usize];
     file.seek(SeekFrom::Start(0))?;
@@ -49,13 +192,278 @@ pub fn get_offset<P: AsRef<Path>>(path: P) -> std::io::Result<u64> {
     Ok(section_table_end.max(last_section_end))
 }
 
+/// Scans the file for SquashFS magic bytes, starting at the offset.
+///
+/// # Arguments
+/// * `file` - The file to scan
+/// * `start` - Offset to start scanning from
+///
+/// # Returns
+/// Offset of the first match, or an error if the magic bytes aren't found
+fn find_squashfs_magic<R: Read + Seek>(file: &mut R, start: u64) -> std::io::Result<u64> {
+    scan_magic(file, start, &SQUASHFS_MAGIC)?.ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Couldn't find squashfs magic bytes",
+        )
+    })
+}
+
+/// Reads the program headers of the ELF.
+///
+/// # Arguments
+/// * `file` - The ELF file
+/// * `header` - Parsed ELF header
+///
+/// # Returns
+/// The program headers, or an error if it fails to parse Elf
+fn read_program_headers<R: Read + Seek>(
+    file: &mut R,
+    header: &Header,
+) -> std::io::Result<Vec<ProgramHeader>> {
+    let program_table_end = header.e_phoff + header.e_phentsize as u64 * header.e_phnum as u64;
+
+    let mut header_data = vec![0; program_table_end as usize];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header_data)?;
+
+    let invalid_data = |e| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+    let ctx = Ctx::new(
+        header.container().map_err(invalid_data)?,
+        header.endianness().map_err(invalid_data)?,
+    );
+    ProgramHeader::parse(
+        &header_data,
+        header.e_phoff as usize,
+        header.e_phnum as usize,
+        ctx,
+    )
+    .map_err(invalid_data)
+}
+
+/// Computes the end of the data covered by the program headers.
+///
+/// # Arguments
+/// * `file` - The appimage file
+/// * `header` - Parsed ELF header
+///
+/// # Returns
+/// End offset of the last segment, or an error if it fails to parse Elf
+fn get_program_headers_end<R: Read + Seek>(file: &mut R, header: &Header) -> std::io::Result<u64> {
+    let program_table_end = header.e_phoff + header.e_phentsize as u64 * header.e_phnum as u64;
+
+    let last_segment_end = read_program_headers(file, header)?
+        .iter()
+        .map(|segment| segment.p_offset + segment.p_filesz)
+        .max()
+        .unwrap_or(0);
+
+    Ok(program_table_end.max(last_segment_end))
+}
+
+/// Checks whether the AppImage runtime is statically linked, i.e. it has no
+/// program interpreter.
+///
+/// # Arguments
+/// * `path` - Path to the appimage file.
+///
+/// # Returns
+/// boolean stating if the runtime is static, or an error if it fails to parse Elf
+pub fn is_static_runtime<P: AsRef<Path>>(path: P) -> std::io::Result<bool> {
+    let mut file = File::open(path)?;
+
+    let mut elf_header_raw = [0; 64];
+    file.read_exact(&mut elf_header_raw)?;
+
+    let header = Elf::parse_header(&elf_header_raw)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    let has_interpreter = read_program_headers(&mut file, &header)?
+        .iter()
+        .any(|segment| segment.p_type == PT_INTERP);
+
+    Ok(!has_interpreter)
+}
+
+/// Checks whether the runtime carries the sections reserved by the Type-2
+/// runtime for update information and signatures.
+///
+/// # Arguments
+/// * `path` - Path to the appimage file.
+///
+/// # Returns
+/// boolean stating if the sections are present, or an error if the file can't be read
+fn has_type2_sections<P: AsRef<Path>>(path: P) -> std::io::Result<bool> {
+    for name in [".upd_info", ".sha256_sig"] {
+        match elf_section_range(&path, name) {
+            Ok(Some(_)) => {}
+            Ok(None) => return Ok(false),
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => return Ok(false),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+/// Finds the location of an ELF section by name.
+///
+/// # Arguments
+/// * `path` - Path to the ELF file.
+/// * `name` - Name of the section, e.g. `.upd_info`
+///
+/// # Returns
+/// Offset and size of the section if it exists, or an error if it fails to parse Elf.
+/// Files that aren't ELF, e.g. script prefixed bundles, have no sections.
+pub fn elf_section_range<P: AsRef<Path>>(
+    path: P,
+    name: &str,
+) -> std::io::Result<Option<(u64, u64)>> {
+    let mut file = File::open(path)?;
+
+    let mut elf_header_raw = [0; 64];
+    file.read_exact(&mut elf_header_raw)?;
+    if elf_header_raw[..4] != ELF_MAGIC {
+        return Ok(None);
+    }
+
+    let header = Elf::parse_header(&elf_header_raw)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    if header.e_shoff == 0 || header.e_shnum == 0 {
+        return Ok(None);
+    }
+
+    let required_bytes = header.e_shoff + header.e_shentsize as u64 * header.e_shnum as u64;
+    let mut header_data = vec![0; required_bytes as usize];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header_data)?;
+
+    let elf = Elf::parse(&header_data)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    Ok(elf
+        .section_headers
+        .iter()
+        .find(|section| elf.shdr_strtab.get_at(section.sh_name) == Some(name))
+        .map(|section| (section.sh_offset, section.sh_size)))
+}
+
+/// Reads the contents of an ELF section by name.
+///
+/// # Arguments
+/// * `path` - Path to the ELF file.
+/// * `name` - Name of the section, e.g. `.upd_info`
+///
+/// # Returns
+/// The raw section contents if the section exists, or an error if it fails to parse Elf
+pub fn read_elf_section<P: AsRef<Path>>(path: P, name: &str) -> std::io::Result<Option<Vec<u8>>> {
+    let Some((offset, size)) = elf_section_range(&path, name)? else {
+        return Ok(None);
+    };
+
+    let mut data = vec![0; size as usize];
+    File::open(path)?.read_exact_at(&mut data, offset)?;
+    Ok(Some(data))
+}
+
+/// Checks whether the magic bytes are present at the offset.
+///
+/// # Arguments
+/// * `file` - The file to check
+/// * `offset` - Offset to check at
+/// * `magic` - The magic bytes
+///
+/// # Returns
+/// boolean stating if the magic bytes are found
+pub(crate) fn has_magic<R: Read + Seek>(
+    file: &mut R,
+    offset: u64,
+    magic: &[u8],
+) -> std::io::Result<bool> {
+    let mut found = vec![0_u8; magic.len()];
+    file.seek(SeekFrom::Start(offset))?;
+    match file.read_exact(&mut found) {
+        Ok(()) => Ok(found == magic),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Scans the file for the magic bytes, starting at the offset.
+///
+/// # Arguments
+/// * `file` - The file to scan
+/// * `start` - Offset to start scanning from
+/// * `magic` - The magic bytes
+///
+/// # Returns
+/// Offset of the first match, if found
+pub(crate) fn scan_magic<R: Read + Seek>(
+    file: &mut R,
+    start: u64,
+    magic: &[u8],
+) -> std::io::Result<Option<u64>> {
+    let mut reader = BufReader::new(file);
+    reader.seek(SeekFrom::Start(start))?;
+
+    let mut buf = vec![0_u8; 64 * 1024];
+    // Keep the last bytes of the previous chunk, in case magic spans two reads
+    let mut carry = 0;
+    let mut position = start;
+
+    loop {
+        let read = reader.read(&mut buf[carry..])?;
+        if read == 0 {
+            return Ok(None);
+        }
+        let len = carry + read;
+        if let Some(index) = buf[..len]
+            .windows(magic.len())
+            .position(|window| window == magic)
+        {
+            return Ok(Some(position - carry as u64 + index as u64));
+        }
+        carry = (magic.len() - 1).min(len);
+        buf.copy_within(len - carry..len, 0);
+        position += read as u64;
+    }
+}
+
+/// The AppImage type, as detected from the magic bytes and the runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppImageKind {
+    /// Legacy ISO 9660 based AppImage (`AI\x01` magic)
+    Type1,
+    /// SquashFS based AppImage with the Type-2 runtime (`AI\x02` magic), which
+    /// reserves the `.upd_info` and `.sha256_sig` sections
+    Type2,
+    /// Type-3 like AppImage: a statically linked runtime that doesn't carry the
+    /// sections reserved by the Type-2 runtime (`AI\x02` magic)
+    Static,
+    /// Not a recognized AppImage
+    Unknown,
+}
+
+/// The filesystem image embedded in an AppImage
+#[allow(clippy::large_enum_variant)]
+pub enum Payload<'a> {
+    /// SquashFS image used by Type-2 AppImages
+    SquashFS(SquashFS<'a>),
+    /// ISO 9660 image used by legacy Type-1 AppImages
+    Iso9660(Iso9660),
+    /// DwarFS image used by some newer runtimes
+    #[cfg(feature = "dwarfs")]
+    DwarFS(DwarFS),
+}
+
 pub struct AppImage<'a> {
-    filter: Option<&'a str>,
-    pub squashfs: SquashFS<'a>,
+    filter: Filter,
+    path: &'a Path,
+    pub payload: Payload<'a>,
 }
 
 impl<'a> AppImage<'a> {
-    /// Creates a new AppImage instance
+    /// Creates a new AppImage instance. Type-1 (ISO 9660) AppImages are detected
+    /// automatically when no offset is provided.
     ///
     /// # Arguments
     ///
@@ -67,13 +475,557 @@ impl<'a> AppImage<'a> {
         path: &'a P,
         offset: Option<u64>,
     ) -> Result<Self> {
+        let filter =
+            filter.map_or_else(Filter::default, |filter| Filter::default().include(filter));
+        Self::with_filter(filter, path, offset)
+    }
+
+    /// Creates a new AppImage instance with a filter made of multiple patterns.
+    /// See [`AppImage::new`].
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - Filter to apply
+    /// * `path` - Path to AppImage
+    /// * `offset` - Offset to seek to
+    pub fn with_filter<P: AsRef<Path>>(
+        filter: Filter,
+        path: &'a P,
+        offset: Option<u64>,
+    ) -> Result<Self> {
+        if offset.is_none() && is_iso9660(&mut File::open(path)?)? {
+            let iso = Iso9660::from_path(path)?;
+            return Ok(AppImage {
+                filter,
+                path: path.as_ref(),
+                payload: Payload::Iso9660(iso),
+            });
+        }
+
+        #[cfg(feature = "dwarfs")]
+        if let Some(offset) = find_dwarfs_offset(path, offset)? {
+            let dwarfs = DwarFS::from_path_with_offset(path, offset)?;
+            return Ok(AppImage {
+                filter,
+                path: path.as_ref(),
+                payload: Payload::DwarFS(dwarfs),
+            });
+        }
+
         let offset = offset.unwrap_or(get_offset(path)?);
         let squashfs = SquashFS::from_path_with_offset(path, offset).map_err(|_| {
             SquishyError::InvalidSquashFS(
                 "Couldn't find squashfs. Try providing valid offset.".to_owned(),
             )
         })?;
-        Ok(AppImage { filter, squashfs })
+        Ok(AppImage {
+            filter,
+            path: path.as_ref(),
+            payload: Payload::SquashFS(squashfs),
+        })
+    }
+
+    /// Creates a new AppImage instance filtering entries with a predicate, e.g. on
+    /// their size, kind or header. See [`AppImage::new`].
+    ///
+    /// # Arguments
+    ///
+    /// * `predicate` - Predicate the entries searched must satisfy
+    /// * `path` - Path to AppImage
+    /// * `offset` - Offset to seek to
+    pub fn with_predicate<F, P>(predicate: F, path: &'a P, offset: Option<u64>) -> Result<Self>
+    where
+        F: Fn(&SquashFSEntry) -> bool + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        Self::with_filter(Filter::default().predicate(predicate), path, offset)
+    }
+
+    /// Returns the SquashFS image of the AppImage. This replaces the former public
+    /// `squashfs` field, which couldn't represent the other payload types.
+    ///
+    /// # Returns
+    /// The SquashFS image, or None if the payload is an ISO 9660 or DwarFS image
+    #[deprecated(note = "use the `payload` field, which covers every payload type")]
+    pub fn squashfs(&self) -> Option<&SquashFS<'a>> {
+        match &self.payload {
+            Payload::SquashFS(squashfs) => Some(squashfs),
+            _ => None,
+        }
+    }
+
+    /// Detects the AppImage type from the magic bytes at offset 8 and the runtime,
+    /// without opening the payload.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the appimage file.
+    ///
+    /// # Returns
+    /// The detected AppImage kind, or an error if the file can't be read
+    pub fn detect_kind<P: AsRef<Path>>(path: P) -> Result<AppImageKind> {
+        let mut file = File::open(&path)?;
+
+        let mut ident = [0_u8; 11];
+        match file.read_exact(&mut ident) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Ok(AppImageKind::Unknown)
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        if &ident[0..4] != b"\x7fELF" {
+            return Ok(AppImageKind::Unknown);
+        }
+
+        let kind = match &ident[8..11] {
+            b"AI\x01" => AppImageKind::Type1,
+            // The Type-2 runtime is statically linked too nowadays, so it's told
+            // apart by the sections it reserves rather than by its linking
+            b"AI\x02" if has_type2_sections(&path)? => AppImageKind::Type2,
+            b"AI\x02" if is_static_runtime(&path).unwrap_or(false) => AppImageKind::Static,
+            b"AI\x02" => AppImageKind::Type2,
+            _ => AppImageKind::Unknown,
+        };
+        Ok(kind)
+    }
+
+    /// Reads the update information from the `.upd_info` ELF section, or the
+    /// application use area for Type-1 AppImages.
+    ///
+    /// # Returns
+    /// The parsed update information, [`UpdateInfo::Unknown`] if it doesn't follow
+    /// a known transport, or None if the AppImage has no update information
+    pub fn update_info(&self) -> Result<Option<UpdateInfo>> {
+        let data = match self.payload {
+            // Type-1 AppImages store it in the ISO 9660 application use area
+            Payload::Iso9660(_) => {
+                let mut data = vec![0_u8; 512];
+                File::open(self.path)?.read_exact_at(&mut data, 33651)?;
+                data
+            }
+            _ => match read_elf_section(self.path, ".upd_info")? {
+                Some(data) => data,
+                None => return Ok(None),
+            },
+        };
+        let info = String::from_utf8_lossy(&data);
+        let info = info.trim_end_matches('\0').trim();
+        if info.is_empty() {
+            return Ok(None);
+        }
+        let Ok(info) = info.parse();
+        Ok(Some(info))
+    }
+
+    #[cfg(feature = "zsync")]
+    /// Fetches and parses the .zsync file referenced by the update information, so
+    /// updaters can compute which blocks of the new version they need.
+    ///
+    /// # Arguments
+    /// * `fetcher` - Fetcher used to download the .zsync file
+    ///
+    /// # Returns
+    /// The parsed zsync file, or None if the update information isn't a zsync URL,
+    /// or an error if it can't be fetched or parsed
+    pub fn fetch_zsync<F: ZsyncFetcher>(&self, fetcher: &F) -> Result<Option<ZsyncFile>> {
+        let Some(info) = self.update_info()? else {
+            return Ok(None);
+        };
+        let Some(url) = info.zsync_url() else {
+            return Ok(None);
+        };
+        ZsyncFile::parse(&fetcher.fetch(url)?).map(Some)
+    }
+
+    /// Reads the embedded signature from the `.sha256_sig` ELF section.
+    ///
+    /// # Returns
+    /// The raw signature bytes, or None if the AppImage isn't signed
+    pub fn signature(&self) -> Result<Option<Vec<u8>>> {
+        self.read_section_trimmed(".sha256_sig")
+    }
+
+    /// Reads the embedded public key from the `.sig_key` ELF section.
+    ///
+    /// # Returns
+    /// The raw public key bytes, or None if the AppImage has no key embedded
+    pub fn signing_key(&self) -> Result<Option<Vec<u8>>> {
+        self.read_section_trimmed(".sig_key")
+    }
+
+    /// Reads the runtime, i.e. the bytes preceding the SquashFS or DwarFS payload.
+    ///
+    /// # Returns
+    /// The runtime ELF bytes, or an error if it can't be read or the AppImage is Type-1
+    pub fn runtime_bytes(&self) -> Result<Vec<u8>> {
+        let offset = match &self.payload {
+            Payload::SquashFS(squashfs) => squashfs.offset(),
+            #[cfg(feature = "dwarfs")]
+            Payload::DwarFS(dwarfs) => dwarfs.offset(),
+            Payload::Iso9660(_) => {
+                return Err(SquishyError::Unsupported(
+                    "Type-1 AppImages don't have a separate runtime".into(),
+                ))
+            }
+        };
+
+        let mut runtime = vec![0_u8; offset as usize];
+        File::open(self.path)?.read_exact_at(&mut runtime, 0)?;
+        Ok(runtime)
+    }
+
+    /// Writes the runtime to the specified destination path, marking it executable.
+    ///
+    /// # Arguments
+    /// * `dest` - The destination path to write the runtime to.
+    ///
+    /// # Returns
+    /// An empty result, or an error if the runtime can't be read or written
+    pub fn write_runtime<P: AsRef<Path>>(&self, dest: P) -> Result<()> {
+        let runtime = self.runtime_bytes()?;
+        fs::write(&dest, runtime)?;
+        fs::set_permissions(dest, Permissions::from_mode(0o755))?;
+        Ok(())
+    }
+
+    /// Computes the SHA256 digest of the AppImage, compatible with the one used by
+    /// appimagetool for signing: the signature sections are zeroed out before hashing.
+    ///
+    /// # Returns
+    /// The SHA256 digest, or an error if the AppImage can't be read
+    pub fn digest(&self) -> Result<[u8; 32]> {
+        let skip_signature = !matches!(self.payload, Payload::Iso9660(_));
+        digest::compute_digest(self.path, skip_signature)
+    }
+
+    /// Computes the SHA256 digest of the AppImage as a lowercase hex string.
+    /// See [`AppImage::digest`].
+    ///
+    /// # Returns
+    /// The hex encoded digest, or an error if the AppImage can't be read
+    pub fn digest_hex(&self) -> Result<String> {
+        Ok(digest::to_hex(&self.digest()?))
+    }
+
+    #[cfg(feature = "verify")]
+    /// Verifies the embedded signature against the AppImage digest, using the embedded public key.
+    ///
+    /// # Returns
+    /// The verification verdict, or an error if the AppImage can't be read
+    pub fn verify_signature(&self) -> Result<SignatureVerdict> {
+        let Some(key) = self.signing_key()? else {
+            return match self.signature()? {
+                Some(_) => Ok(SignatureVerdict::MissingKey),
+                None => Ok(SignatureVerdict::Unsigned),
+            };
+        };
+        self.verify_signature_with_key(&key)
+    }
+
+    #[cfg(feature = "verify")]
+    /// Verifies the embedded signature against the AppImage digest, using the provided public key.
+    ///
+    /// # Arguments
+    /// * `key` - Armored public key to verify against
+    ///
+    /// # Returns
+    /// The verification verdict, or an error if the AppImage can't be read
+    pub fn verify_signature_with_key(&self, key: &[u8]) -> Result<SignatureVerdict> {
+        let Some(signature) = self.signature()? else {
+            return Ok(SignatureVerdict::Unsigned);
+        };
+        signature::verify(&self.digest_hex()?, &signature, key)
+    }
+
+    /// Reads an ELF section of the runtime, stripping the trailing null padding.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the section
+    ///
+    /// # Returns
+    /// The section contents, or None if the section is missing or empty
+    fn read_section_trimmed(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        if let Payload::Iso9660(_) = self.payload {
+            return Ok(None);
+        }
+        let Some(mut data) = read_elf_section(self.path, name)? else {
+            return Ok(None);
+        };
+        let len = data.iter().rposition(|&b| b != 0).map_or(0, |pos| pos + 1);
+        data.truncate(len);
+        Ok((!data.is_empty()).then_some(data))
+    }
+
+    /// Returns a parallel iterator over all the entries in the AppImage payload.
+    pub fn par_entries(&self) -> impl ParallelIterator<Item = SquashFSEntry<'_>> + '_ {
+        match &self.payload {
+            Payload::SquashFS(squashfs) => Either::Left(squashfs.par_entries()),
+            Payload::Iso9660(iso) => {
+                Either::Right(iso.entries().collect::<Vec<_>>().into_par_iter())
+            }
+            #[cfg(feature = "dwarfs")]
+            Payload::DwarFS(dwarfs) => {
+                Either::Right(dwarfs.entries().collect::<Vec<_>>().into_par_iter())
+            }
+        }
+    }
+
+    /// Reads the contents of the specified file from the AppImage payload.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file within the payload.
+    ///
+    /// # Returns
+    /// The contents of the file as a Vec<u8>, or an error if the file is not found.
+    pub fn read_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        match &self.payload {
+            Payload::SquashFS(squashfs) => squashfs.read_file(path),
+            Payload::Iso9660(iso) => iso.read_file(path),
+            #[cfg(feature = "dwarfs")]
+            Payload::DwarFS(dwarfs) => dwarfs.read_file(path),
+        }
+    }
+
+    /// Reads a byte range of the entry from the AppImage payload.
+    ///
+    /// # Arguments
+    /// * `entry` - The file entry to read
+    /// * `offset` - Offset within the file to start reading at
+    /// * `length` - Maximum number of bytes to read
+    ///
+    /// # Returns
+    /// The bytes read, or an error if the entry is not a file or cannot be read
+    pub fn read_range(&self, entry: &SquashFSEntry, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let EntryKind::File(file) = entry.kind else {
+            return Err(SquishyError::NotAFile(entry.path.clone()));
+        };
+        match &self.payload {
+            Payload::SquashFS(squashfs) => squashfs.read_range(file, offset, length),
+            Payload::Iso9660(iso) => iso.read_range(file, offset, length),
+            #[cfg(feature = "dwarfs")]
+            Payload::DwarFS(dwarfs) => dwarfs.read_range(file, offset, length),
+        }
+    }
+
+    /// Computes how many bytes each file takes up in the image once compressed.
+    /// See [`SquashFS::compressed_sizes`].
+    ///
+    /// # Returns
+    /// The compressed sizes of the files by path, or None if the payload isn't a
+    /// SquashFS image, or an error if the image is corrupted
+    pub fn compressed_sizes(&self) -> Option<Result<HashMap<PathBuf, u64>>> {
+        match &self.payload {
+            Payload::SquashFS(squashfs) => Some(squashfs.compressed_sizes()),
+            _ => None,
+        }
+    }
+
+    /// Resolves the symlink chain starting from the specified entry.
+    ///
+    /// # Arguments
+    /// * `entry` - The entry to resolve the symlink for.
+    ///
+    /// # Returns
+    /// The final target entry, or None if the entry is not a symlink, or an error if a cycle is detected.
+    pub fn resolve_symlink(&self, entry: &SquashFSEntry) -> Result<Option<SquashFSEntry<'_>>> {
+        match &self.payload {
+            Payload::SquashFS(squashfs) => squashfs.resolve_symlink(entry),
+            Payload::Iso9660(iso) => iso.resolve_symlink(entry),
+            #[cfg(feature = "dwarfs")]
+            Payload::DwarFS(dwarfs) => dwarfs.resolve_symlink(entry),
+        }
+    }
+
+    /// Find the AppRun entry point at the AppImage root, resolving symlinks, and
+    /// detect whether it is an ELF executable or a script.
+    ///
+    /// # Returns
+    /// The AppRun if found, or an error if it can't be read
+    pub fn find_apprun(&self) -> Result<Option<AppRun<'_>>> {
+        let Some(apprun) = self
+            .par_entries()
+            .find_first(|entry| entry.path.to_string_lossy() == "/AppRun")
+        else {
+            return Ok(None);
+        };
+
+        let path = apprun.path.clone();
+        let entry = match apprun.kind {
+            EntryKind::Symlink(_) => match self.resolve_symlink(&apprun)? {
+                Some(entry) => entry,
+                None => return Ok(None),
+            },
+            _ => apprun,
+        };
+        let kind = match entry.kind {
+            EntryKind::File(_) => AppRunKind::from_header(&self.read_range(&entry, 0, 256)?),
+            _ => AppRunKind::Unknown,
+        };
+        Ok(Some(AppRun { path, entry, kind }))
+    }
+
+    /// Validates the AppImage against the AppImage specification and the conventions
+    /// expected by desktop integration tools: an executable AppRun, exactly one
+    /// top-level desktop file following the Desktop Entry specification with an icon
+    /// matching its `Icon` key, a `.DirIcon`, an appstream id matching the desktop file,
+    /// and update information following a known transport if there is any.
+    /// Symlinked files are followed, and files that can't be read are reported as issues.
+    ///
+    /// # Returns
+    /// The issues found
+    pub fn validate(&self) -> Result<ValidationReport> {
+        let mut issues = Vec::new();
+
+        match self.find_apprun() {
+            Err(e) => issues.push(Issue::error(format!("AppRun can't be read: {}", e))),
+            Ok(None) => issues.push(Issue::error("AppRun is missing")),
+            Ok(Some(apprun)) => {
+                if apprun.entry.header.permissions & 0o111 == 0 {
+                    issues.push(Issue::error("AppRun is not executable"));
+                }
+                if apprun.kind == AppRunKind::Unknown {
+                    issues.push(Issue::warning(
+                        "AppRun is neither an ELF executable nor a script",
+                    ));
+                }
+            }
+        }
+
+        let root = self
+            .par_entries()
+            .filter(|entry| entry.path.parent() == Some(Path::new("/")))
+            .collect::<Vec<_>>();
+        let root_entries = root.iter().map(|entry| &entry.path).collect::<Vec<_>>();
+
+        if !root_entries
+            .iter()
+            .any(|path| *path == Path::new("/.DirIcon"))
+        {
+            issues.push(Issue::warning(".DirIcon is missing"));
+        }
+
+        let desktops = root
+            .iter()
+            .filter(|entry| entry.path.extension().is_some_and(|ext| ext == "desktop"))
+            .collect::<Vec<_>>();
+        let desktop = match desktops.as_slice() {
+            [] => {
+                issues.push(Issue::error("No desktop file at the AppImage root"));
+                None
+            }
+            [desktop] => match self.read_resolved(desktop) {
+                Ok(content) => Some((
+                    desktop.path.clone(),
+                    DesktopEntry::parse(&String::from_utf8_lossy(&content)),
+                )),
+                Err(e) => {
+                    issues.push(Issue::error(format!(
+                        "Desktop file {} can't be read: {}",
+                        desktop.path.display(),
+                        e
+                    )));
+                    None
+                }
+            },
+            _ => {
+                issues.push(Issue::error(format!(
+                    "Expected one desktop file at the AppImage root, found {}",
+                    desktops.len()
+                )));
+                None
+            }
+        };
+
+        if let Some((_, entry)) = &desktop {
+            issues.extend(entry.validate());
+            match &entry.icon {
+                None => issues.push(Issue::error("Desktop file has no Icon key")),
+                Some(icon) => {
+                    let found = root_entries.iter().any(|path| {
+                        path.file_stem() == Some(OsStr::new(icon))
+                            && IconFormat::from_path(path).is_some()
+                    });
+                    if !found {
+                        issues.push(Issue::error(format!(
+                            "No icon named {} (.png, .svg or .xpm) at the AppImage root",
+                            icon
+                        )));
+                    }
+                }
+            }
+        }
+
+        if let Some(appstream) = self.appstream().ok().flatten() {
+            issues.extend(appstream.validate());
+            if let Some(id) = &appstream.id {
+                if let Some(appstream_entry) = self.find_appstream() {
+                    let file_name = appstream_entry.path.file_name().unwrap_or_default();
+                    let expected = [
+                        format!("{}.appdata.xml", id),
+                        format!("{}.metainfo.xml", id),
+                    ];
+                    if !expected.iter().any(|name| file_name == OsStr::new(name)) {
+                        issues.push(Issue::warning(format!(
+                            "AppStream file {} is not named after its id {}",
+                            file_name.to_string_lossy(),
+                            id
+                        )));
+                    }
+                }
+                if let Some((desktop, _)) = &desktop {
+                    let desktop_id = desktop.file_name().unwrap_or_default().to_string_lossy();
+                    let launchable = appstream
+                        .launchable
+                        .clone()
+                        .unwrap_or_else(|| format!("{}.desktop", id.trim_end_matches(".desktop")));
+                    if launchable != desktop_id {
+                        issues.push(Issue::warning(format!(
+                            "AppStream launchable {} doesn't match the desktop file {}",
+                            launchable, desktop_id
+                        )));
+                    }
+                }
+            }
+        } else if self.find_appstream().is_some() {
+            issues.push(Issue::error("AppStream metadata can't be parsed"));
+        }
+
+        match self.update_info() {
+            Err(e) => issues.push(Issue::warning(format!(
+                "Update information can't be read: {}",
+                e
+            ))),
+            Ok(Some(UpdateInfo::Unknown(info))) => issues.push(Issue::warning(format!(
+                "Update information {:?} doesn't follow a known transport",
+                info
+            ))),
+            _ => {}
+        }
+
+        Ok(ValidationReport { issues })
+    }
+
+    /// Reads the contents of the entry, following symlinks.
+    ///
+    /// # Arguments
+    /// * `entry` - The entry to read
+    ///
+    /// # Returns
+    /// The contents of the file the entry resolves to, or an error if it is a
+    /// dangling symlink or can't be read
+    fn read_resolved(&self, entry: &SquashFSEntry) -> Result<Vec<u8>> {
+        let resolved;
+        let entry = match entry.kind {
+            EntryKind::Symlink(_) => {
+                resolved = self.resolve_symlink(entry)?.ok_or_else(|| {
+                    SquishyError::SymlinkError(format!("{} is dangling", entry.path.display()))
+                })?;
+                &resolved
+            }
+            _ => entry,
+        };
+        self.read_range(entry, 0, entry.size as u64)
     }
 
     /// Find icon in AppImage, filtered
@@ -86,7 +1038,7 @@ impl<'a> AppImage<'a> {
     ///
     /// # Returns
     /// A SquashFS entry to the icon, if found
-    pub fn find_icon(&self) -> Option<SquashFSEntry> {
+    pub fn find_icon(&self) -> Option<SquashFSEntry<'_>> {
         let icon = self
             .search_diricon()
             .or_else(|| self.find_largest_icon_path())
@@ -95,67 +1047,316 @@ impl<'a> AppImage<'a> {
 
         if let Some(icon) = &icon {
             if let EntryKind::Symlink(_) = icon.kind {
-                let final_entry = self.squashfs.resolve_symlink(icon).unwrap();
+                let final_entry = self.resolve_symlink(icon).unwrap();
                 return final_entry;
             }
         }
         icon
     }
 
-    /// Find DirIcon at AppImage root
+    /// Find icon in AppImage closest to the preferred pixel size, filtered.
+    /// Sizes are read from icon theme directory names like `256x256`.
+    /// It looks for icon in order:
+    /// - Raster icon with the exact size
+    /// - Scalable (svg) icon
+    /// - Smallest raster icon larger than the size
+    /// - Largest raster icon smaller than the size
+    /// - Any icon found by `find_icon`
+    ///
+    /// # Arguments
+    /// * `size` - Preferred pixel size
     ///
     /// # Returns
     /// A SquashFS entry to the icon, if found
-    fn search_diricon(&self) -> Option<SquashFSEntry> {
-        self.squashfs
+    pub fn find_icon_sized(&self, size: u32) -> Option<SquashFSEntry<'_>> {
+        let mut icons = self.find_all_icons();
+        match closest_icon(&icons, size) {
+            Some(position) => Some(icons.swap_remove(position).entry),
+            None => self.find_icon(),
+        }
+    }
+
+    /// Find icon in AppImage matching the preference, filtered. Without a format,
+    /// this is [`AppImage::find_icon_sized`], or [`AppImage::find_icon`] without a
+    /// size either. With a format, only icons of that format are considered: the
+    /// one closest to the size, like `find_icon_sized`, or else the largest one.
+    ///
+    /// # Arguments
+    /// * `preference` - Preferred pixel size and required format
+    ///
+    /// # Returns
+    /// A SquashFS entry to the icon, if found
+    pub fn find_preferred_icon(&self, preference: IconPreference) -> Option<SquashFSEntry<'_>> {
+        let Some(format) = preference.format else {
+            return match preference.size {
+                Some(size) => self.find_icon_sized(size),
+                None => self.find_icon(),
+            };
+        };
+
+        let mut icons = self.find_all_icons();
+        icons.retain(|icon| icon.format == format);
+        let position = match preference.size {
+            Some(size) => closest_icon(&icons, size),
+            None => icons
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, icon)| icon.size)
+                .map(|(i, _)| i),
+        };
+        match position {
+            Some(position) => Some(icons.swap_remove(position).entry),
+            None => self.find_icon().filter(|icon| {
+                // `.DirIcon` has no extension, so fall back to the contents
+                IconFormat::from_path(&icon.path).or_else(|| {
+                    let header = self.read_range(icon, 0, 1024).ok()?;
+                    IconFormat::from_header(&header)
+                }) == Some(format)
+            }),
+        }
+    }
+
+    /// Find all icons in AppImage, filtered. This includes icons in /usr/share/icons,
+    /// /usr/share/pixmaps and at the AppImage root. Symlinks are resolved, and
+    /// dangling ones are skipped.
+    ///
+    /// # Returns
+    /// All icons found, sorted by path
+    pub fn find_all_icons(&self) -> Vec<Icon<'_>> {
+        let mut icons = self
             .par_entries()
+            .filter_map(|entry| {
+                let path = entry.path.to_string_lossy().to_lowercase();
+                if !is_icon_location(&path) || !self.filter_entry(&entry) {
+                    return None;
+                }
+                let format = IconFormat::from_path(&entry.path)?;
+                let path = entry.path.clone();
+                let entry = match entry.kind {
+                    EntryKind::File(_) => entry,
+                    EntryKind::Symlink(_) => self.resolve_symlink(&entry).ok().flatten()?,
+                    _ => return None,
+                };
+                Some(Icon {
+                    size: size_from_path(&path),
+                    path,
+                    entry,
+                    format,
+                })
+            })
+            .collect::<Vec<_>>();
+        icons.sort_by(|a, b| a.path.cmp(&b.path));
+        icons
+    }
+
+    /// Computes where [`AppImage::write_icon_theme`] writes each size of the
+    /// application icon, without writing anything.
+    ///
+    /// # Arguments
+    /// * `output_dir` - Directory the `hicolor` directory would be created in
+    /// * `output_name` - Optional name to use for the icons, keeping their extensions
+    ///
+    /// # Returns
+    /// The icons and their output paths, or an error if the desktop file can't be read
+    pub fn icon_theme_paths<P: AsRef<Path>>(
+        &self,
+        output_dir: P,
+        output_name: Option<&OsStr>,
+    ) -> Result<Vec<(Icon<'_>, PathBuf)>> {
+        let icon_name = self
+            .desktop_entry()?
+            .and_then(|desktop| desktop.icon)
+            .map(|icon| {
+                let icon = Path::new(&icon);
+                match IconFormat::from_path(icon) {
+                    Some(_) => icon.file_stem().unwrap_or_default().to_os_string(),
+                    None => icon.file_name().unwrap_or_default().to_os_string(),
+                }
+            });
+        let mut icons = self.find_all_icons();
+        if icons
+            .iter()
+            .any(|icon| icon.path.file_stem() == icon_name.as_deref())
+        {
+            icons.retain(|icon| icon.path.file_stem() == icon_name.as_deref());
+        }
+
+        let mut paths = Vec::new();
+        let mut seen = HashSet::new();
+        for icon in icons {
+            let components = icon
+                .path
+                .iter()
+                .map(|component| component.to_string_lossy())
+                .collect::<Vec<_>>();
+            let theme_dir = match components.iter().position(|c| c == "hicolor") {
+                Some(index) => Some(components[index + 1..components.len() - 1].join("/")),
+                None => match icon.format {
+                    IconFormat::Svg => Some("scalable/apps".to_string()),
+                    _ => icon
+                        .size
+                        .map(|size| (size, size))
+                        .or_else(|| png_dimensions(&self.read_range(&icon.entry, 0, 24).ok()?))
+                        .map(|(width, height)| format!("{}x{}/apps", width, height)),
+                },
+            };
+            let Some(theme_dir) = theme_dir else {
+                continue;
+            };
+
+            let output_path = output_dir
+                .as_ref()
+                .join("hicolor")
+                .join(theme_dir)
+                .join(output_file_name(&icon.path, output_name));
+            if seen.insert(output_path.clone()) {
+                paths.push((icon, output_path));
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Writes all sizes of the application icon to the output directory, in the
+    /// `hicolor/<size>/apps/<name>.<ext>` layout of the icon theme specification.
+    /// The icons are those named after the desktop file's `Icon` key, or all icons
+    /// if there's none. Icons already in a hicolor theme keep their layout, while
+    /// others are placed by their pixel size (or `scalable` for svg icons).
+    ///
+    /// # Arguments
+    /// * `output_dir` - Directory to create the `hicolor` directory in
+    /// * `output_name` - Optional name to use for the icons, keeping their extensions
+    /// * `collision` - How to handle icons that already exist
+    ///
+    /// # Returns
+    /// The source and output paths of the written icons, without the existing icons
+    /// that were kept, or an error if they can't be written
+    pub fn write_icon_theme<P: AsRef<Path>>(
+        &self,
+        output_dir: P,
+        output_name: Option<&OsStr>,
+        collision: CollisionStrategy,
+    ) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let mut written = Vec::new();
+        for (icon, output_path) in self.icon_theme_paths(output_dir, output_name)? {
+            if collision.prepare(&output_path, false)? == Collision::Skip {
+                continue;
+            }
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&output_path, self.read_file(&icon.entry.path)?)?;
+            written.push((icon.path, output_path));
+        }
+        Ok(written)
+    }
+
+    #[cfg(feature = "svg")]
+    /// Writes the icon as a PNG to the specified destination path. SVG icons are
+    /// rasterized at the requested size, while other icons are written as is.
+    ///
+    /// # Arguments
+    /// * `entry` - The icon entry
+    /// * `dest` - The destination path to write the icon to
+    /// * `size` - Pixel size to rasterize SVG icons at
+    ///
+    /// # Returns
+    /// An empty result, or an error if the icon can't be read, rasterized or written
+    pub fn write_rasterized_icon<P: AsRef<Path>>(
+        &self,
+        entry: &SquashFSEntry,
+        dest: P,
+        size: u32,
+    ) -> Result<()> {
+        let data = self.read_file(&entry.path)?;
+        let data = if IconFormat::from_path(&entry.path) == Some(IconFormat::Svg) {
+            icon::rasterize_svg(&data, size)?
+        } else {
+            data
+        };
+        fs::write(dest, data)?;
+        Ok(())
+    }
+
+    /// Find DirIcon at AppImage root
+    ///
+    /// # Returns
+    /// A SquashFS entry to the icon, if found
+    fn search_diricon(&self) -> Option<SquashFSEntry<'_>> {
+        self.par_entries()
             .find_first(|entry| entry.path.to_string_lossy() == "/.DirIcon")
     }
 
-    /// Helper method to filter paths
+    /// Helper method to filter entries
     ///
     /// # Returns
-    /// boolean stating if the path matches the filter
-    fn filter_path(&self, path: &str) -> bool {
-        self.filter
-            .as_ref()
-            .map_or(true, |filter| path.contains(filter))
+    /// boolean stating if the entry matches the filter
+    fn filter_entry(&self, entry: &SquashFSEntry) -> bool {
+        self.filter.matches_entry(entry)
     }
 
-    /// Find largest png (preferred) or svg icon in /usr/share/icons, filtered
+    /// Find largest png (preferred) or svg icon in /usr/share/icons, filtered.
+    /// Png icons are ranked by their pixel dimensions.
     ///
     /// # Returns
     /// A SquashFS entry to the icon, if found
-    fn find_largest_icon_path(&self) -> Option<SquashFSEntry> {
-        let png_entries = self.squashfs.par_entries().filter(|entry| {
+    fn find_largest_icon_path(&self) -> Option<SquashFSEntry<'_>> {
+        let png_entries = self.par_entries().filter(|entry| {
             let path = entry.path.to_string_lossy().to_lowercase();
             path.starts_with("/usr/share/icons/")
-                && self.filter_path(&path)
+                && self.filter_entry(entry)
                 && path.ends_with(".png")
         });
 
-        if let Some(entry) = png_entries.max_by_key(|entry| entry.size) {
+        if let Some(entry) = png_entries.max_by_key(|entry| self.icon_rank(entry)) {
             return Some(entry);
         }
 
-        self.squashfs.par_entries().find_first(|entry| {
+        self.par_entries().find_first(|entry| {
             let path = entry.path.to_string_lossy().to_lowercase();
             path.starts_with("/usr/share/icons")
-                && self.filter_path(&path)
+                && self.filter_entry(entry)
                 && path.ends_with(".svg")
         })
     }
 
-    /// Find largest png icon in AppImage, filtered
+    /// Computes the rank of a png icon, by pixel dimensions read from its header
+    /// and then by file size
+    ///
+    /// # Returns
+    /// The rank of the icon, higher is better
+    fn icon_rank(&self, entry: &SquashFSEntry) -> (u64, u32) {
+        let resolved;
+        let entry = if let EntryKind::Symlink(_) = entry.kind {
+            match self.resolve_symlink(entry) {
+                Ok(Some(target)) => {
+                    resolved = target;
+                    &resolved
+                }
+                _ => return (0, 0),
+            }
+        } else {
+            entry
+        };
+
+        let area = self
+            .read_range(entry, 0, 24)
+            .ok()
+            .and_then(|header| png_dimensions(&header))
+            .map_or(0, |(width, height)| width as u64 * height as u64);
+        (area, entry.size)
+    }
+
+    /// Find largest png icon in AppImage, filtered.
+    /// Png icons are ranked by their pixel dimensions.
     ///
     /// # Returns
     /// A SquashFS entry to the icon, if found
-    fn find_png_icon(&self) -> Option<SquashFSEntry> {
-        let png_entries = self.squashfs.par_entries().filter(|entry| {
+    fn find_png_icon(&self) -> Option<SquashFSEntry<'_>> {
+        let png_entries = self.par_entries().filter(|entry| {
             let p = entry.path.to_string_lossy().to_lowercase();
-            self.filter_path(&p) && p.ends_with(".png")
+            self.filter_entry(entry) && p.ends_with(".png")
         });
-        if let Some(entry) = png_entries.max_by_key(|entry| entry.size) {
+        if let Some(entry) = png_entries.max_by_key(|entry| self.icon_rank(entry)) {
             return Some(entry);
         }
         None
@@ -165,10 +1366,10 @@ impl<'a> AppImage<'a> {
     ///
     /// # Returns
     /// A SquashFS entry to the icon, if found
-    fn find_svg_icon(&self) -> Option<SquashFSEntry> {
-        self.squashfs.par_entries().find_first(|entry| {
+    fn find_svg_icon(&self) -> Option<SquashFSEntry<'_>> {
+        self.par_entries().find_first(|entry| {
             let path = entry.path.to_string_lossy().to_lowercase();
-            self.filter_path(&path) && path.ends_with(".svg")
+            self.filter_entry(entry) && path.ends_with(".svg")
         })
     }
 
@@ -176,38 +1377,847 @@ impl<'a> AppImage<'a> {
     ///
     /// # Returns
     /// A SquashFS entry to the desktop file, if found
-    pub fn find_desktop(&self) -> Option<SquashFSEntry> {
-        let desktop = self.squashfs.par_entries().find_first(|entry| {
+    pub fn find_desktop(&self) -> Option<SquashFSEntry<'_>> {
+        let desktop = self.par_entries().find_first(|entry| {
             let path = entry.path.to_string_lossy().to_lowercase();
-            self.filter_path(&path) && path.ends_with(".desktop")
+            self.filter_entry(entry) && path.ends_with(".desktop")
         });
 
         if let Some(desktop) = &desktop {
             if let EntryKind::Symlink(_) = desktop.kind {
-                let final_entry = self.squashfs.resolve_symlink(desktop).unwrap();
+                let final_entry = self.resolve_symlink(desktop).unwrap();
                 return final_entry;
             }
         }
         desktop
     }
 
+    /// Find and parse the desktop file in AppImage, filtered
+    ///
+    /// # Returns
+    /// The parsed desktop entry if a desktop file is found, or an error if it can't be read
+    pub fn desktop_entry(&self) -> Result<Option<DesktopEntry>> {
+        let Some(desktop) = self.find_desktop() else {
+            return Ok(None);
+        };
+        let content = self.read_file(&desktop.path)?;
+        Ok(Some(DesktopEntry::parse(&String::from_utf8_lossy(
+            &content,
+        ))))
+    }
+
+    /// Checks whether the desktop file sets `Terminal=true`, i.e. the application
+    /// must be run in a terminal emulator, filtered
+    ///
+    /// # Returns
+    /// boolean stating if the application runs in a terminal, false if there's no
+    /// desktop file, or an error if it can't be read
+    pub fn is_terminal_app(&self) -> Result<bool> {
+        Ok(self
+            .desktop_entry()?
+            .is_some_and(|desktop| desktop.terminal))
+    }
+
+    /// Find the main application binary by resolving the program in the desktop
+    /// file's `Exec` key. Absolute and relative paths are looked up from the AppImage
+    /// root, while bare names are searched in the common `bin` directories first and
+    /// then anywhere in the AppImage. Symlinks are resolved.
+    ///
+    /// # Returns
+    /// A SquashFS entry to the binary if found, or an error if the desktop file can't be read
+    pub fn find_main_binary(&self) -> Result<Option<SquashFSEntry<'_>>> {
+        let Some(desktop) = self.desktop_entry()? else {
+            return Ok(None);
+        };
+        let Some(program) = desktop.exec_args().into_iter().next() else {
+            return Ok(None);
+        };
+
+        let candidates = if program.contains('/') {
+            vec![Path::new("/").join(program.trim_start_matches("./"))]
+        } else {
+            ["/usr/bin", "/bin", "/usr/sbin", "/sbin", "/usr/games", "/"]
+                .iter()
+                .map(|dir| Path::new(dir).join(&program))
+                .collect()
+        };
+
+        let mut binary = candidates.iter().find_map(|candidate| {
+            self.par_entries()
+                .find_first(|entry| &entry.path == candidate)
+        });
+        if binary.is_none() && !program.contains('/') {
+            binary = self.par_entries().find_first(|entry| {
+                !matches!(entry.kind, EntryKind::Directory)
+                    && entry.path.file_name() == Some(OsStr::new(&program))
+            });
+        }
+
+        match binary {
+            Some(entry) if matches!(entry.kind, EntryKind::Symlink(_)) => {
+                self.resolve_symlink(&entry)
+            }
+            binary => Ok(binary),
+        }
+    }
+
     /// Find appstream file in AppImage (appdata.xml | metainfo.xml)
     ///
     /// # Returns
     /// A SquashFS entry to the appstream, if found
-    pub fn find_appstream(&self) -> Option<SquashFSEntry> {
-        let appstream = self.squashfs.par_entries().find_first(|entry| {
+    pub fn find_appstream(&self) -> Option<SquashFSEntry<'_>> {
+        let appstream = self.par_entries().find_first(|entry| {
             let path = entry.path.to_string_lossy().to_lowercase();
-            self.filter_path(&path)
+            self.filter_entry(entry)
                 && (path.ends_with("appdata.xml") || path.ends_with("metainfo.xml"))
         });
 
         if let Some(appstream) = &appstream {
             if let EntryKind::Symlink(_) = appstream.kind {
-                let final_entry = self.squashfs.resolve_symlink(appstream).unwrap();
+                let final_entry = self.resolve_symlink(appstream).unwrap();
                 return final_entry;
             }
         }
         appstream
     }
+
+    /// Find MIME type definitions in AppImage (usr/share/mime/packages/*.xml), filtered.
+    /// Symlinks are resolved, and dangling ones are skipped.
+    ///
+    /// # Returns
+    /// SquashFS entries to the MIME packages, sorted by path
+    pub fn find_mime_packages(&self) -> Vec<SquashFSEntry<'_>> {
+        let mut packages = self
+            .par_entries()
+            .filter_map(|entry| {
+                let path = entry.path.to_string_lossy().to_lowercase();
+                if !self.filter_entry(&entry)
+                    || !path.starts_with("/usr/share/mime/packages/")
+                    || !path.ends_with(".xml")
+                {
+                    return None;
+                }
+                match entry.kind {
+                    EntryKind::File(_) => Some(entry),
+                    EntryKind::Symlink(_) => self.resolve_symlink(&entry).ok().flatten(),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        packages.sort_by(|a, b| a.path.cmp(&b.path));
+        packages
+    }
+
+    /// Lists the shared libraries (`*.so` and `*.so.*` files) bundled in the AppImage,
+    /// filtered. Symlinks to libraries are not listed.
+    ///
+    /// # Arguments
+    /// * `read_sonames` - Whether to read the SONAME of each library, which reads its
+    ///   ELF header and dynamic section
+    ///
+    /// # Returns
+    /// The bundled libraries, sorted by path
+    pub fn bundled_libraries(&self, read_sonames: bool) -> Vec<BundledLibrary> {
+        let mut libraries = self
+            .par_entries()
+            .filter_map(|entry| {
+                if !self.filter_entry(&entry)
+                    || !matches!(entry.kind, EntryKind::File(_))
+                    || !is_shared_library(&entry.path)
+                {
+                    return None;
+                }
+                let soname = if read_sonames {
+                    library::soname_with(|offset, length| {
+                        self.read_range(&entry, offset, length).ok()
+                    })
+                } else {
+                    None
+                };
+                Some(BundledLibrary {
+                    size: entry.size as u64,
+                    path: entry.path,
+                    soname,
+                })
+            })
+            .collect::<Vec<_>>();
+        libraries.sort_by(|a, b| a.path.cmp(&b.path));
+        libraries
+    }
+
+    /// Detects the application frameworks bundled in the AppImage (Electron, Qt, GTK,
+    /// Java, Python) from characteristic payload files. The filter is not applied.
+    ///
+    /// # Returns
+    /// The detected frameworks, sorted
+    pub fn detect_frameworks(&self) -> Vec<Framework> {
+        let frameworks = self
+            .par_entries()
+            .filter_map(|entry| Framework::from_path(&entry.path))
+            .collect::<HashSet<_>>();
+        let mut frameworks = frameworks.into_iter().collect::<Vec<_>>();
+        frameworks.sort();
+        frameworks
+    }
+
+    /// Finds the SquashFS images and AppImages nested in the AppImage, detected from
+    /// the magic bytes of the file contents, filtered
+    ///
+    /// # Returns
+    /// The nested images found
+    pub fn find_nested_images(&self) -> Vec<NestedImage<'_>> {
+        self.par_entries()
+            .filter(|entry| self.filter_entry(entry))
+            .filter_map(|entry| {
+                detect_nested(entry, |entry| self.read_range(entry, 0, NESTED_HEADER_SIZE))
+            })
+            .collect()
+    }
+
+    /// Opens a nested image, reading it into memory. The returned SquashFS can be
+    /// searched for further nested images with [`SquashFS::find_nested_images`].
+    ///
+    /// # Arguments
+    /// * `nested` - The nested image, as found by [`AppImage::find_nested_images`]
+    ///
+    /// # Returns
+    /// The nested SquashFS, or an error if it can't be read or parsed
+    pub fn open_nested(&self, nested: &NestedImage) -> Result<SquashFS<'static>> {
+        open_nested(self.read_file(&nested.entry.path)?, nested.kind)
+    }
+
+    /// Find license file in AppImage (LICENSE, LICENCE, COPYING or COPYRIGHT, with
+    /// any extension), filtered
+    ///
+    /// # Returns
+    /// A SquashFS entry to the license file, if found
+    pub fn find_license(&self) -> Option<SquashFSEntry<'_>> {
+        self.find_named(|name| {
+            ["LICENSE", "LICENCE", "COPYING", "COPYRIGHT"]
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+        })
+    }
+
+    /// Find changelog file in AppImage (CHANGELOG, CHANGES, NEWS or HISTORY, with
+    /// any extension), filtered
+    ///
+    /// # Returns
+    /// A SquashFS entry to the changelog file, if found
+    pub fn find_changelog(&self) -> Option<SquashFSEntry<'_>> {
+        self.find_named(|name| {
+            let stem = name.split('.').next().unwrap_or_default();
+            name.starts_with("CHANGELOG") || ["CHANGES", "NEWS", "HISTORY"].contains(&stem)
+        })
+    }
+
+    /// Helper method to find a file by its uppercased file name, filtered.
+    /// Symlinks are resolved.
+    ///
+    /// # Returns
+    /// A SquashFS entry to the file, if found
+    fn find_named<F>(&self, predicate: F) -> Option<SquashFSEntry<'_>>
+    where
+        F: Fn(&str) -> bool + Sync + Send,
+    {
+        let entry = self.par_entries().find_first(|entry| {
+            let name = entry.path.file_name().unwrap_or_default();
+            self.filter_entry(entry)
+                && !matches!(entry.kind, EntryKind::Directory)
+                && predicate(&name.to_string_lossy().to_uppercase())
+        })?;
+
+        match entry.kind {
+            EntryKind::Symlink(_) => self
+                .resolve_symlink(&entry)
+                .ok()
+                .flatten()
+                .filter(|entry| matches!(entry.kind, EntryKind::File(_))),
+            _ => Some(entry),
+        }
+    }
+
+    /// Find and parse the appstream file in AppImage, filtered
+    ///
+    /// # Returns
+    /// The parsed appstream metadata if an appstream file is found, or an error if it can't be read or parsed
+    pub fn appstream(&self) -> Result<Option<AppStream>> {
+        let Some(appstream) = self.find_appstream() else {
+            return Ok(None);
+        };
+        let content = self.read_file(&appstream.path)?;
+        AppStream::parse(&String::from_utf8_lossy(&content)).map(Some)
+    }
+
+    /// Gets the application version, from the `X-AppImage-Version` key of the
+    /// desktop file, then the newest appstream release, and finally the AppImage
+    /// file name (e.g. `App-1.2.3-x86_64.AppImage`). Unreadable metadata is skipped.
+    ///
+    /// # Returns
+    /// The application version, if found
+    pub fn version(&self) -> Option<String> {
+        let desktop_version = self
+            .desktop_entry()
+            .ok()
+            .flatten()
+            .and_then(|desktop| desktop.version);
+        desktop_version
+            .or_else(|| {
+                self.appstream()
+                    .ok()
+                    .flatten()
+                    .and_then(|appstream| appstream.releases.into_iter().next())
+                    .and_then(|release| release.version)
+            })
+            .or_else(|| {
+                let stem = self.path.file_stem()?.to_string_lossy();
+                version_from_file_name(&stem).map(|(_, version)| version.to_owned())
+            })
+            .filter(|version| !version.trim().is_empty())
+    }
+
+    /// Gets the application name, from the `Name` key of the desktop file, then
+    /// the appstream name, and finally the AppImage file name without its version
+    /// and architecture. Unreadable metadata is skipped.
+    ///
+    /// # Returns
+    /// The application name
+    pub fn app_name(&self) -> String {
+        let desktop_name = self
+            .desktop_entry()
+            .ok()
+            .flatten()
+            .and_then(|desktop| desktop.name);
+        desktop_name
+            .or_else(|| {
+                self.appstream()
+                    .ok()
+                    .flatten()
+                    .and_then(|appstream| appstream.name)
+            })
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| {
+                let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+                name_from_file_name(&stem).to_owned()
+            })
+    }
+
+    /// Gets the application identifier, from the appstream `id`, and otherwise the
+    /// desktop file name, as software centers do. Unreadable metadata is skipped.
+    ///
+    /// # Returns
+    /// The application identifier, if found
+    pub fn app_id(&self) -> Option<String> {
+        self.appstream()
+            .ok()
+            .flatten()
+            .and_then(|appstream| appstream.id)
+            .or_else(|| {
+                let desktop = self.find_desktop()?;
+                Some(desktop.path.file_stem()?.to_string_lossy().into_owned())
+            })
+    }
+
+    /// Gets the values of the placeholders of a [`NameTemplate`](naming::NameTemplate):
+    /// the application name, identifier and version.
+    ///
+    /// # Returns
+    /// The values
+    pub fn name_values(&self) -> NameValues {
+        NameValues {
+            appname: self.app_name(),
+            id: self.app_id(),
+            version: self.version(),
+        }
+    }
+
+    /// Finds and writes the icon, desktop file and appstream file, and the AppRun if
+    /// requested, to the output directory in one call, using the same naming for all
+    /// of them. Existing output files are handled by the collision strategy of the
+    /// options. The progress is reported to the callback of the options after each
+    /// file found.
+    ///
+    /// # Arguments
+    /// * `output_dir` - Directory to write the files to
+    /// * `options` - Which files to extract and how to name them
+    ///
+    /// # Returns
+    /// A report of the files written, missing or failed
+    pub fn extract_metadata_bundle<P: AsRef<Path>>(
+        &self,
+        output_dir: P,
+        options: &BundleOptions,
+    ) -> BundleReport {
+        let icon_entry = options
+            .icon
+            .then(|| self.find_preferred_icon(options.icon_preference))
+            .flatten();
+        let desktop_entry = options.desktop.then(|| self.find_desktop()).flatten();
+        let appstream_entry = options.appstream.then(|| self.find_appstream()).flatten();
+        let apprun_entry = options
+            .apprun
+            .then(|| self.find_apprun().ok().flatten())
+            .flatten()
+            .map(AppRun::into_entry);
+        let found = [&icon_entry, &desktop_entry, &appstream_entry, &apprun_entry]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        let progress = ProgressTracker::new(
+            found.len() as u64,
+            found.iter().map(|entry| entry.size as u64).sum(),
+            options.progress,
+        );
+
+        let values = options
+            .name_template
+            .map(|_| self.name_values())
+            .unwrap_or_default();
+        let write = |requested: bool,
+                     entry: Option<SquashFSEntry>,
+                     kind: FileKind,
+                     rewrite: Option<&DesktopRewrite>| {
+            if !requested {
+                return BundleStatus::Skipped;
+            }
+            let Some(entry) = entry else {
+                return BundleStatus::Missing;
+            };
+            let output = output_dir
+                .as_ref()
+                .join(options.file_name(&entry.path, kind, &values));
+            let written = options
+                .collision
+                .prepare(&output, false)
+                .and_then(|collision| {
+                    if collision == Collision::Skip {
+                        return Ok(None);
+                    }
+                    // AppRun must stay executable
+                    let copy_permissions = options.copy_permissions || kind == FileKind::AppRun;
+                    self.write_to(&entry, &output, copy_permissions)?;
+                    if let Some(rewrite) = rewrite {
+                        rewrite_desktop_file(&output, rewrite)?;
+                    }
+                    Ok(Some(output.clone()))
+                });
+            progress.file_done(entry.size as u64);
+            match written {
+                Ok(Some(output)) => BundleStatus::Written {
+                    source: entry.path,
+                    output,
+                },
+                Ok(None) => BundleStatus::Exists {
+                    source: entry.path,
+                    output,
+                },
+                Err(error) => BundleStatus::Failed {
+                    source: entry.path,
+                    error,
+                },
+            }
+        };
+
+        let icon_name = icon_entry.as_ref().map(|icon| {
+            let name = options.file_name(&icon.path, FileKind::Icon, &values);
+            Path::new(&name)
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        });
+        let appimage_path = fs::canonicalize(self.path).unwrap_or_else(|_| self.path.to_path_buf());
+        let rewrite = DesktopRewrite {
+            appimage: Some(&appimage_path),
+            icon: icon_name.as_deref(),
+        };
+
+        BundleReport {
+            desktop: write(
+                options.desktop,
+                desktop_entry,
+                FileKind::Desktop,
+                options.rewrite_desktop.then_some(&rewrite),
+            ),
+            icon: write(options.icon, icon_entry, FileKind::Icon, None),
+            appstream: write(
+                options.appstream,
+                appstream_entry,
+                FileKind::Appstream,
+                None,
+            ),
+            apprun: write(options.apprun, apprun_entry, FileKind::AppRun, None),
+        }
+    }
+
+    /// Writes the desktop file to the output directory like [`AppImage::write`],
+    /// rewriting its `Exec`, `TryExec` and `Icon` keys.
+    ///
+    /// # Arguments
+    /// * `entry` - The desktop file entry to write
+    /// * `output_dir` - Directory to write the file to
+    /// * `output_name` - Optional name to use for the output file
+    /// * `copy_permissions` - Whether to copy permissions from the SquashFS entry
+    /// * `rewrite` - The values to replace
+    ///
+    /// # Returns
+    /// Path to the written file, or an error if the entry is not a file or it cannot be written
+    pub fn write_desktop<P: AsRef<Path>>(
+        &self,
+        entry: &SquashFSEntry,
+        output_dir: P,
+        output_name: Option<&OsStr>,
+        copy_permissions: bool,
+        rewrite: &DesktopRewrite,
+    ) -> Result<PathBuf> {
+        let output_path = self.write(entry, output_dir, output_name, copy_permissions)?;
+        rewrite_desktop_file(&output_path, rewrite)?;
+        Ok(output_path)
+    }
+
+    /// Installs the desktop integration files of the AppImage to the default data
+    /// directory. See [`AppImage::integrate_in`].
+    ///
+    /// # Returns
+    /// The installed files, or an error if the AppImage has no desktop file or the files can't be written
+    pub fn integrate(&self) -> Result<Integration> {
+        let data_dir = integration::default_data_dir().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Couldn't determine the data directory",
+            )
+        })?;
+        self.integrate_in(data_dir)
+    }
+
+    /// Installs the desktop integration files of the AppImage to the data directory:
+    /// the desktop file to `applications`, rewritten to launch the AppImage, the icons
+    /// to the hicolor theme (or `pixmaps` if their size is unknown), the appstream
+    /// file to `metainfo`, and the MIME packages to `mime/packages`. The files are
+    /// named after the integration identifier, so they can be removed with
+    /// [`integration::unintegrate`].
+    ///
+    /// # Arguments
+    /// * `data_dir` - The data directory to install the files to
+    ///
+    /// # Returns
+    /// The installed files, or an error if the AppImage has no desktop file or the files can't be written
+    pub fn integrate_in<P: AsRef<Path>>(&self, data_dir: P) -> Result<Integration> {
+        let data_dir = data_dir.as_ref();
+        let Some(desktop) = self.find_desktop() else {
+            return Err(SquishyError::FileNotFound(PathBuf::from("*.desktop")));
+        };
+        let id = integration::integration_id(self.path)?;
+        let appimage_path = fs::canonicalize(self.path)?;
+        let name = |entry: &SquashFSEntry| {
+            let stem = entry.path.file_stem().unwrap_or_default().to_string_lossy();
+            let stem = stem
+                .trim_end_matches(".appdata")
+                .trim_end_matches(".metainfo");
+            integration::file_name(&id, stem)
+        };
+
+        let desktop_name = name(&desktop);
+        let mut icons = self
+            .write_icon_theme(
+                data_dir.join("icons"),
+                Some(desktop_name.as_ref()),
+                CollisionStrategy::Overwrite,
+            )?
+            .into_iter()
+            .map(|(_, output)| output)
+            .collect::<Vec<_>>();
+        if icons.is_empty() {
+            if let Some(icon) = self.find_icon() {
+                let data = self.read_file(&icon.path)?;
+                // `.DirIcon` has no extension, so fall back to the contents
+                let (format, ext) = match IconFormat::from_path(&icon.path) {
+                    Some(format) => (
+                        Some(format),
+                        icon.path.extension().unwrap_or_default().to_string_lossy(),
+                    ),
+                    None => {
+                        let format = IconFormat::from_header(&data);
+                        let ext = format.map_or("png", |format| format.extension());
+                        (format, ext.into())
+                    }
+                };
+                let dir = match format {
+                    Some(IconFormat::Svg) => Some("icons/hicolor/scalable/apps".to_string()),
+                    Some(IconFormat::Png) => png_dimensions(&data)
+                        .map(|(width, height)| format!("icons/hicolor/{}x{}/apps", width, height)),
+                    _ => None,
+                };
+                let dir = data_dir.join(dir.as_deref().unwrap_or("pixmaps"));
+                fs::create_dir_all(&dir)?;
+                let icon_path = dir.join(format!("{}.{}", desktop_name, ext));
+                fs::write(&icon_path, data)?;
+                icons.push(icon_path);
+            }
+        }
+
+        let content = self.read_file(&desktop.path)?;
+        let content = integration::rewrite_integrated_desktop(
+            &String::from_utf8_lossy(&content),
+            &appimage_path,
+            (!icons.is_empty()).then_some(desktop_name.as_str()),
+            &id,
+        );
+        let applications = data_dir.join("applications");
+        fs::create_dir_all(&applications)?;
+        let desktop_path = applications.join(format!("{}.desktop", desktop_name));
+        fs::write(&desktop_path, content)?;
+
+        let appstream = match self.find_appstream() {
+            Some(appstream) => {
+                let appstream_name = name(&appstream);
+                let metainfo = data_dir.join("metainfo");
+                fs::create_dir_all(&metainfo)?;
+                let appstream_path = metainfo.join(format!("{}.metainfo.xml", appstream_name));
+                fs::write(&appstream_path, self.read_file(&appstream.path)?)?;
+                Some(appstream_path)
+            }
+            None => None,
+        };
+
+        let mut mime_packages = Vec::new();
+        for package in self.find_mime_packages() {
+            let packages = data_dir.join("mime/packages");
+            fs::create_dir_all(&packages)?;
+            let package_path = packages.join(format!("{}.xml", name(&package)));
+            fs::write(&package_path, self.read_file(&package.path)?)?;
+            mime_packages.push(package_path);
+        }
+
+        Ok(Integration {
+            id,
+            desktop: desktop_path,
+            icons,
+            appstream,
+            mime_packages,
+        })
+    }
+
+    /// Writes the entry to the output directory. By default, the file is named after
+    /// the entry itself. If `output_name` is provided, the file is renamed to it,
+    /// keeping the original extension (and the `appdata`/`metainfo` suffix for appstream files).
+    ///
+    /// # Arguments
+    /// * `entry` - The SquashFS entry to write
+    /// * `output_dir` - Directory to write the file to
+    /// * `output_name` - Optional name to use for the output file
+    /// * `copy_permissions` - Whether to copy permissions from the SquashFS entry
+    ///
+    /// # Returns
+    /// Path to the written file, or an error if the entry is not a file or it cannot be written
+    pub fn write<P: AsRef<Path>>(
+        &self,
+        entry: &SquashFSEntry,
+        output_dir: P,
+        output_name: Option<&OsStr>,
+        copy_permissions: bool,
+    ) -> Result<PathBuf> {
+        let file_name = output_file_name(&entry.path, output_name);
+        let output_path = output_dir.as_ref().join(file_name);
+        self.write_to(entry, &output_path, copy_permissions)?;
+        Ok(output_path)
+    }
+
+    /// Writes the entry to the output path, creating the directory it is in.
+    ///
+    /// # Arguments
+    /// * `entry` - The SquashFS entry to write
+    /// * `output_path` - Path to write the file to
+    /// * `copy_permissions` - Whether to copy permissions from the SquashFS entry
+    ///
+    /// # Returns
+    /// An empty result, or an error if the entry is not a file or it cannot be written
+    pub fn write_to<P: AsRef<Path>>(
+        &self,
+        entry: &SquashFSEntry,
+        output_path: P,
+        copy_permissions: bool,
+    ) -> Result<()> {
+        let EntryKind::File(basic_file) = entry.kind else {
+            return Err(SquishyError::NotAFile(entry.path.clone()));
+        };
+
+        let output_path = output_path.as_ref();
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match (&self.payload, copy_permissions) {
+            (Payload::SquashFS(squashfs), true) => {
+                squashfs.write_file_with_permissions(basic_file, output_path, entry.header)?
+            }
+            (Payload::SquashFS(squashfs), false) => squashfs.write_file(basic_file, output_path)?,
+            (Payload::Iso9660(iso), true) => {
+                iso.write_file_with_permissions(basic_file, output_path, entry.header)?
+            }
+            (Payload::Iso9660(iso), false) => iso.write_file(basic_file, output_path)?,
+            #[cfg(feature = "dwarfs")]
+            (Payload::DwarFS(dwarfs), true) => {
+                dwarfs.write_file_with_permissions(basic_file, output_path, entry.header)?
+            }
+            #[cfg(feature = "dwarfs")]
+            (Payload::DwarFS(dwarfs), false) => dwarfs.write_file(basic_file, output_path)?,
+        }
+        Ok(())
+    }
+}
+
+/// Finds the icon closest to a pixel size, with sizes read from icon theme directory
+/// names. It looks for icon in order:
+/// - Raster icon with the exact size
+/// - Scalable (svg) icon
+/// - Smallest raster icon larger than the size
+/// - Largest raster icon smaller than the size
+///
+/// # Arguments
+/// * `icons` - The icons to choose from
+/// * `size` - Preferred pixel size
+///
+/// # Returns
+/// Index of the icon, if one has a known size or is scalable
+fn closest_icon(icons: &[Icon], size: u32) -> Option<usize> {
+    icons
+        .iter()
+        .position(|icon| icon.format != IconFormat::Svg && icon.size == Some(size))
+        .or_else(|| icons.iter().position(|icon| icon.format == IconFormat::Svg))
+        .or_else(|| {
+            icons
+                .iter()
+                .enumerate()
+                .filter_map(|(i, icon)| icon.size.filter(|s| *s > size).map(|s| (i, s)))
+                .min_by_key(|(_, s)| *s)
+                .map(|(i, _)| i)
+        })
+        .or_else(|| {
+            icons
+                .iter()
+                .enumerate()
+                .filter_map(|(i, icon)| icon.size.filter(|s| *s < size).map(|s| (i, s)))
+                .max_by_key(|(_, s)| *s)
+                .map(|(i, _)| i)
+        })
+}
+
+/// Rewrites a written desktop file in place, see [`rewrite_desktop`].
+///
+/// # Arguments
+/// * `path` - Path of the written desktop file
+/// * `rewrite` - The values to replace
+///
+/// # Returns
+/// An empty result, or an error if the file can't be read or written
+fn rewrite_desktop_file(path: &Path, rewrite: &DesktopRewrite) -> Result<()> {
+    let content = fs::read(path)?;
+    let content = rewrite_desktop(&String::from_utf8_lossy(&content), rewrite);
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// Splits an AppImage file name into the application name and version, where the
+/// version is the first `-` or `_` separated part starting with a digit, optionally
+/// prefixed by `v`, and containing a dot.
+///
+/// # Arguments
+/// * `stem` - File name without the extension
+///
+/// # Returns
+/// The name preceding the version and the version, if found
+fn version_from_file_name(stem: &str) -> Option<(&str, &str)> {
+    let mut start = 0;
+    for part in stem.split(['-', '_']) {
+        let version = part.strip_prefix(['v', 'V']).unwrap_or(part);
+        if version.starts_with(|c: char| c.is_ascii_digit()) && version.contains('.') {
+            return Some((&stem[..start], version));
+        }
+        start += part.len() + 1;
+    }
+    None
+}
+
+/// Gets the application name from an AppImage file name: the part preceding the
+/// version, see [`version_from_file_name`]. The whole file name is used when it has
+/// no version, or when it starts with the version and the name would be empty.
+///
+/// # Arguments
+/// * `stem` - File name without the extension
+///
+/// # Returns
+/// The application name
+fn name_from_file_name(stem: &str) -> &str {
+    version_from_file_name(stem)
+        .map(|(name, _)| name.trim_end_matches(['-', '_', ' ', '.']))
+        .filter(|name| !name.is_empty())
+        .unwrap_or(stem)
+}
+
+/// Computes the output file name for an entry.
+///
+/// # Arguments
+/// * `path` - Path of the entry within the SquashFS
+/// * `output_name` - Optional name to use instead of the entry's file name
+///
+/// # Returns
+/// The file name to write the entry as
+pub fn output_file_name(path: &Path, output_name: Option<&OsStr>) -> OsString {
+    let original_name = path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+
+    let Some(output_name) = output_name else {
+        return original_name;
+    };
+
+    let file_str = original_name.to_string_lossy();
+    let (_, ext) = split_extension(&file_str);
+    if ext.is_empty() {
+        return original_name;
+    }
+    OsString::from(format!("{}.{}", output_name.to_string_lossy(), ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_version_from_file_name() {
+        assert_eq!(
+            version_from_file_name("Firefox-128.0.3-x86_64"),
+            Some(("Firefox-", "128.0.3"))
+        );
+        assert_eq!(
+            version_from_file_name("my_app_v1.2_amd64"),
+            Some(("my_app_", "1.2"))
+        );
+        assert_eq!(version_from_file_name("1.2-Foo"), Some(("", "1.2")));
+        // A version needs a dot
+        assert_eq!(version_from_file_name("App-2-x86_64"), None);
+    }
+
+    #[test]
+    fn gets_name_from_file_name() {
+        assert_eq!(name_from_file_name("Firefox-128.0.3-x86_64"), "Firefox");
+        assert_eq!(name_from_file_name("my_app_v1.2_amd64"), "my_app");
+        assert_eq!(name_from_file_name("Tool"), "Tool");
+        // Starts with the version
+        assert_eq!(name_from_file_name("1.2-Foo"), "1.2-Foo");
+        assert_eq!(name_from_file_name("v1.2"), "v1.2");
+    }
+
+    #[test]
+    fn renames_output_files() {
+        let path = Path::new("/usr/share/icons/app.png");
+        assert_eq!(output_file_name(path, None), "app.png");
+        assert_eq!(output_file_name(path, Some(OsStr::new("icon"))), "icon.png");
+        // Files without an extension keep their name
+        assert_eq!(
+            output_file_name(Path::new("/AppRun"), Some(OsStr::new("icon"))),
+            "AppRun"
+        );
+    }
 }
@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+
+use crate::SquashFSEntry;
+
+/// How the AppRun entry point is launched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppRunKind {
+    /// ELF executable
+    Elf,
+    /// Script with a shebang line, e.g. `#!/bin/sh`
+    Script {
+        /// Interpreter and its arguments from the shebang line
+        interpreter: String,
+    },
+    /// Neither an ELF executable nor a script
+    Unknown,
+}
+
+impl AppRunKind {
+    /// Detects the kind of executable from the beginning of its contents.
+    ///
+    /// # Arguments
+    /// * `header` - The first bytes of the file
+    ///
+    /// # Returns
+    /// The detected kind
+    pub fn from_header(header: &[u8]) -> Self {
+        if header.starts_with(b"\x7fELF") {
+            return AppRunKind::Elf;
+        }
+        let Some(shebang) = header.strip_prefix(b"#!") else {
+            return AppRunKind::Unknown;
+        };
+        let line = shebang.split(|&b| b == b'\n').next().unwrap_or_default();
+        AppRunKind::Script {
+            interpreter: String::from_utf8_lossy(line).trim().to_string(),
+        }
+    }
+}
+
+/// The AppRun entry point of an AppImage
+#[derive(Debug)]
+pub struct AppRun<'a> {
+    /// Path of the AppRun in the AppImage, before resolving symlinks
+    pub path: PathBuf,
+    /// The AppRun file entry, with symlinks resolved
+    pub entry: SquashFSEntry<'a>,
+    /// How the AppRun is launched
+    pub kind: AppRunKind,
+}
+
+impl<'a> AppRun<'a> {
+    /// Converts the AppRun to its file entry under the `AppRun` path, so it is
+    /// written as `AppRun` rather than under the name of the file it links to.
+    ///
+    /// # Returns
+    /// The resolved file entry, with the path of the AppRun
+    pub fn into_entry(self) -> SquashFSEntry<'a> {
+        SquashFSEntry {
+            path: self.path,
+            ..self.entry
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_kind_from_header() {
+        assert_eq!(AppRunKind::from_header(b"\x7fELF\x02\x01"), AppRunKind::Elf);
+        assert_eq!(
+            AppRunKind::from_header(b"#!/usr/bin/env bash \nexec foo"),
+            AppRunKind::Script {
+                interpreter: "/usr/bin/env bash".into()
+            }
+        );
+        assert_eq!(
+            AppRunKind::from_header(b"#! /bin/sh"),
+            AppRunKind::Script {
+                interpreter: "/bin/sh".into()
+            }
+        );
+        assert_eq!(AppRunKind::from_header(b"MZ"), AppRunKind::Unknown);
+        assert_eq!(AppRunKind::from_header(b""), AppRunKind::Unknown);
+    }
+}
@@ -0,0 +1,500 @@
+use roxmltree::{Document, Node};
+
+use crate::error::SquishyError;
+
+use super::{validate::Issue, Result};
+
+/// Namespace of the `xml:lang` attribute
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Parsed AppStream metadata (appdata/metainfo XML) of a component
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppStream {
+    /// Component type, e.g. `desktop-application`
+    pub kind: Option<String>,
+    /// `<id>`
+    pub id: Option<String>,
+    /// Untranslated `<name>`
+    pub name: Option<String>,
+    /// Untranslated `<summary>`
+    pub summary: Option<String>,
+    /// Untranslated `<description>`, as plain text
+    pub description: Option<String>,
+    /// `<metadata_license>`
+    pub metadata_license: Option<String>,
+    /// `<project_license>`
+    pub project_license: Option<String>,
+    /// `<launchable type="desktop-id">`
+    pub launchable: Option<String>,
+    /// `<screenshots>`
+    pub screenshots: Vec<Screenshot>,
+    /// `<releases>`, newest first as listed in the file
+    pub releases: Vec<Release>,
+}
+
+/// A screenshot listed in the AppStream metadata
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Screenshot {
+    /// Whether this is the default screenshot
+    pub default: bool,
+    /// Untranslated caption
+    pub caption: Option<String>,
+    /// Image URLs
+    pub images: Vec<String>,
+}
+
+/// A release listed in the AppStream metadata
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Release {
+    /// Release version
+    pub version: Option<String>,
+    /// Release date, as written in the file
+    pub date: Option<String>,
+    /// Release timestamp, as written in the file
+    pub timestamp: Option<u64>,
+    /// Release description, as plain text
+    pub description: Option<String>,
+}
+
+impl AppStream {
+    /// Parses AppStream metadata.
+    ///
+    /// # Arguments
+    /// * `content` - Contents of the appdata/metainfo XML file
+    ///
+    /// # Returns
+    /// The parsed metadata, or an error if the XML is invalid
+    pub fn parse(content: &str) -> Result<Self> {
+        let document =
+            Document::parse(content).map_err(|e| SquishyError::InvalidAppStream(e.to_string()))?;
+
+        let root = document.root_element();
+        let component = if root.has_tag_name("component") {
+            root
+        } else {
+            // Collection metadata, use the first component
+            root.children()
+                .find(|node| node.has_tag_name("component"))
+                .ok_or_else(|| SquishyError::InvalidAppStream("No component found".into()))?
+        };
+
+        let launchable = component
+            .children()
+            .find(|node| {
+                node.has_tag_name("launchable") && node.attribute("type") == Some("desktop-id")
+            })
+            .and_then(text);
+
+        let screenshots = child(component, "screenshots")
+            .map(|screenshots| {
+                screenshots
+                    .children()
+                    .filter(|node| node.has_tag_name("screenshot"))
+                    .map(|screenshot| Screenshot {
+                        default: screenshot.attribute("type") == Some("default"),
+                        caption: untranslated(screenshot, "caption").and_then(text),
+                        images: screenshot
+                            .children()
+                            .filter(|node| node.has_tag_name("image"))
+                            .filter_map(text)
+                            .collect(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let releases = child(component, "releases")
+            .map(|releases| {
+                releases
+                    .children()
+                    .filter(|node| node.has_tag_name("release"))
+                    .map(|release| Release {
+                        version: release.attribute("version").map(String::from),
+                        date: release.attribute("date").map(String::from),
+                        timestamp: release
+                            .attribute("timestamp")
+                            .and_then(|timestamp| timestamp.parse().ok()),
+                        description: untranslated(release, "description").map(description_text),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(AppStream {
+            kind: component.attribute("type").map(String::from),
+            id: child(component, "id").and_then(text),
+            name: untranslated(component, "name").and_then(text),
+            summary: untranslated(component, "summary").and_then(text),
+            description: untranslated(component, "description").map(description_text),
+            metadata_license: child(component, "metadata_license").and_then(text),
+            project_license: child(component, "project_license").and_then(text),
+            launchable,
+            screenshots,
+            releases,
+        })
+    }
+
+    /// Validates the metadata: the component id format, required name and summary,
+    /// license tags, and well-formed screenshot URLs.
+    ///
+    /// # Returns
+    /// The violations found
+    pub fn validate(&self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+
+        match &self.id {
+            None => issues.push(Issue::error("AppStream metadata has no id")),
+            Some(id) => issues.extend(validate_id(id)),
+        }
+        if self.kind.is_none() {
+            issues.push(Issue::warning("AppStream component has no type"));
+        }
+        if self.name.is_none() {
+            issues.push(Issue::error("AppStream metadata has no name"));
+        }
+        match &self.summary {
+            None => issues.push(Issue::error("AppStream metadata has no summary")),
+            Some(summary) if summary.ends_with('.') => issues.push(Issue::warning(
+                "AppStream summary should not end with a period",
+            )),
+            Some(_) => {}
+        }
+        if self.metadata_license.is_none() {
+            issues.push(Issue::error("AppStream metadata has no metadata_license"));
+        }
+        if self.project_license.is_none() {
+            issues.push(Issue::warning("AppStream metadata has no project_license"));
+        }
+        if self.kind.as_deref() == Some("desktop-application") && self.launchable.is_none() {
+            issues.push(Issue::warning(
+                "AppStream desktop-application has no desktop-id launchable",
+            ));
+        }
+
+        for (index, screenshot) in self.screenshots.iter().enumerate() {
+            if screenshot.images.is_empty() {
+                issues.push(Issue::error(format!(
+                    "AppStream screenshot {} has no image",
+                    index + 1
+                )));
+            }
+            for image in &screenshot.images {
+                if let Some(issue) = validate_url(image) {
+                    issues.push(issue);
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+/// Validates a component id: reverse-DNS notation made of letters, digits, `_`
+/// and `-`, with segments not starting with a digit.
+///
+/// # Arguments
+/// * `id` - The component id
+///
+/// # Returns
+/// The violations found
+fn validate_id(id: &str) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let segments = id.split('.').collect::<Vec<_>>();
+    if segments.iter().any(|segment| segment.is_empty())
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    {
+        issues.push(Issue::error(format!(
+            "AppStream id {} contains invalid characters or empty segments",
+            id
+        )));
+        return issues;
+    }
+    if segments.len() < 3 {
+        issues.push(Issue::warning(format!(
+            "AppStream id {} should be in reverse-DNS notation, e.g. org.example.App",
+            id
+        )));
+    }
+    if segments
+        .iter()
+        .any(|segment| segment.starts_with(|c: char| c.is_ascii_digit()))
+    {
+        issues.push(Issue::warning(format!(
+            "AppStream id {} has a segment starting with a digit",
+            id
+        )));
+    }
+    if id.contains('-') {
+        issues.push(Issue::warning(format!(
+            "AppStream id {} contains a hyphen, use an underscore instead",
+            id
+        )));
+    }
+    issues
+}
+
+/// Validates a screenshot URL: an absolute http(s) URL with a host.
+///
+/// # Arguments
+/// * `url` - The URL
+///
+/// # Returns
+/// The violation found, if any
+fn validate_url(url: &str) -> Option<Issue> {
+    let (scheme, rest) = url.split_once("://").unwrap_or_default();
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    if !matches!(scheme, "http" | "https") || host.is_empty() || url.contains(char::is_whitespace) {
+        return Some(Issue::error(format!(
+            "AppStream screenshot URL {} is not a valid http(s) URL",
+            url
+        )));
+    }
+    (scheme == "http")
+        .then(|| Issue::warning(format!("AppStream screenshot URL {} should use https", url)))
+}
+
+/// Finds the first child element with the tag name.
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+/// Finds the first child element with the tag name that isn't translated.
+fn untranslated<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.has_tag_name(name) && !is_translated(*child))
+}
+
+/// Whether the element has an `xml:lang` attribute.
+fn is_translated(node: Node) -> bool {
+    node.attribute((XML_NAMESPACE, "lang")).is_some()
+}
+
+/// Returns the trimmed text of an element, if not empty.
+fn text(node: Node) -> Option<String> {
+    let text = node
+        .descendants()
+        .filter(|node| node.is_text())
+        .filter_map(|node| node.text())
+        .collect::<String>();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// Converts a description element to plain text. Paragraphs are separated by
+/// blank lines and list items are prefixed with `- `.
+fn description_text(node: Node) -> String {
+    let mut paragraphs = Vec::new();
+    for child in node.children().filter(|node| node.is_element()) {
+        if is_translated(child) {
+            continue;
+        }
+        match child.tag_name().name() {
+            "p" => paragraphs.extend(text(child)),
+            "ul" | "ol" => {
+                let items = child
+                    .children()
+                    .filter(|node| node.has_tag_name("li") && !is_translated(*node))
+                    .filter_map(text)
+                    .map(|item| format!("- {}", item))
+                    .collect::<Vec<_>>();
+                if !items.is_empty() {
+                    paragraphs.push(items.join("\n"));
+                }
+            }
+            _ => {}
+        }
+    }
+    if paragraphs.is_empty() {
+        return text(node).unwrap_or_default();
+    }
+    paragraphs.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::appimage::validate::Severity;
+
+    const METAINFO: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<component type="desktop-application">
+  <id>org.example.App</id>
+  <metadata_license>CC0-1.0</metadata_license>
+  <project_license>MIT</project_license>
+  <name>Example</name>
+  <name xml:lang="de">Beispiel</name>
+  <summary>Does   things</summary>
+  <description>
+    <p>First paragraph.</p>
+    <p xml:lang="de">Erster Absatz.</p>
+    <ul>
+      <li>One</li>
+      <li>Two</li>
+    </ul>
+  </description>
+  <launchable type="desktop-id">org.example.App.desktop</launchable>
+  <screenshots>
+    <screenshot type="default">
+      <caption>Main window</caption>
+      <image>https://example.org/main.png</image>
+    </screenshot>
+    <screenshot>
+      <image>https://example.org/other.png</image>
+    </screenshot>
+  </screenshots>
+  <releases>
+    <release version="1.1" date="2024-02-01">
+      <description><p>Fixes</p></description>
+    </release>
+    <release version="1.0" timestamp="1700000000"/>
+  </releases>
+</component>"#;
+
+    #[test]
+    fn parses_component() {
+        let appstream = AppStream::parse(METAINFO).unwrap();
+        assert_eq!(appstream.kind.as_deref(), Some("desktop-application"));
+        assert_eq!(appstream.id.as_deref(), Some("org.example.App"));
+        assert_eq!(appstream.name.as_deref(), Some("Example"));
+        assert_eq!(appstream.summary.as_deref(), Some("Does things"));
+        assert_eq!(
+            appstream.description.as_deref(),
+            Some("First paragraph.\n\n- One\n- Two")
+        );
+        assert_eq!(appstream.metadata_license.as_deref(), Some("CC0-1.0"));
+        assert_eq!(appstream.project_license.as_deref(), Some("MIT"));
+        assert_eq!(
+            appstream.launchable.as_deref(),
+            Some("org.example.App.desktop")
+        );
+    }
+
+    #[test]
+    fn parses_screenshots_and_releases() {
+        let appstream = AppStream::parse(METAINFO).unwrap();
+        assert_eq!(
+            appstream.screenshots,
+            [
+                Screenshot {
+                    default: true,
+                    caption: Some("Main window".into()),
+                    images: vec!["https://example.org/main.png".into()],
+                },
+                Screenshot {
+                    default: false,
+                    caption: None,
+                    images: vec!["https://example.org/other.png".into()],
+                },
+            ]
+        );
+        assert_eq!(
+            appstream.releases,
+            [
+                Release {
+                    version: Some("1.1".into()),
+                    date: Some("2024-02-01".into()),
+                    timestamp: None,
+                    description: Some("Fixes".into()),
+                },
+                Release {
+                    version: Some("1.0".into()),
+                    date: None,
+                    timestamp: Some(1700000000),
+                    description: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn uses_first_component_of_collection() {
+        let appstream = AppStream::parse(
+            "<components><component><id>a.b.c</id></component><component><id>d.e.f</id></component></components>",
+        )
+        .unwrap();
+        assert_eq!(appstream.id.as_deref(), Some("a.b.c"));
+    }
+
+    #[test]
+    fn rejects_invalid_documents() {
+        assert!(matches!(
+            AppStream::parse("<component>"),
+            Err(SquishyError::InvalidAppStream(_))
+        ));
+        assert!(matches!(
+            AppStream::parse("<components/>"),
+            Err(SquishyError::InvalidAppStream(_))
+        ));
+    }
+
+    fn issues(appstream: &AppStream) -> Vec<String> {
+        appstream
+            .validate()
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn accepts_valid_metadata() {
+        let appstream = AppStream::parse(METAINFO).unwrap();
+        assert!(issues(&appstream).is_empty(), "{:?}", issues(&appstream));
+    }
+
+    #[test]
+    fn reports_missing_fields() {
+        let issues = issues(&AppStream::default());
+        for message in [
+            "error: AppStream metadata has no id",
+            "warning: AppStream component has no type",
+            "error: AppStream metadata has no name",
+            "error: AppStream metadata has no summary",
+            "error: AppStream metadata has no metadata_license",
+            "warning: AppStream metadata has no project_license",
+        ] {
+            assert!(issues.contains(&message.to_string()), "{}", message);
+        }
+    }
+
+    #[test]
+    fn validates_ids() {
+        assert!(validate_id("org.example.App").is_empty());
+        assert_eq!(validate_id("org..App")[0].severity, Severity::Error);
+        assert_eq!(validate_id("org.example.App!")[0].severity, Severity::Error);
+        assert_eq!(
+            validate_id("example.App")[0].message,
+            "AppStream id example.App should be in reverse-DNS notation, e.g. org.example.App"
+        );
+        assert_eq!(
+            validate_id("org.0example.App")[0].message,
+            "AppStream id org.0example.App has a segment starting with a digit"
+        );
+        assert_eq!(
+            validate_id("org.example.my-app")[0].message,
+            "AppStream id org.example.my-app contains a hyphen, use an underscore instead"
+        );
+    }
+
+    #[test]
+    fn validates_urls() {
+        assert_eq!(validate_url("https://example.org/a.png"), None);
+        assert_eq!(
+            validate_url("http://example.org/a.png").map(|issue| issue.severity),
+            Some(Severity::Warning)
+        );
+        for url in [
+            "example.org/a.png",
+            "ftp://example.org/a.png",
+            "https:///a.png",
+            "https://example.org/a b.png",
+        ] {
+            assert_eq!(
+                validate_url(url).map(|issue| issue.severity),
+                Some(Severity::Error),
+                "{}",
+                url
+            );
+        }
+    }
+}
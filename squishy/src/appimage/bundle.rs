@@ -0,0 +1,157 @@
+use std::{
+    ffi::{OsStr, OsString},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    appimage::{
+        icon::IconPreference,
+        naming::{FileKind, NameTemplate, NameValues},
+        output_file_name,
+    },
+    collision::CollisionStrategy,
+    error::SquishyError,
+    progress::ProgressCallback,
+};
+
+/// Options for extracting the desktop integration files of an AppImage
+#[derive(Clone, Copy)]
+pub struct BundleOptions<'a> {
+    /// Whether to extract the icon
+    pub icon: bool,
+    /// Which icon to extract
+    pub icon_preference: IconPreference,
+    /// Whether to extract the desktop file
+    pub desktop: bool,
+    /// Whether to extract the appstream file
+    pub appstream: bool,
+    /// Whether to extract the AppRun entry point, which keeps its permissions
+    pub apprun: bool,
+    /// Optional name to use for the output files, keeping their extensions
+    pub output_name: Option<&'a OsStr>,
+    /// Template for the output file names, used instead of `output_name`
+    pub name_template: Option<&'a NameTemplate>,
+    /// Whether to copy permissions from the SquashFS entries
+    pub copy_permissions: bool,
+    /// Whether to rewrite the desktop file to launch the AppImage and use the extracted icon
+    pub rewrite_desktop: bool,
+    /// How to handle output files that already exist
+    pub collision: CollisionStrategy,
+    /// Callback receiving the progress after each file
+    pub progress: Option<ProgressCallback<'a>>,
+}
+
+impl fmt::Debug for BundleOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BundleOptions")
+            .field("icon", &self.icon)
+            .field("icon_preference", &self.icon_preference)
+            .field("desktop", &self.desktop)
+            .field("appstream", &self.appstream)
+            .field("apprun", &self.apprun)
+            .field("output_name", &self.output_name)
+            .field("name_template", &self.name_template)
+            .field("copy_permissions", &self.copy_permissions)
+            .field("rewrite_desktop", &self.rewrite_desktop)
+            .field("collision", &self.collision)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+impl Default for BundleOptions<'_> {
+    fn default() -> Self {
+        BundleOptions {
+            icon: true,
+            icon_preference: IconPreference::default(),
+            desktop: true,
+            appstream: true,
+            apprun: false,
+            output_name: None,
+            name_template: None,
+            copy_permissions: false,
+            rewrite_desktop: false,
+            collision: CollisionStrategy::Overwrite,
+            progress: None,
+        }
+    }
+}
+
+impl BundleOptions<'_> {
+    /// Computes the output file name of a bundle file, from the name template if
+    /// any, and otherwise like [`output_file_name`].
+    ///
+    /// # Arguments
+    /// * `path` - Path of the file within the AppImage
+    /// * `kind` - Kind of the file
+    /// * `values` - Values of the template placeholders, see
+    ///   [`AppImage::name_values`](crate::appimage::AppImage::name_values)
+    ///
+    /// # Returns
+    /// The file name to write the file as
+    pub fn file_name(&self, path: &Path, kind: FileKind, values: &NameValues) -> OsString {
+        match self.name_template {
+            Some(template) => template.render(path, kind, values),
+            None => output_file_name(path, self.output_name),
+        }
+    }
+}
+
+/// Outcome of extracting a single file of the bundle
+#[derive(Debug)]
+pub enum BundleStatus {
+    /// The file was written
+    Written {
+        /// Path of the file in the AppImage
+        source: PathBuf,
+        /// Path the file was written to
+        output: PathBuf,
+    },
+    /// The output file already exists and was kept
+    Exists {
+        /// Path of the file in the AppImage
+        source: PathBuf,
+        /// Path of the existing output file
+        output: PathBuf,
+    },
+    /// The file was not found in the AppImage
+    Missing,
+    /// The file was found, but couldn't be written
+    Failed {
+        /// Path of the file in the AppImage
+        source: PathBuf,
+        /// The error that occurred
+        error: SquishyError,
+    },
+    /// The file was not requested
+    Skipped,
+}
+
+/// Report of the desktop integration files extracted from an AppImage
+#[derive(Debug)]
+pub struct BundleReport {
+    pub icon: BundleStatus,
+    pub desktop: BundleStatus,
+    pub appstream: BundleStatus,
+    pub apprun: BundleStatus,
+}
+
+impl BundleReport {
+    /// Checks if every requested file was written.
+    ///
+    /// # Returns
+    /// boolean stating if no requested file is missing or failed
+    pub fn is_complete(&self) -> bool {
+        [&self.icon, &self.desktop, &self.appstream, &self.apprun]
+            .iter()
+            .all(|status| {
+                matches!(
+                    status,
+                    BundleStatus::Written { .. }
+                        | BundleStatus::Exists { .. }
+                        | BundleStatus::Skipped
+                )
+            })
+    }
+}
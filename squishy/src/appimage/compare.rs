@@ -0,0 +1,252 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem::discriminant,
+    path::PathBuf,
+};
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{EntryKind, SquashFSEntry};
+
+use super::{AppImage, Result};
+
+/// Number of bytes compared at a time when comparing file contents
+const COMPARE_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// A file whose contents or metadata differ between two AppImages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedFile {
+    /// Path of the file in the payload
+    pub path: PathBuf,
+    /// Size in the old AppImage
+    pub old_size: u32,
+    /// Size in the new AppImage
+    pub new_size: u32,
+}
+
+/// A desktop file key whose value differs between two AppImages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopChange {
+    /// The key, e.g. `Exec` or `Name[de]`
+    pub key: String,
+    /// Value in the old AppImage, None if the key was added
+    pub old: Option<String>,
+    /// Value in the new AppImage, None if the key was removed
+    pub new: Option<String>,
+}
+
+/// Report of the differences between two AppImages, as produced by [`compare`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompareReport {
+    /// Version of the old AppImage
+    pub old_version: Option<String>,
+    /// Version of the new AppImage
+    pub new_version: Option<String>,
+    /// Payload files only in the new AppImage, sorted
+    pub added: Vec<PathBuf>,
+    /// Payload files only in the old AppImage, sorted
+    pub removed: Vec<PathBuf>,
+    /// Payload files in both AppImages that differ in type, contents, symlink
+    /// target or permissions, sorted
+    pub changed: Vec<ChangedFile>,
+    /// Whether the icon contents differ
+    pub icon_changed: bool,
+    /// Desktop file keys whose values differ, sorted by key
+    pub desktop_changes: Vec<DesktopChange>,
+}
+
+impl CompareReport {
+    /// Checks whether the version differs.
+    pub fn version_changed(&self) -> bool {
+        self.old_version != self.new_version
+    }
+
+    /// Checks whether the AppImages are identical, as far as the report goes.
+    pub fn is_empty(&self) -> bool {
+        !self.version_changed()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && !self.icon_changed
+            && self.desktop_changes.is_empty()
+    }
+}
+
+/// Compares two AppImages, e.g. two versions of the same application, to show
+/// what an update changes. The payload is compared in full; filters aren't applied
+/// to the file lists, but are to the icon and desktop file searches.
+///
+/// # Arguments
+/// * `old` - The old AppImage
+/// * `new` - The new AppImage
+///
+/// # Returns
+/// The comparison report, or an error if either AppImage can't be read
+pub fn compare(old: &AppImage, new: &AppImage) -> Result<CompareReport> {
+    let old_entries = entries_by_path(old);
+    let mut new_entries = entries_by_path(new);
+
+    let mut removed = Vec::new();
+    let mut common = Vec::new();
+    for (path, old_entry) in old_entries {
+        match new_entries.remove(&path) {
+            Some(new_entry) => common.push((old_entry, new_entry)),
+            None => removed.push(path),
+        }
+    }
+    let mut added = new_entries.into_keys().collect::<Vec<_>>();
+    added.sort();
+    removed.sort();
+
+    let mut changed = common
+        .into_par_iter()
+        .filter_map(|(old_entry, new_entry)| {
+            match entries_differ(old, &old_entry, new, &new_entry) {
+                Ok(false) => None,
+                Ok(true) => Some(Ok(ChangedFile {
+                    path: new_entry.path,
+                    old_size: old_entry.size,
+                    new_size: new_entry.size,
+                })),
+                Err(e) => Some(Err(e)),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    changed.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(CompareReport {
+        old_version: old.version(),
+        new_version: new.version(),
+        added,
+        removed,
+        changed,
+        icon_changed: icon_contents(old)? != icon_contents(new)?,
+        desktop_changes: desktop_changes(old, new)?,
+    })
+}
+
+/// Collects the payload entries of the AppImage by path.
+fn entries_by_path<'a>(appimage: &'a AppImage) -> HashMap<PathBuf, SquashFSEntry<'a>> {
+    appimage
+        .par_entries()
+        .map(|entry| (entry.path.clone(), entry))
+        .collect()
+}
+
+/// Checks whether two entries with the same path differ.
+///
+/// # Arguments
+/// * `old` - The old AppImage
+/// * `old_entry` - The entry in the old AppImage
+/// * `new` - The new AppImage
+/// * `new_entry` - The entry in the new AppImage
+///
+/// # Returns
+/// boolean stating if the entries differ, or an error if the files can't be read
+fn entries_differ(
+    old: &AppImage,
+    old_entry: &SquashFSEntry,
+    new: &AppImage,
+    new_entry: &SquashFSEntry,
+) -> Result<bool> {
+    if discriminant(&old_entry.kind) != discriminant(&new_entry.kind)
+        || old_entry.header.permissions != new_entry.header.permissions
+    {
+        return Ok(true);
+    }
+    match (&old_entry.kind, &new_entry.kind) {
+        (EntryKind::Symlink(old_target), EntryKind::Symlink(new_target)) => {
+            Ok(old_target != new_target)
+        }
+        (EntryKind::File(_), EntryKind::File(_)) => {
+            if old_entry.size != new_entry.size {
+                return Ok(true);
+            }
+            contents_differ(old, old_entry, new, new_entry)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Compares the contents of two files of the same size chunk by chunk, stopping
+/// at the first difference.
+///
+/// # Arguments
+/// * `old` - The old AppImage
+/// * `old_entry` - The file in the old AppImage
+/// * `new` - The new AppImage
+/// * `new_entry` - The file in the new AppImage
+///
+/// # Returns
+/// boolean stating if the contents differ, or an error if the files can't be read
+fn contents_differ(
+    old: &AppImage,
+    old_entry: &SquashFSEntry,
+    new: &AppImage,
+    new_entry: &SquashFSEntry,
+) -> Result<bool> {
+    let size = old_entry.size as u64;
+    let mut offset = 0;
+    while offset < size {
+        let old_chunk = old.read_range(old_entry, offset, COMPARE_CHUNK_SIZE)?;
+        let new_chunk = new.read_range(new_entry, offset, COMPARE_CHUNK_SIZE)?;
+        if old_chunk != new_chunk {
+            return Ok(true);
+        }
+        if old_chunk.is_empty() {
+            break;
+        }
+        offset += old_chunk.len() as u64;
+    }
+    Ok(false)
+}
+
+/// Reads the contents of the icon of the AppImage.
+fn icon_contents(appimage: &AppImage) -> Result<Option<Vec<u8>>> {
+    appimage
+        .find_icon()
+        .map(|icon| appimage.read_file(&icon.path))
+        .transpose()
+}
+
+/// Computes the desktop file keys whose values differ.
+///
+/// # Arguments
+/// * `old` - The old AppImage
+/// * `new` - The new AppImage
+///
+/// # Returns
+/// The changed keys sorted by key, or an error if a desktop file can't be read
+fn desktop_changes(old: &AppImage, new: &AppImage) -> Result<Vec<DesktopChange>> {
+    let entries = |appimage: &AppImage| -> Result<BTreeMap<String, String>> {
+        Ok(appimage
+            .desktop_entry()?
+            .map(|desktop| desktop.entries.into_iter().collect())
+            .unwrap_or_default())
+    };
+    let old_entries = entries(old)?;
+    let mut new_entries = entries(new)?;
+
+    let mut changes = Vec::new();
+    for (key, old_value) in old_entries {
+        let new_value = new_entries.remove(&key);
+        if new_value.as_ref() != Some(&old_value) {
+            changes.push(DesktopChange {
+                key,
+                old: Some(old_value),
+                new: new_value,
+            });
+        }
+    }
+    changes.extend(
+        new_entries
+            .into_iter()
+            .map(|(key, new_value)| DesktopChange {
+                key,
+                old: None,
+                new: Some(new_value),
+            }),
+    );
+    changes.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(changes)
+}
@@ -0,0 +1,740 @@
+use std::path::Path;
+
+use super::validate::Issue;
+
+/// Keys defined by the Desktop Entry specification
+const KNOWN_KEYS: [&str; 25] = [
+    "Type",
+    "Version",
+    "Name",
+    "GenericName",
+    "NoDisplay",
+    "Comment",
+    "Icon",
+    "Hidden",
+    "OnlyShowIn",
+    "NotShowIn",
+    "DBusActivatable",
+    "TryExec",
+    "Exec",
+    "Path",
+    "Terminal",
+    "Actions",
+    "MimeType",
+    "Categories",
+    "Implements",
+    "Keywords",
+    "StartupNotify",
+    "StartupWMClass",
+    "URL",
+    "PrefersNonDefaultGPU",
+    "SingleMainWindow",
+];
+
+/// Keys with a boolean value
+const BOOLEAN_KEYS: [&str; 8] = [
+    "NoDisplay",
+    "Hidden",
+    "DBusActivatable",
+    "Terminal",
+    "StartupNotify",
+    "PrefersNonDefaultGPU",
+    "SingleMainWindow",
+    "X-AppImage-Integrate",
+];
+
+/// Main categories registered in the Desktop Menu specification
+const MAIN_CATEGORIES: [&str; 13] = [
+    "AudioVideo",
+    "Audio",
+    "Video",
+    "Development",
+    "Education",
+    "Game",
+    "Graphics",
+    "Network",
+    "Office",
+    "Science",
+    "Settings",
+    "System",
+    "Utility",
+];
+
+/// Additional and reserved categories registered in the Desktop Menu specification
+const ADDITIONAL_CATEGORIES: &[&str] = &[
+    "Building",
+    "Debugger",
+    "IDE",
+    "GUIDesigner",
+    "Profiling",
+    "RevisionControl",
+    "Translation",
+    "Calendar",
+    "ContactManagement",
+    "Database",
+    "Dictionary",
+    "Chart",
+    "Email",
+    "Finance",
+    "FlowChart",
+    "PDA",
+    "ProjectManagement",
+    "Presentation",
+    "Spreadsheet",
+    "WordProcessor",
+    "2DGraphics",
+    "VectorGraphics",
+    "RasterGraphics",
+    "3DGraphics",
+    "Scanning",
+    "OCR",
+    "Photography",
+    "Publishing",
+    "Viewer",
+    "TextTools",
+    "DesktopSettings",
+    "HardwareSettings",
+    "Printing",
+    "PackageManager",
+    "Dialup",
+    "InstantMessaging",
+    "Chat",
+    "IRCClient",
+    "Feed",
+    "FileTransfer",
+    "HamRadio",
+    "News",
+    "P2P",
+    "RemoteAccess",
+    "Telephony",
+    "TelephonyTools",
+    "VideoConference",
+    "WebBrowser",
+    "WebDevelopment",
+    "Midi",
+    "Mixer",
+    "Sequencer",
+    "Tuner",
+    "TV",
+    "AudioVideoEditing",
+    "Player",
+    "Recorder",
+    "DiscBurning",
+    "ActionGame",
+    "AdventureGame",
+    "ArcadeGame",
+    "BoardGame",
+    "BlocksGame",
+    "CardGame",
+    "KidsGame",
+    "LogicGame",
+    "RolePlaying",
+    "Shooter",
+    "Simulation",
+    "SportsGame",
+    "StrategyGame",
+    "Art",
+    "Construction",
+    "Music",
+    "Languages",
+    "ArtificialIntelligence",
+    "Astronomy",
+    "Biology",
+    "Chemistry",
+    "ComputerScience",
+    "DataVisualization",
+    "Economy",
+    "Electricity",
+    "Geography",
+    "Geology",
+    "Geoscience",
+    "History",
+    "Humanities",
+    "ImageProcessing",
+    "Literature",
+    "Maps",
+    "Math",
+    "NumericalAnalysis",
+    "MedicalSoftware",
+    "Physics",
+    "Robotics",
+    "Spirituality",
+    "Sports",
+    "ParallelComputing",
+    "Amusement",
+    "Archiving",
+    "Compression",
+    "Electronics",
+    "Emulator",
+    "Engineering",
+    "FileTools",
+    "FileManager",
+    "TerminalEmulator",
+    "Filesystem",
+    "Monitor",
+    "Security",
+    "Accessibility",
+    "Calculator",
+    "Clock",
+    "TextEditor",
+    "Documentation",
+    "Adult",
+    "Core",
+    "KDE",
+    "GNOME",
+    "XFCE",
+    "DDE",
+    "GTK",
+    "Qt",
+    "Motif",
+    "Java",
+    "ConsoleOnly",
+    "Screensaver",
+    "TrayIcon",
+    "Applet",
+    "Shell",
+];
+
+/// Parsed `[Desktop Entry]` group of a desktop file, following the freedesktop
+/// Desktop Entry specification.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DesktopEntry {
+    /// `Name` key
+    pub name: Option<String>,
+    /// `Exec` key
+    pub exec: Option<String>,
+    /// `Icon` key
+    pub icon: Option<String>,
+    /// `Categories` key
+    pub categories: Vec<String>,
+    /// `MimeType` key
+    pub mime_types: Vec<String>,
+    /// `Terminal` key
+    pub terminal: bool,
+    /// `X-AppImage-Version` key
+    pub version: Option<String>,
+    /// All the keys of the `[Desktop Entry]` group in file order, including localized ones
+    pub entries: Vec<(String, String)>,
+}
+
+impl DesktopEntry {
+    /// Parses the `[Desktop Entry]` group of a desktop file.
+    ///
+    /// # Arguments
+    /// * `content` - Contents of the desktop file
+    ///
+    /// # Returns
+    /// The parsed desktop entry
+    pub fn parse(content: &str) -> Self {
+        let mut entries = Vec::new();
+        let mut in_group = false;
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                in_group = line == "[Desktop Entry]";
+                continue;
+            }
+            if !in_group {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                entries.push((key.trim().to_string(), value.trim().to_string()));
+            }
+        }
+
+        let raw = |key: &str| {
+            entries
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        let get = |key: &str| raw(key).map(unescape);
+
+        DesktopEntry {
+            name: get("Name"),
+            exec: get("Exec"),
+            icon: get("Icon"),
+            categories: raw("Categories").map(split_list).unwrap_or_default(),
+            mime_types: raw("MimeType").map(split_list).unwrap_or_default(),
+            terminal: get("Terminal").is_some_and(|v| v == "true"),
+            version: get("X-AppImage-Version"),
+            entries,
+        }
+    }
+
+    /// Returns the raw value of a key in the `[Desktop Entry]` group.
+    ///
+    /// # Arguments
+    /// * `key` - The key, e.g. `Name[de]`
+    ///
+    /// # Returns
+    /// The value, if the key is present
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Validates the entry against the Desktop Entry specification: required keys,
+    /// known keys and boolean values, registered categories, and a relative `Exec`
+    /// program as required for AppImages.
+    ///
+    /// # Returns
+    /// The violations found
+    pub fn validate(&self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        if self.entries.is_empty() {
+            issues.push(Issue::error("Desktop file has no [Desktop Entry] group"));
+            return issues;
+        }
+
+        let kind = self.get("Type");
+        match kind {
+            None => issues.push(Issue::error("Desktop file is missing the Type key")),
+            Some("Application" | "Link" | "Directory") => {}
+            Some(kind) => issues.push(Issue::error(format!(
+                "Desktop file has an invalid Type {}",
+                kind
+            ))),
+        }
+        if self.name.is_none() {
+            issues.push(Issue::error("Desktop file is missing the Name key"));
+        }
+        if kind == Some("Link") && self.get("URL").is_none() {
+            issues.push(Issue::error(
+                "Desktop file of type Link is missing the URL key",
+            ));
+        }
+
+        if kind == Some("Application") {
+            match self.exec_args().first() {
+                None if self.get("DBusActivatable") != Some("true") => {
+                    issues.push(Issue::error("Desktop file is missing the Exec key"))
+                }
+                Some(program) if program.starts_with('/') => issues.push(Issue::error(format!(
+                    "Exec uses the absolute path {}, which doesn't exist outside the AppImage",
+                    program
+                ))),
+                _ => {}
+            }
+        }
+
+        for (key, value) in &self.entries {
+            let base_key = key.split_once('[').map_or(key.as_str(), |(base, _)| base);
+            if !base_key.starts_with("X-") && !KNOWN_KEYS.contains(&base_key) {
+                issues.push(Issue::warning(format!(
+                    "Desktop file has an unknown key {}",
+                    key
+                )));
+            }
+            if BOOLEAN_KEYS.contains(&base_key) && value != "true" && value != "false" {
+                issues.push(Issue::error(format!(
+                    "Value of {} must be true or false, found {}",
+                    key, value
+                )));
+            }
+        }
+
+        if kind == Some("Application") {
+            for category in &self.categories {
+                if !category.starts_with("X-")
+                    && !MAIN_CATEGORIES.contains(&category.as_str())
+                    && !ADDITIONAL_CATEGORIES.contains(&category.as_str())
+                {
+                    issues.push(Issue::warning(format!(
+                        "Desktop file has an unregistered category {}",
+                        category
+                    )));
+                }
+            }
+            if !self
+                .categories
+                .iter()
+                .any(|category| MAIN_CATEGORIES.contains(&category.as_str()))
+            {
+                issues.push(Issue::warning(
+                    "Desktop file has no main category, so menus may not show it",
+                ));
+            }
+        }
+
+        issues
+    }
+
+    /// Splits the `Exec` key into the program and its arguments, following the
+    /// quoting rules of the specification. Field codes like `%f` or `%U` are removed.
+    ///
+    /// # Returns
+    /// The program followed by its arguments, or an empty list if there's no `Exec` key
+    pub fn exec_args(&self) -> Vec<String> {
+        let Some(exec) = &self.exec else {
+            return Vec::new();
+        };
+
+        let mut args = Vec::new();
+        let mut current = String::new();
+        let mut in_arg = false;
+        let mut quoted = false;
+        let mut chars = exec.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    quoted = !quoted;
+                    in_arg = true;
+                }
+                '\\' if quoted => {
+                    if let Some(next) = chars.next() {
+                        current.push(next);
+                    }
+                }
+                c if c.is_whitespace() && !quoted => {
+                    if in_arg {
+                        args.push(std::mem::take(&mut current));
+                        in_arg = false;
+                    }
+                }
+                _ => {
+                    current.push(c);
+                    in_arg = true;
+                }
+            }
+        }
+        if in_arg {
+            args.push(current);
+        }
+
+        args.into_iter()
+            .filter_map(|arg| {
+                if arg.len() == 2 && arg.starts_with('%') && arg != "%%" {
+                    None
+                } else {
+                    Some(arg.replace("%%", "%"))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Values to replace when rewriting a desktop file
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DesktopRewrite<'a> {
+    /// Path of the AppImage, replacing the program of `Exec` and the value of `TryExec`
+    pub appimage: Option<&'a Path>,
+    /// Icon name, replacing the value of `Icon`
+    pub icon: Option<&'a str>,
+}
+
+/// Rewrites the `Exec`, `TryExec` and `Icon` keys of a desktop file, in all the
+/// groups. The arguments of `Exec` are kept, and everything else is left untouched.
+///
+/// # Arguments
+/// * `content` - Contents of the desktop file
+/// * `rewrite` - The values to replace
+///
+/// # Returns
+/// The rewritten desktop file
+pub fn rewrite_desktop(content: &str, rewrite: &DesktopRewrite) -> String {
+    let appimage = rewrite.appimage.map(|appimage| appimage.to_string_lossy());
+    let mut result = String::with_capacity(content.len());
+    for line in content.lines() {
+        let rewritten = match line.trim().split_once('=') {
+            Some((key, value)) if key.trim() == "Exec" => appimage
+                .as_ref()
+                .map(|appimage| format!("Exec={}{}", quote_exec_arg(appimage), exec_rest(value))),
+            Some((key, _)) if key.trim() == "TryExec" => appimage
+                .as_ref()
+                .map(|appimage| format!("TryExec={}", escape(appimage))),
+            Some((key, _)) if key.trim() == "Icon" => {
+                rewrite.icon.map(|icon| format!("Icon={}", escape(icon)))
+            }
+            _ => None,
+        };
+        result.push_str(rewritten.as_deref().unwrap_or(line));
+        result.push('\n');
+    }
+    result
+}
+
+/// Returns the arguments of an `Exec` value, with the program removed.
+///
+/// # Arguments
+/// * `value` - The raw `Exec` value
+///
+/// # Returns
+/// The remainder of the value after the program, including the leading whitespace
+fn exec_rest(value: &str) -> &str {
+    let value = value.trim_start();
+    let end = if let Some(quoted) = value.strip_prefix('"') {
+        let mut escaped = false;
+        quoted
+            .char_indices()
+            .find(|&(_, c)| {
+                let end = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                end
+            })
+            .map_or(value.len(), |(i, _)| i + 2)
+    } else {
+        value.find(char::is_whitespace).unwrap_or(value.len())
+    };
+    &value[end..]
+}
+
+/// Quotes an argument for an `Exec` value, escaping it as required by the
+/// Desktop Entry specification. `%` is doubled so it isn't taken as a field code.
+///
+/// # Arguments
+/// * `arg` - The argument
+///
+/// # Returns
+/// The quoted and escaped argument
+fn quote_exec_arg(arg: &str) -> String {
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '%' => quoted.push_str("%%"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    escape(&quoted)
+}
+
+/// Escapes a desktop entry string value.
+///
+/// # Arguments
+/// * `value` - The value
+///
+/// # Returns
+/// The escaped value
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\")
+}
+
+/// Unescapes a desktop entry string value.
+///
+/// # Arguments
+/// * `value` - The raw value
+///
+/// # Returns
+/// The unescaped value
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => result.push(' '),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Splits a `;` separated desktop entry list, honoring escaped separators, and
+/// unescapes its items.
+///
+/// # Arguments
+/// * `value` - The raw value
+///
+/// # Returns
+/// The non-empty list items
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // Escape sequences are kept as is, to be unescaped with the item
+            '\\' => {
+                current.push(c);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            ';' => {
+                if !current.is_empty() {
+                    items.push(unescape(&std::mem::take(&mut current)));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        items.push(unescape(&current));
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescapes_values() {
+        assert_eq!(unescape(r"a\sb\nc\td\re"), "a b\nc\td\re");
+        assert_eq!(unescape(r"C:\\path"), r"C:\path");
+        assert_eq!(unescape(r"a\;b"), "a;b");
+        assert_eq!(unescape(r"trailing\"), r"trailing\");
+    }
+
+    #[test]
+    fn splits_lists() {
+        assert_eq!(
+            split_list("Utility;Development;"),
+            ["Utility", "Development"]
+        );
+        assert_eq!(split_list(r"a\;b;c"), ["a;b", "c"]);
+        assert_eq!(split_list(r"a\\;b"), [r"a\", "b"]);
+        assert_eq!(split_list(r"a\sb;;"), ["a b"]);
+        assert!(split_list("").is_empty());
+    }
+
+    #[test]
+    fn parses_desktop_entry_group() {
+        let entry = DesktopEntry::parse(
+            "# comment\n\
+             [Desktop Entry]\n\
+             Name = My\\;App\n\
+             Exec=myapp %U\n\
+             Icon=myapp\n\
+             Categories=Utility;X-Custom\\;Thing;\n\
+             MimeType=text/plain;\n\
+             Terminal=true\n\
+             X-AppImage-Version=1.2.3\n\
+             Name[de]=Meine App\n\
+             [Desktop Action new]\n\
+             Name=New window\n",
+        );
+        assert_eq!(entry.name.as_deref(), Some("My;App"));
+        assert_eq!(entry.exec.as_deref(), Some("myapp %U"));
+        assert_eq!(entry.icon.as_deref(), Some("myapp"));
+        assert_eq!(entry.categories, ["Utility", "X-Custom;Thing"]);
+        assert_eq!(entry.mime_types, ["text/plain"]);
+        assert!(entry.terminal);
+        assert_eq!(entry.version.as_deref(), Some("1.2.3"));
+        assert_eq!(entry.get("Name[de]"), Some("Meine App"));
+        assert_eq!(entry.entries.len(), 8);
+    }
+
+    #[test]
+    fn splits_exec_arguments() {
+        let entry = DesktopEntry {
+            exec: Some(r#""my app" --flag "a \"quoted\" arg" %f 100%%"#.into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            entry.exec_args(),
+            ["my app", "--flag", r#"a "quoted" arg"#, "100%"]
+        );
+        assert!(DesktopEntry::default().exec_args().is_empty());
+    }
+
+    #[test]
+    fn rewrites_exec_try_exec_and_icon() {
+        let rewrite = DesktopRewrite {
+            appimage: Some(Path::new("/opt/My App.AppImage")),
+            icon: Some("my-icon"),
+        };
+        let content = "[Desktop Entry]\nExec=\"old app\" --x %F\nTryExec=old\nIcon=old\nName=App\n";
+        assert_eq!(
+            rewrite_desktop(content, &rewrite),
+            "[Desktop Entry]\n\
+             Exec=\"/opt/My App.AppImage\" --x %F\n\
+             TryExec=/opt/My App.AppImage\n\
+             Icon=my-icon\n\
+             Name=App\n"
+        );
+    }
+
+    #[test]
+    fn keeps_keys_without_replacement() {
+        let content = "[Desktop Entry]\nExec=app\nIcon=app\n";
+        assert_eq!(
+            rewrite_desktop(content, &DesktopRewrite::default()),
+            content
+        );
+    }
+
+    #[test]
+    fn splits_exec_program_from_arguments() {
+        assert_eq!(exec_rest("app --flag %U"), " --flag %U");
+        assert_eq!(exec_rest(r#""my \"app\"" --flag"#), " --flag");
+        assert_eq!(exec_rest("app"), "");
+    }
+
+    #[test]
+    fn quotes_exec_arguments() {
+        assert_eq!(quote_exec_arg("/opt/app"), r#""/opt/app""#);
+        assert_eq!(quote_exec_arg("a$b"), r#""a\\$b""#);
+        assert_eq!(quote_exec_arg("50%"), r#""50%%""#);
+    }
+
+    fn issues(content: &str) -> Vec<String> {
+        DesktopEntry::parse(content)
+            .validate()
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn accepts_valid_entry() {
+        let messages = issues(
+            "[Desktop Entry]\nType=Application\nName=App\nExec=app %U\nIcon=app\n\
+             Categories=Utility;X-Custom;\nTerminal=false\nX-AppImage-Version=1\n",
+        );
+        assert!(messages.is_empty(), "{:?}", messages);
+    }
+
+    #[test]
+    fn reports_missing_required_keys() {
+        let messages = issues("[Desktop Entry]\nType=Application\nCategories=Utility;\n");
+        assert!(messages.contains(&"error: Desktop file is missing the Name key".to_string()));
+        assert!(messages.contains(&"error: Desktop file is missing the Exec key".to_string()));
+
+        assert_eq!(
+            issues("[Other]\nName=x\n"),
+            ["error: Desktop file has no [Desktop Entry] group"]
+        );
+        assert!(issues("[Desktop Entry]\nType=Link\nName=x\n")
+            .contains(&"error: Desktop file of type Link is missing the URL key".to_string()));
+    }
+
+    #[test]
+    fn reports_invalid_values() {
+        let messages = issues(
+            "[Desktop Entry]\nType=Application\nName=App\nExec=/usr/bin/app\n\
+             Terminal=yes\nUnknown=1\nCategories=NotACategory;\n",
+        );
+        assert!(messages
+            .iter()
+            .any(|m| m.starts_with("error: Exec uses the absolute path /usr/bin/app")));
+        assert!(messages
+            .contains(&"error: Value of Terminal must be true or false, found yes".to_string()));
+        assert!(messages.contains(&"warning: Desktop file has an unknown key Unknown".to_string()));
+        assert!(messages.contains(
+            &"warning: Desktop file has an unregistered category NotACategory".to_string()
+        ));
+        assert!(messages.contains(
+            &"warning: Desktop file has no main category, so menus may not show it".to_string()
+        ));
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use sha2::{Digest, Sha256};
+
+use super::{elf_section_range, Result};
+
+/// Sections excluded from the digest, as they are written after signing
+const SIGNATURE_SECTIONS: [&str; 2] = [".sha256_sig", ".sig_key"];
+
+/// Computes the SHA256 digest of the AppImage the way appimagetool does when
+/// signing: the whole file is hashed, with the signature sections zeroed out.
+///
+/// # Arguments
+/// * `path` - Path to the appimage file
+/// * `skip_signature` - Whether to zero out the signature sections
+///
+/// # Returns
+/// The SHA256 digest, or an error if the file can't be read
+pub(crate) fn compute_digest<P: AsRef<Path>>(path: P, skip_signature: bool) -> Result<[u8; 32]> {
+    let path = path.as_ref();
+
+    let mut skipped = Vec::new();
+    if skip_signature {
+        for name in SIGNATURE_SECTIONS {
+            if let Some((offset, size)) = elf_section_range(path, name)? {
+                skipped.push(offset..offset + size);
+            }
+        }
+    }
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0_u8; 64 * 1024];
+    let mut position = 0_u64;
+
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        let chunk = &mut buf[..read];
+        let chunk_range = position..position + read as u64;
+        for range in &skipped {
+            let start = range.start.max(chunk_range.start);
+            let end = range.end.min(chunk_range.end);
+            if start < end {
+                let start = (start - position) as usize;
+                let end = (end - position) as usize;
+                chunk[start..end].fill(0);
+            }
+        }
+        hasher.update(chunk);
+        position += read as u64;
+    }
+
+    Ok(hasher.finalize().into())
+}
+
+/// Encodes the digest as a lowercase hex string.
+///
+/// # Arguments
+/// * `digest` - The digest bytes
+///
+/// # Returns
+/// The hex encoded digest
+pub(crate) fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,381 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File, Permissions},
+    io::{self, BufWriter, Read, Write},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use ::dwarfs::{
+    archive::IsInode, positioned_io::Slice, section::SectionReader, Archive, ArchiveIndex,
+    AsChunks, Dir, InodeKind,
+};
+use backhand::{BasicFile, NodeHeader};
+
+use crate::{error::SquishyError, EntryKind, SquashFSEntry};
+
+use super::{get_elf_end, scan_magic, Result};
+
+/// DwarFS section magic bytes
+const DWARFS_MAGIC: [u8; 6] = *b"DWARFS";
+
+/// Finds the offset of a DwarFS image. If an offset is provided, only that offset
+/// is checked. Otherwise, the file is scanned for the DwarFS magic bytes from the
+/// end of the ELF runtime, or from the start if the file isn't an ELF file. A match
+/// is only accepted if a valid DwarFS section header starts there.
+///
+/// # Arguments
+/// * `path` - Path to the file
+/// * `offset` - Offset to check at, if known
+///
+/// # Returns
+/// Offset of the DwarFS image, if found
+pub fn find_dwarfs_offset<P: AsRef<Path>>(path: P, offset: Option<u64>) -> io::Result<Option<u64>> {
+    let mut file = File::open(&path)?;
+    if let Some(offset) = offset {
+        return Ok(is_dwarfs_header(&file, offset).then_some(offset));
+    }
+
+    let mut start = get_elf_end(&mut file)?;
+    while let Some(found) = scan_magic(&mut file, start, &DWARFS_MAGIC)? {
+        if is_dwarfs_header(&file, found) {
+            return Ok(Some(found));
+        }
+        start = found + 1;
+    }
+    Ok(None)
+}
+
+/// Checks whether a DwarFS section header starts at the offset: the magic bytes
+/// followed by a supported format version, and the first section of a known type.
+///
+/// # Arguments
+/// * `file` - The file
+/// * `offset` - Offset of the header
+///
+/// # Returns
+/// boolean stating if the header is valid
+fn is_dwarfs_header(file: &File, offset: u64) -> bool {
+    SectionReader::new_with_offset(file, offset)
+        .read_header_at(0)
+        .is_ok_and(|header| header.section_number.get() == 0 && header.section_type.is_known())
+}
+
+/// A node in the DwarFS filesystem
+#[derive(Debug)]
+struct DwarfsNode {
+    path: PathBuf,
+    header: NodeHeader,
+    kind: DwarfsNodeKind,
+}
+
+#[derive(Debug)]
+enum DwarfsNodeKind {
+    /// Regular file. The BasicFile's `blocks_start` holds the inode number.
+    File(BasicFile),
+    Directory,
+    Symlink(PathBuf),
+    Other,
+}
+
+/// The DwarFS struct provides read access to DwarFS images, used by some newer
+/// AppImage-like bundles instead of SquashFS.
+pub struct DwarFS {
+    index: Box<ArchiveIndex>,
+    archive: Mutex<Archive<Slice<File>>>,
+    offset: u64,
+    nodes: Vec<DwarfsNode>,
+}
+
+impl DwarFS {
+    /// Reads the DwarFS image at the offset of a file.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the file
+    /// * `offset` - Offset of the DwarFS image
+    ///
+    /// # Returns
+    /// A DwarFS instance, or an error if the image can't be parsed
+    pub fn from_path_with_offset<P: AsRef<Path>>(path: P, offset: u64) -> Result<Self> {
+        let file = File::open(path)?;
+        let size = file.metadata()?.len();
+        let slice = Slice::new(file, offset, Some(size.saturating_sub(offset)));
+        let (index, archive) =
+            Archive::new(slice).map_err(|e| SquishyError::InvalidDwarFS(e.to_string()))?;
+
+        let mut nodes = Vec::new();
+        let root = index.root();
+        nodes.push(DwarfsNode {
+            path: PathBuf::from("/"),
+            header: node_header(root.metadata()),
+            kind: DwarfsNodeKind::Directory,
+        });
+        read_directory(root, Path::new("/"), &mut nodes, &mut HashSet::new());
+
+        Ok(DwarFS {
+            index: Box::new(index),
+            archive: Mutex::new(archive),
+            offset,
+            nodes,
+        })
+    }
+
+    /// Offset of the DwarFS image within the file.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns an iterator over all the entries in the DwarFS filesystem.
+    pub fn entries(&self) -> impl Iterator<Item = SquashFSEntry<'_>> + '_ {
+        self.nodes.iter().map(|node| {
+            let (size, kind) = match &node.kind {
+                DwarfsNodeKind::File(file) => (file.file_size, EntryKind::File(file)),
+                DwarfsNodeKind::Directory => (0, EntryKind::Directory),
+                DwarfsNodeKind::Symlink(target) => (0, EntryKind::Symlink(target.clone())),
+                DwarfsNodeKind::Other => (0, EntryKind::Unknown),
+            };
+            SquashFSEntry {
+                header: node.header,
+                path: node.path.clone(),
+                size,
+                kind,
+            }
+        })
+    }
+
+    /// Reads the contents of the specified file from the DwarFS filesystem.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file within the filesystem.
+    ///
+    /// # Returns
+    /// The contents of the file as a Vec<u8>, or an error if the file is not found.
+    pub fn read_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        let path = path.as_ref();
+        let node = self
+            .nodes
+            .iter()
+            .find(|node| node.path == path)
+            .ok_or_else(|| SquishyError::FileNotFound(path.to_path_buf()))?;
+
+        match &node.kind {
+            DwarfsNodeKind::File(file) => self.read_contents(file),
+            _ => Err(SquishyError::NotAFile(path.to_path_buf())),
+        }
+    }
+
+    /// Reads the contents of the file.
+    ///
+    /// # Arguments
+    /// * `file` - The file within the DwarFS filesystem.
+    ///
+    /// # Returns
+    /// The contents of the file, or an error if it cannot be read.
+    pub fn read_contents(&self, file: &BasicFile) -> Result<Vec<u8>> {
+        self.read_range(file, 0, u64::MAX)
+    }
+
+    /// Reads a byte range of the file.
+    ///
+    /// # Arguments
+    /// * `file` - The file within the DwarFS filesystem.
+    /// * `offset` - Offset within the file to start reading at.
+    /// * `length` - Maximum number of bytes to read.
+    ///
+    /// # Returns
+    /// The bytes read, or an error if the file cannot be read.
+    pub fn read_range(&self, file: &BasicFile, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let dwarfs_file = self
+            .index
+            .get_inode(file.blocks_start)
+            .and_then(|inode| inode.as_file())
+            .ok_or_else(|| SquishyError::InvalidDwarFS("Invalid file inode".into()))?;
+
+        let mut archive = self.archive.lock().unwrap();
+        let mut reader = dwarfs_file.as_reader(&mut archive);
+        io::copy(&mut (&mut reader).take(offset), &mut io::sink())?;
+        let mut contents = Vec::new();
+        reader.take(length).read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
+    /// Writes the contents of the file to the specified destination path.
+    ///
+    /// # Arguments
+    /// * `file` - The file within the DwarFS filesystem.
+    /// * `dest` - The destination path to write the file to.
+    ///
+    /// # Returns
+    /// An empty result, or an error if the file cannot be read or written.
+    pub fn write_file<P: AsRef<Path>>(&self, file: &BasicFile, dest: P) -> Result<()> {
+        let output_file = File::create(dest)?;
+        let mut writer = BufWriter::new(output_file);
+        writer.write_all(&self.read_contents(file)?)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the contents of the file to the specified destination path with permissions.
+    ///
+    /// # Arguments
+    /// * `file` - The file within the DwarFS filesystem.
+    /// * `dest` - The destination path to write the file to.
+    /// * `header` - Node header containing file information.
+    ///
+    /// # Returns
+    /// An empty result, or an error if the file cannot be read or written.
+    pub fn write_file_with_permissions<P: AsRef<Path>>(
+        &self,
+        file: &BasicFile,
+        dest: P,
+        header: NodeHeader,
+    ) -> Result<()> {
+        self.write_file(file, &dest)?;
+        let mode = u32::from(header.permissions);
+        fs::set_permissions(dest, Permissions::from_mode(mode))?;
+        Ok(())
+    }
+
+    /// Resolves the symlink chain starting from the specified entry,
+    /// returning the final target entry or an error if a cycle is detected.
+    ///
+    /// # Arguments
+    /// * `entry` - The entry to resolve the symlink for.
+    ///
+    /// # Returns
+    /// The final target entry, or None if the entry is not a symlink, or an error if a cycle is detected.
+    pub fn resolve_symlink(&self, entry: &SquashFSEntry) -> Result<Option<SquashFSEntry<'_>>> {
+        let EntryKind::Symlink(target) = &entry.kind else {
+            return Ok(None);
+        };
+
+        let mut visited = HashSet::new();
+        visited.insert(entry.path.clone());
+        let mut target = target.clone();
+        loop {
+            if !visited.insert(target.clone()) {
+                return Err(SquishyError::SymlinkError("Cyclic symlink detected".into()));
+            }
+            let Some(target_entry) = self.entries().find(|e| e.path == target) else {
+                return Ok(None);
+            };
+            match &target_entry.kind {
+                EntryKind::Symlink(next_target) => target = next_target.clone(),
+                _ => return Ok(Some(target_entry)),
+            }
+        }
+    }
+}
+
+/// Recursively collects the nodes of a directory.
+///
+/// # Arguments
+/// * `dir` - The directory
+/// * `parent` - Path of the directory
+/// * `nodes` - Nodes collected so far
+/// * `visited` - Directory inodes already read, to guard against loops in corrupted images
+fn read_directory(
+    dir: Dir,
+    parent: &Path,
+    nodes: &mut Vec<DwarfsNode>,
+    visited: &mut HashSet<u32>,
+) {
+    if !visited.insert(dir.inode_num()) {
+        return;
+    }
+    for entry in dir.entries() {
+        let path = parent.join(entry.name());
+        let inode = entry.inode();
+        let header = node_header(inode.metadata());
+        let kind = match inode.classify() {
+            InodeKind::File(file) => DwarfsNodeKind::File(BasicFile {
+                blocks_start: file.inode_num(),
+                frag_index: u32::MAX,
+                block_offset: 0,
+                file_size: file.as_chunks().total_size() as u32,
+                block_sizes: Vec::new(),
+            }),
+            InodeKind::Directory(subdir) => {
+                nodes.push(DwarfsNode {
+                    path: path.clone(),
+                    header,
+                    kind: DwarfsNodeKind::Directory,
+                });
+                read_directory(subdir, &path, nodes, visited);
+                continue;
+            }
+            InodeKind::Symlink(symlink) => {
+                let target = symlink.target();
+                if target.starts_with('/') {
+                    DwarfsNodeKind::Symlink(PathBuf::from(target))
+                } else {
+                    DwarfsNodeKind::Symlink(PathBuf::from(format!("/{}", target)))
+                }
+            }
+            _ => DwarfsNodeKind::Other,
+        };
+        nodes.push(DwarfsNode { path, header, kind });
+    }
+}
+
+/// Converts DwarFS inode metadata to a node header.
+fn node_header(metadata: ::dwarfs::InodeMetadata) -> NodeHeader {
+    NodeHeader::new(
+        metadata.file_type_mode().permission_bits() as u16,
+        metadata.uid(),
+        metadata.gid(),
+        metadata.mtime() as u32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A DwarFS section header: magic, version 2.5, zeroed checksums, section 0
+    /// of type BLOCK, no compression and no payload.
+    fn section_header() -> Vec<u8> {
+        let mut header = DWARFS_MAGIC.to_vec();
+        header.extend_from_slice(&[2, 5]);
+        header.resize(64, 0);
+        header
+    }
+
+    fn write_temp(name: &str, data: &[u8]) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("squishy-dwarfs-{}-{}", std::process::id(), name));
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn skips_magic_without_valid_header() {
+        let mut data = b"#!/bin/sh\necho DWARFS\n".to_vec();
+        data.resize(128, 0);
+        let offset = data.len() as u64;
+        data.extend_from_slice(&section_header());
+        let path = write_temp("scan", &data);
+
+        assert_eq!(find_dwarfs_offset(&path, None).unwrap(), Some(offset));
+        assert_eq!(
+            find_dwarfs_offset(&path, Some(offset)).unwrap(),
+            Some(offset)
+        );
+        assert_eq!(find_dwarfs_offset(&path, Some(15)).unwrap(), None);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_unsupported_headers() {
+        let mut data = vec![0; 64];
+        let mut header = section_header();
+        // Unsupported major version
+        header[6] = 1;
+        data.extend_from_slice(&header);
+        let path = write_temp("version", &data);
+
+        assert_eq!(find_dwarfs_offset(&path, None).unwrap(), None);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,284 @@
+use std::{fmt, sync::Arc};
+
+use glob::MatchOptions;
+use regex::{Regex, RegexBuilder};
+
+use crate::{error::SquishyError, SquashFSEntry};
+
+use super::Result;
+
+/// How a [`Pattern`] matches paths
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PatternKind {
+    /// The path contains the pattern
+    #[default]
+    Substring,
+    /// The path, relative to the AppImage root, matches the glob. `*` doesn't match `/`.
+    Glob,
+    /// The path matches the regular expression anywhere
+    Regex,
+}
+
+/// A case-insensitive pattern matched against paths
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Substring(String),
+    Glob(glob::Pattern),
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Creates a pattern of the kind.
+    ///
+    /// # Arguments
+    /// * `kind` - How the pattern matches paths
+    /// * `pattern` - The pattern
+    ///
+    /// # Returns
+    /// The pattern, or an error if it is not a valid glob or regular expression
+    pub fn new(kind: PatternKind, pattern: &str) -> Result<Self> {
+        match kind {
+            PatternKind::Substring => Ok(Pattern::Substring(pattern.to_lowercase())),
+            PatternKind::Glob => {
+                glob::Pattern::new(pattern.trim_start_matches('/').to_lowercase().as_str())
+                    .map(Pattern::Glob)
+                    .map_err(|e| SquishyError::InvalidPattern(format!("{}: {}", pattern, e)))
+            }
+            PatternKind::Regex => RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map(Pattern::Regex)
+                .map_err(|e| SquishyError::InvalidPattern(e.to_string())),
+        }
+    }
+
+    /// Checks if the path matches the pattern.
+    ///
+    /// # Arguments
+    /// * `path` - The lowercased path
+    ///
+    /// # Returns
+    /// boolean stating if the path matches
+    pub fn matches(&self, path: &str) -> bool {
+        match self {
+            Pattern::Substring(pattern) => path.contains(pattern.as_str()),
+            Pattern::Glob(pattern) => pattern.matches_with(
+                path.trim_start_matches('/'),
+                MatchOptions {
+                    case_sensitive: false,
+                    require_literal_separator: true,
+                    require_literal_leading_dot: false,
+                },
+            ),
+            Pattern::Regex(pattern) => pattern.is_match(path),
+        }
+    }
+}
+
+impl From<&str> for Pattern {
+    fn from(pattern: &str) -> Self {
+        Pattern::Substring(pattern.to_lowercase())
+    }
+}
+
+impl From<String> for Pattern {
+    fn from(pattern: String) -> Self {
+        Pattern::from(pattern.as_str())
+    }
+}
+
+/// How the include patterns of a [`Filter`] are combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FilterMode {
+    /// The path must match at least one include pattern
+    #[default]
+    Any,
+    /// The path must match every include pattern
+    All,
+}
+
+/// Predicate on entries, for filtering on more than the path
+pub type EntryPredicate = Arc<dyn Fn(&SquashFSEntry) -> bool + Send + Sync>;
+
+/// Filter applied to the entries searched by the AppImage finders. Paths are matched
+/// case-insensitively against substrings, globs or regular expressions, and entries
+/// can also be matched by a predicate.
+#[derive(Clone, Default)]
+pub struct Filter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    mode: FilterMode,
+    predicate: Option<EntryPredicate>,
+}
+
+impl fmt::Debug for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Filter")
+            .field("include", &self.include)
+            .field("exclude", &self.exclude)
+            .field("mode", &self.mode)
+            .field("predicate", &self.predicate.is_some())
+            .finish()
+    }
+}
+
+impl Filter {
+    /// Creates an empty filter, matching every path.
+    ///
+    /// # Arguments
+    /// * `mode` - How the include patterns are combined
+    pub fn new(mode: FilterMode) -> Self {
+        Filter {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    /// Adds a pattern the path must match. Strings are matched as substrings.
+    pub fn include<P: Into<Pattern>>(mut self, pattern: P) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Adds a pattern the path must not match. Strings are matched as substrings.
+    pub fn exclude<P: Into<Pattern>>(mut self, pattern: P) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Sets a predicate the entry must satisfy, in addition to the patterns.
+    pub fn predicate<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&SquashFSEntry) -> bool + Send + Sync + 'static,
+    {
+        self.predicate = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Checks if the entry matches the filter: its lowercased path matches the
+    /// patterns (see [`Filter::matches`]) and it satisfies the predicate, if any.
+    ///
+    /// # Arguments
+    /// * `entry` - The entry
+    ///
+    /// # Returns
+    /// boolean stating if the entry matches
+    pub fn matches_entry(&self, entry: &SquashFSEntry) -> bool {
+        self.matches(&entry.path.to_string_lossy().to_lowercase())
+            && self
+                .predicate
+                .as_ref()
+                .is_none_or(|predicate| predicate(entry))
+    }
+
+    /// Checks if the path matches the filter: no exclude pattern matches, and the
+    /// include patterns match according to the mode. A filter without include
+    /// patterns matches every path that isn't excluded.
+    ///
+    /// # Arguments
+    /// * `path` - The lowercased path
+    ///
+    /// # Returns
+    /// boolean stating if the path matches
+    pub fn matches(&self, path: &str) -> bool {
+        if self.exclude.iter().any(|pattern| pattern.matches(path)) {
+            return false;
+        }
+        if self.include.is_empty() {
+            return true;
+        }
+        match self.mode {
+            FilterMode::Any => self.include.iter().any(|pattern| pattern.matches(path)),
+            FilterMode::All => self.include.iter().all(|pattern| pattern.matches(path)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use backhand::NodeHeader;
+
+    use super::*;
+    use crate::EntryKind;
+
+    fn pattern(kind: PatternKind, pattern: &str) -> Pattern {
+        Pattern::new(kind, pattern).unwrap()
+    }
+
+    #[test]
+    fn matches_substrings() {
+        let pattern = Pattern::from("Icons");
+        assert!(pattern.matches("usr/share/icons/app.png"));
+        assert!(!pattern.matches("usr/share/pixmaps/app.png"));
+    }
+
+    #[test]
+    fn matches_globs_from_root() {
+        let glob = pattern(PatternKind::Glob, "/usr/share/*.desktop");
+        assert!(glob.matches("usr/share/app.desktop"));
+        assert!(glob.matches("/usr/share/App.Desktop"));
+        // `*` doesn't match `/`
+        assert!(!glob.matches("usr/share/applications/app.desktop"));
+        assert!(pattern(PatternKind::Glob, "usr/**/*.png").matches("usr/share/icons/a.png"));
+        assert!(matches!(
+            Pattern::new(PatternKind::Glob, "usr/[a"),
+            Err(SquishyError::InvalidPattern(_))
+        ));
+    }
+
+    #[test]
+    fn matches_regexes_anywhere() {
+        let regex = pattern(PatternKind::Regex, r"\d+x\d+");
+        assert!(regex.matches("usr/share/icons/hicolor/256x256/apps/app.png"));
+        assert!(!regex.matches("usr/share/icons/hicolor/scalable/apps/app.svg"));
+        assert!(pattern(PatternKind::Regex, "APP").matches("app.png"));
+        assert!(matches!(
+            Pattern::new(PatternKind::Regex, "("),
+            Err(SquishyError::InvalidPattern(_))
+        ));
+    }
+
+    #[test]
+    fn combines_patterns_by_mode() {
+        let any = Filter::new(FilterMode::Any)
+            .include("icons")
+            .include("pixmaps");
+        assert!(any.matches("usr/share/icons/app.png"));
+        assert!(any.matches("usr/share/pixmaps/app.png"));
+        assert!(!any.matches("usr/share/app.png"));
+
+        let all = Filter::new(FilterMode::All).include("icons").include("256");
+        assert!(all.matches("usr/share/icons/256x256/app.png"));
+        assert!(!all.matches("usr/share/icons/64x64/app.png"));
+
+        assert!(Filter::default().matches("anything"));
+    }
+
+    #[test]
+    fn excludes_take_precedence() {
+        let filter = Filter::default()
+            .include("icons")
+            .exclude(pattern(PatternKind::Glob, "**/*.svg"));
+        assert!(filter.matches("usr/share/icons/app.png"));
+        assert!(!filter.matches("usr/share/icons/app.svg"));
+        assert!(!Filter::default().exclude("doc").matches("usr/share/doc"));
+    }
+
+    #[test]
+    fn applies_predicate_to_entries() {
+        let entry = |path: &str, size| SquashFSEntry {
+            header: NodeHeader::new(0o644, 0, 0, 0),
+            path: PathBuf::from(path),
+            size,
+            kind: EntryKind::Unknown,
+        };
+        let filter = Filter::default()
+            .include("Icons")
+            .predicate(|entry| entry.size > 100);
+        assert!(filter.matches_entry(&entry("/usr/share/ICONS/app.png", 200)));
+        assert!(!filter.matches_entry(&entry("/usr/share/icons/app.png", 10)));
+        assert!(!filter.matches_entry(&entry("/usr/share/app.png", 200)));
+    }
+}
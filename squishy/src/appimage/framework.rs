@@ -0,0 +1,76 @@
+use std::{fmt, path::Path};
+
+/// Application framework bundled in an AppImage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Framework {
+    Electron,
+    Qt,
+    Gtk,
+    Java,
+    Python,
+}
+
+impl Framework {
+    /// Detects the framework a payload file is characteristic of.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the file in the AppImage
+    ///
+    /// # Returns
+    /// The framework, if the file is characteristic of one
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy();
+        let name = name.as_ref();
+        let parent = path
+            .parent()
+            .and_then(|parent| parent.file_name())
+            .map(|parent| parent.to_string_lossy())
+            .unwrap_or_default();
+
+        if matches!(
+            name,
+            "app.asar" | "electron.asar" | "v8_context_snapshot.bin" | "chrome_100_percent.pak"
+        ) {
+            return Some(Framework::Electron);
+        }
+        if ["libQt5Core.so", "libQt6Core.so", "libQtCore.so"]
+            .iter()
+            .any(|lib| name.starts_with(lib))
+            || name == "qt.conf"
+        {
+            return Some(Framework::Qt);
+        }
+        if ["libgtk-3.so", "libgtk-4.so", "libgtk-x11-2.0.so"]
+            .iter()
+            .any(|lib| name.starts_with(lib))
+        {
+            return Some(Framework::Gtk);
+        }
+        if name.starts_with("libjvm.so")
+            || name.ends_with(".jar")
+            || (parent == "bin" && name == "java")
+        {
+            return Some(Framework::Java);
+        }
+        if name.starts_with("libpython")
+            || name == "site-packages"
+            || (parent == "bin" && name.starts_with("python"))
+        {
+            return Some(Framework::Python);
+        }
+        None
+    }
+}
+
+impl fmt::Display for Framework {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Framework::Electron => "Electron",
+            Framework::Qt => "Qt",
+            Framework::Gtk => "GTK",
+            Framework::Java => "Java",
+            Framework::Python => "Python",
+        };
+        write!(f, "{}", name)
+    }
+}
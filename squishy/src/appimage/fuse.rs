@@ -0,0 +1,808 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::{CString, OsStr, OsString},
+    fs::{File, OpenOptions},
+    io::{Error, ErrorKind, Read, Write},
+    os::{
+        fd::{AsRawFd, FromRawFd, RawFd},
+        unix::{ffi::OsStrExt, fs::OpenOptionsExt},
+    },
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use backhand::NodeHeader;
+use rayon::iter::ParallelIterator;
+
+use crate::{error::SquishyError, EntryKind, SquashFSEntry};
+
+use super::{AppImage, Result};
+
+/// Inode number of the root directory
+const ROOT_ID: u64 = 1;
+/// Version of the FUSE kernel protocol spoken
+const KERNEL_VERSION: u32 = 7;
+const KERNEL_MINOR_VERSION: u32 = 31;
+/// Size of the buffer requests are read into, larger than any read request
+const BUFFER_SIZE: usize = 1024 * 1024 + 4096;
+/// Largest write the kernel may send, irrelevant as the filesystem is read-only
+const MAX_WRITE: u32 = 128 * 1024;
+/// Seconds the kernel may cache entries and attributes, which never change
+const TTL: u64 = 86400;
+/// Size of the request header
+const IN_HEADER_SIZE: usize = 40;
+/// `FOPEN_KEEP_CACHE`: keep cached file contents when the file is opened again
+const FOPEN_KEEP_CACHE: u32 = 1 << 1;
+/// Programs that mount FUSE filesystems for unprivileged users
+const FUSERMOUNT: [&str; 2] = ["fusermount3", "fusermount"];
+
+// Operations of the FUSE kernel protocol
+const LOOKUP: u32 = 1;
+const FORGET: u32 = 2;
+const GETATTR: u32 = 3;
+const READLINK: u32 = 5;
+const OPEN: u32 = 14;
+const READ: u32 = 15;
+const STATFS: u32 = 17;
+const RELEASE: u32 = 18;
+const FLUSH: u32 = 25;
+const INIT: u32 = 26;
+const OPENDIR: u32 = 27;
+const READDIR: u32 = 28;
+const RELEASEDIR: u32 = 29;
+const INTERRUPT: u32 = 36;
+const DESTROY: u32 = 38;
+const BATCH_FORGET: u32 = 42;
+
+/// An entry of the mounted filesystem
+struct Node<'a> {
+    entry: SquashFSEntry<'a>,
+    /// Inode number of the directory holding the entry
+    parent: u64,
+    /// Entries of a directory by name
+    children: BTreeMap<OsString, u64>,
+}
+
+/// The entries of the mounted filesystem by inode number, starting at 1 for the
+/// root directory
+struct Tree<'s> {
+    nodes: Vec<Node<'s>>,
+}
+
+/// An image mounted through FUSE, read-only. Requests are served by [`FuseMount::run`]
+/// until the filesystem is unmounted.
+pub struct FuseMount<'s, 'a> {
+    appimage: &'s AppImage<'a>,
+    tree: Tree<'s>,
+    /// Connection to the kernel
+    device: File,
+    mountpoint: PathBuf,
+}
+
+/// Builds a reply out of native endian fields, as the kernel expects.
+#[derive(Default)]
+struct Reply(Vec<u8>);
+
+impl Reply {
+    fn u16(mut self, value: u16) -> Self {
+        self.0.extend_from_slice(&value.to_ne_bytes());
+        self
+    }
+
+    fn u32(mut self, value: u32) -> Self {
+        self.0.extend_from_slice(&value.to_ne_bytes());
+        self
+    }
+
+    fn u64(mut self, value: u64) -> Self {
+        self.0.extend_from_slice(&value.to_ne_bytes());
+        self
+    }
+
+    fn bytes(mut self, value: &[u8]) -> Self {
+        self.0.extend_from_slice(value);
+        self
+    }
+}
+
+/// Encodes a reply to a lookup: the inode number, its generation, how long the
+/// name and attributes may be cached, then the attributes.
+fn entry_reply(ino: u64, attr: &[u8]) -> Reply {
+    Reply::default()
+        .u64(ino)
+        .u64(0)
+        .u64(TTL)
+        .u64(TTL)
+        .u32(0)
+        .u32(0)
+        .bytes(attr)
+}
+
+/// Encodes a reply to a getattr: how long the attributes may be cached, then the
+/// attributes.
+fn attr_reply(attr: &[u8]) -> Reply {
+    Reply::default().u64(TTL).u32(0).u32(0).bytes(attr)
+}
+
+/// Encodes the answer to the session setup, accepting the kernel's protocol version.
+fn init_reply(max_readahead: u32) -> Reply {
+    Reply::default()
+        .u32(KERNEL_VERSION)
+        .u32(KERNEL_MINOR_VERSION)
+        .u32(max_readahead)
+        .u32(0)
+        .u16(16)
+        .u16(12)
+        .u32(MAX_WRITE)
+        .u32(1)
+        .u16(0)
+        .u16(0)
+        .u32(0)
+        .bytes(&[0; 28])
+}
+
+/// Encodes the message sent for a request: the header with the message length,
+/// the negated errno and the request id, then the reply.
+///
+/// # Arguments
+/// * `unique` - Id of the request
+/// * `reply` - The reply, or the errno to reply with
+fn message(unique: u64, reply: std::result::Result<Reply, i32>) -> Vec<u8> {
+    let (error, body) = match reply {
+        Ok(reply) => (0, reply.0),
+        Err(errno) => (-errno, Vec::new()),
+    };
+    Reply::default()
+        .u32(16 + body.len() as u32)
+        .u32(error as u32)
+        .u64(unique)
+        .bytes(&body)
+        .0
+}
+
+/// Reads a native endian u32 from a request.
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    data.get(offset..offset + 4)
+        .map_or(0, |bytes| u32::from_ne_bytes(bytes.try_into().unwrap()))
+}
+
+/// Reads a native endian u64 from a request.
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    data.get(offset..offset + 8)
+        .map_or(0, |bytes| u64::from_ne_bytes(bytes.try_into().unwrap()))
+}
+
+impl<'s, 'a> FuseMount<'s, 'a> {
+    /// Mounts the image at a directory. Root mounts through the kernel directly,
+    /// other users through `fusermount3` or `fusermount`, which must be installed.
+    ///
+    /// # Arguments
+    /// * `appimage` - The image to mount
+    /// * `mountpoint` - Directory to mount the image at
+    ///
+    /// # Returns
+    /// The mounted filesystem, to serve with [`FuseMount::run`], or an error if it
+    /// can't be mounted
+    pub fn mount<P: AsRef<Path>>(appimage: &'s AppImage<'a>, mountpoint: P) -> Result<Self> {
+        let mountpoint = mountpoint.as_ref().canonicalize()?;
+        if !mountpoint.is_dir() {
+            return Err(Error::new(
+                ErrorKind::NotADirectory,
+                format!("{} is not a directory", mountpoint.display()),
+            )
+            .into());
+        }
+        let tree = Tree::new(
+            appimage
+                .par_entries()
+                .filter(|entry| entry.kind != EntryKind::Unknown)
+                .collect(),
+        );
+        // SAFETY: geteuid has no preconditions and can't fail
+        let device = if unsafe { libc::geteuid() } == 0 {
+            mount_kernel(&mountpoint)?
+        } else {
+            mount_fusermount(&mountpoint)?
+        };
+        Ok(FuseMount {
+            appimage,
+            tree,
+            device,
+            mountpoint,
+        })
+    }
+
+    /// Directory the image is mounted at.
+    pub fn mountpoint(&self) -> &Path {
+        &self.mountpoint
+    }
+
+    /// Serves requests until the filesystem is unmounted, e.g. by [`unmount`].
+    ///
+    /// # Returns
+    /// An empty result once unmounted, or an error if the connection to the kernel
+    /// fails
+    pub fn run(&self) -> Result<()> {
+        let mut buffer = vec![0_u8; BUFFER_SIZE];
+        loop {
+            let len = match (&self.device).read(&mut buffer) {
+                Ok(len) => len,
+                Err(e) => match e.raw_os_error() {
+                    // The request was interrupted before it was read
+                    Some(libc::ENOENT) | Some(libc::EINTR) | Some(libc::EAGAIN) => continue,
+                    Some(libc::ENODEV) => return Ok(()),
+                    _ => return Err(e.into()),
+                },
+            };
+            if len < IN_HEADER_SIZE {
+                continue;
+            }
+            let request = &buffer[..len];
+            let opcode = u32_at(request, 4);
+            let unique = u64_at(request, 8);
+            let node = u64_at(request, 16);
+            let body = &request[IN_HEADER_SIZE..];
+
+            let reply = match opcode {
+                FORGET | BATCH_FORGET | INTERRUPT => continue,
+                DESTROY => {
+                    self.reply(unique, Ok(Reply::default()));
+                    return Ok(());
+                }
+                INIT => Ok(init_reply(u32_at(body, 8))),
+                _ => self.handle(opcode, node, body),
+            };
+            self.reply(unique, reply);
+        }
+    }
+
+    /// Answers a request other than the session ones.
+    ///
+    /// # Arguments
+    /// * `opcode` - Operation requested
+    /// * `node` - Inode number the operation applies to
+    /// * `body` - Arguments of the operation
+    ///
+    /// # Returns
+    /// The reply, or the errno to reply with
+    fn handle(&self, opcode: u32, node: u64, body: &[u8]) -> std::result::Result<Reply, i32> {
+        let current = self.tree.node(node)?;
+        match opcode {
+            LOOKUP => {
+                let name = body.split(|&b| b == 0).next().unwrap_or_default();
+                let child = current
+                    .children
+                    .get(OsStr::from_bytes(name))
+                    .ok_or(libc::ENOENT)?;
+                Ok(entry_reply(*child, &self.tree.attr(*child)?))
+            }
+            GETATTR => Ok(attr_reply(&self.tree.attr(node)?)),
+            READLINK => match &current.entry.kind {
+                EntryKind::Symlink(target) => {
+                    // Targets are stored with a `/` prepended
+                    let target = target.as_os_str().as_bytes();
+                    Ok(Reply::default().bytes(target.strip_prefix(b"/").unwrap_or(target)))
+                }
+                _ => Err(libc::EINVAL),
+            },
+            OPEN => {
+                let flags = u32_at(body, 0) as i32;
+                if flags & libc::O_ACCMODE != libc::O_RDONLY {
+                    return Err(libc::EROFS);
+                }
+                match current.entry.kind {
+                    EntryKind::File(_) => Ok(Reply::default().u64(0).u32(FOPEN_KEEP_CACHE).u32(0)),
+                    EntryKind::Directory => Err(libc::EISDIR),
+                    _ => Err(libc::EINVAL),
+                }
+            }
+            OPENDIR => match current.entry.kind {
+                EntryKind::Directory => Ok(Reply::default().u64(0).u32(0).u32(0)),
+                _ => Err(libc::ENOTDIR),
+            },
+            READ => {
+                let offset = u64_at(body, 8);
+                let size = u32_at(body, 16);
+                self.appimage
+                    .read_range(&current.entry, offset, u64::from(size))
+                    .map(|data| Reply::default().bytes(&data))
+                    .map_err(|_| libc::EIO)
+            }
+            READDIR => Ok(self
+                .tree
+                .read_dir(node, current, u64_at(body, 8), u32_at(body, 16))),
+            STATFS => {
+                let size = self
+                    .tree
+                    .nodes
+                    .iter()
+                    .map(|node| u64::from(node.entry.size))
+                    .sum::<u64>();
+                Ok(Reply::default()
+                    .u64(size.div_ceil(4096))
+                    .u64(0)
+                    .u64(0)
+                    .u64(self.tree.nodes.len() as u64)
+                    .u64(0)
+                    .u32(4096)
+                    .u32(255)
+                    .u32(4096)
+                    .u32(0)
+                    .bytes(&[0; 24]))
+            }
+            RELEASE | RELEASEDIR | FLUSH => Ok(Reply::default()),
+            _ => Err(libc::ENOSYS),
+        }
+    }
+
+    /// Sends a reply to a request.
+    ///
+    /// # Arguments
+    /// * `unique` - Id of the request
+    /// * `reply` - The reply, or the errno to reply with
+    fn reply(&self, unique: u64, reply: std::result::Result<Reply, i32>) {
+        // Fails when the request was interrupted, which needs no reply
+        let _ = (&self.device).write(&message(unique, reply));
+    }
+}
+
+impl Drop for FuseMount<'_, '_> {
+    fn drop(&mut self) {
+        let _ = unmount(&self.mountpoint);
+    }
+}
+
+impl<'s> Tree<'s> {
+    /// Arranges the entries of the image by inode number, linking directories to
+    /// their entries. A root directory is added if the entries have none.
+    ///
+    /// # Arguments
+    /// * `entries` - The entries of the image
+    fn new(mut entries: Vec<SquashFSEntry<'s>>) -> Self {
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        if entries
+            .first()
+            .is_none_or(|entry| entry.path != Path::new("/"))
+        {
+            entries.insert(
+                0,
+                SquashFSEntry {
+                    header: NodeHeader::new(0o755, 0, 0, 0),
+                    path: PathBuf::from("/"),
+                    size: 0,
+                    kind: EntryKind::Directory,
+                },
+            );
+        }
+
+        // A root without permissions would make the whole mount inaccessible
+        if entries[0].header.permissions == 0 {
+            entries[0].header.permissions = 0o755;
+        }
+
+        let inos = entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| (entry.path.clone(), index as u64 + 1))
+            .collect::<HashMap<_, _>>();
+        let mut nodes = entries
+            .into_iter()
+            .map(|entry| Node {
+                parent: entry
+                    .path
+                    .parent()
+                    .and_then(|parent| inos.get(parent))
+                    .copied()
+                    .unwrap_or(ROOT_ID),
+                entry,
+                children: BTreeMap::new(),
+            })
+            .collect::<Vec<_>>();
+        for index in 1..nodes.len() {
+            let parent = nodes[index].parent as usize - 1;
+            if let Some(name) = nodes[index].entry.path.file_name().map(OsStr::to_owned) {
+                nodes[parent].children.insert(name, index as u64 + 1);
+            }
+        }
+        Tree { nodes }
+    }
+
+    /// Lists a directory from an offset, as many entries as fit.
+    ///
+    /// # Arguments
+    /// * `ino` - Inode number of the directory
+    /// * `node` - The directory
+    /// * `offset` - Number of entries already listed
+    /// * `size` - Size of the reply to fill
+    ///
+    /// # Returns
+    /// The entries, empty once all are listed
+    fn read_dir(&self, ino: u64, node: &Node, offset: u64, size: u32) -> Reply {
+        let dots = [(OsStr::new("."), ino), (OsStr::new(".."), node.parent)];
+        let entries = dots.into_iter().chain(
+            node.children
+                .iter()
+                .map(|(name, ino)| (name.as_os_str(), *ino)),
+        );
+
+        let mut reply = Reply::default();
+        for (index, (name, ino)) in entries.enumerate().skip(offset as usize) {
+            let name = name.as_bytes();
+            let len = (24 + name.len()).next_multiple_of(8);
+            if reply.0.len() + len > size as usize {
+                break;
+            }
+            let file_type = self
+                .node(ino)
+                .map_or(libc::DT_DIR, |node| match node.entry.kind {
+                    EntryKind::Directory => libc::DT_DIR,
+                    EntryKind::Symlink(_) => libc::DT_LNK,
+                    _ => libc::DT_REG,
+                });
+            reply = reply
+                .u64(ino)
+                .u64(index as u64 + 1)
+                .u32(name.len() as u32)
+                .u32(u32::from(file_type))
+                .bytes(name);
+            reply.0.resize(reply.0.len().next_multiple_of(8), 0);
+        }
+        reply
+    }
+
+    /// Finds an entry by inode number.
+    fn node(&self, ino: u64) -> std::result::Result<&Node<'s>, i32> {
+        ino.checked_sub(1)
+            .and_then(|index| self.nodes.get(index as usize))
+            .ok_or(libc::ENOENT)
+    }
+
+    /// Encodes the attributes of an entry.
+    fn attr(&self, ino: u64) -> std::result::Result<Vec<u8>, i32> {
+        let node = self.node(ino)?;
+        let header = node.entry.header;
+        let (kind, nlink) = match node.entry.kind {
+            EntryKind::Directory => {
+                let subdirs = node
+                    .children
+                    .values()
+                    .filter(|child| {
+                        self.node(**child)
+                            .is_ok_and(|child| child.entry.kind == EntryKind::Directory)
+                    })
+                    .count();
+                (libc::S_IFDIR, 2 + subdirs as u32)
+            }
+            EntryKind::Symlink(_) => (libc::S_IFLNK, 1),
+            _ => (libc::S_IFREG, 1),
+        };
+        let size = match &node.entry.kind {
+            EntryKind::Symlink(target) => target.as_os_str().len().saturating_sub(1) as u64,
+            _ => u64::from(node.entry.size),
+        };
+        let mtime = u64::from(header.mtime);
+        Ok(Reply::default()
+            .u64(ino)
+            .u64(size)
+            .u64(size.div_ceil(512))
+            .u64(mtime)
+            .u64(mtime)
+            .u64(mtime)
+            .u32(0)
+            .u32(0)
+            .u32(0)
+            .u32(kind | u32::from(header.permissions))
+            .u32(nlink)
+            .u32(header.uid)
+            .u32(header.gid)
+            .u32(0)
+            .u32(4096)
+            .u32(0)
+            .0)
+    }
+}
+
+/// Mounts through the kernel, which requires root.
+///
+/// # Arguments
+/// * `mountpoint` - Directory to mount at
+///
+/// # Returns
+/// The connection to the kernel, or an error if mounting fails
+fn mount_kernel(mountpoint: &Path) -> Result<File> {
+    let device = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_CLOEXEC)
+        .open("/dev/fuse")?;
+    let options = CString::new(format!(
+        "fd={},rootmode=40000,user_id=0,group_id=0,default_permissions",
+        device.as_raw_fd()
+    ))
+    .unwrap();
+    let target = CString::new(mountpoint.as_os_str().as_bytes())
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    // SAFETY: the source, target, type and options are NUL-terminated strings that
+    // outlive the call
+    let result = unsafe {
+        libc::mount(
+            c"squishy".as_ptr(),
+            target.as_ptr(),
+            c"fuse.squishy".as_ptr(),
+            libc::MS_RDONLY | libc::MS_NOSUID | libc::MS_NODEV,
+            options.as_ptr().cast(),
+        )
+    };
+    if result != 0 {
+        return Err(Error::last_os_error().into());
+    }
+    Ok(device)
+}
+
+/// Mounts through `fusermount`, which opens the connection to the kernel and
+/// passes it over a socket.
+///
+/// # Arguments
+/// * `mountpoint` - Directory to mount at
+///
+/// # Returns
+/// The connection to the kernel, or an error if `fusermount` isn't installed or fails
+fn mount_fusermount(mountpoint: &Path) -> Result<File> {
+    let mut fds = [0 as RawFd; 2];
+    // SAFETY: `fds` has room for the two descriptors written by socketpair
+    if unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) } != 0 {
+        return Err(Error::last_os_error().into());
+    }
+    // SAFETY: socketpair succeeded, so both descriptors are open and owned by nothing else
+    let (ours, theirs) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+    // Only `fusermount` needs the socket
+    // SAFETY: fcntl on a descriptor owned by `ours`, which stays open during the call
+    unsafe { libc::fcntl(ours.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+
+    let mut status = None;
+    for program in FUSERMOUNT {
+        match Command::new(program)
+            .arg("-o")
+            .arg("ro,nosuid,nodev,default_permissions,fsname=squishy,subtype=squishy")
+            .arg("--")
+            .arg(mountpoint)
+            .env("_FUSE_COMMFD", theirs.as_raw_fd().to_string())
+            .status()
+        {
+            Ok(result) => {
+                status = Some(result);
+                break;
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    drop(theirs);
+    match status {
+        None => {
+            return Err(SquishyError::Unsupported(
+                "Mounting requires root, or fusermount3 or fusermount to be installed".into(),
+            ))
+        }
+        Some(status) if !status.success() => {
+            return Err(Error::other(format!("fusermount failed with {}", status)).into())
+        }
+        _ => {}
+    }
+    receive_fd(&ours)
+}
+
+/// Receives a file descriptor sent over a socket with `SCM_RIGHTS`.
+fn receive_fd(socket: &File) -> Result<File> {
+    let mut data = [0_u8; 1];
+    let mut iov = libc::iovec {
+        iov_base: data.as_mut_ptr().cast(),
+        iov_len: data.len(),
+    };
+    let mut control = [0_u8; 64];
+    // SAFETY: msghdr is a plain C struct, for which all zeroes is a valid value
+    let mut message: libc::msghdr = unsafe { std::mem::zeroed() };
+    message.msg_iov = &mut iov;
+    message.msg_iovlen = 1;
+    message.msg_control = control.as_mut_ptr().cast();
+    message.msg_controllen = control.len() as _;
+
+    // SAFETY: `message` points to `iov` and `control`, which outlive the call, with
+    // their lengths set accordingly
+    if unsafe { libc::recvmsg(socket.as_raw_fd(), &mut message, 0) } < 0 {
+        return Err(Error::last_os_error().into());
+    }
+    // SAFETY: `message` was filled by recvmsg and its control buffer is still alive
+    let header = unsafe { libc::CMSG_FIRSTHDR(&message) };
+    // SAFETY: CMSG_LEN only computes a length
+    let fd_len = unsafe { libc::CMSG_LEN(size_of::<RawFd>() as u32) } as usize;
+    // SAFETY: a non-null header returned by CMSG_FIRSTHDR lies within `control`
+    if header.is_null()
+        || unsafe {
+            (*header).cmsg_level != libc::SOL_SOCKET
+                || (*header).cmsg_type != libc::SCM_RIGHTS
+                || ((*header).cmsg_len as usize) < fd_len
+        }
+    {
+        return Err(Error::other("fusermount didn't pass the FUSE connection").into());
+    }
+    // SAFETY: the header was checked to hold a descriptor, which may be unaligned
+    let fd = unsafe { std::ptr::read_unaligned(libc::CMSG_DATA(header).cast::<RawFd>()) };
+    // SAFETY: the kernel installed `fd` in this process, and nothing else owns it
+    let device = unsafe { File::from_raw_fd(fd) };
+    // SAFETY: fcntl on a descriptor owned by `device`, which stays open during the call
+    unsafe { libc::fcntl(device.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+    Ok(device)
+}
+
+/// Unmounts a mounted image, lazily so that it is detached even while in use.
+/// Serving the image with [`FuseMount::run`] then stops.
+///
+/// # Arguments
+/// * `mountpoint` - Directory the image is mounted at
+///
+/// # Returns
+/// An empty result, or an error if it can't be unmounted
+pub fn unmount<P: AsRef<Path>>(mountpoint: P) -> Result<()> {
+    let mountpoint = mountpoint.as_ref();
+    // SAFETY: geteuid has no preconditions and can't fail
+    if unsafe { libc::geteuid() } == 0 {
+        let target = CString::new(mountpoint.as_os_str().as_bytes())
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        // SAFETY: `target` is a NUL-terminated string that outlives the call
+        if unsafe { libc::umount2(target.as_ptr(), libc::MNT_DETACH) } != 0 {
+            return Err(Error::last_os_error().into());
+        }
+        return Ok(());
+    }
+    for program in FUSERMOUNT {
+        match Command::new(program)
+            .args(["-u", "-z", "--"])
+            .arg(mountpoint)
+            .status()
+        {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => {
+                return Err(Error::other(format!("{} failed with {}", program, status)).into())
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(SquishyError::Unsupported(
+        "Unmounting requires root, or fusermount3 or fusermount to be installed".into(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Size of `fuse_attr`
+    const ATTR_SIZE: usize = 88;
+
+    fn entry(path: &str, permissions: u16, kind: EntryKind<'static>) -> SquashFSEntry<'static> {
+        SquashFSEntry {
+            header: NodeHeader::new(permissions, 1000, 100, 1_700_000_000),
+            path: PathBuf::from(path),
+            size: 0,
+            kind,
+        }
+    }
+
+    /// A tree without a root entry: `/usr` (2), `/usr/bin` (3) and `/usr/link` (4)
+    /// below the added root (1).
+    fn tree() -> Tree<'static> {
+        Tree::new(vec![
+            entry(
+                "/usr/link",
+                0o777,
+                EntryKind::Symlink(PathBuf::from("/usr/bin")),
+            ),
+            entry("/usr", 0o755, EntryKind::Directory),
+            entry("/usr/bin", 0o700, EntryKind::Directory),
+        ])
+    }
+
+    #[test]
+    fn adds_missing_root() {
+        let tree = tree();
+        let root = tree.node(ROOT_ID).unwrap();
+        assert_eq!(root.entry.path, Path::new("/"));
+        assert_eq!(root.entry.header.permissions, 0o755);
+        assert_eq!(root.children.get(OsStr::new("usr")), Some(&2));
+        assert_eq!(tree.node(4).unwrap().parent, 2);
+        assert_eq!(tree.node(0).err(), Some(libc::ENOENT));
+        assert_eq!(tree.node(5).err(), Some(libc::ENOENT));
+    }
+
+    #[test]
+    fn encodes_attributes() {
+        let tree = tree();
+        let attr = tree.attr(2).unwrap();
+        assert_eq!(attr.len(), ATTR_SIZE);
+        assert_eq!(u64_at(&attr, 0), 2);
+        assert_eq!(u64_at(&attr, 32), 1_700_000_000);
+        assert_eq!(u32_at(&attr, 60), libc::S_IFDIR | 0o755);
+        // `.`, `..` and the `bin` subdirectory
+        assert_eq!(u32_at(&attr, 64), 3);
+        assert_eq!(u32_at(&attr, 68), 1000);
+        assert_eq!(u32_at(&attr, 72), 100);
+        assert_eq!(u32_at(&attr, 80), 4096);
+
+        // Symlinks are as large as their target, without the prepended `/`
+        let attr = tree.attr(4).unwrap();
+        assert_eq!(u64_at(&attr, 8), "usr/bin".len() as u64);
+        assert_eq!(u32_at(&attr, 60), libc::S_IFLNK | 0o777);
+    }
+
+    #[test]
+    fn encodes_replies_with_kernel_sizes() {
+        let attr = [0xaa; ATTR_SIZE];
+        // fuse_entry_out
+        let reply = entry_reply(7, &attr).0;
+        assert_eq!(reply.len(), 40 + ATTR_SIZE);
+        assert_eq!(u64_at(&reply, 0), 7);
+        assert_eq!(u64_at(&reply, 16), TTL);
+        assert_eq!(&reply[40..], attr);
+        // fuse_attr_out
+        let reply = attr_reply(&attr).0;
+        assert_eq!(reply.len(), 16 + ATTR_SIZE);
+        assert_eq!(u64_at(&reply, 0), TTL);
+        // fuse_init_out
+        let reply = init_reply(4096).0;
+        assert_eq!(reply.len(), 64);
+        assert_eq!(u32_at(&reply, 0), KERNEL_VERSION);
+        assert_eq!(u32_at(&reply, 4), KERNEL_MINOR_VERSION);
+        assert_eq!(u32_at(&reply, 8), 4096);
+    }
+
+    #[test]
+    fn encodes_message_headers() {
+        let ok = message(9, Ok(Reply::default().u64(1)));
+        assert_eq!(ok.len(), 24);
+        assert_eq!(u32_at(&ok, 0), 24);
+        assert_eq!(u32_at(&ok, 4), 0);
+        assert_eq!(u64_at(&ok, 8), 9);
+
+        let error = message(9, Err(libc::ENOENT));
+        assert_eq!(error.len(), 16);
+        assert_eq!(u32_at(&error, 4) as i32, -libc::ENOENT);
+    }
+
+    #[test]
+    fn lists_directories_in_aligned_records() {
+        let tree = tree();
+        let usr = tree.node(2).unwrap();
+        let listing = tree.read_dir(2, usr, 0, 4096).0;
+        // `.`, `..`, `bin` and `link`, each 24 bytes plus the padded name
+        assert_eq!(listing.len(), 4 * 32);
+
+        let mut names = Vec::new();
+        let mut offset = 0;
+        while offset < listing.len() {
+            let len = u32_at(&listing, offset + 16) as usize;
+            names.push((
+                u64_at(&listing, offset),
+                u64_at(&listing, offset + 8),
+                &listing[offset + 24..offset + 24 + len],
+                u32_at(&listing, offset + 20),
+            ));
+            offset += (24 + len).next_multiple_of(8);
+        }
+        assert_eq!(
+            names,
+            [
+                (2, 1, &b"."[..], u32::from(libc::DT_DIR)),
+                (1, 2, b"..", u32::from(libc::DT_DIR)),
+                (3, 3, b"bin", u32::from(libc::DT_DIR)),
+                (4, 4, b"link", u32::from(libc::DT_LNK)),
+            ]
+        );
+
+        // Resumes after the entries already listed, and stops when full
+        assert_eq!(tree.read_dir(2, usr, 3, 4096).0, listing[96..]);
+        assert_eq!(tree.read_dir(2, usr, 0, 70).0, listing[..64]);
+        assert!(tree.read_dir(2, usr, 4, 4096).0.is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "svg")]
+use resvg::{tiny_skia, usvg};
+
+#[cfg(feature = "svg")]
+use crate::error::SquishyError;
+use crate::SquashFSEntry;
+
+#[cfg(feature = "svg")]
+use super::Result;
+
+/// Image format of an icon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconFormat {
+    Png,
+    Svg,
+    Xpm,
+}
+
+impl IconFormat {
+    /// Detects the icon format from the file extension.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the icon
+    ///
+    /// # Returns
+    /// The icon format, if the extension is a known icon format
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        match ext.as_str() {
+            "png" => Some(IconFormat::Png),
+            "svg" | "svgz" => Some(IconFormat::Svg),
+            "xpm" => Some(IconFormat::Xpm),
+            _ => None,
+        }
+    }
+
+    /// Detects the icon format from the beginning of the file contents.
+    ///
+    /// # Arguments
+    /// * `header` - The first bytes of the icon
+    ///
+    /// # Returns
+    /// The icon format, if the contents look like a known icon format
+    pub fn from_header(header: &[u8]) -> Option<Self> {
+        if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+            return Some(IconFormat::Png);
+        }
+        if header.starts_with(b"/* XPM */") {
+            return Some(IconFormat::Xpm);
+        }
+        let text = String::from_utf8_lossy(&header[..header.len().min(1024)]);
+        text.contains("<svg").then_some(IconFormat::Svg)
+    }
+
+    /// File extension for the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            IconFormat::Png => "png",
+            IconFormat::Svg => "svg",
+            IconFormat::Xpm => "xpm",
+        }
+    }
+}
+
+/// Which icon to pick when an AppImage has several
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IconPreference {
+    /// Preferred pixel size, the largest icon if None
+    pub size: Option<u32>,
+    /// Required image format, any format if None
+    pub format: Option<IconFormat>,
+}
+
+/// An icon found in the AppImage
+#[derive(Debug)]
+pub struct Icon<'a> {
+    /// Path of the icon in the AppImage, before resolving symlinks
+    pub path: PathBuf,
+    /// The icon file entry, with symlinks resolved
+    pub entry: SquashFSEntry<'a>,
+    /// Image format of the icon
+    pub format: IconFormat,
+    /// Pixel size parsed from the icon theme directory (e.g. `256x256`), if any
+    pub size: Option<u32>,
+}
+
+/// Parses the pixel size from an icon theme path, e.g. `hicolor/256x256/apps/app.png`
+/// or `hicolor/256x256@2/apps/app.png`.
+///
+/// # Arguments
+/// * `path` - Path of the icon
+///
+/// # Returns
+/// The pixel size, if the path contains a size directory
+pub fn size_from_path(path: &Path) -> Option<u32> {
+    path.components().rev().skip(1).find_map(|component| {
+        let name = component.as_os_str().to_string_lossy();
+        let (size, scale) = match name.split_once('@') {
+            Some((size, scale)) => (size, scale.parse::<u32>().ok()?),
+            None => (&*name, 1),
+        };
+        let (width, height) = size.split_once('x')?;
+        let width = width.parse::<u32>().ok()?;
+        (height.parse::<u32>().ok()? == width).then_some(width * scale)
+    })
+}
+
+/// Whether the path is a location icons are usually installed to.
+///
+/// # Arguments
+/// * `path` - Lowercased path of the entry
+pub(crate) fn is_icon_location(path: &str) -> bool {
+    path.starts_with("/usr/share/icons/")
+        || path.starts_with("/usr/share/pixmaps/")
+        || path.matches('/').count() == 1
+}
+
+/// Reads the dimensions from the IHDR chunk of a PNG header.
+///
+/// # Arguments
+/// * `header` - At least the first 24 bytes of the PNG file
+///
+/// # Returns
+/// Width and height of the image, if the header is a valid PNG header
+pub fn png_dimensions(header: &[u8]) -> Option<(u32, u32)> {
+    if header.len() < 24 || &header[..8] != b"\x89PNG\r\n\x1a\n" || &header[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(header[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(header[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+#[cfg(feature = "svg")]
+/// Rasterizes an SVG (or SVGZ) image to a square PNG. The image is scaled to fit,
+/// keeping its aspect ratio, and centered.
+///
+/// # Arguments
+/// * `data` - The SVG data
+/// * `size` - Width and height of the resulting PNG
+///
+/// # Returns
+/// The encoded PNG, or an error if the SVG can't be parsed or rendered
+pub fn rasterize_svg(data: &[u8], size: u32) -> Result<Vec<u8>> {
+    let tree = usvg::Tree::from_data(data, &usvg::Options::default())
+        .map_err(|e| SquishyError::InvalidSvg(e.to_string()))?;
+    let mut pixmap = tiny_skia::Pixmap::new(size, size)
+        .ok_or_else(|| SquishyError::InvalidSvg(format!("Invalid size {}", size)))?;
+
+    let tree_size = tree.size();
+    let scale = (size as f32 / tree_size.width()).min(size as f32 / tree_size.height());
+    let offset_x = (size as f32 - tree_size.width() * scale) / 2.0;
+    let offset_y = (size as f32 - tree_size.height() * scale) / 2.0;
+    let transform =
+        tiny_skia::Transform::from_scale(scale, scale).post_translate(offset_x, offset_y);
+
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+    pixmap
+        .encode_png()
+        .map_err(|e| SquishyError::InvalidSvg(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_size_from_theme_path() {
+        let size = |path: &str| size_from_path(Path::new(path));
+        assert_eq!(
+            size("/usr/share/icons/hicolor/256x256/apps/app.png"),
+            Some(256)
+        );
+        assert_eq!(
+            size("/usr/share/icons/hicolor/32x32@2/apps/app.png"),
+            Some(64)
+        );
+        assert_eq!(size("/usr/share/icons/hicolor/scalable/apps/app.svg"), None);
+        assert_eq!(size("/usr/share/icons/hicolor/32x16/apps/app.png"), None);
+        // The file name itself isn't a size directory
+        assert_eq!(size("/48x48"), None);
+    }
+
+    #[test]
+    fn detects_format_from_extension() {
+        let format = |path: &str| IconFormat::from_path(Path::new(path));
+        assert_eq!(format("app.PNG"), Some(IconFormat::Png));
+        assert_eq!(format("app.svgz"), Some(IconFormat::Svg));
+        assert_eq!(format("app.xpm"), Some(IconFormat::Xpm));
+        assert_eq!(format("app.ico"), None);
+        assert_eq!(format(".DirIcon"), None);
+    }
+
+    #[test]
+    fn reads_png_dimensions() {
+        let mut header = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        header.extend_from_slice(&256_u32.to_be_bytes());
+        header.extend_from_slice(&128_u32.to_be_bytes());
+        assert_eq!(png_dimensions(&header), Some((256, 128)));
+
+        assert_eq!(png_dimensions(&header[..23]), None);
+        header[12..16].copy_from_slice(b"IDAT");
+        assert_eq!(png_dimensions(&header), None);
+        assert_eq!(png_dimensions(b"GIF89a..................."), None);
+    }
+}
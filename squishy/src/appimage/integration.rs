@@ -0,0 +1,344 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use super::{
+    desktop::{rewrite_desktop, DesktopEntry, DesktopRewrite},
+    digest::to_hex,
+    Result,
+};
+
+/// Prefix of the files installed for desktop integration
+const FILE_PREFIX: &str = "squishy";
+
+/// Desktop entry key marking a desktop file as installed by squishy
+pub const MARKER_KEY: &str = "X-Squishy-Id";
+
+/// Files installed for the desktop integration of an AppImage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Integration {
+    /// Identifier of the integration, derived from the AppImage path
+    pub id: String,
+    /// The installed desktop file
+    pub desktop: PathBuf,
+    /// The installed icons, one per size
+    pub icons: Vec<PathBuf>,
+    /// The installed appstream file, if the AppImage has one
+    pub appstream: Option<PathBuf>,
+    /// The installed MIME type definitions
+    pub mime_packages: Vec<PathBuf>,
+}
+
+impl Integration {
+    /// Returns all the installed files.
+    pub fn files(&self) -> Vec<&Path> {
+        std::iter::once(self.desktop.as_path())
+            .chain(self.icons.iter().map(PathBuf::as_path))
+            .chain(self.appstream.as_deref())
+            .chain(self.mime_packages.iter().map(PathBuf::as_path))
+            .collect()
+    }
+}
+
+/// Returns the user data directory integration files are installed to:
+/// `$XDG_DATA_HOME`, or `~/.local/share` if it isn't set.
+///
+/// # Returns
+/// The data directory, if it can be determined
+pub fn default_data_dir() -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+}
+
+/// Computes the integration identifier of an AppImage from its absolute path, so
+/// the installed files can be found again even after the AppImage is removed.
+///
+/// # Arguments
+/// * `path` - Path to the appimage file
+///
+/// # Returns
+/// The hex encoded identifier, or an error if the path can't be made absolute
+pub fn integration_id<P: AsRef<Path>>(path: P) -> Result<String> {
+    let path = path.as_ref();
+    let path = fs::canonicalize(path).or_else(|_| std::path::absolute(path))?;
+    let digest = Sha256::digest(path.as_os_str().as_encoded_bytes());
+    Ok(to_hex(&digest[..16]))
+}
+
+/// Builds the name an integration file is installed as, e.g. `squishy-<id>-firefox`.
+///
+/// # Arguments
+/// * `id` - The integration identifier
+/// * `name` - The original name, without extension
+///
+/// # Returns
+/// The file name, without extension
+pub(crate) fn file_name(id: &str, name: &str) -> String {
+    format!("{}-{}-{}", FILE_PREFIX, id, name)
+}
+
+/// Removes the desktop integration files previously installed for the AppImage
+/// to the default data directory.
+///
+/// # Arguments
+/// * `appimage_path` - Path to the appimage file. It doesn't need to exist anymore.
+///
+/// # Returns
+/// The removed files, or an error if they can't be removed
+pub fn unintegrate<P: AsRef<Path>>(appimage_path: P) -> Result<Vec<PathBuf>> {
+    let data_dir = default_data_dir().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Couldn't determine the data directory",
+        )
+    })?;
+    unintegrate_in(appimage_path, data_dir)
+}
+
+/// Removes the desktop integration files previously installed for the AppImage
+/// to the data directory. Nothing is removed unless an installed desktop file
+/// launching the AppImage records its identifier, see [`recorded_id`].
+///
+/// # Arguments
+/// * `appimage_path` - Path to the appimage file. It doesn't need to exist anymore.
+/// * `data_dir` - The data directory the files were installed to
+///
+/// # Returns
+/// The removed files, or an error if they can't be removed
+pub fn unintegrate_in<P: AsRef<Path>, D: AsRef<Path>>(
+    appimage_path: P,
+    data_dir: D,
+) -> Result<Vec<PathBuf>> {
+    match recorded_id(appimage_path, &data_dir)? {
+        Some(id) => remove_integration(&id, data_dir),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Finds the integration identifier recorded with the [`MARKER_KEY`] in the
+/// installed desktop file whose `Exec` launches the AppImage.
+///
+/// # Arguments
+/// * `appimage_path` - Path to the appimage file. It doesn't need to exist anymore.
+/// * `data_dir` - The data directory the files were installed to
+///
+/// # Returns
+/// The recorded identifier, or None if the AppImage isn't integrated
+pub fn recorded_id<P: AsRef<Path>, D: AsRef<Path>>(
+    appimage_path: P,
+    data_dir: D,
+) -> Result<Option<String>> {
+    let path = appimage_path.as_ref();
+    let path = fs::canonicalize(path).or_else(|_| std::path::absolute(path))?;
+    let path = path.to_string_lossy();
+
+    Ok(
+        installed_desktop_files(data_dir.as_ref()).find_map(|(_, entry)| {
+            let id = entry.get(MARKER_KEY)?;
+            (is_valid_id(id) && entry.exec_args().first().is_some_and(|exec| *exec == path))
+                .then(|| id.to_string())
+        }),
+    )
+}
+
+/// Parses the installed desktop files of the data directory.
+///
+/// # Arguments
+/// * `data_dir` - The data directory
+///
+/// # Returns
+/// The path and parsed entry of every readable desktop file
+fn installed_desktop_files(data_dir: &Path) -> impl Iterator<Item = (PathBuf, DesktopEntry)> {
+    fs::read_dir(data_dir.join("applications"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|file| file.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "desktop"))
+        .filter_map(|path| {
+            let content = fs::read_to_string(&path).ok()?;
+            Some((path, DesktopEntry::parse(&content)))
+        })
+}
+
+/// Whether the identifier has the format of [`integration_id`]: 32 hex digits.
+fn is_valid_id(id: &str) -> bool {
+    id.len() == 32 && id.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+/// Removes the desktop integration files installed with the identifier. Files are
+/// matched by their name, and desktop files also by the [`MARKER_KEY`] they contain.
+/// Nothing is removed unless an installed desktop file records the identifier.
+///
+/// # Arguments
+/// * `id` - The integration identifier
+/// * `data_dir` - The data directory the files were installed to
+///
+/// # Returns
+/// The removed files, or an error if the identifier is malformed or the files can't be removed
+pub fn remove_integration<D: AsRef<Path>>(id: &str, data_dir: D) -> Result<Vec<PathBuf>> {
+    if !is_valid_id(id) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Invalid integration identifier {}", id),
+        )
+        .into());
+    }
+    let data_dir = data_dir.as_ref();
+    let recorded =
+        installed_desktop_files(data_dir).any(|(_, entry)| entry.get(MARKER_KEY) == Some(id));
+    if !recorded {
+        return Ok(Vec::new());
+    }
+
+    let prefix = file_name(id, "");
+    let marker = format!("{}={}", MARKER_KEY, id);
+
+    let mut dirs = vec![
+        data_dir.join("applications"),
+        data_dir.join("metainfo"),
+        data_dir.join("mime/packages"),
+        data_dir.join("pixmaps"),
+    ];
+    if let Ok(sizes) = fs::read_dir(data_dir.join("icons/hicolor")) {
+        dirs.extend(sizes.flatten().map(|size| size.path().join("apps")));
+    }
+
+    let mut removed = Vec::new();
+    for dir in dirs {
+        let Ok(files) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let path = file.path();
+            let name = file.file_name();
+            let matches = name.to_string_lossy().starts_with(&prefix)
+                || (path.extension().is_some_and(|ext| ext == "desktop")
+                    && fs::read_to_string(&path)
+                        .is_ok_and(|content| content.lines().any(|line| line.trim() == marker)));
+            if matches {
+                fs::remove_file(&path)?;
+                removed.push(path);
+            }
+        }
+    }
+    removed.sort();
+    Ok(removed)
+}
+
+/// Rewrites a desktop file to launch the AppImage (see [`rewrite_desktop`]) and
+/// adds the [`MARKER_KEY`] to the `[Desktop Entry]` group.
+///
+/// # Arguments
+/// * `content` - Contents of the desktop file
+/// * `appimage_path` - Absolute path to the appimage file
+/// * `icon` - Name of the installed icon, if any
+/// * `id` - The integration identifier
+///
+/// # Returns
+/// The rewritten desktop file
+pub(crate) fn rewrite_integrated_desktop(
+    content: &str,
+    appimage_path: &Path,
+    icon: Option<&str>,
+    id: &str,
+) -> String {
+    let rewrite = DesktopRewrite {
+        appimage: Some(appimage_path),
+        icon,
+    };
+    let mut result = String::with_capacity(content.len());
+    for line in rewrite_desktop(content, &rewrite).lines() {
+        result.push_str(line);
+        result.push('\n');
+        if line.trim() == "[Desktop Entry]" {
+            result.push_str(&format!("{}={}\n", MARKER_KEY, id));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "0123456789abcdef0123456789abcdef";
+
+    fn data_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "squishy-integration-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("applications")).unwrap();
+        fs::create_dir_all(dir.join("icons/hicolor/64x64/apps")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn adds_marker_to_desktop_entry_group() {
+        let content = rewrite_integrated_desktop(
+            "[Desktop Entry]\nExec=app %U\nIcon=app\n[Desktop Action new]\nExec=app --new\n",
+            Path::new("/opt/App.AppImage"),
+            Some("squishy-icon"),
+            ID,
+        );
+        let entry = DesktopEntry::parse(&content);
+        assert_eq!(entry.get(MARKER_KEY), Some(ID));
+        assert_eq!(entry.exec_args(), ["/opt/App.AppImage"]);
+        assert_eq!(entry.icon.as_deref(), Some("squishy-icon"));
+        assert_eq!(content.matches(MARKER_KEY).count(), 1);
+    }
+
+    #[test]
+    fn removes_recorded_integration_only() {
+        let dir = data_dir("recorded");
+        let appimage = dir.join("App.AppImage");
+        let desktop =
+            rewrite_integrated_desktop("[Desktop Entry]\nExec=app\n", &appimage, None, ID);
+        let desktop_path = dir
+            .join("applications")
+            .join(format!("{}.desktop", file_name(ID, "app")));
+        fs::write(&desktop_path, desktop).unwrap();
+        let icon_path = dir
+            .join("icons/hicolor/64x64/apps")
+            .join(format!("{}.png", file_name(ID, "app")));
+        fs::write(&icon_path, b"").unwrap();
+        let other = dir.join("applications/other.desktop");
+        fs::write(&other, "[Desktop Entry]\nExec=other\n").unwrap();
+
+        assert_eq!(recorded_id(&appimage, &dir).unwrap().as_deref(), Some(ID));
+        assert_eq!(recorded_id(dir.join("Other.AppImage"), &dir).unwrap(), None);
+        assert!(unintegrate_in(dir.join("Other.AppImage"), &dir)
+            .unwrap()
+            .is_empty());
+
+        let mut expected = vec![desktop_path, icon_path];
+        expected.sort();
+        assert_eq!(unintegrate_in(&appimage, &dir).unwrap(), expected);
+        assert!(other.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_files_without_recorded_id() {
+        let dir = data_dir("unrecorded");
+        // Named like an integration file, but no desktop file records the identifier
+        let icon_path = dir
+            .join("icons/hicolor/64x64/apps")
+            .join(format!("{}.png", file_name(ID, "app")));
+        fs::write(&icon_path, b"").unwrap();
+
+        assert!(remove_integration(ID, &dir).unwrap().is_empty());
+        assert!(icon_path.exists());
+        assert!(remove_integration("", &dir).is_err());
+        assert!(remove_integration("../../etc", &dir).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
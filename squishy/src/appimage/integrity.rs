@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use rayon::iter::ParallelIterator;
+
+use crate::{error::SquishyError, EntryKind};
+
+use super::AppImage;
+
+/// Number of bytes read at once while checking a file
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// A payload file that can't be read back intact
+#[derive(Debug)]
+pub struct DamagedEntry {
+    /// Path of the file in the payload
+    pub path: PathBuf,
+    /// The error encountered while reading the file
+    pub error: SquishyError,
+}
+
+impl AppImage<'_> {
+    /// Checks the integrity of the payload by reading back every regular file,
+    /// which decompresses all of its data blocks and fragments, and checking that
+    /// the amount of data read matches the size recorded in the metadata. Files
+    /// are read in chunks and in parallel.
+    ///
+    /// # Returns
+    /// The damaged files sorted by path, empty if the payload is intact
+    pub fn check_integrity(&self) -> Vec<DamagedEntry> {
+        let mut damaged = self
+            .par_entries()
+            .filter(|entry| matches!(entry.kind, EntryKind::File(_)))
+            .filter_map(|entry| {
+                let size = entry.size as u64;
+                let mut position = 0;
+                while position < size {
+                    match self.read_range(&entry, position, CHUNK_SIZE) {
+                        Ok(chunk) if chunk.is_empty() => break,
+                        Ok(chunk) => position += chunk.len() as u64,
+                        Err(error) => {
+                            return Some(DamagedEntry {
+                                path: entry.path,
+                                error,
+                            })
+                        }
+                    }
+                }
+                (position != size).then(|| DamagedEntry {
+                    error: SquishyError::Io(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        format!("Read {} of {} bytes", position, size),
+                    )),
+                    path: entry.path,
+                })
+            })
+            .collect::<Vec<_>>();
+        damaged.sort_by(|a, b| a.path.cmp(&b.path));
+        damaged
+    }
+}
@@ -0,0 +1,776 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, Permissions},
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    os::unix::fs::{FileExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use backhand::{BasicFile, NodeHeader};
+
+use crate::{error::SquishyError, EntryKind, SquashFSEntry};
+
+use super::Result;
+
+/// Offset of the primary volume descriptor (sector 16 of 2048 bytes)
+const VOLUME_DESCRIPTORS_START: u64 = 16 * 2048;
+
+/// ISO 9660 standard identifier
+const ISO9660_MAGIC: &[u8; 5] = b"CD001";
+
+/// Directory record flag marking a directory
+const FLAG_DIRECTORY: u8 = 0x02;
+
+/// Length of the fixed part of a directory record, before the file identifier
+const RECORD_HEADER_LEN: usize = 33;
+
+/// Checks whether the file contains an ISO 9660 filesystem.
+///
+/// # Arguments
+/// * `file` - The file to check
+///
+/// # Returns
+/// boolean stating if the ISO 9660 signature is found
+pub fn is_iso9660(file: &mut File) -> std::io::Result<bool> {
+    let mut magic = [0_u8; 5];
+    file.seek(SeekFrom::Start(VOLUME_DESCRIPTORS_START + 1))?;
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == ISO9660_MAGIC),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// A node in the ISO 9660 filesystem
+#[derive(Debug)]
+struct IsoNode {
+    path: PathBuf,
+    header: NodeHeader,
+    kind: IsoNodeKind,
+}
+
+#[derive(Debug)]
+enum IsoNodeKind {
+    File(IsoFile),
+    Directory,
+    Symlink(PathBuf),
+}
+
+/// A regular file in the ISO 9660 filesystem. Entries expose the BasicFile, which
+/// only carries the file size; the data is located through the extent.
+#[derive(Debug)]
+struct IsoFile {
+    /// Location of the file extent, in logical blocks
+    extent: u64,
+    file: BasicFile,
+}
+
+/// Rock Ridge information found in the system use area of a directory record
+#[derive(Default)]
+struct RockRidge {
+    name: Option<String>,
+    mode: Option<u32>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    symlink: Option<String>,
+}
+
+/// The Iso9660 struct provides read access to the ISO 9660 filesystem used by Type-1 AppImages.
+/// Rock Ridge extensions are used for file names, permissions and symlinks when present.
+pub struct Iso9660 {
+    source: File,
+    /// Length of the source file, bounding every extent read
+    len: u64,
+    block_size: u64,
+    nodes: Vec<IsoNode>,
+    /// Extent locations of the files, by address of the BasicFile exposed in the entries
+    extents: HashMap<usize, u64>,
+}
+
+impl Iso9660 {
+    /// Reads the ISO 9660 filesystem from a file.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the file
+    ///
+    /// # Returns
+    /// An Iso9660 instance, or an error if the filesystem can't be parsed
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let source = File::open(path)?;
+        let len = source.metadata()?.len();
+
+        let mut descriptor = [0_u8; 2048];
+        let mut sector = VOLUME_DESCRIPTORS_START;
+        // Find the primary volume descriptor (type 1), terminator is type 255
+        loop {
+            source.read_exact_at(&mut descriptor, sector)?;
+            if &descriptor[1..6] != ISO9660_MAGIC || descriptor[0] == 255 {
+                return Err(SquishyError::InvalidIso9660(
+                    "Primary volume descriptor not found".into(),
+                ));
+            }
+            if descriptor[0] == 1 {
+                break;
+            }
+            sector += 2048;
+        }
+
+        let block_size = u16::from_le_bytes([descriptor[128], descriptor[129]]) as u64;
+        if !matches!(block_size, 512 | 1024 | 2048) {
+            return Err(SquishyError::InvalidIso9660(format!(
+                "Invalid logical block size {}",
+                block_size
+            )));
+        }
+        let root_record = &descriptor[156..190];
+        let root_extent = u32::from_le_bytes(root_record[2..6].try_into().unwrap()) as u64;
+        let root_size = u32::from_le_bytes(root_record[10..14].try_into().unwrap()) as u64;
+
+        let mut iso = Iso9660 {
+            source,
+            len,
+            block_size,
+            nodes: Vec::new(),
+            extents: HashMap::new(),
+        };
+
+        let root_header = NodeHeader::new(0o755, 0, 0, record_mtime(root_record));
+        iso.nodes.push(IsoNode {
+            path: PathBuf::from("/"),
+            header: root_header,
+            kind: IsoNodeKind::Directory,
+        });
+
+        let mut visited = HashSet::new();
+        iso.read_directory(Path::new("/"), root_extent, root_size, &mut visited)?;
+
+        // The nodes don't move from here on, so the BasicFile addresses stay valid
+        iso.extents = iso
+            .nodes
+            .iter()
+            .filter_map(|node| match &node.kind {
+                IsoNodeKind::File(file) => {
+                    Some((&file.file as *const BasicFile as usize, file.extent))
+                }
+                _ => None,
+            })
+            .collect();
+
+        Ok(iso)
+    }
+
+    /// Checks that an extent lies within the image.
+    ///
+    /// # Arguments
+    /// * `extent` - Location of the extent, in logical blocks
+    /// * `size` - Size of the extent
+    ///
+    /// # Returns
+    /// Byte offset of the extent, or an error if it goes past the end of the image
+    fn extent_offset(&self, extent: u64, size: u64) -> Result<u64> {
+        extent
+            .checked_mul(self.block_size)
+            .filter(|start| start.checked_add(size).is_some_and(|end| end <= self.len))
+            .ok_or_else(|| SquishyError::InvalidIso9660("Extent out of bounds".into()))
+    }
+
+    /// Finds the byte offset of the file data.
+    ///
+    /// # Arguments
+    /// * `file` - The file, as exposed by the entries of this filesystem
+    ///
+    /// # Returns
+    /// Byte offset of the file data, or an error if the file isn't part of this filesystem
+    fn file_offset(&self, file: &BasicFile) -> Result<u64> {
+        let extent = self
+            .extents
+            .get(&(file as *const BasicFile as usize))
+            .ok_or_else(|| {
+                SquishyError::InvalidIso9660("File doesn't belong to this image".into())
+            })?;
+        Ok(extent * self.block_size)
+    }
+
+    /// Recursively reads the directory records of a directory extent.
+    ///
+    /// # Arguments
+    /// * `parent` - Path of the directory
+    /// * `extent` - Location of the directory extent, in logical blocks
+    /// * `size` - Size of the directory extent
+    /// * `visited` - Extents already read, to guard against loops in corrupted images
+    fn read_directory(
+        &mut self,
+        parent: &Path,
+        extent: u64,
+        size: u64,
+        visited: &mut HashSet<u64>,
+    ) -> Result<()> {
+        if !visited.insert(extent) {
+            return Ok(());
+        }
+
+        let start = self.extent_offset(extent, size)?;
+        let mut data = vec![0_u8; size as usize];
+        self.source.read_exact_at(&mut data, start)?;
+
+        let mut subdirectories = Vec::new();
+        let mut position = 0;
+        while position < data.len() {
+            let record_len = data[position] as usize;
+            if record_len == 0 {
+                // Records don't cross sector boundaries, skip to the next sector
+                position = (position / 2048 + 1) * 2048;
+                continue;
+            }
+            let record = data
+                .get(position..position + record_len)
+                .ok_or_else(|| SquishyError::InvalidIso9660("Truncated directory record".into()))?;
+            position += record_len;
+
+            let name = record_name(record)?;
+            // Skip "." and ".." entries
+            if name == [0] || name == [1] {
+                continue;
+            }
+
+            let system_use_start = RECORD_HEADER_LEN + name.len() + (1 - name.len() % 2);
+            let rock_ridge =
+                self.parse_rock_ridge(record.get(system_use_start..).unwrap_or(&[]))?;
+
+            let name = rock_ridge.name.clone().unwrap_or_else(|| iso_name(name));
+            if !is_valid_name(&name) {
+                return Err(SquishyError::InvalidIso9660(format!(
+                    "Invalid file name {:?} in {}",
+                    name,
+                    parent.display()
+                )));
+            }
+            let path = parent.join(&name);
+
+            let child_extent = u32::from_le_bytes(record[2..6].try_into().unwrap());
+            let child_size = u32::from_le_bytes(record[10..14].try_into().unwrap());
+            let is_dir = record[25] & FLAG_DIRECTORY != 0;
+
+            let default_mode = if is_dir { 0o755 } else { 0o644 };
+            let header = NodeHeader::new(
+                rock_ridge.mode.map_or(default_mode, |mode| mode & 0o7777) as u16,
+                rock_ridge.uid.unwrap_or(0),
+                rock_ridge.gid.unwrap_or(0),
+                record_mtime(record),
+            );
+
+            let kind = if let Some(target) = rock_ridge.symlink {
+                let target = if target.starts_with('/') {
+                    PathBuf::from(target)
+                } else {
+                    PathBuf::from(format!("/{}", target))
+                };
+                IsoNodeKind::Symlink(target)
+            } else if is_dir {
+                subdirectories.push((path.clone(), child_extent as u64, child_size as u64));
+                IsoNodeKind::Directory
+            } else {
+                self.extent_offset(child_extent as u64, child_size as u64)?;
+                IsoNodeKind::File(IsoFile {
+                    extent: child_extent as u64,
+                    file: BasicFile {
+                        blocks_start: 0,
+                        frag_index: u32::MAX,
+                        block_offset: 0,
+                        file_size: child_size,
+                        block_sizes: Vec::new(),
+                    },
+                })
+            };
+
+            self.nodes.push(IsoNode { path, header, kind });
+        }
+
+        for (path, extent, size) in subdirectories {
+            self.read_directory(&path, extent, size, visited)?;
+        }
+
+        Ok(())
+    }
+
+    /// Parses the Rock Ridge entries of a system use area, following continuation areas.
+    ///
+    /// # Arguments
+    /// * `system_use` - The system use area of a directory record
+    ///
+    /// # Returns
+    /// The Rock Ridge information found
+    fn parse_rock_ridge(&self, system_use: &[u8]) -> Result<RockRidge> {
+        let mut rock_ridge = RockRidge::default();
+        let mut area = system_use.to_vec();
+        let mut continuations = 0;
+
+        loop {
+            let mut continuation = None;
+            let mut position = 0;
+            while position + 4 <= area.len() {
+                let signature = &area[position..position + 2];
+                let len = area[position + 2] as usize;
+                if len < 4 || position + len > area.len() {
+                    break;
+                }
+                let entry = &area[position + 4..position + len];
+                match signature {
+                    b"NM" if !entry.is_empty() => {
+                        let name = rock_ridge.name.get_or_insert_with(String::new);
+                        name.push_str(&String::from_utf8_lossy(&entry[1..]));
+                    }
+                    b"PX" if entry.len() >= 32 => {
+                        rock_ridge.mode = Some(u32::from_le_bytes(entry[0..4].try_into().unwrap()));
+                        rock_ridge.uid =
+                            Some(u32::from_le_bytes(entry[16..20].try_into().unwrap()));
+                        rock_ridge.gid =
+                            Some(u32::from_le_bytes(entry[24..28].try_into().unwrap()));
+                    }
+                    b"SL" if !entry.is_empty() => {
+                        let target = rock_ridge.symlink.get_or_insert_with(String::new);
+                        parse_symlink_components(&entry[1..], target);
+                    }
+                    b"CE" if entry.len() >= 24 => {
+                        let block = u32::from_le_bytes(entry[0..4].try_into().unwrap()) as u64;
+                        let offset = u32::from_le_bytes(entry[8..12].try_into().unwrap()) as u64;
+                        let size = u32::from_le_bytes(entry[16..20].try_into().unwrap()) as usize;
+                        continuation = Some((block * self.block_size + offset, size));
+                    }
+                    b"ST" => break,
+                    _ => {}
+                }
+                position += len;
+            }
+
+            // Limit the number of continuation areas followed in case of loops. A
+            // continuation area never spans more than one block.
+            match continuation {
+                Some((location, size)) if continuations < 16 && size as u64 <= self.block_size => {
+                    if location.saturating_add(size as u64) > self.len {
+                        return Err(SquishyError::InvalidIso9660(
+                            "Continuation area out of bounds".into(),
+                        ));
+                    }
+                    area = vec![0_u8; size];
+                    self.source.read_exact_at(&mut area, location)?;
+                    continuations += 1;
+                }
+                _ => break,
+            }
+        }
+
+        Ok(rock_ridge)
+    }
+
+    /// Returns an iterator over all the entries in the ISO 9660 filesystem.
+    pub fn entries(&self) -> impl Iterator<Item = SquashFSEntry<'_>> + '_ {
+        self.nodes.iter().map(|node| {
+            let (size, kind) = match &node.kind {
+                IsoNodeKind::File(file) => (file.file.file_size, EntryKind::File(&file.file)),
+                IsoNodeKind::Directory => (0, EntryKind::Directory),
+                IsoNodeKind::Symlink(target) => (0, EntryKind::Symlink(target.clone())),
+            };
+            SquashFSEntry {
+                header: node.header,
+                path: node.path.clone(),
+                size,
+                kind,
+            }
+        })
+    }
+
+    /// Reads the contents of the specified file from the ISO 9660 filesystem.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file within the filesystem.
+    ///
+    /// # Returns
+    /// The contents of the file as a Vec<u8>, or an error if the file is not found.
+    pub fn read_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        let path = path.as_ref();
+        let node = self
+            .nodes
+            .iter()
+            .find(|node| node.path == path)
+            .ok_or_else(|| SquishyError::FileNotFound(path.to_path_buf()))?;
+
+        match &node.kind {
+            IsoNodeKind::File(file) => self.read_contents(&file.file),
+            _ => Err(SquishyError::NotAFile(path.to_path_buf())),
+        }
+    }
+
+    /// Reads the contents of the file.
+    ///
+    /// # Arguments
+    /// * `file` - The file within the ISO 9660 filesystem.
+    ///
+    /// # Returns
+    /// The contents of the file, or an error if it cannot be read.
+    pub fn read_contents(&self, file: &BasicFile) -> Result<Vec<u8>> {
+        let start = self.file_offset(file)?;
+        let mut contents = vec![0_u8; file.file_size as usize];
+        self.source.read_exact_at(&mut contents, start)?;
+        Ok(contents)
+    }
+
+    /// Reads a byte range of the file.
+    ///
+    /// # Arguments
+    /// * `file` - The file within the ISO 9660 filesystem.
+    /// * `offset` - Offset within the file to start reading at.
+    /// * `length` - Maximum number of bytes to read.
+    ///
+    /// # Returns
+    /// The bytes read, or an error if the file cannot be read.
+    pub fn read_range(&self, file: &BasicFile, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let file_size = file.file_size as u64;
+        if offset >= file_size {
+            return Ok(Vec::new());
+        }
+        let start = self.file_offset(file)?;
+        let length = length.min(file_size - offset);
+        let mut contents = vec![0_u8; length as usize];
+        self.source.read_exact_at(&mut contents, start + offset)?;
+        Ok(contents)
+    }
+
+    /// Writes the contents of the file to the specified destination path.
+    ///
+    /// # Arguments
+    /// * `file` - The file within the ISO 9660 filesystem.
+    /// * `dest` - The destination path to write the file to.
+    ///
+    /// # Returns
+    /// An empty result, or an error if the file cannot be read or written.
+    pub fn write_file<P: AsRef<Path>>(&self, file: &BasicFile, dest: P) -> Result<()> {
+        let output_file = File::create(dest)?;
+        let mut writer = BufWriter::new(output_file);
+        writer.write_all(&self.read_contents(file)?)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the contents of the file to the specified destination path with permissions.
+    ///
+    /// # Arguments
+    /// * `file` - The file within the ISO 9660 filesystem.
+    /// * `dest` - The destination path to write the file to.
+    /// * `header` - Node header containing file information.
+    ///
+    /// # Returns
+    /// An empty result, or an error if the file cannot be read or written.
+    pub fn write_file_with_permissions<P: AsRef<Path>>(
+        &self,
+        file: &BasicFile,
+        dest: P,
+        header: NodeHeader,
+    ) -> Result<()> {
+        self.write_file(file, &dest)?;
+        let mode = u32::from(header.permissions);
+        fs::set_permissions(dest, Permissions::from_mode(mode))?;
+        Ok(())
+    }
+
+    /// Resolves the symlink chain starting from the specified entry,
+    /// returning the final target entry or an error if a cycle is detected.
+    ///
+    /// # Arguments
+    /// * `entry` - The entry to resolve the symlink for.
+    ///
+    /// # Returns
+    /// The final target entry, or None if the entry is not a symlink, or an error if a cycle is detected.
+    pub fn resolve_symlink(&self, entry: &SquashFSEntry) -> Result<Option<SquashFSEntry<'_>>> {
+        let EntryKind::Symlink(target) = &entry.kind else {
+            return Ok(None);
+        };
+
+        let mut visited = HashSet::new();
+        visited.insert(entry.path.clone());
+        let mut target = target.clone();
+        loop {
+            if !visited.insert(target.clone()) {
+                return Err(SquishyError::SymlinkError("Cyclic symlink detected".into()));
+            }
+            let Some(target_entry) = self.entries().find(|e| e.path == target) else {
+                return Ok(None);
+            };
+            match &target_entry.kind {
+                EntryKind::Symlink(next_target) => target = next_target.clone(),
+                _ => return Ok(Some(target_entry)),
+            }
+        }
+    }
+}
+
+/// Converts an ISO 9660 file identifier to a file name, dropping the version suffix.
+///
+/// # Arguments
+/// * `name` - Raw file identifier
+///
+/// # Returns
+/// The file name
+fn iso_name(name: &[u8]) -> String {
+    let name = String::from_utf8_lossy(name);
+    let name = name.split(';').next().unwrap_or_default();
+    name.strip_suffix('.').unwrap_or(name).to_string()
+}
+
+/// Returns the file identifier of a directory record.
+///
+/// # Arguments
+/// * `record` - The directory record
+///
+/// # Returns
+/// The raw file identifier, or an error if the record is too short to hold it
+fn record_name(record: &[u8]) -> Result<&[u8]> {
+    let name_len = *record
+        .get(RECORD_HEADER_LEN - 1)
+        .ok_or_else(|| SquishyError::InvalidIso9660("Directory record too short".into()))?
+        as usize;
+    record
+        .get(RECORD_HEADER_LEN..RECORD_HEADER_LEN + name_len)
+        .ok_or_else(|| SquishyError::InvalidIso9660("File identifier out of bounds".into()))
+}
+
+/// Checks that a file name is a single path component, so joining it to the
+/// parent directory can't escape it.
+///
+/// # Arguments
+/// * `name` - The file name
+///
+/// # Returns
+/// boolean stating if the name is safe to use
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\0'])
+}
+
+/// Appends the components of a Rock Ridge SL entry to the symlink target.
+///
+/// # Arguments
+/// * `components` - Component records of the SL entry
+/// * `target` - Symlink target being built
+fn parse_symlink_components(components: &[u8], target: &mut String) {
+    let mut position = 0;
+    let mut continued = false;
+    while position + 2 <= components.len() {
+        let flags = components[position];
+        let len = components[position + 1] as usize;
+        let content = components
+            .get(position + 2..position + 2 + len)
+            .unwrap_or_default();
+        position += 2 + len;
+
+        if !continued && !target.is_empty() && !target.ends_with('/') {
+            target.push('/');
+        }
+        // The component continues in the next component record
+        continued = flags & 0x01 != 0;
+        match flags & 0x0e {
+            0x02 => target.push('.'),
+            0x04 => target.push_str(".."),
+            0x08 => target.push('/'),
+            _ => target.push_str(&String::from_utf8_lossy(content)),
+        }
+    }
+}
+
+/// Converts the recording date of a directory record to a unix timestamp.
+///
+/// # Arguments
+/// * `record` - The directory record
+///
+/// # Returns
+/// Seconds since the unix epoch
+fn record_mtime(record: &[u8]) -> u32 {
+    let date = &record[18..25];
+    let year = 1900 + date[0] as i64;
+    let (month, day) = (date[1] as i64, date[2] as i64);
+    let (hour, minute, second) = (date[3] as i64, date[4] as i64, date[5] as i64);
+    // Offset from GMT in 15 minute intervals
+    let gmt_offset = date[6] as i8 as i64 * 15 * 60;
+
+    if month == 0 || day == 0 {
+        return 0;
+    }
+
+    // Days from civil, see http://howardhinnant.github.io/date_algorithms.html
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    let timestamp = days * 86400 + hour * 3600 + minute * 60 + second - gmt_offset;
+    timestamp.clamp(0, u32::MAX as i64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a directory record.
+    fn record(extent: u32, size: u32, flags: u8, name: &[u8], system_use: &[u8]) -> Vec<u8> {
+        let mut record = vec![0_u8; RECORD_HEADER_LEN];
+        record[2..6].copy_from_slice(&extent.to_le_bytes());
+        record[10..14].copy_from_slice(&size.to_le_bytes());
+        record[25] = flags;
+        record[32] = name.len() as u8;
+        record.extend_from_slice(name);
+        if name.len().is_multiple_of(2) {
+            record.push(0);
+        }
+        record.extend_from_slice(system_use);
+        record[0] = record.len() as u8;
+        record
+    }
+
+    /// Builds a Rock Ridge NM entry.
+    fn nm(name: &[u8]) -> Vec<u8> {
+        let mut entry = vec![b'N', b'M', 5 + name.len() as u8, 1, 0];
+        entry.extend_from_slice(name);
+        entry
+    }
+
+    /// Writes an image with the records in the root directory (sector 18) and
+    /// "hello" in sector 19.
+    fn write_image(name: &str, records: &[Vec<u8>]) -> PathBuf {
+        let mut image = vec![0_u8; 20 * 2048];
+
+        let pvd = &mut image[16 * 2048..17 * 2048];
+        pvd[0] = 1;
+        pvd[1..6].copy_from_slice(ISO9660_MAGIC);
+        pvd[128..130].copy_from_slice(&2048_u16.to_le_bytes());
+        pvd[156..190].copy_from_slice(&record(18, 2048, FLAG_DIRECTORY, &[0], &[]));
+
+        let terminator = &mut image[17 * 2048..18 * 2048];
+        terminator[0] = 255;
+        terminator[1..6].copy_from_slice(ISO9660_MAGIC);
+
+        let mut directory = record(18, 2048, FLAG_DIRECTORY, &[0], &[]);
+        directory.extend(record(18, 2048, FLAG_DIRECTORY, &[1], &[]));
+        for record in records {
+            directory.extend_from_slice(record);
+        }
+        image[18 * 2048..18 * 2048 + directory.len()].copy_from_slice(&directory);
+        image[19 * 2048..19 * 2048 + 5].copy_from_slice(b"hello");
+
+        let path = std::env::temp_dir().join(format!(
+            "squishy-iso9660-{}-{}.iso",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, image).unwrap();
+        path
+    }
+
+    fn open(name: &str, records: &[Vec<u8>]) -> Result<Iso9660> {
+        let path = write_image(name, records);
+        let iso = Iso9660::from_path(&path);
+        fs::remove_file(path).unwrap();
+        iso
+    }
+
+    #[test]
+    fn reads_file_contents() {
+        let iso = open("read", &[record(19, 5, 0, b"HELLO.TXT;1", &[])]).unwrap();
+        assert_eq!(iso.read_file("/HELLO.TXT").unwrap(), b"hello");
+
+        let entry = iso.entries().find(|e| e.path == Path::new("/HELLO.TXT"));
+        let Some(SquashFSEntry {
+            kind: EntryKind::File(file),
+            ..
+        }) = entry
+        else {
+            panic!("file entry not found");
+        };
+        assert_eq!(iso.read_range(file, 1, 3).unwrap(), b"ell");
+        assert_eq!(iso.read_range(file, 3, 100).unwrap(), b"lo");
+    }
+
+    #[test]
+    fn uses_rock_ridge_name() {
+        let iso = open("nm", &[record(19, 5, 0, b"HELLO.TXT;1", &nm(b"hello.txt"))]).unwrap();
+        assert_eq!(iso.read_file("/hello.txt").unwrap(), b"hello");
+    }
+
+    #[test]
+    fn rejects_escaping_names() {
+        for (index, name) in [&b".."[..], b"../etc", b"a/b", b"."]
+            .into_iter()
+            .enumerate()
+        {
+            let record = record(19, 5, 0, b"A;1", &nm(name));
+            let result = open(&format!("escape{}", index), &[record]);
+            assert!(matches!(result, Err(SquishyError::InvalidIso9660(_))));
+        }
+    }
+
+    #[test]
+    fn rejects_short_records() {
+        let mut short = record(19, 5, 0, b"A;1", &[]);
+        short.truncate(20);
+        short[0] = 20;
+        assert!(matches!(
+            open("short", &[short]),
+            Err(SquishyError::InvalidIso9660(_))
+        ));
+
+        let mut bad_name_len = record(19, 5, 0, b"A;1", &[]);
+        bad_name_len[32] = 200;
+        assert!(matches!(
+            open("name-len", &[bad_name_len]),
+            Err(SquishyError::InvalidIso9660(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_out_of_bounds_extents() {
+        let file = record(1000, 5, 0, b"A;1", &[]);
+        assert!(matches!(
+            open("file-extent", &[file]),
+            Err(SquishyError::InvalidIso9660(_))
+        ));
+
+        let directory = record(19, u32::MAX, FLAG_DIRECTORY, b"DIR", &[]);
+        assert!(matches!(
+            open("dir-size", &[directory]),
+            Err(SquishyError::InvalidIso9660(_))
+        ));
+    }
+
+    #[test]
+    fn converts_iso_names() {
+        assert_eq!(iso_name(b"HELLO.TXT;1"), "HELLO.TXT");
+        assert_eq!(iso_name(b"README.;1"), "README");
+        assert_eq!(iso_name(b"DIR"), "DIR");
+    }
+
+    #[test]
+    fn parses_symlink_components() {
+        let mut target = String::new();
+        // Root, "usr", "lib"
+        parse_symlink_components(b"\x08\x00\x00\x03usr\x00\x03lib", &mut target);
+        assert_eq!(target, "/usr/lib");
+
+        let mut target = String::new();
+        // Parent, "lib"
+        parse_symlink_components(b"\x04\x00\x00\x03lib", &mut target);
+        assert_eq!(target, "../lib");
+    }
+
+    #[test]
+    fn converts_record_dates() {
+        let mut record = record(0, 0, 0, &[0], &[]);
+        // 2000-01-02 03:04:05 GMT
+        record[18..25].copy_from_slice(&[100, 1, 2, 3, 4, 5, 0]);
+        assert_eq!(record_mtime(&record), 946_782_245);
+
+        // Same time at GMT+1
+        record[24] = 4;
+        assert_eq!(record_mtime(&record), 946_782_245 - 3600);
+    }
+}
@@ -0,0 +1,184 @@
+use std::path::{Path, PathBuf};
+
+use goblin::{
+    container::Ctx,
+    elf::{
+        dynamic::DT_SONAME,
+        header::header64,
+        program_header::{ProgramHeader, PT_DYNAMIC},
+        Dynamic, Elf,
+    },
+};
+
+/// A shared library bundled in the AppImage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundledLibrary {
+    /// Path of the library in the AppImage
+    pub path: PathBuf,
+    /// Size of the library in bytes
+    pub size: u64,
+    /// `DT_SONAME` of the library, if requested and present
+    pub soname: Option<String>,
+}
+
+/// Whether the file name looks like a shared library, e.g. `libssl.so` or `libssl.so.3`.
+///
+/// # Arguments
+/// * `path` - Path of the file
+///
+/// # Returns
+/// boolean stating if the file name is a shared library name
+pub fn is_shared_library(path: &Path) -> bool {
+    let Some(name) = path.file_name() else {
+        return false;
+    };
+    let name = name.to_string_lossy();
+    name.ends_with(".so") || name.contains(".so.")
+}
+
+/// Reads the `DT_SONAME` from the dynamic section of an ELF shared library.
+///
+/// # Arguments
+/// * `data` - Contents of the library
+///
+/// # Returns
+/// The SONAME, if the data is an ELF file with one
+pub fn soname(data: &[u8]) -> Option<String> {
+    Elf::parse(data).ok()?.soname.map(String::from)
+}
+
+/// Reads the `DT_SONAME` of an ELF shared library through ranged reads: only the
+/// ELF header, the program headers, the dynamic section and the SONAME string are
+/// read, instead of the whole file.
+///
+/// # Arguments
+/// * `read` - Reads up to `length` bytes at an offset of the file, given as `(offset, length)`
+///
+/// # Returns
+/// The SONAME, if the file is an ELF file with one
+pub fn soname_with<F>(read: F) -> Option<String>
+where
+    F: Fn(u64, u64) -> Option<Vec<u8>>,
+{
+    let read_exact =
+        |offset: u64, length: u64| read(offset, length).filter(|data| data.len() as u64 == length);
+
+    let header = Elf::parse_header(&read(0, header64::SIZEOF_EHDR as u64)?).ok()?;
+    let ctx = Ctx::new(header.container().ok()?, header.endianness().ok()?);
+    let phdrs_size = header.e_phentsize as u64 * header.e_phnum as u64;
+    let phdrs_data = read_exact(header.e_phoff, phdrs_size)?;
+    let mut phdrs = ProgramHeader::parse(&phdrs_data, 0, header.e_phnum as usize, ctx).ok()?;
+
+    let dynamic_phdr = phdrs.iter_mut().find(|phdr| phdr.p_type == PT_DYNAMIC)?;
+    let dynamic_data = read_exact(dynamic_phdr.p_offset, dynamic_phdr.p_filesz)?;
+    // The dynamic section is parsed from its own buffer
+    dynamic_phdr.p_offset = 0;
+    let dynamic = Dynamic::parse(&dynamic_data, &phdrs, ctx).ok()??;
+
+    let soname = dynamic
+        .dyns
+        .iter()
+        .find(|dynamic| dynamic.d_tag == DT_SONAME)?
+        .d_val;
+    let length = (dynamic.info.strsz as u64).checked_sub(soname)?;
+    let name = read((dynamic.info.strtab as u64).checked_add(soname)?, length)?;
+    let end = name.iter().position(|&byte| byte == 0)?;
+    String::from_utf8(name[..end].to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Offset of the dynamic section in the built library
+    const DYNAMIC: u64 = 0xb0;
+
+    /// Builds a 64-bit little-endian shared library with a single load segment
+    /// and a dynamic section holding the SONAME.
+    fn library(soname: &str) -> Vec<u8> {
+        const STRTAB: u64 = DYNAMIC + 4 * 16;
+        let strtab = [b"\0", soname.as_bytes(), b"\0"].concat();
+        let size = STRTAB + strtab.len() as u64;
+
+        let mut data = Vec::new();
+        data.extend_from_slice(b"\x7fELF\x02\x01\x01");
+        data.resize(16, 0);
+        for (value, width) in [
+            (3, 2),  // e_type: ET_DYN
+            (62, 2), // e_machine: x86_64
+            (1, 4),  // e_version
+            (0, 8),  // e_entry
+            (64, 8), // e_phoff
+            (0, 8),  // e_shoff
+            (0, 4),  // e_flags
+            (64, 2), // e_ehsize
+            (56, 2), // e_phentsize
+            (2, 2),  // e_phnum
+            (64, 2), // e_shentsize
+            (0, 2),  // e_shnum
+            (0, 2),  // e_shstrndx
+        ] {
+            data.extend_from_slice(&u64::to_le_bytes(value)[..width]);
+        }
+        for (p_type, offset, filesz) in [(1, 0, size), (2, DYNAMIC, 4 * 16)] {
+            for value in [
+                p_type | (4 << 32),
+                offset,
+                offset,
+                offset,
+                filesz,
+                filesz,
+                8,
+            ] {
+                data.extend_from_slice(&u64::to_le_bytes(value));
+            }
+        }
+        assert_eq!(data.len() as u64, DYNAMIC);
+        for (tag, value) in [
+            (5, STRTAB),               // DT_STRTAB
+            (10, strtab.len() as u64), // DT_STRSZ
+            (14, 1),                   // DT_SONAME
+            (0, 0),                    // DT_NULL
+        ] {
+            data.extend_from_slice(&u64::to_le_bytes(tag));
+            data.extend_from_slice(&u64::to_le_bytes(value));
+        }
+        data.extend_from_slice(&strtab);
+        data
+    }
+
+    fn read_range(data: &[u8]) -> impl Fn(u64, u64) -> Option<Vec<u8>> + '_ {
+        |offset, length| {
+            let data = data.get(offset as usize..)?;
+            Some(data[..data.len().min(length as usize)].to_vec())
+        }
+    }
+
+    #[test]
+    fn recognizes_library_names() {
+        assert!(is_shared_library(Path::new("usr/lib/libssl.so")));
+        assert!(is_shared_library(Path::new("usr/lib/libssl.so.3")));
+        assert!(!is_shared_library(Path::new("usr/lib/libssl.a")));
+        assert!(!is_shared_library(Path::new("usr/share/doc/some.socket")));
+    }
+
+    #[test]
+    fn reads_soname_through_ranges() {
+        let data = library("libfoo.so.1");
+        assert_eq!(soname(&data).as_deref(), Some("libfoo.so.1"));
+        assert_eq!(
+            soname_with(read_range(&data)).as_deref(),
+            Some("libfoo.so.1")
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_libraries() {
+        let data = library("libfoo.so.1");
+        assert_eq!(
+            soname_with(read_range(&data[..DYNAMIC as usize + 16])),
+            None
+        );
+        assert_eq!(soname_with(read_range(b"not an elf file")), None);
+    }
+}
@@ -0,0 +1,159 @@
+use std::{ffi::OsString, path::Path};
+
+use crate::error::SquishyError;
+
+use super::Result;
+
+/// Placeholders a name template can use
+const PLACEHOLDERS: [&str; 6] = ["appname", "id", "version", "kind", "name", "ext"];
+
+/// Kind of desktop integration file being named
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Icon,
+    Desktop,
+    Appstream,
+    AppRun,
+}
+
+impl FileKind {
+    /// Name of the kind, as substituted for `{kind}`
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileKind::Icon => "icon",
+            FileKind::Desktop => "desktop",
+            FileKind::Appstream => "appstream",
+            FileKind::AppRun => "apprun",
+        }
+    }
+}
+
+/// Values of the placeholders describing the AppImage, see [`AppImage::name_values`](super::AppImage::name_values)
+#[derive(Debug, Clone, Default)]
+pub struct NameValues {
+    /// Application name
+    pub appname: String,
+    /// Application identifier, if known
+    pub id: Option<String>,
+    /// Application version, if known
+    pub version: Option<String>,
+}
+
+/// A part of a parsed template
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(String),
+    Placeholder(&'static str),
+}
+
+/// Template for the names of the written files, e.g. `{appname}-{kind}.{ext}` or
+/// `{id}.{ext}`. The placeholders are:
+/// - `{appname}`: application name
+/// - `{id}`: application identifier, or the application name if unknown
+/// - `{version}`: application version, empty if unknown
+/// - `{kind}`: `icon`, `desktop`, `appstream` or `apprun`
+/// - `{name}`: original file name without its extension
+/// - `{ext}`: original extension, including the `appdata`/`metainfo` suffix of
+///   appstream files
+#[derive(Debug, Clone)]
+pub struct NameTemplate {
+    parts: Vec<Part>,
+}
+
+impl NameTemplate {
+    /// Parses a template.
+    ///
+    /// # Arguments
+    /// * `template` - The template
+    ///
+    /// # Returns
+    /// The template, or an error if it is empty, contains a `/` or an unknown or
+    /// unclosed placeholder
+    pub fn new(template: &str) -> Result<Self> {
+        let invalid =
+            |reason: &str| SquishyError::InvalidTemplate(format!("{}: {}", template, reason));
+        if template.is_empty() {
+            return Err(invalid("empty template"));
+        }
+        if template.contains('/') {
+            return Err(invalid("names can't contain /"));
+        }
+
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_owned()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| invalid("unclosed placeholder"))?;
+            let name = &rest[start + 1..start + end];
+            let placeholder = PLACEHOLDERS
+                .into_iter()
+                .find(|placeholder| *placeholder == name)
+                .ok_or_else(|| invalid(&format!("unknown placeholder {{{}}}", name)))?;
+            parts.push(Part::Placeholder(placeholder));
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_owned()));
+        }
+        Ok(NameTemplate { parts })
+    }
+
+    /// Builds the output file name of an entry. A `.` left at the end of the name,
+    /// e.g. by `{ext}` for files without an extension, is removed.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the entry within the AppImage
+    /// * `kind` - Kind of the file
+    /// * `values` - Values describing the AppImage
+    ///
+    /// # Returns
+    /// The file name to write the entry as
+    pub fn render(&self, path: &Path, kind: FileKind, values: &NameValues) -> OsString {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let (name, ext) = split_extension(&file_name);
+
+        let mut output = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => output.push_str(literal),
+                Part::Placeholder(placeholder) => {
+                    let value = match *placeholder {
+                        "appname" => &values.appname,
+                        "id" => values.id.as_ref().unwrap_or(&values.appname),
+                        "version" => values.version.as_deref().unwrap_or_default(),
+                        "kind" => kind.name(),
+                        "name" => name,
+                        _ => ext,
+                    };
+                    // Values come from the AppImage metadata, so they mustn't add directories
+                    output.push_str(&value.replace('/', "_"));
+                }
+            }
+        }
+        OsString::from(output.trim_end_matches('.'))
+    }
+}
+
+/// Splits a file name into its name and extension. The `appdata`/`metainfo` suffix
+/// of appstream files is kept in the extension.
+///
+/// # Arguments
+/// * `file_name` - The file name
+///
+/// # Returns
+/// The name and the extension, empty if there is none
+pub(crate) fn split_extension(file_name: &str) -> (&str, &str) {
+    for suffix in ["appdata.xml", "metainfo.xml"] {
+        if let Some(name) = file_name.strip_suffix(suffix) {
+            return (name.trim_end_matches('.'), suffix);
+        }
+    }
+    match file_name.rsplit_once('.') {
+        Some((name, ext)) if !name.is_empty() => (name, ext),
+        _ => (file_name, ""),
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::{BufReader, Cursor};
+
+use rayon::iter::ParallelIterator;
+
+use crate::{EntryKind, SquashFS, SquashFSEntry};
+
+use super::{get_offset_from_reader, Result, ELF_MAGIC, SQUASHFS_MAGIC};
+
+/// Number of bytes needed to detect a nested image
+pub(crate) const HEADER_SIZE: u64 = 12;
+
+/// Size of the SquashFS superblock, the smallest possible image
+const SUPERBLOCK_SIZE: u32 = 96;
+
+/// Kind of an image nested in another image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NestedKind {
+    /// Plain SquashFS image
+    SquashFS,
+    /// SquashFS based AppImage
+    AppImage,
+}
+
+impl NestedKind {
+    /// Detects the kind of a nested image from the first bytes of its contents.
+    ///
+    /// # Arguments
+    /// * `header` - First bytes of the file
+    ///
+    /// # Returns
+    /// The kind of the image, or None if it isn't a supported image
+    pub fn from_header(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&SQUASHFS_MAGIC) {
+            Some(NestedKind::SquashFS)
+        } else if header.starts_with(&ELF_MAGIC) && header.get(8..11) == Some(b"AI\x02") {
+            Some(NestedKind::AppImage)
+        } else {
+            None
+        }
+    }
+}
+
+/// An image found inside another image
+#[derive(Debug)]
+pub struct NestedImage<'a> {
+    /// The entry holding the image
+    pub entry: SquashFSEntry<'a>,
+    /// Kind of the image
+    pub kind: NestedKind,
+}
+
+/// Checks whether the entry holds a nested image.
+///
+/// # Arguments
+/// * `entry` - The entry to check
+/// * `read_header` - Reads the first bytes of the entry
+///
+/// # Returns
+/// The nested image, if the entry holds one
+pub(crate) fn detect_nested<'a, F>(
+    entry: SquashFSEntry<'a>,
+    read_header: F,
+) -> Option<NestedImage<'a>>
+where
+    F: Fn(&SquashFSEntry<'a>) -> Result<Vec<u8>>,
+{
+    if !matches!(entry.kind, EntryKind::File(_)) || entry.size < SUPERBLOCK_SIZE {
+        return None;
+    }
+    let header = read_header(&entry).ok()?;
+    let kind = NestedKind::from_header(&header)?;
+    Some(NestedImage { entry, kind })
+}
+
+/// Opens the SquashFS image held in memory. The data is owned by the returned
+/// SquashFS, so it can be searched for further nested images.
+///
+/// # Arguments
+/// * `data` - Contents of the nested image
+/// * `kind` - Kind of the nested image
+///
+/// # Returns
+/// The nested SquashFS, or an error if it can't be parsed
+pub(crate) fn open_nested(data: Vec<u8>, kind: NestedKind) -> Result<SquashFS<'static>> {
+    let mut reader = Cursor::new(data);
+    let offset = match kind {
+        NestedKind::SquashFS => 0,
+        NestedKind::AppImage => get_offset_from_reader(&mut reader)?,
+    };
+    SquashFS::new(BufReader::new(reader), Some(offset))
+}
+
+impl SquashFS<'_> {
+    /// Finds the SquashFS images and AppImages nested in the SquashFS, detected
+    /// from the magic bytes of the file contents.
+    ///
+    /// # Returns
+    /// The nested images found
+    pub fn find_nested_images(&self) -> Vec<NestedImage<'_>> {
+        self.par_entries()
+            .filter_map(|entry| {
+                detect_nested(entry, |entry| match entry.kind {
+                    EntryKind::File(file) => self.read_range(file, 0, HEADER_SIZE),
+                    _ => Ok(Vec::new()),
+                })
+            })
+            .collect()
+    }
+
+    /// Opens a nested image, reading it into memory.
+    ///
+    /// # Arguments
+    /// * `nested` - The nested image, as found by [`SquashFS::find_nested_images`]
+    ///
+    /// # Returns
+    /// The nested SquashFS, or an error if it can't be read or parsed
+    pub fn open_nested(&self, nested: &NestedImage) -> Result<SquashFS<'static>> {
+        open_nested(self.read_file(&nested.entry.path)?, nested.kind)
+    }
+}
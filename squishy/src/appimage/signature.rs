@@ -0,0 +1,68 @@
+use pgp::{
+    composed::{Deserializable, DetachedSignature, SignedPublicKey},
+    types::KeyDetails,
+};
+
+use super::Result;
+
+/// Result of verifying the signature embedded in an AppImage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureVerdict {
+    /// The signature is valid for the payload digest
+    Valid {
+        /// Fingerprint of the key that made the signature
+        fingerprint: String,
+    },
+    /// The signature doesn't match the payload digest or the key
+    Invalid(String),
+    /// The AppImage has no embedded signature
+    Unsigned,
+    /// The AppImage is signed, but no public key is available to verify it
+    MissingKey,
+}
+
+impl SignatureVerdict {
+    /// Whether the signature was successfully verified
+    pub fn is_valid(&self) -> bool {
+        matches!(self, SignatureVerdict::Valid { .. })
+    }
+}
+
+/// Verifies an armored detached signature over the AppImage digest.
+/// appimagetool signs the hex encoded digest rather than the raw bytes.
+///
+/// # Arguments
+/// * `digest` - Hex encoded AppImage digest
+/// * `signature` - Armored detached signature
+/// * `key` - Armored public key
+///
+/// # Returns
+/// The verification verdict
+pub(crate) fn verify(digest: &str, signature: &[u8], key: &[u8]) -> Result<SignatureVerdict> {
+    let signature = match DetachedSignature::from_armor_single(signature) {
+        Ok((signature, _)) => signature,
+        Err(e) => return Ok(SignatureVerdict::Invalid(e.to_string())),
+    };
+    let key = match SignedPublicKey::from_armor_single(key) {
+        Ok((key, _)) => key,
+        Err(e) => return Ok(SignatureVerdict::Invalid(e.to_string())),
+    };
+
+    if signature.verify(&key, digest.as_bytes()).is_ok() {
+        return Ok(SignatureVerdict::Valid {
+            fingerprint: format!("{:X}", key.fingerprint()),
+        });
+    }
+
+    for subkey in &key.public_subkeys {
+        if signature.verify(subkey, digest.as_bytes()).is_ok() {
+            return Ok(SignatureVerdict::Valid {
+                fingerprint: format!("{:X}", subkey.key.fingerprint()),
+            });
+        }
+    }
+
+    Ok(SignatureVerdict::Invalid(
+        "Signature doesn't match the AppImage digest".into(),
+    ))
+}
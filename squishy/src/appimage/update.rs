@@ -0,0 +1,163 @@
+use std::{fmt, str::FromStr};
+
+/// Update information embedded in an AppImage, as described in the AppImage specification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateInfo {
+    /// `zsync|<url>`
+    Zsync { url: String },
+    /// `gh-releases-zsync|<username>|<repository>|<release>|<filename>`
+    GhReleasesZsync {
+        username: String,
+        repository: String,
+        release: String,
+        filename: String,
+    },
+    /// `pling-v1-zsync|<product id>|<filename>`
+    PlingV1Zsync {
+        product_id: String,
+        filename: String,
+    },
+    /// Any other update information string, including a known transport with
+    /// missing or empty fields
+    Unknown(String),
+}
+
+impl UpdateInfo {
+    /// Gets the URL of the .zsync file, when the update information is a plain zsync URL.
+    /// The other transports need a lookup on their service to find the URL.
+    ///
+    /// # Returns
+    /// The .zsync URL, if known
+    pub fn zsync_url(&self) -> Option<&str> {
+        match self {
+            UpdateInfo::Zsync { url } => Some(url),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for UpdateInfo {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split('|').collect::<Vec<_>>();
+        let info = match parts.as_slice() {
+            _ if parts.iter().any(|part| part.is_empty()) => UpdateInfo::Unknown(s.to_string()),
+            ["zsync", url] => UpdateInfo::Zsync {
+                url: url.to_string(),
+            },
+            ["gh-releases-zsync", username, repository, release, filename] => {
+                UpdateInfo::GhReleasesZsync {
+                    username: username.to_string(),
+                    repository: repository.to_string(),
+                    release: release.to_string(),
+                    filename: filename.to_string(),
+                }
+            }
+            ["pling-v1-zsync", product_id, filename] => UpdateInfo::PlingV1Zsync {
+                product_id: product_id.to_string(),
+                filename: filename.to_string(),
+            },
+            _ => UpdateInfo::Unknown(s.to_string()),
+        };
+        Ok(info)
+    }
+}
+
+impl fmt::Display for UpdateInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateInfo::Zsync { url } => write!(f, "zsync|{}", url),
+            UpdateInfo::GhReleasesZsync {
+                username,
+                repository,
+                release,
+                filename,
+            } => write!(
+                f,
+                "gh-releases-zsync|{}|{}|{}|{}",
+                username, repository, release, filename
+            ),
+            UpdateInfo::PlingV1Zsync {
+                product_id,
+                filename,
+            } => write!(f, "pling-v1-zsync|{}|{}", product_id, filename),
+            UpdateInfo::Unknown(info) => write!(f, "{}", info),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> UpdateInfo {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_transports() {
+        assert_eq!(
+            parse("zsync|https://example.com/app.AppImage.zsync"),
+            UpdateInfo::Zsync {
+                url: "https://example.com/app.AppImage.zsync".into()
+            }
+        );
+        assert_eq!(
+            parse("gh-releases-zsync|user|repo|latest|App-*x86_64.AppImage.zsync"),
+            UpdateInfo::GhReleasesZsync {
+                username: "user".into(),
+                repository: "repo".into(),
+                release: "latest".into(),
+                filename: "App-*x86_64.AppImage.zsync".into(),
+            }
+        );
+        assert_eq!(
+            parse("pling-v1-zsync|1234|App.AppImage.zsync"),
+            UpdateInfo::PlingV1Zsync {
+                product_id: "1234".into(),
+                filename: "App.AppImage.zsync".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_transports() {
+        assert_eq!(
+            parse("bintray-zsync|a|b|c|d"),
+            UpdateInfo::Unknown("bintray-zsync|a|b|c|d".into())
+        );
+        // Wrong number of fields for the transport
+        assert_eq!(
+            parse("gh-releases-zsync|user|repo"),
+            UpdateInfo::Unknown("gh-releases-zsync|user|repo".into())
+        );
+        // Empty fields
+        assert_eq!(parse("zsync|"), UpdateInfo::Unknown("zsync|".into()));
+        assert_eq!(
+            parse("pling-v1-zsync||a.zsync"),
+            UpdateInfo::Unknown("pling-v1-zsync||a.zsync".into())
+        );
+    }
+
+    #[test]
+    fn round_trips_through_display() {
+        for info in [
+            "zsync|https://example.com/a.zsync",
+            "gh-releases-zsync|user|repo|latest|a.zsync",
+            "pling-v1-zsync|1|a.zsync",
+            "something else",
+        ] {
+            assert_eq!(parse(info).to_string(), info);
+        }
+    }
+
+    #[test]
+    fn only_plain_zsync_has_url() {
+        assert_eq!(
+            parse("zsync|https://example.com/a.zsync").zsync_url(),
+            Some("https://example.com/a.zsync")
+        );
+        assert_eq!(parse("pling-v1-zsync|1|a.zsync").zsync_url(), None);
+    }
+}
@@ -0,0 +1,301 @@
+use crate::error::SquishyError;
+
+use super::Result;
+
+/// Fetches the contents of a URL, e.g. through an HTTP client. squishy doesn't do
+/// any networking itself, so updaters provide their own fetcher.
+pub trait ZsyncFetcher {
+    /// Fetches the contents of the URL.
+    ///
+    /// # Arguments
+    /// * `url` - The URL to fetch
+    ///
+    /// # Returns
+    /// The fetched bytes, or an error if the URL can't be fetched
+    fn fetch(&self, url: &str) -> std::io::Result<Vec<u8>>;
+}
+
+impl<F> ZsyncFetcher for F
+where
+    F: Fn(&str) -> std::io::Result<Vec<u8>>,
+{
+    fn fetch(&self, url: &str) -> std::io::Result<Vec<u8>> {
+        self(url)
+    }
+}
+
+/// Checksums of a single block of the target file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockChecksum {
+    /// Rolling checksum, truncated to the `Hash-Lengths` rsum bytes
+    pub rsum: u32,
+    /// MD4 checksum, truncated to the `Hash-Lengths` checksum bytes
+    pub checksum: Vec<u8>,
+}
+
+/// Parsed .zsync control file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ZsyncFile {
+    /// `zsync` version that made the file
+    pub version: Option<String>,
+    /// `Filename` of the target file
+    pub filename: Option<String>,
+    /// `MTime` of the target file, as written in the file
+    pub mtime: Option<String>,
+    /// `Blocksize`
+    pub block_size: u64,
+    /// `Length` of the target file
+    pub length: u64,
+    /// `Hash-Lengths`: number of consecutive matching blocks required
+    pub seq_matches: u8,
+    /// `Hash-Lengths`: bytes of the rolling checksum stored per block
+    pub rsum_bytes: u8,
+    /// `Hash-Lengths`: bytes of the MD4 checksum stored per block
+    pub checksum_bytes: u8,
+    /// `URL`s of the target file, as written in the file
+    pub urls: Vec<String>,
+    /// `SHA-1` of the target file, hex encoded
+    pub sha1: Option<String>,
+    /// All the header fields in file order
+    pub headers: Vec<(String, String)>,
+    /// Checksums of the blocks of the target file
+    pub blocks: Vec<BlockChecksum>,
+}
+
+impl ZsyncFile {
+    /// Parses a .zsync control file: the header, followed by the block checksums.
+    ///
+    /// # Arguments
+    /// * `data` - Contents of the .zsync file
+    ///
+    /// # Returns
+    /// The parsed zsync file, or an error if it is malformed
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let invalid = |msg: &str| SquishyError::InvalidZsync(msg.to_owned());
+
+        let header_end = data
+            .windows(2)
+            .position(|window| window == b"\n\n")
+            .ok_or_else(|| invalid("Missing end of header"))?;
+        let header = String::from_utf8_lossy(&data[..header_end]);
+
+        let mut zsync = ZsyncFile {
+            seq_matches: 1,
+            rsum_bytes: 4,
+            checksum_bytes: 16,
+            ..Default::default()
+        };
+        for line in header.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                return Err(invalid(&format!("Invalid header line: {}", line)));
+            };
+            let value = value.trim().to_owned();
+            match key {
+                "zsync" => zsync.version = Some(value.clone()),
+                "Filename" => zsync.filename = Some(value.clone()),
+                "MTime" => zsync.mtime = Some(value.clone()),
+                "Blocksize" => {
+                    zsync.block_size = value
+                        .parse()
+                        .ok()
+                        .filter(|&size| size > 0)
+                        .ok_or_else(|| invalid("Invalid Blocksize"))?
+                }
+                "Length" => zsync.length = value.parse().map_err(|_| invalid("Invalid Length"))?,
+                "Hash-Lengths" => {
+                    let lengths = value
+                        .split(',')
+                        .map(|length| length.trim().parse::<u8>())
+                        .collect::<std::result::Result<Vec<_>, _>>()
+                        .map_err(|_| invalid("Invalid Hash-Lengths"))?;
+                    let [seq_matches, rsum_bytes, checksum_bytes] = lengths[..] else {
+                        return Err(invalid("Invalid Hash-Lengths"));
+                    };
+                    if !(1..=2).contains(&seq_matches)
+                        || !(1..=4).contains(&rsum_bytes)
+                        || !(3..=16).contains(&checksum_bytes)
+                    {
+                        return Err(invalid("Invalid Hash-Lengths"));
+                    }
+                    zsync.seq_matches = seq_matches;
+                    zsync.rsum_bytes = rsum_bytes;
+                    zsync.checksum_bytes = checksum_bytes;
+                }
+                "URL" => zsync.urls.push(value.clone()),
+                "SHA-1" => zsync.sha1 = Some(value.to_lowercase()),
+                _ => {}
+            }
+            zsync.headers.push((key.to_owned(), value));
+        }
+
+        if zsync.block_size == 0 {
+            return Err(invalid("Missing Blocksize"));
+        }
+
+        let entry_size = zsync.rsum_bytes as usize + zsync.checksum_bytes as usize;
+        let table = &data[header_end + 2..];
+        let block_count =
+            usize::try_from(zsync.block_count()).map_err(|_| invalid("Invalid Length"))?;
+        let table_size = block_count
+            .checked_mul(entry_size)
+            .ok_or_else(|| invalid("Invalid Length"))?;
+        if table.len() < table_size {
+            return Err(invalid("Truncated block checksums"));
+        }
+        zsync.blocks = table
+            .chunks_exact(entry_size)
+            .take(block_count)
+            .map(|entry| {
+                let (rsum, checksum) = entry.split_at(zsync.rsum_bytes as usize);
+                let mut rsum_raw = [0_u8; 4];
+                rsum_raw[4 - rsum.len()..].copy_from_slice(rsum);
+                BlockChecksum {
+                    rsum: u32::from_be_bytes(rsum_raw),
+                    checksum: checksum.to_vec(),
+                }
+            })
+            .collect();
+
+        Ok(zsync)
+    }
+
+    /// Number of blocks in the target file.
+    pub fn block_count(&self) -> u64 {
+        self.length.div_ceil(self.block_size)
+    }
+
+    /// Resolves the target file URLs against the URL the .zsync file was fetched
+    /// from, as relative URLs are allowed.
+    ///
+    /// # Arguments
+    /// * `zsync_url` - URL of the .zsync file
+    ///
+    /// # Returns
+    /// The absolute URLs of the target file
+    pub fn resolve_urls(&self, zsync_url: &str) -> Vec<String> {
+        self.urls
+            .iter()
+            .map(|url| resolve_url(zsync_url, url))
+            .collect()
+    }
+}
+
+/// Resolves a possibly relative URL against a base URL.
+///
+/// # Arguments
+/// * `base` - The absolute base URL
+/// * `url` - The URL to resolve
+///
+/// # Returns
+/// The absolute URL
+fn resolve_url(base: &str, url: &str) -> String {
+    if url.contains("://") {
+        return url.to_owned();
+    }
+    let base = base.split(['?', '#']).next().unwrap_or(base);
+    let origin_end = base
+        .find("://")
+        .and_then(|scheme_end| {
+            base[scheme_end + 3..]
+                .find('/')
+                .map(|pos| scheme_end + 3 + pos)
+        })
+        .unwrap_or(base.len());
+
+    if let Some(path) = url.strip_prefix('/') {
+        format!("{}/{}", &base[..origin_end], path)
+    } else {
+        let dir_end = base[origin_end..]
+            .rfind('/')
+            .map_or(base.len(), |pos| origin_end + pos);
+        format!("{}/{}", &base[..dir_end], url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zsync(header: &str, table: &[u8]) -> Vec<u8> {
+        [header.as_bytes(), b"\n\n", table].concat()
+    }
+
+    fn error(data: &[u8]) -> String {
+        match ZsyncFile::parse(data) {
+            Err(SquishyError::InvalidZsync(msg)) => msg,
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_header_and_blocks() {
+        let header = "zsync: 0.6.2\nFilename: App.AppImage\nBlocksize: 2048\nLength: 3000\n\
+                      Hash-Lengths: 2,2,3\nURL: App.AppImage\nSHA-1: ABCDEF";
+        let table = [0, 1, 0xaa, 0xbb, 0xcc, 0, 2, 0xdd, 0xee, 0xff];
+        let zsync = ZsyncFile::parse(&zsync(header, &table)).unwrap();
+
+        assert_eq!(zsync.version.as_deref(), Some("0.6.2"));
+        assert_eq!(zsync.filename.as_deref(), Some("App.AppImage"));
+        assert_eq!(zsync.block_count(), 2);
+        assert_eq!(
+            (zsync.seq_matches, zsync.rsum_bytes, zsync.checksum_bytes),
+            (2, 2, 3)
+        );
+        assert_eq!(zsync.sha1.as_deref(), Some("abcdef"));
+        assert_eq!(
+            zsync.blocks,
+            [
+                BlockChecksum {
+                    rsum: 1,
+                    checksum: vec![0xaa, 0xbb, 0xcc]
+                },
+                BlockChecksum {
+                    rsum: 2,
+                    checksum: vec![0xdd, 0xee, 0xff]
+                },
+            ]
+        );
+        assert_eq!(zsync.headers.len(), 7);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert_eq!(error(b"Blocksize: 2048\n"), "Missing end of header");
+        assert_eq!(error(&zsync("Length: 1", &[])), "Missing Blocksize");
+        assert_eq!(error(&zsync("Blocksize: 0", &[])), "Invalid Blocksize");
+        assert_eq!(
+            error(&zsync("Blocksize: 1\nHash-Lengths: 1,5,16", &[])),
+            "Invalid Hash-Lengths"
+        );
+        assert_eq!(
+            error(&zsync("Blocksize: 1\nLength: 2", &[0; 20])),
+            "Truncated block checksums"
+        );
+    }
+
+    #[test]
+    fn rejects_overflowing_lengths() {
+        let header = format!("Blocksize: 1\nLength: {}", u64::MAX);
+        assert!(matches!(
+            ZsyncFile::parse(&zsync(&header, &[])),
+            Err(SquishyError::InvalidZsync(_))
+        ));
+    }
+
+    #[test]
+    fn resolves_relative_urls() {
+        let base = "https://example.com/releases/App.zsync?raw=1";
+        assert_eq!(
+            resolve_url(base, "App.AppImage"),
+            "https://example.com/releases/App.AppImage"
+        );
+        assert_eq!(
+            resolve_url(base, "/files/App.AppImage"),
+            "https://example.com/files/App.AppImage"
+        );
+        assert_eq!(
+            resolve_url(base, "https://mirror.org/App.AppImage"),
+            "https://mirror.org/App.AppImage"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use crate::Result;
+
+/// How to handle an output path that already exists when writing an entry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CollisionStrategy {
+    /// Keep the existing file and don't write the entry
+    #[default]
+    Skip,
+    /// Replace the existing file. Existing directories are kept, so a directory
+    /// entry is merged into them
+    Overwrite,
+    /// Rename the existing file to `<name>.~N~`, with the first free N, then write
+    /// the entry. Existing directories are kept, like with `Overwrite`
+    Backup,
+}
+
+/// What was done to the output path before writing an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Collision {
+    /// Nothing existed at the path, the entry can be written
+    None,
+    /// The path exists and the entry must not be written
+    Skip,
+    /// The existing file was removed, or the existing directory is kept to write
+    /// a directory entry into
+    Replaced,
+    /// The existing file was renamed to the path held
+    BackedUp(PathBuf),
+}
+
+impl CollisionStrategy {
+    /// Prepares an output path for writing an entry. Symlinks at the path are
+    /// handled themselves rather than followed, so writing never goes through a
+    /// symlink that was already there.
+    ///
+    /// # Arguments
+    /// * `dest` - Path the entry is about to be written to
+    /// * `is_dir` - Whether the entry is a directory
+    ///
+    /// # Returns
+    /// What was done to the path, or an error if the existing file can't be removed
+    /// or renamed, or is a directory while the entry isn't
+    pub fn prepare<P: AsRef<Path>>(self, dest: P, is_dir: bool) -> Result<Collision> {
+        let dest = dest.as_ref();
+        let Ok(metadata) = dest.symlink_metadata() else {
+            return Ok(Collision::None);
+        };
+        if self == CollisionStrategy::Skip {
+            return Ok(Collision::Skip);
+        }
+        if metadata.is_dir() {
+            if is_dir {
+                return Ok(Collision::Replaced);
+            }
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} is a directory", dest.display()),
+            )
+            .into());
+        }
+
+        match self {
+            CollisionStrategy::Overwrite => {
+                fs::remove_file(dest)?;
+                Ok(Collision::Replaced)
+            }
+            _ => {
+                let backup = backup_path(dest);
+                fs::rename(dest, &backup)?;
+                Ok(Collision::BackedUp(backup))
+            }
+        }
+    }
+}
+
+/// Finds a free numbered backup path for a file, like `cp --backup=numbered`.
+///
+/// # Arguments
+/// * `path` - Path of the file to back up
+///
+/// # Returns
+/// The first `<path>.~N~` that doesn't exist
+fn backup_path(path: &Path) -> PathBuf {
+    (1..)
+        .map(|n| {
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(format!(".~{}~", n));
+            PathBuf::from(backup)
+        })
+        .find(|backup| backup.symlink_metadata().is_err())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("squishy-collision-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn allows_missing_paths() {
+        let dir = temp_dir("missing");
+        for strategy in [
+            CollisionStrategy::Skip,
+            CollisionStrategy::Overwrite,
+            CollisionStrategy::Backup,
+        ] {
+            assert_eq!(
+                strategy.prepare(dir.join("file"), false).unwrap(),
+                Collision::None
+            );
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn skips_existing_paths() {
+        let dir = temp_dir("skip");
+        let file = dir.join("file");
+        fs::write(&file, "old").unwrap();
+        assert_eq!(
+            CollisionStrategy::Skip.prepare(&file, false).unwrap(),
+            Collision::Skip
+        );
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn replaces_files_and_keeps_directories() {
+        let dir = temp_dir("overwrite");
+        let file = dir.join("file");
+        fs::write(&file, "old").unwrap();
+        assert_eq!(
+            CollisionStrategy::Overwrite.prepare(&file, false).unwrap(),
+            Collision::Replaced
+        );
+        assert!(!file.exists());
+
+        let sub = dir.join("sub");
+        fs::create_dir(&sub).unwrap();
+        assert_eq!(
+            CollisionStrategy::Overwrite.prepare(&sub, true).unwrap(),
+            Collision::Replaced
+        );
+        assert!(sub.is_dir());
+        assert!(CollisionStrategy::Overwrite.prepare(&sub, false).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn removes_symlinks_without_following() {
+        let dir = temp_dir("symlink");
+        let target = dir.join("target");
+        fs::write(&target, "kept").unwrap();
+        let link = dir.join("link");
+        unix::fs::symlink(&target, &link).unwrap();
+
+        assert_eq!(
+            CollisionStrategy::Overwrite.prepare(&link, false).unwrap(),
+            Collision::Replaced
+        );
+        assert!(link.symlink_metadata().is_err());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "kept");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn backs_up_to_free_numbered_paths() {
+        let dir = temp_dir("backup");
+        let file = dir.join("file");
+        fs::write(dir.join("file.~1~"), "older").unwrap();
+        fs::write(&file, "old").unwrap();
+
+        let backup = dir.join("file.~2~");
+        assert_eq!(
+            CollisionStrategy::Backup.prepare(&file, false).unwrap(),
+            Collision::BackedUp(backup.clone())
+        );
+        assert!(!file.exists());
+        assert_eq!(fs::read_to_string(backup).unwrap(), "old");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -13,9 +13,42 @@ pub enum SquishyError {
     #[error("SquashFS error: {0}")]
     InvalidSquashFS(String),
 
+    #[error("DwarFS error: {0}")]
+    InvalidDwarFS(String),
+
+    #[error("ISO 9660 error: {0}")]
+    InvalidIso9660(String),
+
+    #[error("Snap error: {0}")]
+    InvalidSnap(String),
+
+    #[error("AppStream error: {0}")]
+    InvalidAppStream(String),
+
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+
+    #[error("Invalid name template: {0}")]
+    InvalidTemplate(String),
+
+    #[error("SVG error: {0}")]
+    InvalidSvg(String),
+
+    #[error("zsync error: {0}")]
+    InvalidZsync(String),
+
     #[error("Symlink error: {0}")]
     SymlinkError(String),
 
     #[error("File not found: {0}")]
     FileNotFound(PathBuf),
+
+    #[error("Not a regular file: {0}")]
+    NotAFile(PathBuf),
+
+    #[error("Unsupported operation: {0}")]
+    Unsupported(String),
+
+    #[error("Thread pool error: {0}")]
+    ThreadPool(String),
 }
@@ -0,0 +1,319 @@
+use std::{collections::HashMap, fs::File, os::unix::fs::FileExt, path::PathBuf};
+
+use crate::{error::SquishyError, Result, SquashFS};
+
+/// Bit of a metadata block header set when the block is stored uncompressed
+const METADATA_UNCOMPRESSED: u16 = 0x8000;
+/// Xattr index of inodes without extended attributes
+pub(crate) const NO_XATTRS: u32 = u32::MAX;
+
+/// Reads consecutive metadata blocks of a table, caching the decompressed blocks.
+pub(crate) struct MetadataReader<'s, 'a> {
+    squashfs: &'s SquashFS<'a>,
+    source: &'s File,
+    /// Absolute position of the table in the source
+    table: u64,
+    /// Decompressed blocks by their position in the table, along with the
+    /// position of the next block
+    blocks: HashMap<u64, (Vec<u8>, u64)>,
+}
+
+impl<'s, 'a> MetadataReader<'s, 'a> {
+    pub(crate) fn new(squashfs: &'s SquashFS<'a>, source: &'s File, table: u64) -> Self {
+        MetadataReader {
+            squashfs,
+            source,
+            table,
+            blocks: HashMap::new(),
+        }
+    }
+
+    /// Reads the metadata block at a position in the table.
+    ///
+    /// # Arguments
+    /// * `block` - Position of the block, relative to the start of the table
+    ///
+    /// # Returns
+    /// The decompressed block and the position of the next block, or an error if
+    /// it can't be read
+    fn block(&mut self, block: u64) -> Result<&(Vec<u8>, u64)> {
+        if !self.blocks.contains_key(&block) {
+            let mut header = [0_u8; 2];
+            self.source.read_exact_at(&mut header, self.table + block)?;
+            let header = u16::from_le_bytes(header);
+            let size = header & !METADATA_UNCOMPRESSED;
+            let data = self.squashfs.read_raw(
+                self.source,
+                self.table + block + 2,
+                size as u32,
+                header & METADATA_UNCOMPRESSED != 0,
+            )?;
+            self.blocks.insert(block, (data, block + 2 + size as u64));
+        }
+        Ok(&self.blocks[&block])
+    }
+
+    /// Reads bytes that may span several metadata blocks, and moves the position
+    /// past them.
+    ///
+    /// # Arguments
+    /// * `position` - Block position and offset in the decompressed block
+    /// * `len` - Number of bytes to read
+    ///
+    /// # Returns
+    /// The bytes, or an error if the table ends before them
+    pub(crate) fn read(&mut self, position: &mut (u64, usize), len: usize) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(len);
+        while bytes.len() < len {
+            let (data, next) = self.block(position.0)?;
+            if position.1 >= data.len() {
+                if data.is_empty() {
+                    return Err(invalid("Empty metadata block"));
+                }
+                *position = (*next, position.1 - data.len());
+                continue;
+            }
+            let take = (len - bytes.len()).min(data.len() - position.1);
+            bytes.extend_from_slice(&data[position.1..position.1 + take]);
+            position.1 += take;
+        }
+        Ok(bytes)
+    }
+
+    pub(crate) fn read_u16(&mut self, position: &mut (u64, usize)) -> Result<u16> {
+        let bytes = self.read(position, 2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(crate) fn read_u32(&mut self, position: &mut (u64, usize)) -> Result<u32> {
+        let bytes = self.read(position, 4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub(crate) fn read_u64(&mut self, position: &mut (u64, usize)) -> Result<u64> {
+        let bytes = self.read(position, 8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+/// Error for tables that can't be parsed.
+pub(crate) fn invalid(msg: &str) -> SquishyError {
+    SquishyError::InvalidSquashFS(format!("Invalid metadata: {}", msg))
+}
+
+/// Splits an inode or xattr reference into the block position and the offset in it.
+pub(crate) fn reference_position(reference: u64) -> (u64, usize) {
+    (reference >> 16, (reference & 0xffff) as usize)
+}
+
+/// Directory listing location of a directory inode
+struct DirLocation {
+    block: u64,
+    offset: usize,
+    /// Size of the listing in bytes
+    size: u32,
+}
+
+/// Fields of an inode that backhand doesn't keep
+pub(crate) struct Inode {
+    /// Inode number, shared by the paths of hard links
+    pub number: u32,
+    /// Whether the inode is a regular file
+    pub is_file: bool,
+    /// Index in the xattr table, `NO_XATTRS` if the inode has no extended attributes
+    pub xattr_index: u32,
+    /// Location of the listing, for directories
+    dir: Option<DirLocation>,
+}
+
+impl SquashFS<'_> {
+    /// Reads the inodes of all entries from the inode and directory tables.
+    ///
+    /// # Arguments
+    /// * `purpose` - What the inodes are read for, to explain errors
+    ///
+    /// # Returns
+    /// The inodes with the paths of the entries, in the same form as
+    /// `SquashFSEntry::path`, or an error if the SquashFS wasn't opened from a
+    /// path, or the tables can't be read.
+    pub(crate) fn read_inodes(&self, purpose: &str) -> Result<Vec<(PathBuf, Inode)>> {
+        let source = self.source_for(purpose)?;
+        let mut inodes =
+            MetadataReader::new(self, source, self.offset + self.superblock.inode_table);
+        let mut dirs = MetadataReader::new(self, source, self.offset + self.superblock.dir_table);
+
+        let mut read = Vec::new();
+        let mut pending = vec![(PathBuf::from("/"), self.superblock.root_inode)];
+        while let Some((path, reference)) = pending.pop() {
+            let inode = read_inode(&mut inodes, reference)?;
+            if let Some(dir) = &inode.dir {
+                for (name, child) in read_dir(&mut dirs, dir)? {
+                    pending.push((path.join(name), child));
+                }
+            }
+            read.push((path, inode));
+        }
+        Ok(read)
+    }
+
+    /// Returns the source file, needed to read tables that backhand doesn't keep.
+    ///
+    /// # Arguments
+    /// * `purpose` - What the source is needed for, to explain the error
+    ///
+    /// # Returns
+    /// The source, or an error if the SquashFS wasn't opened from a path
+    pub(crate) fn source_for(&self, purpose: &str) -> Result<&File> {
+        self.source.as_ref().ok_or_else(|| {
+            SquishyError::Unsupported(format!(
+                "{} requires a SquashFS opened from a path",
+                purpose
+            ))
+        })
+    }
+
+    /// Reads the inode numbers of all entries. Hard links share the number of
+    /// their inode.
+    ///
+    /// # Returns
+    /// The inode numbers by entry path, in the same form as `SquashFSEntry::path`,
+    /// or an error if the SquashFS wasn't opened from a path, or the tables can't
+    /// be read.
+    pub fn inode_numbers(&self) -> Result<HashMap<PathBuf, u32>> {
+        Ok(self
+            .read_inodes("Reading inode numbers")?
+            .into_iter()
+            .map(|(path, inode)| (path, inode.number))
+            .collect())
+    }
+
+    /// Finds the files with several paths, which are hard links to the same inode.
+    ///
+    /// # Returns
+    /// The paths of each hard linked file, sorted, or an error if the SquashFS
+    /// wasn't opened from a path, or the tables can't be read.
+    pub fn hard_links(&self) -> Result<Vec<Vec<PathBuf>>> {
+        let mut paths = HashMap::<u32, Vec<PathBuf>>::new();
+        for (path, inode) in self.read_inodes("Finding hard links")? {
+            if inode.is_file {
+                paths.entry(inode.number).or_default().push(path);
+            }
+        }
+        let mut links = paths
+            .into_values()
+            .filter(|paths| paths.len() > 1)
+            .map(|mut paths| {
+                paths.sort();
+                paths
+            })
+            .collect::<Vec<_>>();
+        links.sort();
+        Ok(links)
+    }
+}
+
+/// Reads the fields of an inode that backhand doesn't keep.
+///
+/// # Arguments
+/// * `inodes` - Reader of the inode table
+/// * `reference` - Reference of the inode
+///
+/// # Returns
+/// The inode, or an error if it can't be read
+fn read_inode(inodes: &mut MetadataReader, reference: u64) -> Result<Inode> {
+    let mut position = reference_position(reference);
+    let inode_type = inodes.read_u16(&mut position)?;
+    // Permissions, uid, gid and mtime
+    inodes.read(&mut position, 10)?;
+    let number = inodes.read_u32(&mut position)?;
+    let mut inode = Inode {
+        number,
+        is_file: matches!(inode_type, 2 | 9),
+        xattr_index: NO_XATTRS,
+        dir: None,
+    };
+    match inode_type {
+        1 => {
+            let block = inodes.read_u32(&mut position)? as u64;
+            inodes.read_u32(&mut position)?;
+            let size = inodes.read_u16(&mut position)? as u32;
+            let offset = inodes.read_u16(&mut position)? as usize;
+            inode.dir = Some(DirLocation {
+                block,
+                offset,
+                size,
+            });
+        }
+        8 => {
+            inodes.read_u32(&mut position)?;
+            let size = inodes.read_u32(&mut position)?;
+            let block = inodes.read_u32(&mut position)? as u64;
+            inodes.read_u32(&mut position)?;
+            inodes.read_u16(&mut position)?;
+            let offset = inodes.read_u16(&mut position)? as usize;
+            inode.xattr_index = inodes.read_u32(&mut position)?;
+            inode.dir = Some(DirLocation {
+                block,
+                offset,
+                size,
+            });
+        }
+        9 => {
+            // Blocks start, file size, sparse, link count, fragment and offset
+            inodes.read(&mut position, 36)?;
+            inode.xattr_index = inodes.read_u32(&mut position)?;
+        }
+        10 => {
+            inodes.read_u32(&mut position)?;
+            let target_size = inodes.read_u32(&mut position)?;
+            inodes.read(&mut position, target_size as usize)?;
+            inode.xattr_index = inodes.read_u32(&mut position)?;
+        }
+        11 | 12 => {
+            inodes.read(&mut position, 8)?;
+            inode.xattr_index = inodes.read_u32(&mut position)?;
+        }
+        13 | 14 => {
+            inodes.read_u32(&mut position)?;
+            inode.xattr_index = inodes.read_u32(&mut position)?;
+        }
+        2..=7 => {}
+        _ => return Err(invalid(&format!("Unknown inode type {}", inode_type))),
+    }
+    Ok(inode)
+}
+
+/// Reads the entries of a directory listing.
+///
+/// # Arguments
+/// * `dirs` - Reader of the directory table
+/// * `dir` - Location of the listing
+///
+/// # Returns
+/// The names and inode references of the entries
+fn read_dir(dirs: &mut MetadataReader, dir: &DirLocation) -> Result<Vec<(String, u64)>> {
+    // The stored size counts 3 bytes for the `.` and `..` entries, which aren't stored
+    let size = dir.size.saturating_sub(3) as usize;
+    let mut position = (dir.block, dir.offset);
+    let mut read = 0;
+    let mut entries = Vec::new();
+    while read < size {
+        let count = dirs.read_u32(&mut position)? as usize + 1;
+        let start = dirs.read_u32(&mut position)? as u64;
+        dirs.read_u32(&mut position)?;
+        read += 12;
+        for _ in 0..count {
+            let offset = dirs.read_u16(&mut position)? as u64;
+            // Inode number difference and type
+            dirs.read(&mut position, 4)?;
+            let name_size = dirs.read_u16(&mut position)? as usize + 1;
+            let name = dirs.read(&mut position, name_size)?;
+            read += 8 + name_size;
+            entries.push((
+                String::from_utf8_lossy(&name).into_owned(),
+                (start << 16) | offset,
+            ));
+        }
+    }
+    Ok(entries)
+}
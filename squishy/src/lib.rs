@@ -1,28 +1,66 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fs::{self, File, Permissions},
-    io::{BufReader, BufWriter, Read, Seek},
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom},
     os::unix::fs::PermissionsExt,
     path::{Path, PathBuf},
 };
 
-use backhand::{kind::Kind, BasicFile, FilesystemReader, InnerNode, NodeHeader};
+use backhand::{
+    compression::CompressionOptions, kind::Kind, BasicFile, BufReadSeek, FilesystemReader,
+    InnerNode, NodeHeader, Squashfs, SuperBlock,
+};
 use error::SquishyError;
 
+use backhand::compression::{CompressionAction, DefaultCompressor};
 #[cfg(feature = "rayon")]
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use std::os::unix::fs::FileExt;
 
 #[cfg(feature = "appimage")]
 pub mod appimage;
 
+pub mod collision;
 pub mod error;
+mod inode;
+pub mod progress;
+pub mod scan;
+#[cfg(feature = "snap")]
+pub mod snap;
+pub mod xattr;
 
 pub type Result<T> = std::result::Result<T, SquishyError>;
 
+#[cfg(feature = "rayon")]
+/// Configures the number of threads used by parallel operations such as
+/// `par_entries` and parallel extraction. It must be called before any parallel
+/// work is done, as the thread pool can only be initialized once.
+///
+/// # Arguments
+/// * `threads` - Number of threads to use. `0` lets rayon choose based on available CPUs.
+///
+/// # Returns
+/// An empty result, or an error if the thread pool was already initialized.
+pub fn set_threads(threads: usize) -> Result<()> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()
+        .map_err(|e| SquishyError::ThreadPool(e.to_string()))
+}
+
 /// The SquashFS struct provides an interface for reading and interacting with a SquashFS filesystem.
 /// It wraps a FilesystemReader, which is responsible for reading the contents of the SquashFS file.
 pub struct SquashFS<'a> {
     reader: FilesystemReader<'a>,
+    /// Handle to the underlying file, used for positional reads of raw data blocks.
+    /// Only available when the SquashFS is opened from a path.
+    source: Option<File>,
+    /// Offset of the SquashFS data within the source.
+    offset: u64,
+    /// Superblock of the SquashFS image.
+    superblock: SuperBlock,
+    /// Compression options stored in the image, if any.
+    compression_options: Option<CompressionOptions>,
 }
 
 /// The SquashFSEntry struct represents a single file or directory entry within the SquashFS filesystem.
@@ -56,13 +94,28 @@ impl<'a> SquashFS<'a> {
     where
         R: Read + Seek + Send + 'a,
     {
-        let offset = offset.unwrap_or(
-            Self::find_squashfs_offset(&mut reader).map_err(|_| SquishyError::NoSquashFsFound)?,
-        );
+        let offset = match offset {
+            Some(offset) => offset,
+            None => Self::find_squashfs_offset(&mut reader)
+                .map_err(|_| SquishyError::NoSquashFsFound)?,
+        };
+        let mut reader: Box<dyn BufReadSeek + 'a> = Box::new(reader);
+        reader.seek(SeekFrom::Start(offset))?;
+        let kind = Kind::from_target("le_v4_0").unwrap();
+        let (superblock, compression_options) =
+            Squashfs::superblock_and_compression_options(&mut reader, &kind)
+                .map_err(|e| SquishyError::InvalidSquashFS(e.to_string()))?;
+        reader.seek(SeekFrom::Start(offset))?;
         let reader = FilesystemReader::from_reader_with_offset(reader, offset)
             .map_err(|e| SquishyError::InvalidSquashFS(e.to_string()))?;
 
-        Ok(Self { reader })
+        Ok(Self {
+            reader,
+            source: None,
+            offset,
+            superblock,
+            compression_options,
+        })
     }
 
     /// Creates a new SquashFS instance from a file path. Tries to find offset automatically.
@@ -73,9 +126,14 @@ impl<'a> SquashFS<'a> {
     /// # Returns
     /// A SquashFS instance if the SquashFS data is found and valid, or an error if it is not.
     pub fn from_path<P: AsRef<Path>>(path: &'a P) -> Result<Self> {
-        let file = File::open(path).unwrap();
+        let file = File::open(path)?;
+        let source = file.try_clone()?;
         let reader = BufReader::new(file);
-        SquashFS::new(reader, None)
+        let squashfs = SquashFS::new(reader, None)?;
+        Ok(Self {
+            source: Some(source),
+            ..squashfs
+        })
     }
 
     /// Creates a new SquashFS instance from a file path.
@@ -87,9 +145,31 @@ impl<'a> SquashFS<'a> {
     /// # Returns
     /// A SquashFS instance if the SquashFS data is found and valid, or an error if it is not.
     pub fn from_path_with_offset<P: AsRef<Path>>(path: &'a P, offset: u64) -> Result<Self> {
-        let file = File::open(path).unwrap();
+        let file = File::open(path)?;
+        let source = file.try_clone()?;
         let reader = BufReader::new(file);
-        SquashFS::new(reader, Some(offset))
+        let squashfs = SquashFS::new(reader, Some(offset))?;
+        Ok(Self {
+            source: Some(source),
+            ..squashfs
+        })
+    }
+
+    /// Returns the offset of the SquashFS data within the source.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the superblock of the SquashFS image, holding the compression
+    /// algorithm, block size, inode and fragment counts and creation time.
+    pub fn superblock(&self) -> &SuperBlock {
+        &self.superblock
+    }
+
+    /// Returns the compression options stored in the SquashFS image, or None if
+    /// the image uses the defaults of its compression algorithm.
+    pub fn compression_options(&self) -> Option<&CompressionOptions> {
+        self.compression_options.as_ref()
     }
 
     /// Finds the starting offset of the SquashFS data within the input file.
@@ -116,7 +196,7 @@ impl<'a> SquashFS<'a> {
     }
 
     /// Returns an iterator over all the entries in the SquashFS filesystem.
-    pub fn entries(&self) -> impl Iterator<Item = SquashFSEntry> + '_ {
+    pub fn entries(&self) -> impl Iterator<Item = SquashFSEntry<'_>> + '_ {
         self.reader.files().map(|node| {
             let size = match &node.inner {
                 InnerNode::File(file) => file.basic.file_size,
@@ -143,7 +223,7 @@ impl<'a> SquashFS<'a> {
 
     #[cfg(feature = "rayon")]
     /// Returns a parallel iterator over all the entries in the SquashFS filesystem.
-    pub fn par_entries(&self) -> impl ParallelIterator<Item = SquashFSEntry> + '_ {
+    pub fn par_entries(&self) -> impl ParallelIterator<Item = SquashFSEntry<'_>> + '_ {
         self.reader
             .files()
             .map(|node| {
@@ -177,7 +257,7 @@ impl<'a> SquashFS<'a> {
     ///
     /// # Arguments
     /// * `predicate` - A function that takes a &Path and returns a bool, indicating whether the entry should be included.
-    pub fn find_entries<F>(&self, predicate: F) -> impl Iterator<Item = SquashFSEntry> + '_
+    pub fn find_entries<F>(&self, predicate: F) -> impl Iterator<Item = SquashFSEntry<'_>> + '_
     where
         F: Fn(&Path) -> bool + 'a,
     {
@@ -197,12 +277,9 @@ impl<'a> SquashFS<'a> {
         for node in self.reader.files() {
             if node.fullpath == path {
                 if let InnerNode::File(file) = &node.inner {
-                    let mut reader = self.reader.file(&file.basic).reader().bytes();
-                    let mut contents = Vec::new();
-
-                    while let Some(Ok(byte)) = reader.next() {
-                        contents.push(byte);
-                    }
+                    let mut reader = self.reader.file(&file.basic).reader();
+                    let mut contents = Vec::with_capacity(file.basic.file_size as usize);
+                    reader.read_to_end(&mut contents)?;
 
                     return Ok(contents);
                 }
@@ -223,11 +300,7 @@ impl<'a> SquashFS<'a> {
     /// An empty result, or an error if the file cannot be read or written.
     pub fn write_file<P: AsRef<Path>>(&self, file: &BasicFile, dest: P) -> Result<()> {
         let output_file = File::create(dest)?;
-        let mut writer = BufWriter::with_capacity(file.file_size as usize, &output_file);
-        let file = self.reader.file(file);
-        let mut reader = file.reader();
-        std::io::copy(&mut reader, &mut writer)?;
-        Ok(())
+        self.write_contents(file, &output_file)
     }
 
     /// Writes the contents of the specified file from the SquashFS filesystem
@@ -249,13 +322,284 @@ impl<'a> SquashFS<'a> {
         let output_file = File::create(&dest)?;
         let mode = u32::from(header.permissions);
         fs::set_permissions(dest, Permissions::from_mode(mode))?;
-        let mut writer = BufWriter::with_capacity(file.file_size as usize, &output_file);
+        self.write_contents(file, &output_file)
+    }
+
+    /// Writes the contents of the file to an already opened output file.
+    /// Files spanning multiple data blocks are decompressed in parallel when possible.
+    ///
+    /// # Arguments
+    /// * `file` - The basic file within the SquashFS filesystem.
+    /// * `output_file` - The file to write the contents to.
+    ///
+    /// # Returns
+    /// An empty result, or an error if the file cannot be read or written.
+    fn write_contents(&self, file: &BasicFile, output_file: &File) -> Result<()> {
+        #[cfg(feature = "rayon")]
+        if self.source.is_some() && file.block_sizes.len() > 1 {
+            return self.par_write_blocks(file, output_file);
+        }
+
+        let mut writer = BufWriter::with_capacity(file.file_size as usize, output_file);
         let file = self.reader.file(file);
         let mut reader = file.reader();
         std::io::copy(&mut reader, &mut writer)?;
         Ok(())
     }
 
+    #[cfg(feature = "rayon")]
+    /// Writes the contents of the specified file from the SquashFS filesystem
+    /// to the specified destination path, decompressing its data blocks in parallel.
+    ///
+    /// Falls back to sequential decompression when the SquashFS wasn't opened from a path.
+    ///
+    /// # Arguments
+    /// * `file` - The basic file within the SquashFS filesystem.
+    /// * `dest` - The destination path to write the file to.
+    ///
+    /// # Returns
+    /// An empty result, or an error if the file cannot be read or written.
+    pub fn par_write_file<P: AsRef<Path>>(&self, file: &BasicFile, dest: P) -> Result<()> {
+        let output_file = File::create(dest)?;
+        if self.source.is_some() {
+            self.par_write_blocks(file, &output_file)
+        } else {
+            self.write_contents(file, &output_file)
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    /// Decompresses the data blocks of the file across the thread pool and writes
+    /// each of them at its offset in the output file. The fragment (tail end), if any,
+    /// is written last.
+    ///
+    /// # Arguments
+    /// * `file` - The basic file within the SquashFS filesystem.
+    /// * `output_file` - The file to write the contents to.
+    ///
+    /// # Returns
+    /// An empty result, or an error if the file cannot be read or written.
+    fn par_write_blocks(&self, file: &BasicFile, output_file: &File) -> Result<()> {
+        let source = self
+            .source
+            .as_ref()
+            .ok_or_else(|| SquishyError::InvalidSquashFS("No source file available".into()))?;
+        let block_size = self.reader.block_size as u64;
+
+        // Sparse blocks (size 0) are left as holes, so the file has to be sized upfront
+        output_file.set_len(file.file_size as u64)?;
+
+        let mut position = self.offset + file.blocks_start as u64;
+        let blocks = file
+            .block_sizes
+            .iter()
+            .map(|block| {
+                let start = position;
+                position += block.size() as u64;
+                (start, *block)
+            })
+            .collect::<Vec<_>>();
+
+        blocks.into_par_iter().enumerate().try_for_each(
+            |(index, (start, block))| -> Result<()> {
+                if block.size() == 0 {
+                    return Ok(());
+                }
+                let data = self.read_raw(source, start, block.size(), block.uncompressed())?;
+                output_file.write_all_at(&data, index as u64 * block_size)?;
+                Ok(())
+            },
+        )?;
+
+        if let Some(tail) = self.read_fragment_tail(source, file)? {
+            let tail_start = file.block_sizes.len() as u64 * block_size;
+            output_file.write_all_at(&tail, tail_start)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the tail end of the file stored in a fragment block.
+    ///
+    /// # Arguments
+    /// * `source` - The source file to read from.
+    /// * `file` - The basic file within the SquashFS filesystem.
+    ///
+    /// # Returns
+    /// The tail end of the file if it's stored in a fragment, or an error if it cannot be read.
+    fn read_fragment_tail(&self, source: &File, file: &BasicFile) -> Result<Option<Vec<u8>>> {
+        let Some(fragment) = self.reader.file(file).fragment() else {
+            return Ok(None);
+        };
+
+        let tail_len = self.tail_len(file)? as usize;
+        let data = self.read_raw(
+            source,
+            self.offset + fragment.start,
+            fragment.size.size(),
+            fragment.size.uncompressed(),
+        )?;
+        let frag_start = file.block_offset as usize;
+        let tail = data
+            .get(frag_start..frag_start + tail_len)
+            .ok_or_else(|| SquishyError::InvalidSquashFS("Fragment out of bounds".into()))?;
+        Ok(Some(tail.to_vec()))
+    }
+
+    /// Computes the length of the tail end of a file, the part after its data blocks.
+    ///
+    /// # Arguments
+    /// * `file` - The basic file within the SquashFS filesystem.
+    ///
+    /// # Returns
+    /// The length of the tail end, or an error if the data blocks cover more than the file size.
+    fn tail_len(&self, file: &BasicFile) -> Result<u64> {
+        let tail_start = file.block_sizes.len() as u64 * self.reader.block_size as u64;
+        (file.file_size as u64)
+            .checked_sub(tail_start)
+            .ok_or_else(|| {
+                SquishyError::InvalidSquashFS("File size smaller than its blocks".into())
+            })
+    }
+
+    /// Reads a byte range of the specified file from the SquashFS filesystem.
+    /// Only the data blocks covering the range are decompressed when possible.
+    ///
+    /// # Arguments
+    /// * `file` - The basic file within the SquashFS filesystem.
+    /// * `offset` - Offset within the file to start reading at.
+    /// * `length` - Maximum number of bytes to read.
+    ///
+    /// # Returns
+    /// The bytes read, which is shorter than `length` if the range goes past the end of the file,
+    /// or an error if the file cannot be read.
+    pub fn read_range(&self, file: &BasicFile, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let file_size = file.file_size as u64;
+        if offset >= file_size || length == 0 {
+            return Ok(Vec::new());
+        }
+        let end = file_size.min(offset.saturating_add(length));
+        let mut contents = Vec::with_capacity((end - offset) as usize);
+
+        let Some(source) = &self.source else {
+            let mut reader = self.reader.file(file).reader();
+            std::io::copy(&mut (&mut reader).take(offset), &mut std::io::sink())?;
+            reader.take(end - offset).read_to_end(&mut contents)?;
+            return Ok(contents);
+        };
+
+        let block_size = self.reader.block_size as u64;
+        let first_block = offset / block_size;
+        let last_block = (end - 1) / block_size;
+
+        let mut position = self.offset + file.blocks_start as u64;
+        for (index, block) in file.block_sizes.iter().enumerate() {
+            let index = index as u64;
+            if index > last_block {
+                break;
+            }
+            let start = position;
+            position += block.size() as u64;
+            if index < first_block {
+                continue;
+            }
+
+            let block_start = index * block_size;
+            let data = if block.size() == 0 {
+                vec![0_u8; block_size.min(file_size - block_start) as usize]
+            } else {
+                self.read_raw(source, start, block.size(), block.uncompressed())?
+            };
+            // Blocks can decompress to less than expected in corrupted images
+            let to = ((end - block_start) as usize).min(data.len());
+            let from = ((offset.max(block_start) - block_start) as usize).min(to);
+            contents.extend_from_slice(&data[from..to]);
+        }
+
+        let tail_start = file.block_sizes.len() as u64 * block_size;
+        if end > tail_start {
+            if let Some(tail) = self.read_fragment_tail(source, file)? {
+                let to = ((end - tail_start) as usize).min(tail.len());
+                let from = ((offset.max(tail_start) - tail_start) as usize).min(to);
+                contents.extend_from_slice(&tail[from..to]);
+            }
+        }
+
+        Ok(contents)
+    }
+
+    /// Reads a raw block from the source and decompresses it if required.
+    ///
+    /// # Arguments
+    /// * `source` - The source file to read from.
+    /// * `start` - Absolute position of the block in the source.
+    /// * `size` - Size of the block on disk.
+    /// * `uncompressed` - Whether the block is stored uncompressed.
+    ///
+    /// # Returns
+    /// The decompressed block data, or an error if it cannot be read.
+    fn read_raw(
+        &self,
+        source: &File,
+        start: u64,
+        size: u32,
+        uncompressed: bool,
+    ) -> Result<Vec<u8>> {
+        let mut raw = vec![0_u8; size as usize];
+        source.read_exact_at(&mut raw, start)?;
+        if uncompressed {
+            return Ok(raw);
+        }
+
+        let mut data = Vec::with_capacity(self.reader.block_size as usize);
+        DefaultCompressor
+            .decompress(&raw, &mut data, self.reader.compressor)
+            .map_err(|e| SquishyError::InvalidSquashFS(e.to_string()))?;
+        Ok(data)
+    }
+
+    /// Computes how many bytes each file takes up in the image once compressed:
+    /// its data blocks, plus a share of the fragment block holding its tail end.
+    /// A fragment is split between the files sharing it by the length of their tails.
+    ///
+    /// # Returns
+    /// The compressed sizes of the files by path, or an error if a file has more
+    /// data blocks than its size covers
+    pub fn compressed_sizes(&self) -> Result<HashMap<PathBuf, u64>> {
+        let mut fragment_tails = HashMap::<u32, u64>::new();
+        let files = self
+            .entries()
+            .filter_map(|entry| match entry.kind {
+                EntryKind::File(file) => Some((entry.path, file)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for (_, file) in &files {
+            if self.reader.file(file).fragment().is_some() {
+                *fragment_tails.entry(file.frag_index).or_default() += self.tail_len(file)?;
+            }
+        }
+
+        files
+            .into_iter()
+            .map(|(path, file)| {
+                let blocks = file
+                    .block_sizes
+                    .iter()
+                    .map(|block| block.size() as u64)
+                    .sum::<u64>();
+                let tail = match self.reader.file(file).fragment() {
+                    Some(fragment) => {
+                        let total = fragment_tails[&file.frag_index].max(1);
+                        fragment.size.size() as u64 * self.tail_len(file)? / total
+                    }
+                    None => 0,
+                };
+                Ok((path, blocks + tail))
+            })
+            .collect()
+    }
+
     /// Resolves the symlink chain starting from the specified entry,
     /// returning the final target entry or an error if a cycle is detected.
     ///
@@ -264,7 +608,7 @@ impl<'a> SquashFS<'a> {
     ///
     /// # Returns
     /// The final target entry, or None if the entry is not a symlink, or an error if a cycle is detected.
-    pub fn resolve_symlink(&self, entry: &SquashFSEntry) -> Result<Option<SquashFSEntry>> {
+    pub fn resolve_symlink(&self, entry: &SquashFSEntry) -> Result<Option<SquashFSEntry<'_>>> {
         match &entry.kind {
             EntryKind::Symlink(target) => {
                 let mut visited = HashSet::new();
@@ -288,7 +632,7 @@ impl<'a> SquashFS<'a> {
         &self,
         target: &Path,
         visited: &mut HashSet<PathBuf>,
-    ) -> Result<Option<SquashFSEntry>> {
+    ) -> Result<Option<SquashFSEntry<'_>>> {
         if !visited.insert(target.to_path_buf()) {
             return Err(SquishyError::SymlinkError("Cyclic symlink detected".into()));
         }
@@ -305,3 +649,48 @@ impl<'a> SquashFS<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use backhand::{DataSize, FilesystemWriter};
+
+    use super::*;
+
+    /// Builds an in-memory image holding a file stored in a fragment.
+    fn image() -> Vec<u8> {
+        let mut writer = FilesystemWriter::default();
+        writer
+            .push_file(
+                Cursor::new(b"contents".to_vec()),
+                "file",
+                NodeHeader::new(0o644, 0, 0, 0),
+            )
+            .unwrap();
+        let mut data = Cursor::new(Vec::new());
+        writer.write(&mut data).unwrap();
+        data.into_inner()
+    }
+
+    #[test]
+    fn rejects_files_with_blocks_past_their_size() {
+        let squashfs = SquashFS::new(BufReader::new(Cursor::new(image())), Some(0)).unwrap();
+        let Some(EntryKind::File(file)) = squashfs
+            .entries()
+            .map(|entry| entry.kind)
+            .find(|kind| matches!(kind, EntryKind::File(_)))
+        else {
+            panic!("missing file");
+        };
+        assert_eq!(squashfs.tail_len(file).unwrap(), 8);
+        assert_eq!(squashfs.compressed_sizes().unwrap().len(), 1);
+
+        let mut corrupted = file.clone();
+        corrupted.block_sizes.push(DataSize::new(1, true));
+        assert!(matches!(
+            squashfs.tail_len(&corrupted),
+            Err(SquishyError::InvalidSquashFS(_))
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Progress of an operation writing several files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of files processed so far
+    pub files_done: u64,
+    /// Number of files to process
+    pub files_total: u64,
+    /// Number of bytes processed so far
+    pub bytes_done: u64,
+    /// Number of bytes to process
+    pub bytes_total: u64,
+}
+
+/// Callback receiving the progress after each processed file. It may be called
+/// from several threads at once.
+pub type ProgressCallback<'a> = &'a (dyn Fn(Progress) + Sync);
+
+/// Thread-safe progress counter, reporting to a callback after each file
+pub struct ProgressTracker<'a> {
+    files_total: u64,
+    bytes_total: u64,
+    files_done: AtomicU64,
+    bytes_done: AtomicU64,
+    callback: Option<ProgressCallback<'a>>,
+}
+
+impl<'a> ProgressTracker<'a> {
+    /// Creates a progress tracker and reports the initial progress.
+    ///
+    /// # Arguments
+    /// * `files_total` - Number of files to process
+    /// * `bytes_total` - Number of bytes to process
+    /// * `callback` - Callback receiving the progress, if any
+    pub fn new(files_total: u64, bytes_total: u64, callback: Option<ProgressCallback<'a>>) -> Self {
+        let tracker = ProgressTracker {
+            files_total,
+            bytes_total,
+            files_done: AtomicU64::new(0),
+            bytes_done: AtomicU64::new(0),
+            callback,
+        };
+        tracker.report(0, 0);
+        tracker
+    }
+
+    /// Records a processed file, whether it was written, skipped or failed, and
+    /// reports the progress.
+    ///
+    /// # Arguments
+    /// * `bytes` - Size of the file
+    pub fn file_done(&self, bytes: u64) {
+        let files_done = self.files_done.fetch_add(1, Ordering::Relaxed) + 1;
+        let bytes_done = self.bytes_done.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.report(files_done, bytes_done);
+    }
+
+    /// Returns the current progress.
+    pub fn progress(&self) -> Progress {
+        Progress {
+            files_done: self.files_done.load(Ordering::Relaxed),
+            files_total: self.files_total,
+            bytes_done: self.bytes_done.load(Ordering::Relaxed),
+            bytes_total: self.bytes_total,
+        }
+    }
+
+    /// Reports the progress to the callback.
+    fn report(&self, files_done: u64, bytes_done: u64) {
+        if let Some(callback) = self.callback {
+            callback(Progress {
+                files_done,
+                files_total: self.files_total,
+                bytes_done,
+                bytes_total: self.bytes_total,
+            });
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    fs::File,
+    io::{ErrorKind, Read},
+    os::unix::fs::FileExt,
+    path::Path,
+};
+
+use crate::Result;
+
+/// Magic bytes at the start of a little endian SquashFS 4 superblock
+const MAGIC: &[u8; 4] = b"hsqs";
+/// Size of the superblock
+const SUPERBLOCK_SIZE: usize = 96;
+/// Number of bytes searched at once
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// A SquashFS superblock found in a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuperblockCandidate {
+    /// Offset of the superblock in the file, to be passed as `--offset`
+    pub offset: u64,
+    /// Major and minor version of the format
+    pub version: (u16, u16),
+    /// Name of the compressor, e.g. `zstd`
+    pub compressor: &'static str,
+    /// Size of the data blocks
+    pub block_size: u32,
+    /// Number of inodes
+    pub inode_count: u32,
+    /// Size of the filesystem in bytes
+    pub bytes_used: u64,
+    /// Modification time of the filesystem, in seconds since the unix epoch
+    pub mod_time: u32,
+    /// Whether the filesystem extends past the end of the file
+    pub truncated: bool,
+}
+
+/// Name of a SquashFS compressor id.
+fn compressor_name(id: u16) -> Option<&'static str> {
+    match id {
+        1 => Some("gzip"),
+        2 => Some("lzma"),
+        3 => Some("lzo"),
+        4 => Some("xz"),
+        5 => Some("lz4"),
+        6 => Some("zstd"),
+        _ => None,
+    }
+}
+
+/// Parses a superblock and checks that its fields are consistent, which rules out
+/// most magic bytes found by chance.
+///
+/// # Arguments
+/// * `sb` - The superblock bytes
+/// * `offset` - Offset of the superblock in the file
+/// * `file_size` - Size of the file
+///
+/// # Returns
+/// The candidate, or None if the fields aren't those of a SquashFS 4 superblock
+fn parse_superblock(
+    sb: &[u8; SUPERBLOCK_SIZE],
+    offset: u64,
+    file_size: u64,
+) -> Option<SuperblockCandidate> {
+    let u16_at = |at: usize| u16::from_le_bytes(sb[at..at + 2].try_into().unwrap());
+    let u32_at = |at: usize| u32::from_le_bytes(sb[at..at + 4].try_into().unwrap());
+    let u64_at = |at: usize| u64::from_le_bytes(sb[at..at + 8].try_into().unwrap());
+
+    let block_size = u32_at(12);
+    let block_log = u16_at(22);
+    let version = (u16_at(28), u16_at(30));
+    let bytes_used = u64_at(40);
+    let inode_table = u64_at(64);
+    let dir_table = u64_at(72);
+    if version != (4, 0)
+        || !(12..=20).contains(&block_log)
+        || block_size != 1 << block_log
+        || bytes_used < SUPERBLOCK_SIZE as u64
+        || inode_table >= bytes_used
+        || dir_table < inode_table
+        || dir_table >= bytes_used
+    {
+        return None;
+    }
+
+    Some(SuperblockCandidate {
+        offset,
+        version,
+        compressor: compressor_name(u16_at(20))?,
+        block_size,
+        inode_count: u32_at(4),
+        bytes_used,
+        mod_time: u32_at(8),
+        truncated: offset.saturating_add(bytes_used) > file_size,
+    })
+}
+
+/// Searches a file for SquashFS superblocks, e.g. to find the offset of a
+/// filesystem inside a firmware blob or an unusual bundle. Only SquashFS 4
+/// superblocks with consistent fields are reported.
+///
+/// # Arguments
+/// * `path` - Path of the file
+///
+/// # Returns
+/// The superblocks found, by offset, or an error if the file can't be read
+pub fn scan<P: AsRef<Path>>(path: P) -> Result<Vec<SuperblockCandidate>> {
+    let mut file = File::open(path)?;
+    let file_size = file.metadata()?.len();
+
+    let mut candidates = Vec::new();
+    let mut buffer = vec![0_u8; CHUNK_SIZE];
+    // Bytes kept from the previous chunk, so magic bytes spanning two chunks are found
+    let mut kept = 0;
+    let mut position = 0_u64;
+    loop {
+        let read = match file.read(&mut buffer[kept..]) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let len = kept + read;
+        let start = position - kept as u64;
+
+        for (index, window) in buffer[..len].windows(MAGIC.len()).enumerate() {
+            if window != MAGIC {
+                continue;
+            }
+            let offset = start + index as u64;
+            let mut sb = [0_u8; SUPERBLOCK_SIZE];
+            if file.read_exact_at(&mut sb, offset).is_err() {
+                continue;
+            }
+            if let Some(candidate) = parse_superblock(&sb, offset, file_size) {
+                candidates.push(candidate);
+            }
+        }
+
+        kept = (MAGIC.len() - 1).min(len);
+        buffer.copy_within(len - kept..len, 0);
+        position += read as u64;
+    }
+
+    Ok(candidates)
+}
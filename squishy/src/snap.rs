@@ -0,0 +1,341 @@
+use std::path::Path;
+
+use crate::{error::SquishyError, EntryKind, Result, SquashFS, SquashFSEntry};
+
+/// Path of the snap metadata within a snap
+const SNAP_YAML: &str = "/meta/snap.yaml";
+
+/// Directory of the desktop files and icons within a snap
+const GUI_DIR: &str = "/meta/gui";
+
+/// Metadata of a snap, read from `meta/snap.yaml`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapMetadata {
+    /// `name`
+    pub name: String,
+    /// `version`
+    pub version: Option<String>,
+    /// `summary`
+    pub summary: Option<String>,
+    /// `description`
+    pub description: Option<String>,
+    /// `base`, e.g. `core22`
+    pub base: Option<String>,
+    /// `grade`, e.g. `stable` or `devel`
+    pub grade: Option<String>,
+    /// `confinement`, e.g. `strict` or `classic`
+    pub confinement: Option<String>,
+    /// Names of the apps declared under `apps`
+    pub apps: Vec<String>,
+}
+
+impl SnapMetadata {
+    /// Parses `meta/snap.yaml`. Only the top-level scalar keys and the names of
+    /// the apps are read; nested values are skipped.
+    ///
+    /// # Arguments
+    /// * `content` - Contents of the snap.yaml file
+    ///
+    /// # Returns
+    /// The parsed metadata, or an error if the snap name is missing
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut metadata = SnapMetadata::default();
+        let mut name = None;
+        let lines: Vec<&str> = content.lines().collect();
+        let mut i = 0;
+
+        while i < lines.len() {
+            let line = lines[i];
+            i += 1;
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            // Nested values are handled by the top-level key they belong to
+            if line.starts_with([' ', '\t']) {
+                continue;
+            }
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = scalar(value);
+
+            let value = if value.starts_with(['|', '>']) {
+                let block = block_lines(&lines, &mut i);
+                if value.starts_with('|') {
+                    block.join("\n")
+                } else {
+                    block.join(" ")
+                }
+            } else if value.is_empty() {
+                if key == "apps" {
+                    metadata.apps = child_keys(&lines, &mut i);
+                }
+                continue;
+            } else {
+                value.to_owned()
+            };
+
+            match key.trim() {
+                "name" => name = Some(value),
+                "version" => metadata.version = Some(value),
+                "summary" => metadata.summary = Some(value),
+                "description" => metadata.description = Some(value),
+                "base" => metadata.base = Some(value),
+                "grade" => metadata.grade = Some(value),
+                "confinement" => metadata.confinement = Some(value),
+                _ => {}
+            }
+        }
+
+        metadata.name = name
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| SquishyError::InvalidSnap("Missing snap name".into()))?;
+        Ok(metadata)
+    }
+}
+
+/// Reads a YAML scalar, removing its surrounding quotes or its trailing comment.
+fn scalar(value: &str) -> &str {
+    let value = value.trim();
+    for quote in ['\'', '"'] {
+        if let Some(rest) = value.strip_prefix(quote) {
+            if let Some(end) = rest.find(quote) {
+                return &rest[..end];
+            }
+        }
+    }
+    value
+        .find(" #")
+        .map_or(value, |pos| value[..pos].trim_end())
+}
+
+/// Collects the indented lines of a block scalar, removing the common indentation.
+///
+/// # Arguments
+/// * `lines` - Lines of the document
+/// * `i` - Index of the line following the block indicator, advanced past the block
+fn block_lines(lines: &[&str], i: &mut usize) -> Vec<String> {
+    let start = *i;
+    while *i < lines.len() && (lines[*i].trim().is_empty() || lines[*i].starts_with([' ', '\t'])) {
+        *i += 1;
+    }
+    let block = &lines[start..*i];
+    let indent = block
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    let mut block: Vec<String> = block
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end().to_owned())
+        .collect();
+    while block.last().is_some_and(|line| line.is_empty()) {
+        block.pop();
+    }
+    block
+}
+
+/// Collects the keys of the mapping nested under a top-level key.
+///
+/// # Arguments
+/// * `lines` - Lines of the document
+/// * `i` - Index of the line following the top-level key, advanced past the mapping
+fn child_keys(lines: &[&str], i: &mut usize) -> Vec<String> {
+    let mut keys = Vec::new();
+    let mut indent = None;
+    while *i < lines.len() {
+        let line = lines[*i];
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            *i += 1;
+            continue;
+        }
+        let line_indent = line.len() - line.trim_start().len();
+        if line_indent == 0 {
+            break;
+        }
+        *i += 1;
+        if *indent.get_or_insert(line_indent) != line_indent {
+            continue;
+        }
+        if let Some((key, _)) = line.trim().split_once(':') {
+            keys.push(scalar(key).to_owned());
+        }
+    }
+    keys
+}
+
+/// The Snap struct provides access to the metadata of a snap package, which is
+/// a plain SquashFS image with its metadata stored under `meta/`.
+pub struct Snap<'a> {
+    pub squashfs: SquashFS<'a>,
+}
+
+impl<'a> SquashFS<'a> {
+    /// Opens a snap package.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the snap file.
+    ///
+    /// # Returns
+    /// A Snap instance, or an error if the file isn't a SquashFS image containing `meta/snap.yaml`.
+    pub fn open_snap<P: AsRef<Path>>(path: &'a P) -> Result<Snap<'a>> {
+        let squashfs = SquashFS::from_path_with_offset(path, 0)?;
+        if !squashfs
+            .entries()
+            .any(|entry| entry.path == Path::new(SNAP_YAML))
+        {
+            return Err(SquishyError::InvalidSnap(
+                "Couldn't find meta/snap.yaml".into(),
+            ));
+        }
+        Ok(Snap { squashfs })
+    }
+}
+
+impl Snap<'_> {
+    /// Reads and parses `meta/snap.yaml`.
+    ///
+    /// # Returns
+    /// The snap metadata, or an error if it can't be read or parsed
+    pub fn metadata(&self) -> Result<SnapMetadata> {
+        let content = self.squashfs.read_file(SNAP_YAML)?;
+        SnapMetadata::parse(&String::from_utf8_lossy(&content))
+    }
+
+    /// Finds the icon of the snap, `meta/gui/icon.png` or `meta/gui/icon.svg`.
+    /// Symlinks are resolved.
+    ///
+    /// # Returns
+    /// The icon entry, if found
+    pub fn find_icon(&self) -> Option<SquashFSEntry<'_>> {
+        let icon = self
+            .squashfs
+            .find_entries(|path| {
+                path.parent() == Some(Path::new(GUI_DIR))
+                    && path
+                        .file_stem()
+                        .is_some_and(|stem| stem.eq_ignore_ascii_case("icon"))
+                    && path.extension().is_some_and(|ext| {
+                        ext.eq_ignore_ascii_case("png") || ext.eq_ignore_ascii_case("svg")
+                    })
+            })
+            .min_by_key(|entry| {
+                entry
+                    .path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+            })?;
+        self.resolve(icon)
+    }
+
+    /// Finds the desktop files of the snap apps under `meta/gui/`.
+    /// Symlinks are resolved.
+    ///
+    /// # Returns
+    /// The desktop file entries
+    pub fn find_desktop_files(&self) -> Vec<SquashFSEntry<'_>> {
+        self.squashfs
+            .find_entries(|path| {
+                path.starts_with(GUI_DIR)
+                    && path
+                        .extension()
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("desktop"))
+            })
+            .filter_map(|entry| self.resolve(entry))
+            .collect()
+    }
+
+    /// Reads the contents of the specified file from the snap.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file within the snap.
+    ///
+    /// # Returns
+    /// The contents of the file as a Vec<u8>, or an error if the file is not found.
+    pub fn read_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        self.squashfs.read_file(path)
+    }
+
+    /// Resolves the entry if it is a symlink.
+    fn resolve<'b>(&'b self, entry: SquashFSEntry<'b>) -> Option<SquashFSEntry<'b>> {
+        match entry.kind {
+            EntryKind::Symlink(_) => self.squashfs.resolve_symlink(&entry).ok().flatten(),
+            _ => Some(entry),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METADATA: &str = r#"name: hello-world
+version: '6.4'
+summary: "The 'hello-world' of snaps" # shown in the store
+description: |
+  This is a simple snap.
+
+  It prints hello.
+base: core22
+grade: stable
+confinement: strict
+apps:
+  env:
+    command: bin/env
+  hello-world:
+    command: bin/echo
+    plugs: [home]
+  # Commented out
+  sh:
+    command: bin/sh
+architectures:
+  - amd64
+"#;
+
+    #[test]
+    fn parses_metadata() {
+        let metadata = SnapMetadata::parse(METADATA).unwrap();
+        assert_eq!(
+            metadata,
+            SnapMetadata {
+                name: "hello-world".into(),
+                version: Some("6.4".into()),
+                summary: Some("The 'hello-world' of snaps".into()),
+                description: Some("This is a simple snap.\n\nIt prints hello.".into()),
+                base: Some("core22".into()),
+                grade: Some("stable".into()),
+                confinement: Some("strict".into()),
+                apps: vec!["env".into(), "hello-world".into(), "sh".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn folds_block_scalars() {
+        let metadata =
+            SnapMetadata::parse("name: app\ndescription: >\n  Folded\n  text\n\nbase: core\n")
+                .unwrap();
+        assert_eq!(metadata.description.as_deref(), Some("Folded text"));
+        assert_eq!(metadata.base.as_deref(), Some("core"));
+    }
+
+    #[test]
+    fn requires_name() {
+        for content in ["version: 1\n", "name: ''\n", ""] {
+            assert!(matches!(
+                SnapMetadata::parse(content),
+                Err(SquishyError::InvalidSnap(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn reads_scalars() {
+        assert_eq!(scalar(" 'quoted # value' "), "quoted # value");
+        assert_eq!(scalar(" \"double\" "), "double");
+        assert_eq!(scalar(" plain # comment"), "plain");
+        assert_eq!(scalar(" a#b "), "a#b");
+    }
+}
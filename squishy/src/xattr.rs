@@ -0,0 +1,136 @@
+use std::{collections::HashMap, fs::File, os::unix::fs::FileExt, path::PathBuf};
+
+use crate::{
+    inode::{invalid, reference_position, MetadataReader, NO_XATTRS},
+    Result, SquashFS,
+};
+
+/// Value of unset table positions in the superblock
+const NOT_SET: u64 = u64::MAX;
+/// Bit of an xattr type set when the value is stored out of line
+const XATTR_VALUE_OOL: u16 = 0x100;
+/// Size of a metadata block once decompressed
+const METADATA_SIZE: u64 = 8192;
+/// Size of an entry of the xattr id table
+const XATTR_ID_SIZE: u64 = 16;
+
+/// An extended attribute of an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Xattr {
+    /// Full name of the attribute, including its namespace, e.g. `security.capability`
+    pub name: String,
+    /// Raw value of the attribute
+    pub value: Vec<u8>,
+}
+
+impl SquashFS<'_> {
+    /// Whether the image stores extended attributes.
+    pub fn has_xattrs(&self) -> bool {
+        self.superblock.xattr_table != NOT_SET
+    }
+
+    /// Reads the extended attributes of all entries. backhand doesn't keep them
+    /// when reading the image, so the inode, directory and xattr tables are read
+    /// again here.
+    ///
+    /// # Returns
+    /// The attributes by entry path, in the same form as `SquashFSEntry::path`,
+    /// with only the entries that have any. An error if the SquashFS wasn't opened
+    /// from a path, or the tables can't be read.
+    pub fn xattrs(&self) -> Result<HashMap<PathBuf, Vec<Xattr>>> {
+        if !self.has_xattrs() {
+            return Ok(HashMap::new());
+        }
+        let mut table = XattrTable::new(self, self.source_for("Reading xattrs")?)?;
+        self.read_inodes("Reading xattrs")?
+            .into_iter()
+            .filter(|(_, inode)| inode.xattr_index != NO_XATTRS)
+            .map(|(path, inode)| Ok((path, table.get(inode.xattr_index)?)))
+            .collect()
+    }
+}
+
+/// Xattr id table, mapping xattr indexes of inodes to their key/value pairs
+struct XattrTable<'s, 'a> {
+    /// Reader of the key/value pairs
+    pairs: MetadataReader<'s, 'a>,
+    /// Reader of the ids, starting at the image start as the id blocks are
+    /// located by their position in the image
+    ids: MetadataReader<'s, 'a>,
+    /// Positions of the id blocks in the image
+    id_blocks: Vec<u64>,
+    count: u32,
+}
+
+impl<'s, 'a> XattrTable<'s, 'a> {
+    fn new(squashfs: &'s SquashFS<'a>, source: &'s File) -> Result<Self> {
+        let offset = squashfs.offset;
+        let mut header = [0_u8; 16];
+        source.read_exact_at(&mut header, offset + squashfs.superblock.xattr_table)?;
+        let pairs_start = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let count = u32::from_le_bytes(header[8..12].try_into().unwrap());
+
+        let block_count = (count as u64 * XATTR_ID_SIZE).div_ceil(METADATA_SIZE) as usize;
+        let mut pointers = vec![0_u8; block_count * 8];
+        source.read_exact_at(&mut pointers, offset + squashfs.superblock.xattr_table + 16)?;
+        let id_blocks = pointers
+            .chunks_exact(8)
+            .map(|pointer| u64::from_le_bytes(pointer.try_into().unwrap()))
+            .collect();
+
+        Ok(XattrTable {
+            pairs: MetadataReader::new(squashfs, source, offset + pairs_start),
+            ids: MetadataReader::new(squashfs, source, offset),
+            id_blocks,
+            count,
+        })
+    }
+
+    /// Reads the attributes of an xattr index.
+    ///
+    /// # Arguments
+    /// * `index` - Xattr index of the inode
+    ///
+    /// # Returns
+    /// The attributes, or an error if the index is out of range or the pairs
+    /// can't be read
+    fn get(&mut self, index: u32) -> Result<Vec<Xattr>> {
+        if index >= self.count {
+            return Err(invalid(&format!("Xattr index {} out of range", index)));
+        }
+        let position = index as u64 * XATTR_ID_SIZE;
+        let block = self.id_blocks[(position / METADATA_SIZE) as usize];
+        let mut id_position = (block, (position % METADATA_SIZE) as usize);
+        let reference = self.ids.read_u64(&mut id_position)?;
+        let count = self.ids.read_u32(&mut id_position)?;
+
+        let mut pair_position = reference_position(reference);
+        (0..count)
+            .map(|_| {
+                let kind = self.pairs.read_u16(&mut pair_position)?;
+                let name_size = self.pairs.read_u16(&mut pair_position)? as usize;
+                let name = self.pairs.read(&mut pair_position, name_size)?;
+                let prefix = match kind & !XATTR_VALUE_OOL {
+                    0 => "user.",
+                    1 => "trusted.",
+                    2 => "security.",
+                    other => return Err(invalid(&format!("Unknown xattr type {}", other))),
+                };
+
+                let value_size = self.pairs.read_u32(&mut pair_position)? as usize;
+                let value = if kind & XATTR_VALUE_OOL != 0 {
+                    let mut value_position =
+                        reference_position(self.pairs.read_u64(&mut pair_position)?);
+                    let size = self.pairs.read_u32(&mut value_position)? as usize;
+                    self.pairs.read(&mut value_position, size)?
+                } else {
+                    self.pairs.read(&mut pair_position, value_size)?
+                };
+                Ok(Xattr {
+                    name: format!("{}{}", prefix, String::from_utf8_lossy(&name)),
+                    value,
+                })
+            })
+            .collect()
+    }
+}